@@ -1,14 +1,19 @@
 use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
 
+use goose::agents::Budget;
 use goose::config::{Config, ExtensionConfig};
+use goose::model::ReasoningEffort;
 
 use crate::commands::bench::agent_generator;
 use crate::commands::configure::handle_configure;
 use crate::commands::info::handle_info;
 use crate::commands::mcp::run_server;
 use crate::commands::recipe::{handle_deeplink, handle_validate};
-use crate::commands::session::{handle_session_list, handle_session_remove};
+use crate::commands::session::{
+    handle_session_export, handle_session_fork, handle_session_import, handle_session_list,
+    handle_session_remove, handle_session_search,
+};
 use crate::logging::setup_logging;
 use crate::recipe::load_recipe;
 use crate::session;
@@ -17,6 +22,7 @@ use goose_bench::bench_config::BenchRunConfig;
 use goose_bench::runners::bench_runner::BenchRunner;
 use goose_bench::runners::eval_runner::EvalRunner;
 use goose_bench::runners::model_runner::ModelRunner;
+use serde_json::Value;
 use std::io::Read;
 use std::path::PathBuf;
 
@@ -49,6 +55,67 @@ struct Identifier {
     path: Option<PathBuf>,
 }
 
+/// Limits for each run of the agent, on top of the ones in the config
+#[derive(Args)]
+struct BudgetArgs {
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Stop after this many model responses and ask to continue"
+    )]
+    max_turns: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Stop after this many tool calls and ask to continue"
+    )]
+    max_tool_calls: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Stop after using this many tokens and ask to continue"
+    )]
+    max_tokens: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "USD",
+        help = "Stop after spending this much (estimated) and ask to continue"
+    )]
+    max_cost: Option<f64>,
+}
+
+#[derive(Args, Debug, Default)]
+struct ReasoningArgs {
+    #[arg(
+        long,
+        value_name = "EFFORT",
+        help = "How much the model should reason: off, low, medium or high",
+        long_help = "How much the model should reason before answering: off, low, medium or high. Sets reasoning_effort for OpenAI o-series models and the extended thinking budget for Claude and Gemini 2.5. Toggle it during a session with /think."
+    )]
+    reasoning_effort: Option<ReasoningEffort>,
+
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        help = "Tokens the model may spend thinking, overriding the effort's default"
+    )]
+    thinking_budget: Option<u32>,
+}
+
+impl From<BudgetArgs> for Budget {
+    fn from(args: BudgetArgs) -> Self {
+        Budget {
+            max_turns: args.max_turns,
+            max_tool_calls: args.max_tool_calls,
+            max_tokens: args.max_tokens,
+            max_cost: args.max_cost,
+        }
+    }
+}
+
 fn extract_identifier(identifier: Identifier) -> session::Identifier {
     if let Some(name) = identifier.name {
         session::Identifier::Name(name)
@@ -100,6 +167,70 @@ enum SessionCommand {
         )]
         regex: String,
     },
+    #[command(about = "Search the messages of all sessions")]
+    Search {
+        /// Words that must each start a word of a message, ignoring case
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of matches to show",
+            default_value = "20"
+        )]
+        limit: usize,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(about = "Fork a session into a new branch")]
+    Fork {
+        #[arg(help = "Id of the session to fork")]
+        id: String,
+
+        #[arg(
+            long,
+            help = "Index of the message the branch diverges at, defaults to the end",
+            long_help = "Index of the message the branch diverges at. The branch keeps all messages before it. Defaults to copying the whole session."
+        )]
+        at: Option<usize>,
+
+        #[arg(
+            long,
+            help = "Replace the user message at --at with this text and send it",
+            requires = "at"
+        )]
+        edit: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Id for the new session, defaults to a new timestamp id"
+        )]
+        name: Option<String>,
+    },
+    #[command(about = "Export a session in the JSONL session format")]
+    Export {
+        #[arg(help = "Id of the session to export")]
+        id: String,
+
+        #[arg(short, long, help = "File to write to instead of stdout")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Import a session from a file in the JSONL session format")]
+    Import {
+        #[arg(help = "File to import")]
+        path: PathBuf,
+
+        #[arg(long, help = "Id for the imported session, defaults to the file name")]
+        id: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -238,6 +369,12 @@ enum Command {
             value_delimiter = ','
         )]
         builtins: Vec<String>,
+
+        #[command(flatten)]
+        budget: BudgetArgs,
+
+        #[command(flatten)]
+        reasoning: ReasoningArgs,
     },
 
     /// Execute commands from an instruction file
@@ -347,6 +484,12 @@ enum Command {
             value_delimiter = ','
         )]
         builtins: Vec<String>,
+
+        #[command(flatten)]
+        budget: BudgetArgs,
+
+        #[command(flatten)]
+        reasoning: ReasoningArgs,
     },
 
     /// Recipe utilities for validation and deeplinking
@@ -390,6 +533,10 @@ struct InputConfig {
     contents: Option<String>,
     extensions_override: Option<Vec<ExtensionConfig>>,
     additional_system_prompt: Option<String>,
+    budget: Option<Budget>,
+    reasoning_effort: Option<ReasoningEffort>,
+    thinking_budget: Option<u32>,
+    output_schema: Option<Value>,
 }
 
 pub async fn cli() -> Result<()> {
@@ -415,6 +562,8 @@ pub async fn cli() -> Result<()> {
             extensions,
             remote_extensions,
             builtins,
+            budget,
+            reasoning,
         }) => {
             return match command {
                 Some(SessionCommand::List {
@@ -429,6 +578,48 @@ pub async fn cli() -> Result<()> {
                     handle_session_remove(id, regex)?;
                     return Ok(());
                 }
+                Some(SessionCommand::Search {
+                    query,
+                    limit,
+                    format,
+                }) => {
+                    handle_session_search(query.join(" "), limit, format)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Fork { id, at, edit, name }) => {
+                    let new_id = handle_session_fork(id, at, edit.is_some(), name)?;
+                    if let Some(edit) = edit {
+                        // Resend the edited message on the new branch
+                        let mut session: crate::Session = build_session(SessionBuilderConfig {
+                            identifier: Some(session::Identifier::Name(new_id)),
+                            resume: true,
+                            extensions,
+                            remote_extensions,
+                            builtins,
+                            extensions_override: None,
+                            additional_system_prompt: None,
+                            debug,
+                            budget: budget.into(),
+                            reasoning_effort: reasoning.reasoning_effort,
+                            thinking_budget: reasoning.thinking_budget,
+                        })
+                        .await;
+                        setup_logging(
+                            session.session_file().file_stem().and_then(|s| s.to_str()),
+                            None,
+                        )?;
+                        let _ = session.interactive(Some(edit)).await;
+                    }
+                    Ok(())
+                }
+                Some(SessionCommand::Export { id, output }) => {
+                    handle_session_export(id, output)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Import { path, id }) => {
+                    handle_session_import(path, id)?;
+                    Ok(())
+                }
                 None => {
                     // Run session command by default
                     let mut session: crate::Session = build_session(SessionBuilderConfig {
@@ -440,6 +631,9 @@ pub async fn cli() -> Result<()> {
                         extensions_override: None,
                         additional_system_prompt: None,
                         debug,
+                        budget: budget.into(),
+                        reasoning_effort: reasoning.reasoning_effort,
+                        thinking_budget: reasoning.thinking_budget,
                     })
                     .await;
                     setup_logging(
@@ -463,6 +657,8 @@ pub async fn cli() -> Result<()> {
             remote_extensions,
             builtins,
             params,
+            budget,
+            reasoning,
         }) => {
             let input_config = match (instructions, input_text, recipe) {
                 (Some(file), _, _) if file == "-" => {
@@ -475,6 +671,10 @@ pub async fn cli() -> Result<()> {
                         contents: Some(input),
                         extensions_override: None,
                         additional_system_prompt: None,
+                        budget: None,
+                        reasoning_effort: None,
+                        thinking_budget: None,
+                        output_schema: None,
                     }
                 }
                 (Some(file), _, _) => {
@@ -489,12 +689,20 @@ pub async fn cli() -> Result<()> {
                         contents: Some(contents),
                         extensions_override: None,
                         additional_system_prompt: None,
+                        budget: None,
+                        reasoning_effort: None,
+                        thinking_budget: None,
+                        output_schema: None,
                     }
                 }
                 (_, Some(text), _) => InputConfig {
                     contents: Some(text),
                     extensions_override: None,
                     additional_system_prompt: None,
+                    budget: None,
+                    reasoning_effort: None,
+                    thinking_budget: None,
+                    output_schema: None,
                 },
                 (_, _, Some(file)) => {
                     let recipe = load_recipe(&file, true, Some(params)).unwrap_or_else(|err| {
@@ -505,6 +713,10 @@ pub async fn cli() -> Result<()> {
                         contents: recipe.prompt,
                         extensions_override: recipe.extensions,
                         additional_system_prompt: recipe.instructions,
+                        budget: recipe.budget,
+                        reasoning_effort: recipe.reasoning_effort,
+                        thinking_budget: recipe.thinking_budget,
+                        output_schema: recipe.output_schema,
                     }
                 }
                 (None, None, None) => {
@@ -522,6 +734,11 @@ pub async fn cli() -> Result<()> {
                 extensions_override: input_config.extensions_override,
                 additional_system_prompt: input_config.additional_system_prompt,
                 debug,
+                // Limits given on the command line take precedence over the recipe's
+                budget: Budget::from(budget).or(input_config.budget.unwrap_or_default()),
+                // Same for reasoning settings
+                reasoning_effort: reasoning.reasoning_effort.or(input_config.reasoning_effort),
+                thinking_budget: reasoning.thinking_budget.or(input_config.thinking_budget),
             })
             .await;
 
@@ -533,7 +750,21 @@ pub async fn cli() -> Result<()> {
             if interactive {
                 let _ = session.interactive(input_config.contents).await;
             } else if let Some(contents) = input_config.contents {
-                let _ = session.headless(contents).await;
+                if let Err(e) = session.headless(contents).await {
+                    // Fail the run so scripts and CI (e.g. replaying a cassette) notice
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                if let Some(schema) = input_config.output_schema {
+                    match session.structured_result(&schema).await {
+                        // On stdout by itself so it can be piped into other tools
+                        Ok(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+                        Err(e) => {
+                            eprintln!("Error: failed to produce the recipe's output: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
             } else {
                 eprintln!("Error: no text provided for prompt in headless mode");
                 std::process::exit(1);
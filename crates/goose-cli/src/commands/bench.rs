@@ -2,6 +2,7 @@ use crate::session::build_session;
 use crate::session::SessionBuilderConfig;
 use crate::{logging, session, Session};
 use async_trait::async_trait;
+use goose::agents::Budget;
 use goose::message::Message;
 use goose_bench::bench_session::{BenchAgent, BenchBaseSession};
 use goose_bench::eval_suites::ExtensionRequirements;
@@ -40,6 +41,9 @@ pub async fn agent_generator(
         extensions_override: None,
         additional_system_prompt: None,
         debug: false,
+        budget: Budget::default(),
+        reasoning_effort: None,
+        thinking_budget: None,
     })
     .await;
 
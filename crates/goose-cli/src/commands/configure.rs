@@ -14,6 +14,8 @@ use goose::config::{
     PermissionManager,
 };
 use goose::message::Message;
+use goose::providers::base::ModelInfo;
+use goose::providers::models::list_models;
 use goose::providers::{create, providers};
 use mcp_core::tool::ToolAnnotations;
 use mcp_core::Tool;
@@ -327,9 +329,7 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
 
     // Select model, defaulting to the provider's recommended model UNLESS there is an env override
     let default_model = std::env::var("GOOSE_MODEL").unwrap_or(provider_meta.default_model.clone());
-    let model: String = cliclack::input("Enter a model from that provider:")
-        .default_input(&default_model)
-        .interact()?;
+    let model = select_model_dialog(provider_name, &default_model).await?;
 
     // Test the configuration
     let spin = spinner();
@@ -398,6 +398,70 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     }
 }
 
+/// Choose a model from the provider's live model list, or type one in if it can't be listed
+async fn select_model_dialog(
+    provider_name: &str,
+    default_model: &str,
+) -> Result<String, Box<dyn Error>> {
+    let spin = spinner();
+    spin.start("Fetching available models...");
+    let models = match list_models(provider_name, false).await {
+        Ok(Some(models)) if !models.is_empty() => {
+            spin.stop("");
+            models
+        }
+        Ok(_) => {
+            spin.stop("");
+            Vec::new()
+        }
+        Err(e) => {
+            spin.stop(style(format!("Could not list models: {}", e)).dim());
+            Vec::new()
+        }
+    };
+
+    if !models.is_empty() {
+        const CUSTOM_MODEL: &str = "__custom__";
+        let mut select = cliclack::select("Select a model from that provider:");
+        if models.iter().any(|model| model.name == default_model) {
+            select = select.initial_value(default_model.to_string());
+        }
+        for model in &models {
+            select = select.item(model.name.clone(), &model.name, model_hint(model));
+        }
+        let selected: String = select
+            .item(
+                CUSTOM_MODEL.to_string(),
+                "Other",
+                "Enter a model name manually",
+            )
+            .interact()?;
+        if selected != CUSTOM_MODEL {
+            return Ok(selected);
+        }
+    }
+
+    let model: String = cliclack::input("Enter a model from that provider:")
+        .default_input(default_model)
+        .interact()?;
+    Ok(model)
+}
+
+/// Summarize the context length and capabilities of a model for the model selection
+fn model_hint(model: &ModelInfo) -> String {
+    let mut hint = vec![format!("{}k context", model.context_limit / 1000)];
+    for (supported, capability) in [
+        (model.supports_tools, "tools"),
+        (model.supports_vision, "vision"),
+        (model.supports_reasoning, "reasoning"),
+    ] {
+        if supported == Some(true) {
+            hint.push(capability.to_string());
+        }
+    }
+    hint.join(", ")
+}
+
 /// Configure extensions that can be used with goose
 /// Dialog for toggling which extensions are enabled/disabled
 pub fn toggle_extensions_dialog() -> Result<(), Box<dyn Error>> {
@@ -466,6 +530,11 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
             "Remote Extension",
             "Connect to a remote extension via SSE",
         )
+        .item(
+            "streamable_http",
+            "Remote Extension (Streamable HTTP)",
+            "Connect to a remote extension at a single HTTP endpoint",
+        )
         .interact()?;
 
     match extension_type {
@@ -630,7 +699,8 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
 
             cliclack::outro(format!("Added {} extension", style(name).green()))?;
         }
-        "sse" => {
+        "sse" | "streamable_http" => {
+            let streamable = extension_type == "streamable_http";
             let extensions = ExtensionConfigManager::get_all_names()?;
             let name: String = cliclack::input("What would you like to call this extension?")
                 .placeholder("my-remote-extension")
@@ -645,8 +715,16 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                 })
                 .interact()?;
 
-            let uri: String = cliclack::input("What is the SSE endpoint URI?")
-                .placeholder("http://localhost:8000/events")
+            let (prompt, placeholder) = if streamable {
+                ("What is the MCP endpoint URI?", "http://localhost:8000/mcp")
+            } else {
+                (
+                    "What is the SSE endpoint URI?",
+                    "http://localhost:8000/events",
+                )
+            };
+            let uri: String = cliclack::input(prompt)
+                .placeholder(placeholder)
                 .validate(|input: &String| {
                     if input.is_empty() {
                         Err("Please enter a URI")
@@ -717,17 +795,50 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                 }
             }
 
-            ExtensionConfigManager::set(ExtensionEntry {
-                enabled: true,
-                config: ExtensionConfig::Sse {
+            let config = if streamable {
+                let mut headers = HashMap::new();
+                if cliclack::confirm("Would you like to add request headers?").interact()? {
+                    loop {
+                        let key: String = cliclack::input("Header name:")
+                            .placeholder("Authorization")
+                            .interact()?;
+                        let value: String = cliclack::input(
+                            "Header value (${KEY} is replaced with that environment variable):",
+                        )
+                        .placeholder("Bearer ${API_KEY}")
+                        .interact()?;
+                        headers.insert(key, value);
+
+                        if !cliclack::confirm("Add another header?").interact()? {
+                            break;
+                        }
+                    }
+                }
+                ExtensionConfig::StreamableHttp {
                     name: name.clone(),
                     uri,
                     envs: Envs::new(envs),
                     env_keys,
+                    headers,
                     description,
                     timeout: Some(timeout),
                     bundled: None,
-                },
+                }
+            } else {
+                ExtensionConfig::Sse {
+                    name: name.clone(),
+                    uri,
+                    envs: Envs::new(envs),
+                    env_keys,
+                    description,
+                    timeout: Some(timeout),
+                    bundled: None,
+                }
+            };
+
+            ExtensionConfigManager::set(ExtensionEntry {
+                enabled: true,
+                config,
             })?;
 
             cliclack::outro(format!("Added {} extension", style(name).green()))?;
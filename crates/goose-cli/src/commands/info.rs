@@ -2,6 +2,8 @@ use anyhow::Result;
 use console::style;
 use etcetera::{choose_app_strategy, AppStrategy};
 use goose::config::Config;
+use goose::providers::pricing::model_pricing;
+use goose::session::session_store;
 use serde_yaml;
 
 fn print_aligned(label: &str, value: &str, width: usize) {
@@ -40,6 +42,8 @@ pub fn handle_info(verbose: bool) -> Result<()> {
         print_aligned(label, path, basic_padding);
     }
 
+    print_usage_info(config, basic_padding);
+
     // Print verbose info if requested
     if verbose {
         println!("\n{}", style("Goose Configuration:").cyan().bold());
@@ -63,3 +67,38 @@ pub fn handle_info(verbose: bool) -> Result<()> {
 
     Ok(())
 }
+
+/// Print the configured model's pricing and the estimated cost across all sessions
+fn print_usage_info(config: &Config, padding: usize) {
+    println!("\n{}", style("Goose Usage:").cyan().bold());
+
+    match config.get_param::<String>("GOOSE_MODEL") {
+        Ok(model) => {
+            let pricing = match model_pricing(&model) {
+                Some(pricing) => format!(
+                    "${} input / ${} output per 1M tokens",
+                    pricing.input, pricing.output
+                ),
+                None => "unknown (set GOOSE_PRICING to configure)".to_string(),
+            };
+            print_aligned("Model:", &model, padding);
+            print_aligned("Pricing:", &pricing, padding);
+        }
+        Err(_) => print_aligned("Model:", "(not configured)", padding),
+    }
+
+    let total_cost: f64 = session_store()
+        .and_then(|store| store.list())
+        .map(|sessions| {
+            sessions
+                .iter()
+                .filter_map(|session| session.metadata.accumulated_cost)
+                .fold(0.0, |total, cost| total + cost)
+        })
+        .unwrap_or_default();
+    print_aligned(
+        "Total cost:",
+        &format!("${:.4} (estimated)", total_cost),
+        padding,
+    );
+}
@@ -1,7 +1,12 @@
 use anyhow::{Context, Result};
+use goose::message::MessageContent;
 use goose::session::info::{get_session_info, SessionInfo, SortOrder};
+use goose::session::{generate_session_id, session_store, SessionSearchResult};
+use mcp_core::role::Role;
 use regex::Regex;
 use std::fs;
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
 
 pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
     println!("The following sessions will be removed:");
@@ -15,9 +20,9 @@ pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
             .interact()?;
 
     if should_delete {
+        let store = session_store()?;
         for session in sessions {
-            fs::remove_file(session.path.clone())
-                .with_context(|| format!("Failed to remove session file '{}'", session.path))?;
+            store.remove(&session.id)?;
             println!("Session `{}` removed.", session.id);
         }
     } else {
@@ -106,6 +111,34 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
                     if verbose {
                         println!("  {}", output);
                         println!("    Path: {}", path);
+                        if let Some(parent) = &metadata.parent {
+                            println!(
+                                "    Forked from: {} at message {}",
+                                parent.session_id, parent.message_index
+                            );
+                        }
+                        if !metadata.branches.is_empty() {
+                            println!("    Branches: {}", metadata.branches.join(", "));
+                        }
+                        if let Some(cost) = metadata.accumulated_cost {
+                            println!("    Cost: ${:.4} (estimated)", cost);
+                        }
+                        if metadata.model_usage.len() > 1 {
+                            for (model, usage) in &metadata.model_usage {
+                                let cost = usage
+                                    .cost
+                                    .map(|cost| format!(", ${:.4}", cost))
+                                    .unwrap_or_default();
+                                println!(
+                                    "    {}: {} responses, {} in / {} out tokens{}",
+                                    model,
+                                    usage.responses,
+                                    usage.input_tokens,
+                                    usage.output_tokens,
+                                    cost
+                                );
+                            }
+                        }
                     } else {
                         println!("{}", output);
                     }
@@ -115,3 +148,115 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
     }
     Ok(())
 }
+
+pub fn handle_session_search(query: String, limit: usize, format: String) -> Result<()> {
+    let results = session_store()?.search(&query, limit)?;
+
+    match format.as_str() {
+        "json" => {
+            println!("{}", serde_json::to_string(&results)?);
+        }
+        _ => {
+            if results.is_empty() {
+                println!("No messages found matching '{}'", query);
+                return Ok(());
+            }
+            for SessionSearchResult {
+                session_id,
+                description,
+                message_index,
+                role,
+                snippet,
+            } in results
+            {
+                let description = if description.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    description
+                };
+                println!(
+                    "{} - {} - message {}",
+                    session_id, description, message_index
+                );
+                println!("    {:?}: {}", role, snippet);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_session_export(id: String, output: Option<PathBuf>) -> Result<()> {
+    let store = session_store()?;
+    match output {
+        Some(path) => {
+            let mut file = io::BufWriter::new(
+                fs::File::create(&path)
+                    .with_context(|| format!("Failed to create '{}'", path.display()))?,
+            );
+            store.export_jsonl(&id, &mut file)?;
+            file.flush()?;
+            eprintln!("Session `{}` exported to {}", id, path.display());
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            store.export_jsonl(&id, &mut stdout)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_session_import(path: PathBuf, id: Option<String>) -> Result<()> {
+    let id = match id {
+        Some(id) => id,
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Cannot derive a session id from '{}'", path.display())
+            })?,
+    };
+
+    let file =
+        fs::File::open(&path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+    session_store()?.import_jsonl(&id, &mut BufReader::new(file))?;
+    println!("Session `{}` imported.", id);
+    Ok(())
+}
+
+/// Fork a session, returning the id of the new session
+///
+/// When `edit` is set the message at `at` must be a user message, which the caller
+/// replaces by sending the edited text on the new branch.
+pub fn handle_session_fork(
+    id: String,
+    at: Option<usize>,
+    edit: bool,
+    name: Option<String>,
+) -> Result<String> {
+    let store = session_store()?;
+    let messages = store.read_messages(&id)?;
+    let message_index = at.unwrap_or(messages.len());
+
+    if edit {
+        let is_user_text = messages.get(message_index).is_some_and(|message| {
+            message.role == Role::User
+                && matches!(message.content.first(), Some(MessageContent::Text(_)))
+        });
+        if !is_user_text {
+            return Err(anyhow::anyhow!(
+                "Message {} of session '{}' is not a user message that can be edited",
+                message_index,
+                id
+            ));
+        }
+    }
+
+    let new_id = name.unwrap_or_else(generate_session_id);
+    store.fork(&id, &new_id, message_index)?;
+    println!(
+        "Session `{}` forked from `{}` at message {}.",
+        new_id, id, message_index
+    );
+    Ok(new_id)
+}
@@ -1,7 +1,8 @@
 use console::style;
 use goose::agents::extension::ExtensionError;
-use goose::agents::Agent;
+use goose::agents::{Agent, Budget};
 use goose::config::{Config, ExtensionConfig, ExtensionConfigManager};
+use goose::model::ReasoningEffort;
 use goose::providers::create;
 use goose::session;
 use goose::session::Identifier;
@@ -33,6 +34,12 @@ pub struct SessionBuilderConfig {
     pub additional_system_prompt: Option<String>,
     /// Enable debug printing
     pub debug: bool,
+    /// Limits for each run of the agent, on top of the ones in the config
+    pub budget: Budget,
+    /// How much the model should reason, overriding GOOSE_REASONING_EFFORT
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Thinking budget in tokens, overriding GOOSE_THINKING_BUDGET
+    pub thinking_budget: Option<u32>,
 }
 
 pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
@@ -46,7 +53,13 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
     let model: String = config
         .get_param("GOOSE_MODEL")
         .expect("No model configured. Run 'goose configure' first");
-    let model_config = goose::model::ModelConfig::new(model.clone());
+    let mut model_config = goose::model::ModelConfig::new(model.clone());
+    if session_config.reasoning_effort.is_some() {
+        model_config = model_config.with_reasoning_effort(session_config.reasoning_effort);
+    }
+    if session_config.thinking_budget.is_some() {
+        model_config = model_config.with_thinking_budget(session_config.thinking_budget);
+    }
 
     // Create the agent
     let agent: Agent = Agent::new();
@@ -175,6 +188,8 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         session.agent.extend_system_prompt(additional_prompt).await;
     }
 
+    session.agent.set_budget(session_config.budget).await;
+
     // Only override system prompt if a system override exists
     let system_prompt_file: Option<String> = config.get_param("GOOSE_SYSTEM_PROMPT_FILE_PATH").ok();
     if let Some(ref path) = system_prompt_file {
@@ -77,12 +77,20 @@ impl GooseCompleter {
 
     /// Complete flags for the /mode command
     fn complete_mode_flags(&self, line: &str) -> Result<(usize, Vec<Pair>)> {
-        let modes = ["auto", "approve", "smart_approve", "chat"];
+        self.complete_command_options(line, "/mode", &["auto", "approve", "smart_approve", "chat"])
+    }
 
+    /// Complete the argument of a command that takes one of a fixed set of options
+    fn complete_command_options(
+        &self,
+        line: &str,
+        command: &str,
+        modes: &[&str],
+    ) -> Result<(usize, Vec<Pair>)> {
         let parts: Vec<&str> = line.split_whitespace().collect();
 
-        // If we're just after "/mode" with a space, show all options
-        if line == "/mode " {
+        // If we're just after the command with a space, show all options
+        if line == format!("{} ", command) {
             return Ok((
                 line.len(),
                 modes
@@ -130,6 +138,7 @@ impl GooseCompleter {
             "/prompt",
             "/mode",
             "/recipe",
+            "/think",
         ];
 
         // Find commands that match the prefix
@@ -338,6 +347,14 @@ impl Completer for GooseCompleter {
             if line.starts_with("/mode") {
                 return self.complete_mode_flags(line);
             }
+
+            if line.starts_with("/think") {
+                return self.complete_command_options(
+                    line,
+                    "/think",
+                    &["off", "low", "medium", "high"],
+                );
+            }
         }
 
         // Default: no completions
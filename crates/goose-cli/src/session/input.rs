@@ -19,6 +19,7 @@ pub enum InputResult {
     EndPlan,
     Recipe(Option<String>),
     Clear,
+    Think(Option<String>),
 }
 
 #[derive(Debug)]
@@ -92,6 +93,7 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
     const CMD_PLAN: &str = "/plan";
     const CMD_ENDPLAN: &str = "/endplan";
     const CMD_RECIPE: &str = "/recipe";
+    const CMD_THINK: &str = "/think";
 
     match input {
         "/exit" | "/quit" => Some(InputResult::Exit),
@@ -135,6 +137,10 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
         s if s.starts_with(CMD_PLAN) => parse_plan_command(s[CMD_PLAN.len()..].trim().to_string()),
         s if s == CMD_ENDPLAN => Some(InputResult::EndPlan),
         s if s.starts_with(CMD_RECIPE) => parse_recipe_command(s),
+        s if s == CMD_THINK => Some(InputResult::Think(None)),
+        s if s.starts_with("/think ") => Some(InputResult::Think(Some(
+            s[CMD_THINK.len()..].trim().to_string(),
+        ))),
         _ => None,
     }
 }
@@ -244,6 +250,7 @@ fn print_help() {
 /endplan - Exit plan mode and return to 'normal' goose mode.
 /recipe [filepath] - Generate a recipe from the current conversation and save it to the specified filepath (must end with .yaml).
                        If no filepath is provided, it will be saved to ./recipe.yaml.
+/think [off|low|medium|high] - Toggle extended thinking, or set how much the model should reason
 /? or /help - Display this help message
 
 Navigation:
@@ -489,4 +496,21 @@ mod tests {
         let result = handle_slash_command("/recipe /path/to/file.txt");
         assert!(matches!(result, Some(InputResult::Retry)));
     }
+
+    #[test]
+    fn test_think_command() {
+        assert!(matches!(
+            handle_slash_command("/think"),
+            Some(InputResult::Think(None))
+        ));
+
+        if let Some(InputResult::Think(effort)) = handle_slash_command("/think  high ") {
+            assert_eq!(effort, Some("high".to_string()));
+        } else {
+            panic!("Expected Think");
+        }
+
+        // Not a command
+        assert!(handle_slash_command("/thinking").is_none());
+    }
 }
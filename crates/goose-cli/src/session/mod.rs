@@ -18,9 +18,11 @@ use completion::GooseCompleter;
 use etcetera::choose_app_strategy;
 use etcetera::AppStrategy;
 use goose::agents::extension::{Envs, ExtensionConfig};
-use goose::agents::{Agent, SessionConfig};
+use goose::agents::{Agent, AgentEvent, SessionConfig, SAMPLING_CONFIRMATION_PREFIX};
 use goose::config::Config;
 use goose::message::{Message, MessageContent};
+use goose::model::ReasoningEffort;
+use goose::providers::utils::{detect_attachment_paths, load_attachment_file};
 use goose::session;
 use input::InputResult;
 use mcp_core::handler::ToolError;
@@ -33,6 +35,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio;
+use tokio_util::sync::CancellationToken;
 
 pub enum RunMode {
     Normal,
@@ -276,6 +279,18 @@ impl Session {
         Ok(None)
     }
 
+    /// A user message with any PDFs and audio files it references by absolute path attached
+    fn user_message(text: &str) -> Message {
+        let mut message = Message::user().with_text(text);
+        for path in detect_attachment_paths(text) {
+            match load_attachment_file(path) {
+                Ok(content) => message = message.with_content(content),
+                Err(e) => eprintln!("Warning: Could not attach {}: {}", path, e),
+            }
+        }
+        message
+    }
+
     pub async fn get_prompt(&mut self, name: &str, arguments: Value) -> Result<Vec<PromptMessage>> {
         let result = self.agent.get_prompt(name, arguments).await?;
         Ok(result.messages)
@@ -283,7 +298,7 @@ impl Session {
 
     /// Process a single message and get the response
     async fn process_message(&mut self, message: String) -> Result<()> {
-        self.messages.push(Message::user().with_text(&message));
+        self.messages.push(Self::user_message(&message));
         // Get the provider from the agent for description generation
         let provider = self.agent.provider().await?;
 
@@ -354,7 +369,7 @@ impl Session {
                         RunMode::Normal => {
                             save_history(&mut editor);
 
-                            self.messages.push(Message::user().with_text(&content));
+                            self.messages.push(Self::user_message(&content));
 
                             // Get the provider from the agent for description generation
                             let provider = self.agent.provider().await?;
@@ -486,6 +501,18 @@ impl Session {
                     save_history(&mut editor);
                     self.handle_prompt_command(opts).await?;
                 }
+                InputResult::Think(effort) => {
+                    save_history(&mut editor);
+
+                    match self.set_reasoning_effort(effort.as_deref()).await {
+                        Ok(effort) => output::goose_mode_message(&format!(
+                            "Reasoning effort set to '{}'",
+                            effort
+                        )),
+                        Err(e) => output::render_error(&e.to_string()),
+                    }
+                    continue;
+                }
                 InputResult::Recipe(filepath_opt) => {
                     println!("{}", console::style("Generating Recipe").green());
 
@@ -594,13 +621,58 @@ impl Session {
         Ok(())
     }
 
+    /// Set how much the model reasons, toggling extended thinking when no effort is given
+    async fn set_reasoning_effort(&mut self, effort: Option<&str>) -> Result<ReasoningEffort> {
+        let model_config = self.agent.provider().await?.get_model_config();
+        let effort = match effort {
+            Some(effort) => effort.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+            None => {
+                let thinking = match model_config.reasoning_effort {
+                    Some(effort) => effort != ReasoningEffort::Off,
+                    None => model_config.thinking_budget().is_some(),
+                };
+                if thinking {
+                    ReasoningEffort::Off
+                } else {
+                    ReasoningEffort::Medium
+                }
+            }
+        };
+
+        let provider_name: String = Config::global().get_param("GOOSE_PROVIDER")?;
+        let provider = goose::providers::create(
+            &provider_name,
+            model_config.with_reasoning_effort(Some(effort)),
+        )?;
+        self.agent.update_provider(provider).await?;
+        Ok(effort)
+    }
+
     /// Process a single message and exit
     pub async fn headless(&mut self, message: String) -> Result<()> {
         self.process_message(message).await
     }
 
+    /// Ask the model for the outcome of the session as JSON matching the schema
+    pub async fn structured_result(&self, schema: &Value) -> Result<Value> {
+        let provider = self.agent.provider().await?;
+        let mut messages = self.messages.clone();
+        messages.push(Message::user().with_text(
+            "Report the final result of the work above. Respond only with the result, matching the requested schema.",
+        ));
+        let (result, _usage) = provider
+            .complete_structured(
+                "You summarize the outcome of a completed task as structured data.",
+                &messages,
+                schema,
+            )
+            .await?;
+        Ok(result)
+    }
+
     async fn process_agent_response(&mut self, interactive: bool) -> Result<()> {
         let session_id = session::Identifier::Path(self.session_file.clone());
+        let cancel_token = CancellationToken::new();
         let mut stream = self
             .agent
             .reply(
@@ -610,6 +682,7 @@ impl Session {
                     working_dir: std::env::current_dir()
                         .expect("failed to get current session working directory"),
                 }),
+                Some(cancel_token.clone()),
             )
             .await?;
 
@@ -618,20 +691,43 @@ impl Session {
             tokio::select! {
                 result = stream.next() => {
                     match result {
-                        Some(Ok(message)) => {
+                        Some(Ok(AgentEvent::MessageDelta(delta))) => {
+                            if interactive {output::hide_thinking()};
+                            output::render_message_delta(&delta);
+                        }
+                        Some(Ok(AgentEvent::HistoryReplaced(messages))) => {
+                            self.messages = messages;
+                            session::persist_messages(&self.session_file, &self.messages, None).await?;
+
+                            if interactive {output::hide_thinking()};
+                            let msg = format!("Context compacted\n{}\nGoose shortened the conversation to stay within the model's context limit.", "-".repeat(50));
+                            output::render_text(&msg, Some(Color::Yellow), true);
+                            if interactive {output::show_thinking()};
+                        }
+                        Some(Ok(AgentEvent::Message(message))) => {
                             // If it's a confirmation request, get approval but otherwise do not render/persist
                             if let Some(MessageContent::ToolConfirmationRequest(confirmation)) = message.content.first() {
                                 output::hide_thinking();
 
-                                // Format the confirmation prompt
-                                let prompt = "Goose would like to call the above tool, do you allow?".to_string();
-
-                                // Get confirmation from user
-                                let permission = cliclack::select(prompt)
-                                    .item(Permission::AllowOnce, "Allow", "Allow the tool call once")
-                                    .item(Permission::AlwaysAllow, "Always Allow", "Always allow the tool call")
-                                    .item(Permission::DenyOnce, "Deny", "Deny the tool call")
-                                    .interact()?;
+                                // Extensions asking to use the model are confirmed much like tool calls
+                                let permission = if confirmation.id.starts_with(SAMPLING_CONFIRMATION_PREFIX) {
+                                    let prompt = format!("The {} extension would like to ask the model for a completion, do you allow?", confirmation.tool_name);
+                                    cliclack::select(prompt)
+                                        .item(Permission::AllowOnce, "Allow", "Allow this request once")
+                                        .item(Permission::AlwaysAllow, "Always Allow", "Allow the extension's requests for the rest of the session")
+                                        .item(Permission::DenyOnce, "Deny", "Deny the request")
+                                        .interact()?
+                                } else {
+                                    // Format the confirmation prompt
+                                    let prompt = "Goose would like to call the above tool, do you allow?".to_string();
+
+                                    // Get confirmation from user
+                                    cliclack::select(prompt)
+                                        .item(Permission::AllowOnce, "Allow", "Allow the tool call once")
+                                        .item(Permission::AlwaysAllow, "Always Allow", "Always allow the tool call")
+                                        .item(Permission::DenyOnce, "Deny", "Deny the tool call")
+                                        .interact()?
+                                };
                                 self.agent.handle_confirmation(confirmation.id.clone(), PermissionConfirmation {
                                     principal_type: PrincipalType::Tool,
                                     permission,
@@ -682,8 +778,39 @@ impl Session {
                                             working_dir: std::env::current_dir()
                                                 .expect("failed to get current session working directory"),
                                         }),
+                                        Some(cancel_token.clone()),
                                     )
                                     .await?;
+                            } else if let Some(MessageContent::BudgetExceeded(exceeded)) = message.content.first() {
+                                if interactive {output::hide_thinking()};
+                                output::render_text(&exceeded.msg, Some(Color::Yellow), true);
+
+                                // Unattended runs stop here, interactive sessions can grant another run
+                                let keep_going = interactive
+                                    && cliclack::confirm("Do you want Goose to continue?")
+                                        .initial_value(false)
+                                        .interact()?;
+                                if !keep_going {
+                                    break;
+                                }
+                                self.agent.extend_budget(exceeded).await;
+
+                                output::show_thinking();
+                                stream = self
+                                    .agent
+                                    .reply(
+                                        &self.messages,
+                                        Some(SessionConfig {
+                                            id: session_id.clone(),
+                                            working_dir: std::env::current_dir()
+                                                .expect("failed to get current session working directory"),
+                                        }),
+                                        Some(cancel_token.clone()),
+                                    )
+                                    .await?;
+                            } else if let Some(MessageContent::ToolProgress(progress)) = message.content.first() {
+                                // Shown in the spinner while the tool runs, never kept
+                                if interactive {output::show_tool_progress(progress)};
                             }
                             // otherwise we have a model/tool to render
                             else {
@@ -711,10 +838,26 @@ impl Session {
                             );
                             break;
                         }
-                        None => break,
+                        None => {
+                            if cancel_token.is_cancelled() {
+                                drop(stream);
+                                if let Err(e) = self.handle_interrupted_messages(true).await {
+                                    eprintln!("Error handling interruption: {}", e);
+                                }
+                            }
+                            break;
+                        }
                     }
                 }
                 _ = tokio::signal::ctrl_c() => {
+                    if !cancel_token.is_cancelled() {
+                        // Let the agent wind down the turn so cancelled tool calls still get a
+                        // response in the history, which we persist as the stream drains
+                        cancel_token.cancel();
+                        continue;
+                    }
+
+                    // A second interrupt stops waiting for the agent altogether
                     drop(stream);
                     if let Err(e) = self.handle_interrupted_messages(true).await {
                         eprintln!("Error handling interruption: {}", e);
@@ -987,7 +1130,7 @@ impl Session {
 
 fn get_reasoner() -> Result<Arc<dyn Provider>, anyhow::Error> {
     use goose::model::ModelConfig;
-    use goose::providers::create;
+    use goose::providers::create_with_retries;
 
     let (reasoner_provider, reasoner_model) = match (
         std::env::var("GOOSE_PLANNER_PROVIDER"),
@@ -1011,7 +1154,7 @@ fn get_reasoner() -> Result<Arc<dyn Provider>, anyhow::Error> {
     };
 
     let model_config = ModelConfig::new(reasoner_model);
-    let reasoner = create(&reasoner_provider, model_config)?;
+    let reasoner = create_with_retries(&reasoner_provider, model_config)?;
 
     Ok(reasoner)
 }
@@ -1,12 +1,15 @@
 use bat::WrappingMode;
 use console::{style, Color};
 use goose::config::Config;
-use goose::message::{Message, MessageContent, ToolRequest, ToolResponse};
+use goose::message::{
+    Message, MessageContent, MessageDelta, ToolProgress, ToolRequest, ToolResponse,
+};
 use mcp_core::prompt::PromptArgument;
 use mcp_core::tool::ToolCall;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 
 // Re-export theme for use in main
@@ -90,6 +93,12 @@ impl ThinkingIndicator {
             spinner.stop("");
         }
     }
+
+    pub fn set_message(&mut self, message: &str) {
+        if let Some(spinner) = &self.spinner {
+            spinner.set_message(message);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -113,17 +122,97 @@ pub fn hide_thinking() {
     THINKING.with(|t| t.borrow_mut().hide());
 }
 
+/// Show how far along a running tool is in place of the thinking message
+pub fn show_tool_progress(progress: &ToolProgress) {
+    let message = format_tool_progress(progress);
+    THINKING.with(|t| t.borrow_mut().set_message(&message));
+}
+
+// Longest tool progress message shown, so the spinner stays on one line
+const MAX_PROGRESS_MESSAGE_CHARS: usize = 80;
+
+fn format_tool_progress(progress: &ToolProgress) -> String {
+    let message = progress.message.as_deref().map(|message| {
+        if message.chars().count() > MAX_PROGRESS_MESSAGE_CHARS {
+            let shortened: String = message.chars().take(MAX_PROGRESS_MESSAGE_CHARS).collect();
+            format!("{}...", shortened)
+        } else {
+            message.to_string()
+        }
+    });
+    let percent = progress
+        .total
+        .filter(|total| *total > 0.0)
+        .map(|total| format!("{:.0}%", progress.progress / total * 100.0));
+    match (message, percent) {
+        (Some(message), Some(percent)) => format!("{} ({})", message, percent),
+        (Some(message), None) => message,
+        (None, Some(percent)) => percent,
+        (None, None) => format!("{}...", progress.progress),
+    }
+}
+
+// Which parts of the message currently being generated were already printed as deltas
+#[derive(Default)]
+struct StreamedOutput {
+    text: bool,
+    thinking: bool,
+}
+
+thread_local! {
+    static STREAMED: RefCell<StreamedOutput> = RefCell::new(StreamedOutput::default());
+}
+
+/// Print a partial update to the assistant message as it arrives
+///
+/// Streamed text is printed as is, the complete message that follows then skips
+/// the parts that were already shown.
+pub fn render_message_delta(delta: &MessageDelta) {
+    match delta {
+        MessageDelta::Text { text } => {
+            STREAMED.with(|s| s.borrow_mut().text = true);
+            print!("{}", text);
+        }
+        MessageDelta::Thinking { thinking } => {
+            if std::env::var("GOOSE_CLI_SHOW_THINKING").is_ok() {
+                let first =
+                    STREAMED.with(|s| !std::mem::replace(&mut s.borrow_mut().thinking, true));
+                if first {
+                    println!("\n{}", style("Thinking:").dim().italic());
+                }
+                print!("{}", style(thinking).dim());
+            }
+        }
+        // Tool calls are rendered once their arguments are complete
+        MessageDelta::ToolCall { .. } => return,
+    }
+    let _ = std::io::stdout().flush();
+}
+
 pub fn render_message(message: &Message, debug: bool) {
     let theme = get_theme();
+    let streamed = STREAMED.with(|s| std::mem::take(&mut *s.borrow_mut()));
+    if streamed.text || streamed.thinking {
+        println!();
+    }
 
     for content in &message.content {
         match content {
+            MessageContent::Text(_) if streamed.text => {}
+            MessageContent::Thinking(_) if streamed.thinking => {}
             MessageContent::Text(text) => print_markdown(&text.text, theme),
             MessageContent::ToolRequest(req) => render_tool_request(req, theme, debug),
             MessageContent::ToolResponse(resp) => render_tool_response(resp, theme, debug),
             MessageContent::Image(image) => {
                 println!("Image: [data: {}, type: {}]", image.data, image.mime_type);
             }
+            MessageContent::Document(document) => {
+                let name = document.name.as_deref().unwrap_or("unnamed");
+                println!("Document: [name: {}, type: {}]", name, document.mime_type);
+            }
+            MessageContent::Audio(audio) => {
+                println!("Audio: [type: {}]", audio.mime_type);
+            }
             MessageContent::Thinking(thinking) => {
                 if std::env::var("GOOSE_CLI_SHOW_THINKING").is_ok() {
                     println!("\n{}", style("Thinking:").dim().italic());
@@ -565,6 +654,29 @@ mod tests {
     use super::*;
     use std::env;
 
+    #[test]
+    fn test_format_tool_progress() {
+        let mut progress = ToolProgress {
+            id: "1".to_string(),
+            progress: 3.0,
+            total: Some(4.0),
+            message: Some("Compiling goose".to_string()),
+        };
+        assert_eq!(format_tool_progress(&progress), "Compiling goose (75%)");
+
+        progress.total = None;
+        assert_eq!(format_tool_progress(&progress), "Compiling goose");
+
+        progress.message = Some("x".repeat(100));
+        assert_eq!(
+            format_tool_progress(&progress),
+            format!("{}...", "x".repeat(80))
+        );
+
+        progress.message = None;
+        assert_eq!(format_tool_progress(&progress), "3...");
+    }
+
     #[test]
     fn test_short_paths_unchanged() {
         assert_eq!(shorten_path("/usr/bin", false), "/usr/bin");
@@ -3,7 +3,7 @@ use std::ptr;
 use std::sync::Arc;
 
 use futures::StreamExt;
-use goose::agents::Agent;
+use goose::agents::{Agent, AgentEvent};
 use goose::message::Message;
 use goose::model::ModelConfig;
 use goose::providers::databricks::DatabricksProvider;
@@ -247,7 +247,7 @@ pub unsafe extern "C" fn goose_agent_send_message(
 
     // Block on the async call using our global runtime
     let response = get_runtime().block_on(async {
-        let mut stream = match agent.reply(&messages, None).await {
+        let mut stream = match agent.reply(&messages, None, None).await {
             Ok(stream) => stream,
             Err(e) => return format!("Error getting reply from agent: {}", e),
         };
@@ -256,7 +256,13 @@ pub unsafe extern "C" fn goose_agent_send_message(
 
         while let Some(message_result) = stream.next().await {
             match message_result {
-                Ok(message) => {
+                // Partial deltas are only useful for live display, the full message follows
+                Ok(AgentEvent::MessageDelta(_)) => {}
+                // Each call starts from a single message, so there is no history to replace
+                Ok(AgentEvent::HistoryReplaced(_)) => {}
+                // Progress of running tools is only useful for live display as well
+                Ok(AgentEvent::Message(message)) if message.is_tool_progress() => {}
+                Ok(AgentEvent::Message(message)) => {
                     // Get text or serialize to JSON
                     // Note: Message doesn't have as_text method, we'll serialize to JSON
                     if let Ok(json) = serde_json::to_string(&message) {
@@ -12,7 +12,9 @@ use std::{
     io::Cursor,
     path::{Path, PathBuf},
     pin::Pin,
+    time::{Duration, Instant},
 };
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command;
 use url::Url;
 
@@ -29,7 +31,7 @@ use mcp_core::{
     tool::ToolAnnotations,
 };
 use mcp_server::router::CapabilitiesBuilder;
-use mcp_server::Router;
+use mcp_server::{Progress, Router};
 
 use mcp_core::role::Role;
 
@@ -90,6 +92,41 @@ pub fn load_prompt_files() -> HashMap<String, Prompt> {
     prompts
 }
 
+/// The shortest time between two progress reports of the shell tool, so chatty commands do
+/// not flood the client
+const SHELL_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Read a command's output to its end, reporting how many lines came so far along with the
+/// latest one
+async fn read_output(
+    output: impl AsyncRead + Unpin,
+    progress: &Progress,
+) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(output);
+    let mut bytes = Vec::new();
+    let mut lines = 0;
+    let mut last_report: Option<Instant> = None;
+    loop {
+        let start = bytes.len();
+        if reader.read_until(b'\n', &mut bytes).await? == 0 {
+            break;
+        }
+        lines += 1;
+
+        if !progress.is_requested()
+            || last_report.is_some_and(|at| at.elapsed() < SHELL_PROGRESS_INTERVAL)
+        {
+            continue;
+        }
+        let line = String::from_utf8_lossy(&bytes[start..]).trim().to_string();
+        if !line.is_empty() {
+            progress.report(lines as f64, None, Some(line));
+            last_report = Some(Instant::now());
+        }
+    }
+    Ok(bytes)
+}
+
 pub struct DeveloperRouter {
     tools: Vec<Tool>,
     prompts: Arc<HashMap<String, Prompt>>,
@@ -456,7 +493,7 @@ impl DeveloperRouter {
     }
 
     // Shell command execution with platform-specific handling
-    async fn bash(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+    async fn bash(&self, params: Value, progress: Progress) -> Result<Vec<Content>, ToolError> {
         let command =
             params
                 .get("command")
@@ -491,7 +528,7 @@ impl DeveloperRouter {
         let cmd_with_redirect = format_command_for_platform(command);
 
         // Execute the command using platform-specific shell
-        let child = Command::new(&shell_config.executable)
+        let mut child = Command::new(&shell_config.executable)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
@@ -501,13 +538,19 @@ impl DeveloperRouter {
             .spawn()
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
-        // Wait for the command to complete and get output
-        let output = child
+        // Read the output as it comes, so the client can follow long running commands
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stdout = read_output(stdout, &progress)
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        // Wait for the command to complete
+        child
             .wait_with_output()
             .await
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
-        let stdout_str = String::from_utf8_lossy(&output.stdout);
+        let stdout_str = String::from_utf8_lossy(&stdout);
         let output_str = stdout_str;
 
         // Check the character count of the output
@@ -1048,12 +1091,21 @@ impl Router for DeveloperRouter {
         &self,
         tool_name: &str,
         arguments: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        self.call_tool_with_progress(tool_name, arguments, Progress::default())
+    }
+
+    fn call_tool_with_progress(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        progress: Progress,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
         let this = self.clone();
         let tool_name = tool_name.to_string();
         Box::pin(async move {
             match tool_name.as_str() {
-                "shell" => this.bash(arguments).await,
+                "shell" => this.bash(arguments, progress).await,
                 "text_editor" => this.text_editor(arguments).await,
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
@@ -1211,6 +1263,15 @@ mod tests {
         temp_dir.close().unwrap();
     }
 
+    #[tokio::test]
+    async fn test_read_output_keeps_all_output() {
+        let output = b"Compiling goose\n\nFinished in 2m\nno trailing newline";
+        let read = read_output(&output[..], &Progress::default())
+            .await
+            .unwrap();
+        assert_eq!(read, output);
+    }
+
     #[tokio::test]
     #[serial]
     #[cfg(windows)]
@@ -29,6 +29,11 @@ pub async fn run(name: &str) -> Result<()> {
     let server = Server::new(router.unwrap_or_else(|| panic!("Unknown server requested {}", name)));
     let transport = ByteTransport::new(stdin(), stdout());
 
+    // On Ctrl-C the requests already running are finished before exiting
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
     tracing::info!("Server initialized and ready to handle requests");
-    Ok(server.run(transport).await?)
+    Ok(server.run_until(transport, shutdown).await?)
 }
@@ -1,11 +1,12 @@
 use goose::agents::extension::Envs;
 use goose::agents::extension::ToolInfo;
-use goose::agents::ExtensionConfig;
+use goose::agents::{Budget, ExtensionConfig};
 use goose::config::permission::PermissionLevel;
 use goose::config::ExtensionEntry;
 use goose::message::{
-    ContextLengthExceeded, FrontendToolRequest, Message, MessageContent, RedactedThinkingContent,
-    ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
+    AudioContent, BudgetExceeded, BudgetLimit, ContextLengthExceeded, DocumentContent,
+    FrontendToolRequest, Message, MessageContent, RedactedThinkingContent, ThinkingContent,
+    ToolConfirmationRequest, ToolProgress, ToolRequest, ToolResponse,
 };
 use goose::permission::permission_confirmation::PrincipalType;
 use goose::providers::base::{ConfigKey, ModelInfo, ProviderMetadata};
@@ -34,9 +35,16 @@ use utoipa::OpenApi;
         super::routes::config_management::providers,
         super::routes::config_management::upsert_permissions,
         super::routes::agent::get_tools,
+        super::routes::agent::create_agent,
+        super::routes::agent::list_agents,
+        super::routes::agent::get_agent,
+        super::routes::agent::destroy_agent,
         super::routes::reply::confirm_permission,
+        super::routes::reply::cancel_reply,
         super::routes::context::manage_context,
         super::routes::session::list_sessions,
+        super::routes::session::search_sessions,
+        super::routes::session::fork_session,
         super::routes::session::get_session_history
     ),
     components(schemas(
@@ -49,16 +57,26 @@ use utoipa::OpenApi;
         super::routes::config_management::ExtensionQuery,
         super::routes::config_management::ToolPermission,
         super::routes::config_management::UpsertPermissionsQuery,
+        super::routes::agent::CreateAgentRequest,
+        super::routes::agent::AgentInfo,
         super::routes::reply::PermissionConfirmationRequest,
+        super::routes::reply::CancelReplyRequest,
         super::routes::context::ContextManageRequest,
         super::routes::context::ContextManageResponse,
         super::routes::session::SessionListResponse,
         super::routes::session::SessionHistoryResponse,
+        super::routes::session::SessionSearchResponse,
+        super::routes::session::ForkSessionRequest,
+        goose::session::SessionParent,
+        goose::session::ModelUsage,
+        goose::session::SessionSearchResult,
         Message,
         MessageContent,
         Content,
         EmbeddedResource,
         ImageContent,
+        DocumentContent,
+        AudioContent,
         Annotations,
         TextContent,
         ToolResponse,
@@ -70,6 +88,10 @@ use utoipa::OpenApi;
         FrontendToolRequest,
         ResourceContents,
         ContextLengthExceeded,
+        BudgetExceeded,
+        BudgetLimit,
+        ToolProgress,
+        Budget,
         Role,
         ProviderMetadata,
         ExtensionEntry,
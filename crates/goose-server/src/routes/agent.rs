@@ -1,15 +1,18 @@
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     routing::{get, post},
     Json, Router,
 };
+use goose::agents::{Agent, Budget};
 use goose::config::Config;
 use goose::config::PermissionManager;
 use goose::model::ModelConfig;
-use goose::providers::create;
+use goose::providers::base::ModelInfo;
+use goose::providers::{create, models};
+use goose::session;
 use goose::{
     agents::{extension::ToolInfo, extension_manager::get_parameter_names},
     config::permission::PermissionLevel,
@@ -17,6 +20,11 @@ use goose::{
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// How long listing the models of a provider may take before falling back to its known models
+const LIST_MODELS_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Serialize)]
 struct VersionsResponse {
@@ -47,6 +55,9 @@ struct ProviderDetails {
     name: String,
     description: String,
     models: Vec<String>,
+    /// Context length and capabilities of the models, when the provider can list them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    model_info: Vec<ModelInfo>,
     required_keys: Vec<String>,
 }
 
@@ -67,6 +78,41 @@ pub struct GetToolsQuery {
     extension_name: Option<String>,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct CreateAgentRequest {
+    /// Session the agent belongs to, a new id is generated when omitted
+    session_id: Option<String>,
+    /// Provider to start the agent with, it can also be set later through /agent/update_provider
+    provider: Option<String>,
+    /// Model for the provider, defaults to GOOSE_MODEL
+    model: Option<String>,
+    /// Limits for each reply of the agent, on top of the GOOSE_MAX_* config values
+    budget: Option<Budget>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AgentInfo {
+    session_id: String,
+    /// Model used by the agent, if a provider has been configured
+    model: Option<String>,
+    extensions: Vec<String>,
+}
+
+impl AgentInfo {
+    async fn new(session_id: String, agent: &Agent) -> Self {
+        let model = agent
+            .provider()
+            .await
+            .ok()
+            .map(|provider| provider.get_model_config().model_name);
+        Self {
+            session_id,
+            model,
+            extensions: agent.list_extensions().await,
+        }
+    }
+}
+
 async fn get_versions() -> Json<VersionsResponse> {
     let versions = ["goose".to_string()];
     let default_version = "goose".to_string();
@@ -85,9 +131,9 @@ async fn extend_prompt(
     verify_secret_key(&headers, &state)?;
 
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
     agent.extend_system_prompt(payload.extension.clone()).await;
     Ok(Json(ExtendPromptResponse { success: true }))
 }
@@ -98,23 +144,52 @@ async fn list_providers() -> Json<Vec<ProviderList>> {
     let providers: HashMap<String, ProviderFile> =
         serde_json::from_str(contents).expect("Failed to parse providers_and_keys.json");
 
-    let response: Vec<ProviderList> = providers
-        .into_iter()
-        .map(|(id, provider)| ProviderList {
-            id,
-            details: ProviderDetails {
-                name: provider.name,
-                description: provider.description,
-                models: provider.models,
-                required_keys: provider.required_keys,
-            },
-        })
-        .collect();
+    // Offer the models the provider reports where it can list them, and the known models otherwise
+    let response: Vec<ProviderList> =
+        futures::future::join_all(providers.into_iter().map(|(id, provider)| async move {
+            let model_info = live_models(&id).await.unwrap_or_default();
+            let models = if model_info.is_empty() {
+                provider.models
+            } else {
+                model_info.iter().map(|model| model.name.clone()).collect()
+            };
+            ProviderList {
+                id,
+                details: ProviderDetails {
+                    name: provider.name,
+                    description: provider.description,
+                    models,
+                    model_info,
+                    required_keys: provider.required_keys,
+                },
+            }
+        }))
+        .await;
 
     // Return the response as JSON.
     Json(response)
 }
 
+/// The models listed by a configured provider, None if it isn't configured or can't list them
+async fn live_models(provider_name: &str) -> Option<Vec<ModelInfo>> {
+    match tokio::time::timeout(
+        LIST_MODELS_TIMEOUT,
+        models::list_models(provider_name, false),
+    )
+    .await
+    {
+        Ok(Ok(models)) => models,
+        Ok(Err(e)) => {
+            tracing::debug!("Could not list the models of {}: {}", provider_name, e);
+            None
+        }
+        Err(_) => {
+            tracing::debug!("Timed out listing the models of {}", provider_name);
+            None
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/agent/tools",
@@ -125,6 +200,7 @@ async fn list_providers() -> Json<Vec<ProviderList>> {
         (status = 200, description = "Tools retrieved successfully", body = Vec<ToolInfo>),
         (status = 401, description = "Unauthorized - invalid secret key"),
         (status = 424, description = "Agent not initialized"),
+        (status = 404, description = "The session in X-Session-Id has no agent"),
         (status = 500, description = "Internal server error")
     )
 )]
@@ -138,9 +214,9 @@ async fn get_tools(
     let config = Config::global();
     let goose_mode = config.get_param("GOOSE_MODE").unwrap_or("auto".to_string());
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
     let permission_manager = PermissionManager::default();
 
     let mut tools: Vec<ToolInfo> = agent
@@ -178,6 +254,7 @@ async fn get_tools(
     path = "/agent/update_provider",
     responses(
         (status = 200, description = "Update provider completed", body = String),
+        (status = 404, description = "The session in X-Session-Id has no agent"),
         (status = 500, description = "Internal server error")
     )
 )]
@@ -197,9 +274,9 @@ async fn update_agent_provider(
     }
 
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
 
     let config = Config::global();
     let model = payload.model.unwrap_or_else(|| {
@@ -217,6 +294,140 @@ async fn update_agent_provider(
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    post,
+    path = "/agents",
+    request_body = CreateAgentRequest,
+    responses(
+        (status = 200, description = "Agent created for the session", body = AgentInfo),
+        (status = 400, description = "Invalid provider or model"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 409, description = "The session already has an agent")
+    )
+)]
+async fn create_agent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateAgentRequest>,
+) -> Result<Json<AgentInfo>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let session_id = payload
+        .session_id
+        .unwrap_or_else(session::generate_session_id);
+    if state.find_session_agent(&session_id).await.is_some() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let agent = Agent::new();
+    if let Some(provider) = payload.provider {
+        let model = payload
+            .model
+            .or_else(|| Config::global().get_param("GOOSE_MODEL").ok())
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let provider = create(&provider, ModelConfig::new(model)).map_err(|e| {
+            tracing::error!(
+                "Failed to create provider for session {}: {}",
+                session_id,
+                e
+            );
+            StatusCode::BAD_REQUEST
+        })?;
+        agent
+            .update_provider(provider)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    if let Some(budget) = payload.budget {
+        agent.set_budget(budget).await;
+    }
+
+    let agent = Arc::new(agent);
+    state
+        .add_session_agent(session_id.clone(), agent.clone())
+        .await
+        .map_err(|_| StatusCode::CONFLICT)?;
+
+    Ok(Json(AgentInfo::new(session_id, &agent).await))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agents",
+    responses(
+        (status = 200, description = "Agents created for sessions", body = Vec<AgentInfo>),
+        (status = 401, description = "Unauthorized - invalid secret key")
+    )
+)]
+async fn list_agents(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<AgentInfo>>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let mut agents = Vec::new();
+    for session_id in state.list_session_agents().await {
+        // The agent may have been destroyed since the ids were listed
+        if let Some(agent) = state.find_session_agent(&session_id).await {
+            agents.push(AgentInfo::new(session_id, &agent).await);
+        }
+    }
+    Ok(Json(agents))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agents/{session_id}",
+    params(
+        ("session_id" = String, Path, description = "Session the agent belongs to")
+    ),
+    responses(
+        (status = 200, description = "Agent found", body = AgentInfo),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "The session has no agent")
+    )
+)]
+async fn get_agent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<Json<AgentInfo>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agent = state
+        .find_session_agent(&session_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(AgentInfo::new(session_id, &agent).await))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/agents/{session_id}",
+    params(
+        ("session_id" = String, Path, description = "Session the agent belongs to")
+    ),
+    responses(
+        (status = 200, description = "Agent destroyed"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "The session has no agent")
+    )
+)]
+async fn destroy_agent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    state
+        .remove_session_agent(&session_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(StatusCode::OK)
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/agent/versions", get(get_versions))
@@ -224,5 +435,135 @@ pub fn routes(state: Arc<AppState>) -> Router {
         .route("/agent/prompt", post(extend_prompt))
         .route("/agent/tools", get(get_tools))
         .route("/agent/update_provider", post(update_agent_provider))
+        .route("/agents", get(list_agents).post(create_agent))
+        .route("/agents/:session_id", get(get_agent).delete(destroy_agent))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::utils::SESSION_ID_HEADER;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    fn request(method: &str, uri: &str, body: Option<serde_json::Value>) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .header("x-secret-key", "test-secret")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap()
+    }
+
+    async fn json_body(response: axum::response::Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_agent_lifecycle() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let app = routes(state.clone());
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/agents",
+                Some(serde_json::json!({"session_id": "window-1"})),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let created = json_body(response).await;
+        assert_eq!(created["session_id"], "window-1");
+        assert!(created["model"].is_null());
+
+        // A session can only have one agent
+        let response = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/agents",
+                Some(serde_json::json!({"session_id": "window-1"})),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/agents", Some(serde_json::json!({}))))
+            .await
+            .unwrap();
+        let generated = json_body(response).await["session_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/agents", None))
+            .await
+            .unwrap();
+        let listed = json_body(response).await;
+        let mut ids: Vec<&str> = listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|agent| agent["session_id"].as_str().unwrap())
+            .collect();
+        ids.sort();
+        let mut expected = vec!["window-1", generated.as_str()];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // Each session gets its own agent, separate from the default one
+        let default_agent = state.get_session_agent(None).await.unwrap();
+        let session_agent = state.get_session_agent(Some("window-1")).await.unwrap();
+        assert!(!Arc::ptr_eq(&default_agent, &session_agent));
+
+        // Naming a session without an agent doesn't fall back to the default one
+        let mut unknown = request(
+            "POST",
+            "/agent/prompt",
+            Some(serde_json::json!({"extension": "Be brief"})),
+        );
+        unknown
+            .headers_mut()
+            .insert(SESSION_ID_HEADER, "unknown".parse().unwrap());
+        let response = app.clone().oneshot(unknown).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/agents/window-1", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("DELETE", "/agents/window-1", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/agents/window-1", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .oneshot(request("DELETE", "/agents/window-1", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
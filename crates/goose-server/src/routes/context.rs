@@ -1,4 +1,4 @@
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{
     extract::State,
@@ -39,6 +39,7 @@ pub struct ContextManageResponse {
         (status = 200, description = "Context managed successfully", body = ContextManageResponse),
         (status = 401, description = "Unauthorized - Invalid or missing API key"),
         (status = 412, description = "Precondition failed - Agent not available"),
+        (status = 404, description = "The session in X-Session-Id has no agent"),
         (status = 500, description = "Internal server error")
     ),
     security(
@@ -54,9 +55,9 @@ async fn manage_context(
     verify_secret_key(&headers, &state)?;
 
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
 
     let mut processed_messages: Vec<Message> = vec![];
     let mut token_counts: Vec<usize> = vec![];
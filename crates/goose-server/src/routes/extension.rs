@@ -1,9 +1,10 @@
+use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::OnceLock;
 
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{extract::State, routing::post, Json, Router};
 use goose::agents::{extension::Envs, ExtensionConfig};
@@ -30,6 +31,24 @@ enum ExtensionConfigRequest {
         env_keys: Vec<String>,
         timeout: Option<u64>,
     },
+    /// Streamable HTTP extension, served at a single endpoint.
+    #[serde(rename = "streamable_http")]
+    StreamableHttp {
+        /// The name to identify this extension
+        name: String,
+        /// The URI of the extension's MCP endpoint.
+        uri: String,
+        #[serde(default)]
+        /// Map of environment variable key to values.
+        envs: Envs,
+        /// List of environment variable keys. The server will fetch their values from the keyring.
+        #[serde(default)]
+        env_keys: Vec<String>,
+        /// Headers sent with every request, `${KEY}` is replaced with the value of that env.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        timeout: Option<u64>,
+    },
     /// Standard I/O (stdio) extension.
     #[serde(rename = "stdio")]
     Stdio {
@@ -176,6 +195,23 @@ async fn add_extension(
             timeout,
             bundled: None,
         },
+        ExtensionConfigRequest::StreamableHttp {
+            name,
+            uri,
+            envs,
+            env_keys,
+            headers,
+            timeout,
+        } => ExtensionConfig::StreamableHttp {
+            name,
+            uri,
+            envs,
+            env_keys,
+            headers,
+            description: None,
+            timeout,
+            bundled: None,
+        },
         ExtensionConfigRequest::Stdio {
             name,
             cmd,
@@ -231,9 +267,9 @@ async fn add_extension(
 
     // Get a reference to the agent
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
     let response = agent.add_extension(extension_config).await;
 
     // Respond with the result.
@@ -265,9 +301,9 @@ async fn remove_extension(
 
     // Get a reference to the agent
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
     agent.remove_extension(&name).await;
 
     Ok(Json(ExtensionResponse {
@@ -1,4 +1,4 @@
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{
     extract::State,
@@ -10,8 +10,8 @@ use axum::{
 use bytes::Bytes;
 use futures::{stream::StreamExt, Stream};
 use goose::{
-    agents::SessionConfig,
-    message::{Message, MessageContent},
+    agents::{AgentEvent, SessionConfig},
+    message::{Message, MessageContent, MessageDelta},
     permission::permission_confirmation::PrincipalType,
 };
 use goose::{
@@ -83,6 +83,8 @@ impl IntoResponse for SseResponse {
 #[serde(tag = "type")]
 enum MessageEvent {
     Message { message: Message },
+    MessageDelta { delta: MessageDelta },
+    HistoryReplaced { messages: Vec<Message> },
     Error { error: String },
     Finish { reason: String },
 }
@@ -101,6 +103,14 @@ async fn stream_event(
     tx.send(format!("data: {}\n\n", json)).await
 }
 
+/// The session a reply is tracked and cancelled under, the one in the headers that also picks
+/// the agent, or else the one in the body
+fn reply_key(headers: &HeaderMap, session_id: &str) -> String {
+    session_id_from_headers(headers)
+        .unwrap_or(session_id)
+        .to_string()
+}
+
 async fn handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -120,51 +130,35 @@ async fn handler(
         .session_id
         .unwrap_or_else(session::generate_session_id);
 
+    // The agent comes from the session in the headers, the one in the body names the history
+    let agent = state
+        .get_session_agent(session_id_from_headers(&headers))
+        .await
+        .map_err(|e| e.status_code())?;
+
+    let reply_key = reply_key(&headers, &session_id);
+    let reply = state.start_reply(&reply_key).await;
+
     // Spawn task to handle streaming
     tokio::spawn(async move {
-        let agent = state.get_agent().await;
-        let agent = match agent {
-            Ok(agent) => {
-                let provider = agent.provider().await;
-                match provider {
-                    Ok(_) => agent,
-                    Err(_) => {
-                        let _ = stream_event(
-                            MessageEvent::Error {
-                                error: "No provider configured".to_string(),
-                            },
-                            &tx,
-                        )
-                        .await;
-                        let _ = stream_event(
-                            MessageEvent::Finish {
-                                reason: "error".to_string(),
-                            },
-                            &tx,
-                        )
-                        .await;
-                        return;
-                    }
-                }
-            }
-            Err(_) => {
-                let _ = stream_event(
-                    MessageEvent::Error {
-                        error: "No agent configured".to_string(),
-                    },
-                    &tx,
-                )
-                .await;
-                let _ = stream_event(
-                    MessageEvent::Finish {
-                        reason: "error".to_string(),
-                    },
-                    &tx,
-                )
-                .await;
-                return;
-            }
-        };
+        if agent.provider().await.is_err() {
+            state.finish_reply(&reply_key, reply.id).await;
+            let _ = stream_event(
+                MessageEvent::Error {
+                    error: "No provider configured".to_string(),
+                },
+                &tx,
+            )
+            .await;
+            let _ = stream_event(
+                MessageEvent::Finish {
+                    reason: "error".to_string(),
+                },
+                &tx,
+            )
+            .await;
+            return;
+        }
 
         // Get the provider first, before starting the reply stream
         let provider = agent.provider().await;
@@ -176,12 +170,14 @@ async fn handler(
                     id: session::Identifier::Name(session_id.clone()),
                     working_dir: PathBuf::from(session_working_dir),
                 }),
+                Some(reply.cancel_token.clone()),
             )
             .await
         {
             Ok(stream) => stream,
             Err(e) => {
                 tracing::error!("Failed to start reply stream: {:?}", e);
+                state.finish_reply(&reply_key, reply.id).await;
                 let _ = stream_event(
                     MessageEvent::Error {
                         error: e.to_string(),
@@ -208,7 +204,27 @@ async fn handler(
             tokio::select! {
                 response = timeout(Duration::from_millis(500), stream.next()) => {
                     match response {
-                        Ok(Some(Ok(message))) => {
+                        Ok(Some(Ok(AgentEvent::MessageDelta(delta)))) => {
+                            if let Err(e) = stream_event(MessageEvent::MessageDelta { delta }, &tx).await {
+                                tracing::error!("Error sending message through channel: {}", e);
+                                break;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::HistoryReplaced(messages)))) => {
+                            all_messages = messages.clone();
+                            if let Err(e) = stream_event(MessageEvent::HistoryReplaced { messages }, &tx).await {
+                                tracing::error!("Error sending message through channel: {}", e);
+                                break;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::Message(message)))) if message.is_tool_progress() => {
+                            // Progress is streamed for display while the tool runs, but not stored
+                            if let Err(e) = stream_event(MessageEvent::Message { message }, &tx).await {
+                                tracing::error!("Error sending message through channel: {}", e);
+                                break;
+                            }
+                        }
+                        Ok(Some(Ok(AgentEvent::Message(message)))) => {
                             all_messages.push(message.clone());
                             if let Err(e) = stream_event(MessageEvent::Message { message }, &tx).await {
                                 tracing::error!("Error sending message through channel: {}", e);
@@ -255,6 +271,9 @@ async fn handler(
             }
         }
 
+        drop(stream);
+        state.finish_reply(&reply_key, reply.id).await;
+
         // Send finish event
         let _ = stream_event(
             MessageEvent::Finish {
@@ -296,9 +315,9 @@ async fn ask_handler(
         .unwrap_or_else(session::generate_session_id);
 
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
 
     // Get the provider first, before starting the reply stream
     let provider = agent.provider().await;
@@ -308,6 +327,8 @@ async fn ask_handler(
 
     // Get response from agent
     let mut response_text = String::new();
+    let reply_key = reply_key(&headers, &session_id);
+    let reply = state.start_reply(&reply_key).await;
     let mut stream = match agent
         .reply(
             &messages,
@@ -315,12 +336,14 @@ async fn ask_handler(
                 id: session::Identifier::Name(session_id.clone()),
                 working_dir: PathBuf::from(session_working_dir),
             }),
+            Some(reply.cancel_token.clone()),
         )
         .await
     {
         Ok(stream) => stream,
         Err(e) => {
             tracing::error!("Failed to start reply stream: {:?}", e);
+            state.finish_reply(&reply_key, reply.id).await;
             return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
     };
@@ -331,7 +354,12 @@ async fn ask_handler(
 
     while let Some(response) = stream.next().await {
         match response {
-            Ok(message) => {
+            Ok(AgentEvent::MessageDelta(_)) => {}
+            Ok(AgentEvent::HistoryReplaced(messages)) => {
+                all_messages = messages;
+            }
+            Ok(AgentEvent::Message(message)) if message.is_tool_progress() => {}
+            Ok(AgentEvent::Message(message)) => {
                 if message.role == Role::Assistant {
                     for content in &message.content {
                         if let MessageContent::Text(text) = content {
@@ -344,10 +372,13 @@ async fn ask_handler(
             }
             Err(e) => {
                 tracing::error!("Error processing as_ai message: {}", e);
+                state.finish_reply(&reply_key, reply.id).await;
                 return Err(StatusCode::INTERNAL_SERVER_ERROR);
             }
         }
     }
+    drop(stream);
+    state.finish_reply(&reply_key, reply.id).await;
 
     // Add the complete response message to the conversation history
     if !response_message.content.is_empty() {
@@ -391,6 +422,7 @@ fn default_principal_type() -> PrincipalType {
     responses(
         (status = 200, description = "Permission action is confirmed", body = Value),
         (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "The session in X-Session-Id has no agent"),
         (status = 500, description = "Internal server error")
     )
 )]
@@ -402,9 +434,9 @@ pub async fn confirm_permission(
     verify_secret_key(&headers, &state)?;
 
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
 
     let permission = match request.action.as_str() {
         "always_allow" => Permission::AlwaysAllow,
@@ -425,6 +457,42 @@ pub async fn confirm_permission(
     Ok(Json(Value::Object(serde_json::Map::new())))
 }
 
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CancelReplyRequest {
+    /// The session to cancel the reply of, when the request has no session header
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/reply/cancel",
+    request_body = CancelReplyRequest,
+    responses(
+        (status = 200, description = "The reply in flight for the session was cancelled", body = Value),
+        (status = 400, description = "Neither the headers nor the body name a session"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "No reply in flight for the session")
+    )
+)]
+pub async fn cancel_reply(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CancelReplyRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let Some(session_id) = session_id_from_headers(&headers).or(request.session_id.as_deref())
+    else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if state.cancel_reply(session_id).await {
+        Ok(Json(json!({"status": "cancelled"})))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ToolResultRequest {
     id: String,
@@ -458,9 +526,9 @@ async fn submit_tool_result(
     };
 
     let agent = state
-        .get_agent()
+        .get_session_agent(session_id_from_headers(&headers))
         .await
-        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+        .map_err(|e| e.status_code())?;
     agent.handle_tool_result(payload.id, payload.result).await;
     Ok(Json(json!({"status": "ok"})))
 }
@@ -469,6 +537,7 @@ async fn submit_tool_result(
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/reply", post(handler))
+        .route("/reply/cancel", post(cancel_reply))
         .route("/ask", post(ask_handler))
         .route("/confirm", post(confirm_permission))
         .route("/tool_result", post(submit_tool_result))
@@ -519,6 +588,7 @@ mod tests {
 
     mod integration_tests {
         use super::*;
+        use crate::routes::utils::SESSION_ID_HEADER;
         use axum::{body::Body, http::Request};
         use std::sync::Arc;
         use tower::ServiceExt;
@@ -560,5 +630,73 @@ mod tests {
             // Assert response status
             assert_eq!(response.status(), StatusCode::OK);
         }
+
+        #[tokio::test]
+        async fn test_cancel_reply_endpoint() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            // A reply that finishes after a newer one started leaves the newer one tracked
+            let earlier = state.start_reply("test-session").await;
+            let reply = state.start_reply("test-session").await;
+            state.finish_reply("test-session", earlier.id).await;
+            let app = routes(state);
+
+            let cancel_request = || {
+                Request::builder()
+                    .uri("/reply/cancel")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::from(
+                        serde_json::to_string(&CancelReplyRequest {
+                            session_id: Some("test-session".to_string()),
+                        })
+                        .unwrap(),
+                    ))
+                    .unwrap()
+            };
+
+            let response = app.clone().oneshot(cancel_request()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(reply.cancel_token.is_cancelled());
+            assert!(!earlier.cancel_token.is_cancelled());
+
+            // Nothing is in flight for the session anymore
+            let response = app.clone().oneshot(cancel_request()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        #[tokio::test]
+        async fn test_cancel_reply_by_session_header() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            // Replies are tracked under the session header when there is one
+            let mut headers = HeaderMap::new();
+            headers.insert(SESSION_ID_HEADER, "header-session".parse().unwrap());
+            let reply = state
+                .start_reply(&reply_key(&headers, "body-session"))
+                .await;
+            let app = routes(state);
+
+            let request = Request::builder()
+                .uri("/reply/cancel")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .header(SESSION_ID_HEADER, "header-session")
+                .body(Body::from(r#"{"session_id": "body-session"}"#))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(reply.cancel_token.is_cancelled());
+
+            let request = Request::builder()
+                .uri("/reply/cancel")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .body(Body::from("{}"))
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
     }
 }
@@ -3,17 +3,20 @@ use std::sync::Arc;
 
 use crate::state::AppState;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
 use goose::message::Message;
 use goose::session;
 use goose::session::info::{get_session_info, SessionInfo, SortOrder};
-use goose::session::SessionMetadata;
-use serde::Serialize;
-use utoipa::ToSchema;
+use goose::session::{session_store, SessionMetadata, SessionSearchResult};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Number of matches returned by a search unless the request asks for a different amount
+const DEFAULT_SEARCH_LIMIT: usize = 50;
 
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -59,6 +62,53 @@ async fn list_sessions(
     Ok(Json(SessionListResponse { sessions }))
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct SessionSearchQuery {
+    /// Words that must each start a word of a message, ignoring case
+    query: String,
+    /// Maximum number of matches to return
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSearchResponse {
+    /// Matching messages, most recently modified sessions first
+    results: Vec<SessionSearchResult>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/search",
+    params(SessionSearchQuery),
+    responses(
+        (status = 200, description = "Messages matching the query", body = SessionSearchResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Session Management"
+)]
+// Search the messages of all sessions
+async fn search_sessions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<SessionSearchQuery>,
+) -> Result<Json<SessionSearchResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let results = session_store()
+        .and_then(|store| store.search(&query.query, query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)))
+        .map_err(|e| {
+            tracing::error!("Failed to search sessions: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(SessionSearchResponse { results }))
+}
+
 #[utoipa::path(
     get,
     path = "/sessions/{session_id}",
@@ -104,10 +154,88 @@ async fn get_session_history(
     }))
 }
 
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkSessionRequest {
+    /// Index of the message the fork diverges at, all messages before it are kept.
+    /// Defaults to copying the whole session.
+    message_index: Option<usize>,
+    /// Id for the new session, made of letters, digits, `_` and `-`. A new one is generated if
+    /// not provided
+    new_session_id: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/fork",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session to fork")
+    ),
+    request_body = ForkSessionRequest,
+    responses(
+        (status = 200, description = "Session forked, returns the new session", body = SessionHistoryResponse),
+        (status = 400, description = "Invalid session id, or message index past the end of the session"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 409, description = "A session with the new id already exists"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Session Management"
+)]
+// Fork a session into a new branch, to edit and resend a message send the edited
+// message to the new session after forking at the index of the original
+async fn fork_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(request): Json<ForkSessionRequest>,
+) -> Result<Json<SessionHistoryResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let internal_error = |e: anyhow::Error| {
+        tracing::error!("Failed to fork session: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let new_session_id = request
+        .new_session_id
+        .unwrap_or_else(session::generate_session_id);
+    if !session::is_valid_session_id(&session_id) || !session::is_valid_session_id(&new_session_id)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let store = session_store().map_err(internal_error)?;
+    if !store.exists(&session_id).map_err(internal_error)? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if store.exists(&new_session_id).map_err(internal_error)? {
+        return Err(StatusCode::CONFLICT);
+    }
+    let messages = store.read_messages(&session_id).map_err(internal_error)?;
+    let message_index = request.message_index.unwrap_or(messages.len());
+    if message_index > messages.len() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let metadata = store
+        .fork(&session_id, &new_session_id, message_index)
+        .map_err(internal_error)?;
+
+    Ok(Json(SessionHistoryResponse {
+        session_id: new_session_id,
+        metadata,
+        messages: messages[..message_index].to_vec(),
+    }))
+}
+
 // Configure routes for this module
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/sessions", get(list_sessions))
+        .route("/sessions/search", get(search_sessions))
         .route("/sessions/:session_id", get(get_session_history))
+        .route("/sessions/:session_id/fork", post(fork_session))
         .with_state(state)
 }
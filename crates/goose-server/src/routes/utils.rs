@@ -37,6 +37,16 @@ pub fn verify_secret_key(headers: &HeaderMap, state: &AppState) -> Result<Status
     }
 }
 
+/// Header naming the session whose agent a request is meant for
+pub const SESSION_ID_HEADER: &str = "X-Session-Id";
+
+/// Read the session id a request targets, if it names one
+pub fn session_id_from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
 /// Inspects a configuration key to determine if it's set, its location, and value (for non-secret keys)
 #[allow(dead_code)]
 pub fn inspect_key(key_name: &str, is_secret: bool) -> Result<KeyInfo, Box<dyn Error>> {
@@ -116,6 +126,11 @@ pub fn check_provider_configured(metadata: &ProviderMetadata) -> bool {
         .filter(|key| key.required)
         .collect();
 
+    // Nothing to configure, e.g. a custom provider that takes no API key
+    if required_keys.is_empty() {
+        return true;
+    }
+
     // Special case: If a provider has exactly one required key and that key
     // has a default value, check if it's explicitly set
     if required_keys.len() == 1 && required_keys[0].default.is_some() {
@@ -1,5 +1,36 @@
+use axum::http::StatusCode;
 use goose::agents::Agent;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
+
+/// Why a request could not be given an agent
+#[derive(thiserror::Error, Debug)]
+pub enum AgentLookupError {
+    #[error("Agent needs to be created first.")]
+    NotCreated,
+    #[error("Session {0} has no agent")]
+    UnknownSession(String),
+}
+
+impl AgentLookupError {
+    /// The status a route answers with when it has no agent to use
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AgentLookupError::NotCreated => StatusCode::PRECONDITION_FAILED,
+            AgentLookupError::UnknownSession(_) => StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+/// A reply streaming for a session
+pub struct Reply {
+    /// Tells this reply apart from later ones for the same session
+    pub id: u64,
+    pub cancel_token: CancellationToken,
+}
 
 /// Shared reference to an Agent that can be cloned cheaply
 /// without cloning the underlying Agent object
@@ -15,6 +46,11 @@ pub type AgentRef = Arc<Agent>;
 pub struct AppState {
     // agent: SharedAgentStore,
     agent: Option<AgentRef>,
+    /// Agents created for a specific session, each with its own provider and extensions
+    agents: Arc<RwLock<HashMap<String, AgentRef>>>,
+    /// The latest reply streaming for each session, with the token that cancels it
+    replies: Arc<Mutex<HashMap<String, (u64, CancellationToken)>>>,
+    next_reply_id: Arc<AtomicU64>,
     pub secret_key: String,
 }
 
@@ -22,6 +58,9 @@ impl AppState {
     pub async fn new(agent: AgentRef, secret_key: String) -> Arc<AppState> {
         Arc::new(Self {
             agent: Some(agent.clone()),
+            agents: Arc::new(RwLock::new(HashMap::new())),
+            replies: Arc::new(Mutex::new(HashMap::new())),
+            next_reply_id: Arc::new(AtomicU64::new(0)),
             secret_key,
         })
     }
@@ -31,4 +70,94 @@ impl AppState {
             .clone()
             .ok_or_else(|| anyhow::anyhow!("Agent needs to be created first."))
     }
+
+    /// Get the agent registered for a session, or the shared default agent for requests
+    /// that don't name a session
+    ///
+    /// A session that is named but has no agent is an error rather than the default agent,
+    /// so a request meant for one window can't end up changing another.
+    pub async fn get_session_agent(
+        &self,
+        session_id: Option<&str>,
+    ) -> Result<Arc<Agent>, AgentLookupError> {
+        match session_id {
+            Some(session_id) => self
+                .find_session_agent(session_id)
+                .await
+                .ok_or_else(|| AgentLookupError::UnknownSession(session_id.to_string())),
+            None => self
+                .get_agent()
+                .await
+                .map_err(|_| AgentLookupError::NotCreated),
+        }
+    }
+
+    /// Register a new agent for a session, failing if the session already has one
+    pub async fn add_session_agent(
+        &self,
+        session_id: String,
+        agent: AgentRef,
+    ) -> Result<(), anyhow::Error> {
+        let mut agents = self.agents.write().await;
+        if agents.contains_key(&session_id) {
+            return Err(anyhow::anyhow!(
+                "Session {} already has an agent",
+                session_id
+            ));
+        }
+        agents.insert(session_id, agent);
+        Ok(())
+    }
+
+    /// Look up the agent registered for a session, without falling back to the default agent
+    pub async fn find_session_agent(&self, session_id: &str) -> Option<Arc<Agent>> {
+        self.agents.read().await.get(session_id).cloned()
+    }
+
+    /// Ids of all sessions that have their own agent, sorted for stable output
+    pub async fn list_session_agents(&self) -> Vec<String> {
+        let mut session_ids: Vec<String> = self.agents.read().await.keys().cloned().collect();
+        session_ids.sort();
+        session_ids
+    }
+
+    /// Remove the agent registered for a session, returning it if there was one
+    pub async fn remove_session_agent(&self, session_id: &str) -> Option<Arc<Agent>> {
+        self.agents.write().await.remove(session_id)
+    }
+
+    /// Track a reply starting for a session, making it the one that cancelling the session
+    /// cancels
+    pub async fn start_reply(&self, session_id: &str) -> Reply {
+        let id = self.next_reply_id.fetch_add(1, Ordering::Relaxed);
+        let cancel_token = CancellationToken::new();
+        self.replies
+            .lock()
+            .await
+            .insert(session_id.to_string(), (id, cancel_token.clone()));
+        Reply { id, cancel_token }
+    }
+
+    /// Stop tracking a reply once it has finished, leaving a newer reply for the same
+    /// session in place
+    pub async fn finish_reply(&self, session_id: &str, reply_id: u64) {
+        let mut replies = self.replies.lock().await;
+        if replies
+            .get(session_id)
+            .is_some_and(|(id, _)| *id == reply_id)
+        {
+            replies.remove(session_id);
+        }
+    }
+
+    /// Cancel the reply in flight for a session, returning false if there wasn't one
+    pub async fn cancel_reply(&self, session_id: &str) -> bool {
+        match self.replies.lock().await.remove(session_id) {
+            Some((_, cancel_token)) => {
+                cancel_token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
 }
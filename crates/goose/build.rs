@@ -1,9 +1,16 @@
+#[cfg(feature = "local-gguf")]
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
+#[cfg(feature = "local-gguf")]
+use std::path::PathBuf;
 
 const BASE_DIR: &str = "../../tokenizer_files";
 const TOKENIZERS: &[&str] = &["Xenova/gpt-4o", "Xenova/claude-tokenizer"];
+/// The llama.cpp release whose `llama.h` the local-gguf bindings follow
+#[cfg(feature = "local-gguf")]
+const LLAMA_CPP_BUILD: &str = "5200";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -12,6 +19,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed={}", BASE_DIR);
 
+    #[cfg(feature = "local-gguf")]
+    link_llama_cpp()?;
+
     for tokenizer_name in TOKENIZERS {
         download_tokenizer(tokenizer_name).await?;
     }
@@ -19,6 +29,185 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Link the libllama that `llama.pc` describes, refusing any other llama.cpp release
+///
+/// llama.cpp installs a `llama.pc` whose version ends in the release's build number. The
+/// library is linked from the directory the file names, and the structs in its `llama.h` are
+/// checked against the bindings, so the build fails rather than link a library it can't verify.
+#[cfg(feature = "local-gguf")]
+fn link_llama_cpp() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed=LLAMA_CPP_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=PKG_CONFIG_PATH");
+    println!("cargo:rerun-if-changed=src/providers/llama_cpp_layout.c");
+
+    let pc_file = find_llama_pc()?;
+    println!("cargo:rerun-if-changed={}", pc_file.display());
+    let pc = PkgConfig::read(&pc_file)?;
+
+    let version = pc.field("Version").unwrap_or_default();
+    if version.rsplit('.').next() != Some(LLAMA_CPP_BUILD) {
+        return Err(format!(
+            "{} is for llama.cpp version {}, but the local-gguf bindings follow llama.h of b{}",
+            pc_file.display(),
+            version,
+            LLAMA_CPP_BUILD
+        )
+        .into());
+    }
+
+    let lib_dir = match std::env::var("LLAMA_CPP_LIB_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => pc
+            .variable("libdir")
+            .map(PathBuf::from)
+            .ok_or_else(|| format!("{} doesn't say where libllama is", pc_file.display()))?,
+    };
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+
+    let include_dir = pc
+        .variable("includedir")
+        .ok_or_else(|| format!("{} doesn't say where llama.h is", pc_file.display()))?;
+    println!("cargo:rerun-if-changed={}/llama.h", include_dir);
+    cc::Build::new()
+        .file("src/providers/llama_cpp_layout.c")
+        .include(include_dir)
+        .try_compile("goose_llama_cpp_layout")
+        .map_err(|e| {
+            format!(
+                "llama.h in {} doesn't match the local-gguf bindings for b{}: {}",
+                include_dir, LLAMA_CPP_BUILD, e
+            )
+        })?;
+    Ok(())
+}
+
+/// The `llama.pc` installed with the libllama to link
+///
+/// With `LLAMA_CPP_LIB_DIR` set only the file next to that library counts, otherwise the
+/// `PKG_CONFIG_PATH` and the usual install locations are searched.
+#[cfg(feature = "local-gguf")]
+fn find_llama_pc() -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(dir) = std::env::var_os("LLAMA_CPP_LIB_DIR") {
+        let pc_file = PathBuf::from(dir).join("pkgconfig").join("llama.pc");
+        if !pc_file.exists() {
+            return Err(format!(
+                "Could not find {} to check that libllama is llama.cpp b{}",
+                pc_file.display(),
+                LLAMA_CPP_BUILD
+            )
+            .into());
+        }
+        return Ok(pc_file);
+    }
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Some(paths) = std::env::var_os("PKG_CONFIG_PATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+    for prefix in ["/usr/local", "/usr"] {
+        dirs.push(PathBuf::from(prefix).join("lib/pkgconfig"));
+        // Debian and Ubuntu install into a directory named for the target, e.g.
+        // /usr/lib/x86_64-linux-gnu
+        if let Some(multiarch) = multiarch_dir() {
+            dirs.push(
+                PathBuf::from(prefix)
+                    .join("lib")
+                    .join(multiarch)
+                    .join("pkgconfig"),
+            );
+        }
+        dirs.push(PathBuf::from(prefix).join("lib64/pkgconfig"));
+    }
+    dirs.push(PathBuf::from("/opt/homebrew/lib/pkgconfig"));
+
+    dirs.into_iter()
+        .map(|dir| dir.join("llama.pc"))
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            format!(
+                "Could not find llama.pc to check that libllama is llama.cpp b{}, set \
+                LLAMA_CPP_LIB_DIR to the directory libllama is installed in",
+                LLAMA_CPP_BUILD
+            )
+            .into()
+        })
+}
+
+/// The Debian multiarch directory name of the target, such as `aarch64-linux-gnu`
+#[cfg(feature = "local-gguf")]
+fn multiarch_dir() -> Option<String> {
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").ok()?;
+    let os = std::env::var("CARGO_CFG_TARGET_OS").ok()?;
+    let env = std::env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    if os != "linux" {
+        return None;
+    }
+    let abi = match (arch.as_str(), env.as_str()) {
+        ("arm", "gnu") => "gnueabihf".to_string(),
+        (_, env) => env.to_string(),
+    };
+    Some(format!("{}-linux-{}", arch, abi))
+}
+
+/// The variables and fields of a pkg-config file
+#[cfg(feature = "local-gguf")]
+struct PkgConfig {
+    variables: HashMap<String, String>,
+    fields: HashMap<String, String>,
+}
+
+#[cfg(feature = "local-gguf")]
+impl PkgConfig {
+    fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut pc = Self {
+            variables: HashMap::new(),
+            fields: HashMap::new(),
+        };
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            // Fields are `Name: value`, variables `name=value`, whichever separator comes first
+            match (line.find(':'), line.find('=')) {
+                (Some(colon), equals) if equals.is_none_or(|equals| colon < equals) => {
+                    let value = pc.expand(line[colon + 1..].trim());
+                    pc.fields.insert(line[..colon].trim().to_string(), value);
+                }
+                (_, Some(equals)) => {
+                    let value = pc.expand(line[equals + 1..].trim());
+                    pc.variables
+                        .insert(line[..equals].trim().to_string(), value);
+                }
+                _ => {}
+            }
+        }
+        Ok(pc)
+    }
+
+    /// Substitute the `${name}` references to variables defined earlier in the file
+    fn expand(&self, value: &str) -> String {
+        let mut expanded = value.to_string();
+        while let Some(start) = expanded.find("${") {
+            let Some(len) = expanded[start..].find('}') else {
+                break;
+            };
+            let name = &expanded[start + 2..start + len];
+            let replacement = self.variables.get(name).cloned().unwrap_or_default();
+            expanded.replace_range(start..start + len + 1, &replacement);
+        }
+        expanded
+    }
+
+    fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
 async fn download_tokenizer(repo_id: &str) -> Result<(), Box<dyn Error>> {
     let dir_name = repo_id.replace('/', "--");
     let download_dir = format!("{}/{}", BASE_DIR, dir_name);
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use dotenv::dotenv;
 use futures::StreamExt;
-use goose::agents::{Agent, ExtensionConfig};
+use goose::agents::{Agent, AgentEvent, ExtensionConfig};
 use goose::config::{DEFAULT_EXTENSION_DESCRIPTION, DEFAULT_EXTENSION_TIMEOUT};
 use goose::message::Message;
 use goose::providers::databricks::DatabricksProvider;
@@ -34,12 +34,11 @@ async fn main() {
     let messages = vec![Message::user()
         .with_text("can you summarize the readme.md in this dir using just a haiku?")];
 
-    let mut stream = agent.reply(&messages, None).await.unwrap();
-    while let Some(message) = stream.next().await {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&message.unwrap()).unwrap()
-        );
-        println!("\n");
+    let mut stream = agent.reply(&messages, None, None).await.unwrap();
+    while let Some(event) = stream.next().await {
+        if let AgentEvent::Message(message) = event.unwrap() {
+            println!("{}", serde_json::to_string_pretty(&message).unwrap());
+            println!("\n");
+        }
     }
 }
@@ -1,37 +1,55 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use futures::stream::BoxStream;
-use futures::TryStreamExt;
+use futures::{StreamExt, TryStreamExt};
 
 use crate::config::{Config, ExtensionConfigManager, PermissionManager};
-use crate::message::Message;
+use crate::context_mgmt::ContextStrategy;
+use crate::message::{BudgetExceeded, Message, ToolProgress};
 use crate::permission::permission_judge::check_tool_permissions;
-use crate::permission::PermissionConfirmation;
-use crate::providers::base::Provider;
+use crate::permission::{Permission, PermissionConfirmation};
+use crate::providers::base::{Provider, ProviderStreamEvent};
 use crate::providers::errors::ProviderError;
 use crate::recipe::{Author, Recipe};
-use regex::Regex;
-use serde_json::Value;
+use crate::session;
+use crate::token_counter::TokenCounter;
+use serde_json::{json, Value};
 use tokio::sync::{mpsc, Mutex};
-use tracing::{debug, error, instrument};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, instrument, warn};
 
+use crate::agents::budget::{Budget, BudgetUsage};
 use crate::agents::extension::{ExtensionConfig, ExtensionResult, ToolInfo};
-use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
+use crate::agents::extension_manager::{
+    get_parameter_names, ExtensionManager, ToolProgressReceiver,
+};
 use crate::agents::platform_tools::{
     PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME,
     PLATFORM_READ_RESOURCE_TOOL_NAME, PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
 };
 use crate::agents::prompt_manager::PromptManager;
+use crate::agents::sampling::{SamplingReceiver, SamplingRequest, SAMPLING_CONFIRMATION_PREFIX};
 use crate::agents::types::SessionConfig;
-use crate::agents::types::{FrontendTool, ToolResultReceiver};
+use crate::agents::types::{AgentEvent, FrontendTool, ToolResultReceiver};
 use mcp_core::{
     prompt::Prompt, protocol::GetPromptResult, tool::Tool, Content, ToolError, ToolResult,
 };
 
 use super::platform_tools;
-use super::tool_execution::{ToolFuture, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::tool_execution::{
+    cancel_pending_tool_requests, ToolFuture, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE,
+};
+
+/// Fraction of the model's context limit above which the conversation is compacted up front
+const DEFAULT_AUTO_COMPACT_THRESHOLD: f32 = 0.8;
+
+/// What the agent handles while it waits for tool calls to complete
+enum ToolWaitEvent {
+    Sampling(SamplingRequest),
+    Progress(ToolProgress),
+}
 
 /// The main goose Agent
 pub struct Agent {
@@ -44,6 +62,11 @@ pub struct Agent {
     pub(super) confirmation_rx: Mutex<mpsc::Receiver<(String, PermissionConfirmation)>>,
     pub(super) tool_result_tx: mpsc::Sender<(String, ToolResult<Vec<Content>>)>,
     pub(super) tool_result_rx: ToolResultReceiver,
+    pub(super) budget: Mutex<Budget>,
+    pub(super) sampling_requests: Mutex<SamplingReceiver>,
+    /// Extensions the user allowed to sample for the rest of the session
+    pub(super) sampling_allowed: Mutex<HashSet<String>>,
+    pub(super) tool_progress: Mutex<ToolProgressReceiver>,
 }
 
 impl Agent {
@@ -51,10 +74,16 @@ impl Agent {
         // Create channels with buffer size 32 (adjust if needed)
         let (confirm_tx, confirm_rx) = mpsc::channel(32);
         let (tool_tx, tool_rx) = mpsc::channel(32);
+        let (sampling_tx, sampling_rx) = mpsc::unbounded_channel();
+        let (progress_tx, progress_rx) = mpsc::unbounded_channel();
 
         Self {
             provider: Mutex::new(None),
-            extension_manager: Mutex::new(ExtensionManager::new()),
+            extension_manager: Mutex::new(
+                ExtensionManager::new()
+                    .with_sampling(sampling_tx)
+                    .with_tool_progress(progress_tx),
+            ),
             frontend_tools: Mutex::new(HashMap::new()),
             frontend_instructions: Mutex::new(None),
             prompt_manager: Mutex::new(PromptManager::new()),
@@ -62,6 +91,10 @@ impl Agent {
             confirmation_rx: Mutex::new(confirm_rx),
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
+            budget: Mutex::new(Budget::default()),
+            sampling_requests: Mutex::new(sampling_rx),
+            sampling_allowed: Mutex::new(HashSet::new()),
+            tool_progress: Mutex::new(progress_rx),
         }
     }
 }
@@ -153,7 +186,7 @@ impl Agent {
             ))
         } else {
             extension_manager
-                .dispatch_tool_call(tool_call.clone())
+                .dispatch_tool_call(tool_call.clone(), &request_id)
                 .await
         };
 
@@ -309,12 +342,18 @@ impl Agent {
         }
     }
 
-    #[instrument(skip(self, messages, session), fields(user_message))]
+    /// Run the agent loop for the conversation in `messages`, streaming back its events
+    ///
+    /// Cancelling `cancel_token` stops the turn: the provider request is dropped, running
+    /// tools are cancelled and any tool requests still waiting get a cancelled response,
+    /// so the history yielded so far stays consistent.
+    #[instrument(skip(self, messages, session, cancel_token), fields(user_message))]
     pub async fn reply(
         &self,
         messages: &[Message],
         session: Option<SessionConfig>,
-    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<Message>>> {
+        cancel_token: Option<CancellationToken>,
+    ) -> anyhow::Result<BoxStream<'_, anyhow::Result<AgentEvent>>> {
         let mut messages = messages.to_vec();
         let reply_span = tracing::Span::current();
 
@@ -322,10 +361,25 @@ impl Agent {
         let config = Config::global();
 
         // Setup tools and prompt
+        let mut tools_version = self.extension_manager.lock().await.tools_version();
         let (mut tools, mut toolshim_tools, mut system_prompt) =
             self.prepare_tools_and_prompt().await?;
 
         let goose_mode = config.get_param("GOOSE_MODE").unwrap_or("auto".to_string());
+        let context_strategy: ContextStrategy = config
+            .get_param("GOOSE_CONTEXT_STRATEGY")
+            .unwrap_or_default();
+        let compact_threshold: f32 = config
+            .get_param("GOOSE_AUTO_COMPACT_THRESHOLD")
+            .unwrap_or(DEFAULT_AUTO_COMPACT_THRESHOLD);
+        let budget = self.budget.lock().await.clone().or(Budget::from_config());
+        // Tokens and cost are limited per session, so pick up what earlier runs spent
+        let mut budget_usage = match &session {
+            Some(session) => session::read_metadata(&session::get_path(session.id.clone()))
+                .map(|metadata| BudgetUsage::from_session(&metadata))
+                .unwrap_or_default(),
+            None => BudgetUsage::default(),
+        };
 
         let (tools_with_readonly_annotation, tools_without_annotation) =
             Self::categorize_tools_by_annotation(&tools);
@@ -340,15 +394,101 @@ impl Agent {
 
         Ok(Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
+            let cancel_token = cancel_token.unwrap_or_default();
+            let mut token_counter: Option<TokenCounter> = None;
+            let mut checked_len = 0;
+            let mut compacted_after_error = false;
             loop {
-                match Self::generate_response_from_provider(
+                if cancel_token.is_cancelled() {
+                    break;
+                }
+
+                // Extensions can change their tools at any time and say so with a notification
+                let current_tools_version = self.extension_manager.lock().await.tools_version();
+                if current_tools_version != tools_version {
+                    tools_version = current_tools_version;
+                    (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+                }
+
+                // Limits are checked between turns, so the tool calls of the last turn always
+                // get their responses before the loop stops
+                if let Some(exceeded) = budget_usage.exceeded(&budget) {
+                    yield AgentEvent::Message(Message::assistant().with_budget_exceeded(exceeded));
+                    break;
+                }
+
+                // Compact ahead of time rather than waiting for the provider to reject the request
+                if context_strategy != ContextStrategy::Off
+                    && compact_threshold > 0.0
+                    && messages.len() > checked_len
+                {
+                    checked_len = messages.len();
+                    let model_config = self.provider().await?.get_model_config();
+                    let token_counter = token_counter
+                        .get_or_insert_with(|| TokenCounter::new(model_config.tokenizer_name()));
+                    let tokens = token_counter.count_chat_tokens(&system_prompt, &messages, &tools);
+                    if tokens as f32 > model_config.context_limit() as f32 * compact_threshold {
+                        match self.compact_context(&messages, context_strategy).await {
+                            Ok(compacted) => {
+                                messages = compacted;
+                                checked_len = messages.len();
+                                yield AgentEvent::HistoryReplaced(messages.clone());
+                            }
+                            Err(e) => warn!("Unable to compact the conversation: {}", e),
+                        }
+                    }
+                }
+
+                // Stream the response, forwarding deltas until the complete message arrives
+                let mut completion = None;
+                match cancel_token.run_until_cancelled(Self::stream_response_from_provider(
                     self.provider().await?,
                     &system_prompt,
                     &messages,
                     &tools,
                     &toolshim_tools,
-                ).await {
+                )).await {
+                    Some(Ok(mut provider_stream)) => {
+                        while let Some(Some(event)) = cancel_token.run_until_cancelled(provider_stream.next()).await {
+                            match event {
+                                Ok(ProviderStreamEvent::Delta(delta)) => {
+                                    yield AgentEvent::MessageDelta(delta);
+                                }
+                                Ok(ProviderStreamEvent::Complete(response, usage)) => {
+                                    completion = Some(Ok((response, usage)));
+                                }
+                                Err(e) => {
+                                    completion = Some(Err(e));
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Some(Err(e)) => completion = Some(Err(e)),
+                    None => {}
+                }
+                if completion.is_none() && cancel_token.is_cancelled() {
+                    // Nothing from this turn made it into the history yet, so just stop
+                    break;
+                }
+                let completion = completion.unwrap_or_else(|| {
+                    Err(ProviderError::ExecutionError(
+                        "The response stream ended before the message was complete".to_string(),
+                    ))
+                });
+
+                match completion {
                     Ok((response, usage)) => {
+                        compacted_after_error = false;
+
+                        // Without a tokenizer, counts are estimates tuned by what the provider reports
+                        if let (Some(counter), Some(input_tokens)) = (&token_counter, usage.usage.input_tokens) {
+                            if counter.is_estimate() {
+                                let estimated = counter.count_chat_tokens(&system_prompt, &messages, &tools);
+                                counter.calibrate(estimated, input_tokens.max(0) as usize);
+                            }
+                        }
+
                         // record usage for the session in the session file
                         if let Some(session_config) = session.clone() {
                             Self::update_session_metrics(session_config, &usage, messages.len()).await?;
@@ -362,11 +502,12 @@ impl Agent {
 
 
                         // Yield the assistant's response with frontend tool requests filtered out
-                        yield filtered_response.clone();
+                        yield AgentEvent::Message(filtered_response.clone());
 
                         tokio::task::yield_now().await;
 
                         let num_tool_requests = frontend_requests.len() + remaining_requests.len();
+                        budget_usage.record_turn(&usage, num_tool_requests);
                         if num_tool_requests == 0 {
                             break;
                         }
@@ -383,15 +524,20 @@ impl Agent {
                         // we have a stream of frontend tools to handle, inside the stream
                         // execution is yeield back to this reply loop, and is of the same Message
                         // type, so we can yield that back up to be handled
-                        while let Some(msg) = frontend_tool_stream.try_next().await? {
-                            yield msg;
+                        while let Some(Some(msg)) = cancel_token
+                            .run_until_cancelled(frontend_tool_stream.try_next())
+                            .await
+                            .transpose()?
+                        {
+                            yield AgentEvent::Message(msg);
                         }
+                        drop(frontend_tool_stream);
 
                         // Clone goose_mode once before the match to avoid move issues
                         let mode = goose_mode.clone();
                         if mode.as_str() == "chat" {
                             // Skip all tool calls in chat mode
-                            for request in remaining_requests {
+                            for request in &remaining_requests {
                                 let mut response = message_tool_response.lock().await;
                                 *response = response.clone().with_tool_response(
                                     request.id.clone(),
@@ -445,9 +591,14 @@ impl Agent {
                             // Execution is yielded back to this reply loop, and is of the same Message
                             // type, so we can yield the Message back up to be handled and grab any
                             // confirmations or denials
-                            while let Some(msg) = tool_approval_stream.try_next().await? {
-                                yield msg;
+                            while let Some(Some(msg)) = cancel_token
+                                .run_until_cancelled(tool_approval_stream.try_next())
+                                .await
+                                .transpose()?
+                            {
+                                yield AgentEvent::Message(msg);
                             }
+                            drop(tool_approval_stream);
 
                             tool_futures = {
                                 // Lock the mutex asynchronously
@@ -456,8 +607,44 @@ impl Agent {
                                 futures_lock.drain(..).collect::<Vec<_>>()
                             };
 
-                            // Wait for all tool calls to complete
-                            let results = futures::future::join_all(tool_futures).await;
+                            // Wait for all tool calls to complete, dropping them if the turn is
+                            // cancelled so the extensions are told to stop. Meanwhile extensions
+                            // report their progress, and may ask for completions, which need the
+                            // user's approval
+                            let mut tool_results = std::pin::pin!(cancel_token
+                                .run_until_cancelled(futures::future::join_all(tool_futures)));
+                            let mut sampling_requests = self.sampling_requests.lock().await;
+                            let mut tool_progress = self.tool_progress.lock().await;
+                            let results = loop {
+                                let event = tokio::select! {
+                                    results = &mut tool_results => break results.unwrap_or_default(),
+                                    Some(request) = sampling_requests.recv() => ToolWaitEvent::Sampling(request),
+                                    Some(progress) = tool_progress.recv() => ToolWaitEvent::Progress(progress),
+                                };
+                                let request = match event {
+                                    ToolWaitEvent::Sampling(request) => request,
+                                    ToolWaitEvent::Progress(progress) => {
+                                        yield AgentEvent::Message(Message::assistant().with_tool_progress(progress));
+                                        continue;
+                                    }
+                                };
+
+                                let permission = if self.is_sampling_allowed(&request.extension).await {
+                                    Some(Permission::AlwaysAllow)
+                                } else {
+                                    let id = format!("{}{}", SAMPLING_CONFIRMATION_PREFIX, uuid::Uuid::new_v4());
+                                    yield AgentEvent::Message(Self::sampling_confirmation(&id, &request));
+                                    cancel_token
+                                        .run_until_cancelled(self.wait_for_confirmation(&id))
+                                        .await
+                                        .flatten()
+                                };
+                                self.answer_sampling_request(request, permission, &mut budget_usage, &budget).await;
+                            };
+                            drop(sampling_requests);
+                            // Progress reported just before a call completed is out of date
+                            while tool_progress.try_recv().is_ok() {}
+                            drop(tool_progress);
                             let mut all_install_successful = true;
 
                             for (request_id, output) in results.into_iter() {
@@ -474,25 +661,56 @@ impl Agent {
                             }
                         }
 
+                        if cancel_token.is_cancelled() {
+                            let mut response = message_tool_response.lock().await;
+                            *response = cancel_pending_tool_requests(
+                                response.clone(),
+                                frontend_requests.iter().chain(remaining_requests.iter()),
+                            );
+                        }
+
                         let final_message_tool_resp = message_tool_response.lock().await.clone();
-                        yield final_message_tool_resp.clone();
+                        yield AgentEvent::Message(final_message_tool_resp.clone());
 
                         messages.push(response);
                         messages.push(final_message_tool_resp);
                     },
+                    Err(ProviderError::ContextLengthExceeded(e))
+                        if context_strategy != ContextStrategy::Off && !compacted_after_error =>
+                    {
+                        // Compact the history and retry the same turn, but only once so a
+                        // conversation that can't be shrunk any further doesn't loop forever
+                        compacted_after_error = true;
+                        match self.compact_context(&messages, context_strategy).await {
+                            Ok(compacted) => {
+                                debug!("Compacted the conversation after: {}", e);
+                                messages = compacted;
+                                checked_len = messages.len();
+                                yield AgentEvent::HistoryReplaced(messages.clone());
+                                continue;
+                            }
+                            Err(compact_error) => {
+                                warn!("Unable to compact the conversation: {}", compact_error);
+                                yield AgentEvent::Message(Message::assistant().with_context_length_exceeded(
+                                    "The context length of the model has been exceeded. Please start a new session and try again.",
+                                ));
+                                break;
+                            }
+                        }
+                    },
                     Err(ProviderError::ContextLengthExceeded(_)) => {
                         // At this point, the last message should be a user message
                         // because call to provider led to context length exceeded error
                         // Immediately yield a special message and break
-                        yield Message::assistant().with_context_length_exceeded(
+                        yield AgentEvent::Message(Message::assistant().with_context_length_exceeded(
                             "The context length of the model has been exceeded. Please start a new session and try again.",
-                        );
+                        ));
                         break;
                     },
                     Err(e) => {
                         // Create an error message & terminate the stream
                         error!("Error: {}", e);
-                        yield Message::assistant().with_text(format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error."));
+                        yield AgentEvent::Message(Message::assistant().with_text(format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")));
                         break;
                     }
                 }
@@ -509,6 +727,17 @@ impl Agent {
         prompt_manager.add_system_prompt_extra(instruction);
     }
 
+    /// Limit how far the agent may go, on top of the limits in the config
+    pub async fn set_budget(&self, budget: Budget) {
+        *self.budget.lock().await = budget;
+    }
+
+    /// Raise the limit a run stopped at, once the user agreed to let the agent continue
+    pub async fn extend_budget(&self, exceeded: &BudgetExceeded) {
+        let mut budget = self.budget.lock().await;
+        *budget = budget.clone().or(Budget::from_config()).extended(exceeded);
+    }
+
     /// Update the provider used by this agent
     pub async fn update_provider(&self, provider: Arc<dyn Provider>) -> Result<()> {
         *self.provider.lock().await = Some(provider);
@@ -596,86 +825,35 @@ impl Agent {
         );
 
         let recipe_prompt = prompt_manager.get_recipe_prompt().await;
-        let tools = extension_manager.get_prefixed_tools(None).await?;
 
         messages.push(Message::user().with_text(recipe_prompt));
 
-        let (result, _usage) = self
-            .provider
-            .lock()
-            .await
-            .as_ref()
-            .unwrap()
-            .complete(&system_prompt, &messages, &tools)
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "instructions": {"type": "string"},
+                "activities": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["instructions", "activities"]
+        });
+        let (response, _usage) = provider
+            .complete_structured(&system_prompt, &messages, &schema)
             .await?;
 
-        let content = result.as_concat_text();
-
-        // the response may be contained in ```json ```, strip that before parsing json
-        let re = Regex::new(r"(?s)```[^\n]*\n(.*?)\n```").unwrap();
-        let clean_content = re
-            .captures(&content)
-            .and_then(|caps| caps.get(1).map(|m| m.as_str()))
-            .unwrap_or(&content)
-            .trim()
+        // The response has been validated against the schema
+        let instructions = response["instructions"]
+            .as_str()
+            .unwrap_or_default()
             .to_string();
-
-        // try to parse json response from the LLM
-        let (instructions, activities) =
-            if let Ok(json_content) = serde_json::from_str::<Value>(&clean_content) {
-                let instructions = json_content
-                    .get("instructions")
-                    .ok_or_else(|| anyhow!("Missing 'instructions' in json response"))?
-                    .as_str()
-                    .ok_or_else(|| anyhow!("instructions' is not a string"))?
-                    .to_string();
-
-                let activities = json_content
-                    .get("activities")
-                    .ok_or_else(|| anyhow!("Missing 'activities' in json response"))?
-                    .as_array()
-                    .ok_or_else(|| anyhow!("'activities' is not an array'"))?
+        let activities: Vec<String> = response["activities"]
+            .as_array()
+            .map(|activities| {
+                activities
                     .iter()
-                    .map(|act| {
-                        act.as_str()
-                            .map(|s| s.to_string())
-                            .ok_or(anyhow!("'activities' array element is not a string"))
-                    })
-                    .collect::<Result<_, _>>()?;
-
-                (instructions, activities)
-            } else {
-                // If we can't get valid JSON, try string parsing
-                // Use split_once to get the content after "Instructions:".
-                let after_instructions = content
-                    .split_once("instructions:")
-                    .map(|(_, rest)| rest)
-                    .unwrap_or(&content);
-
-                // Split once more to separate instructions from activities.
-                let (instructions_part, activities_text) = after_instructions
-                    .split_once("activities:")
-                    .unwrap_or((after_instructions, ""));
-
-                let instructions = instructions_part
-                    .trim_end_matches(|c: char| c.is_whitespace() || c == '#')
-                    .trim()
-                    .to_string();
-                let activities_text = activities_text.trim();
-
-                // Regex to remove bullet markers or numbers with an optional dot.
-                let bullet_re = Regex::new(r"^[•\-\*\d]+\.?\s*").expect("Invalid regex");
-
-                // Process each line in the activities section.
-                let activities: Vec<String> = activities_text
-                    .lines()
-                    .map(|line| bullet_re.replace(line, "").to_string())
-                    .map(|s| s.trim().to_string())
-                    .filter(|line| !line.is_empty())
-                    .collect();
-
-                (instructions, activities)
-            };
+                    .filter_map(|activity| activity.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
 
         let extensions = ExtensionConfigManager::get_all().unwrap_or_default();
         let extension_configs: Vec<_> = extensions
@@ -704,3 +882,168 @@ impl Agent {
         Ok(recipe)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MessageContent;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use mcp_core::ToolCall;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio_util::sync::CancellationToken;
+
+    use super::super::tool_execution::CANCELLED_RESPONSE;
+
+    /// Answers with a call to `tool`, or never answers if `hang` is set
+    struct MockProvider {
+        model_config: ModelConfig,
+        hang: bool,
+        tool: &'static str,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.hang {
+                futures::future::pending::<()>().await;
+            }
+            Ok((
+                Message::assistant()
+                    .with_tool_request("tool1", Ok(ToolCall::new(self.tool, json!({})))),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    /// An agent whose provider keeps calling the frontend tool `ask_user`
+    async fn agent_with_mock_provider(hang: bool) -> (Agent, Arc<MockProvider>) {
+        agent_calling_tool("ask_user", hang).await
+    }
+
+    async fn agent_calling_tool(tool: &'static str, hang: bool) -> (Agent, Arc<MockProvider>) {
+        let agent = Agent::new();
+        let provider = Arc::new(MockProvider {
+            model_config: ModelConfig::new("test-model".to_string()),
+            hang,
+            tool,
+            calls: AtomicUsize::new(0),
+        });
+        agent.update_provider(provider.clone()).await.unwrap();
+        agent
+            .add_extension(ExtensionConfig::Frontend {
+                name: "frontend".to_string(),
+                tools: vec![Tool::new("ask_user", "Ask the user", json!({}), None)],
+                instructions: None,
+                bundled: None,
+            })
+            .await
+            .unwrap();
+        (agent, provider)
+    }
+
+    #[tokio::test]
+    async fn test_cancel_while_waiting_for_provider() -> Result<()> {
+        let (agent, provider) = agent_with_mock_provider(true).await;
+        let cancel_token = CancellationToken::new();
+        let messages = vec![Message::user().with_text("hello")];
+
+        let canceller = cancel_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let events: Vec<AgentEvent> = agent
+            .reply(&messages, None, Some(cancel_token))
+            .await?
+            .try_collect()
+            .await?;
+        assert!(events.is_empty());
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_answers_pending_tool_requests() -> Result<()> {
+        let (agent, provider) = agent_with_mock_provider(false).await;
+        let cancel_token = CancellationToken::new();
+        let messages = vec![Message::user().with_text("hello")];
+
+        let mut stream = agent
+            .reply(&messages, None, Some(cancel_token.clone()))
+            .await?;
+        let mut last_message = None;
+        while let Some(event) = stream.try_next().await? {
+            if let AgentEvent::Message(message) = event {
+                // The frontend never answers, so the turn only ends once it is cancelled
+                if let Some(MessageContent::FrontendToolRequest(_)) = message.content.first() {
+                    cancel_token.cancel();
+                }
+                last_message = Some(message);
+            }
+        }
+
+        let last_message = last_message.expect("the turn should end with the tool responses");
+        assert_eq!(last_message.role, mcp_core::role::Role::User);
+        let response = last_message.content[0]
+            .as_tool_response()
+            .expect("a tool response");
+        assert_eq!(response.id, "tool1");
+        assert_eq!(
+            response.tool_result.as_ref().unwrap()[0].as_text(),
+            Some(CANCELLED_RESPONSE)
+        );
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_turn_budget_stops_the_loop() -> Result<()> {
+        // An unknown tool only gets an error back, so without a budget this would loop forever
+        let (agent, provider) = agent_calling_tool("missing_tool", false).await;
+        agent
+            .set_budget(Budget {
+                max_turns: Some(3),
+                ..Default::default()
+            })
+            .await;
+        let messages = vec![Message::user().with_text("hello")];
+
+        let events: Vec<AgentEvent> = agent
+            .reply(&messages, None, None)
+            .await?
+            .try_collect()
+            .await?;
+
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+        let Some(AgentEvent::Message(last_message)) = events.last() else {
+            panic!("the loop should end with a message");
+        };
+        match &last_message.content[0] {
+            MessageContent::BudgetExceeded(exceeded) => {
+                assert_eq!(exceeded.limit, crate::message::BudgetLimit::Turns);
+                assert_eq!(exceeded.used, 3.0);
+            }
+            other => panic!("expected the budget to be exceeded, got {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::message::{BudgetExceeded, BudgetLimit};
+use crate::providers::base::ProviderUsage;
+use crate::providers::pricing;
+use crate::session::SessionMetadata;
+
+/// Limits on how far the agent may go before it stops and asks to continue
+///
+/// Turns and tool calls are counted per run of the agent loop, tokens and cost across the whole
+/// session. Each limit is optional. Limits set on the agent (e.g. from a recipe or command line flags)
+/// take precedence over the `GOOSE_MAX_TURNS`, `GOOSE_MAX_TOOL_CALLS`, `GOOSE_MAX_TOKENS` and
+/// `GOOSE_MAX_COST` config values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Budget {
+    /// Maximum number of responses requested from the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+    /// Maximum number of tool calls the model may make
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<u32>,
+    /// Maximum number of input and output tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Maximum estimated cost in USD, see `providers::pricing`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+impl Budget {
+    /// The budget set through the config file or environment
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        Self {
+            max_turns: config.get_param("GOOSE_MAX_TURNS").ok(),
+            max_tool_calls: config.get_param("GOOSE_MAX_TOOL_CALLS").ok(),
+            max_tokens: config.get_param("GOOSE_MAX_TOKENS").ok(),
+            max_cost: config.get_param("GOOSE_MAX_COST").ok(),
+        }
+    }
+
+    /// Fill in the limits this budget leaves unset from `fallback`
+    pub fn or(self, fallback: Budget) -> Self {
+        Self {
+            max_turns: self.max_turns.or(fallback.max_turns),
+            max_tool_calls: self.max_tool_calls.or(fallback.max_tool_calls),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            max_cost: self.max_cost.or(fallback.max_cost),
+        }
+    }
+
+    /// Raise the limit that was reached by its own size, for when the user lets the agent go on
+    ///
+    /// Turns and tool calls start over with every run, so those limits stay as they are.
+    pub fn extended(self, exceeded: &BudgetExceeded) -> Self {
+        match exceeded.limit {
+            BudgetLimit::Tokens => Self {
+                max_tokens: self.max_tokens.map(|max| exceeded.used as u64 + max),
+                ..self
+            },
+            BudgetLimit::Cost => Self {
+                max_cost: self.max_cost.map(|max| exceeded.used + max),
+                ..self
+            },
+            BudgetLimit::Turns | BudgetLimit::ToolCalls => self,
+        }
+    }
+}
+
+/// What a run of the agent loop has used so far, checked against a `Budget` between turns
+#[derive(Debug, Default)]
+pub(crate) struct BudgetUsage {
+    turns: u32,
+    tool_calls: u32,
+    tokens: u64,
+    cost: f64,
+}
+
+impl BudgetUsage {
+    /// Start a run with the tokens and cost the session has already spent
+    pub fn from_session(metadata: &SessionMetadata) -> Self {
+        let tokens = metadata.accumulated_total_tokens.or_else(|| {
+            match (
+                metadata.accumulated_input_tokens,
+                metadata.accumulated_output_tokens,
+            ) {
+                (None, None) => None,
+                (input, output) => Some(input.unwrap_or(0) + output.unwrap_or(0)),
+            }
+        });
+        Self {
+            tokens: tokens.unwrap_or(0).max(0) as u64,
+            cost: metadata.accumulated_cost.unwrap_or(0.0),
+            ..Default::default()
+        }
+    }
+
+    /// Record a response from the model and the number of tool calls it made
+    pub fn record_turn(&mut self, usage: &ProviderUsage, tool_calls: usize) {
+        self.turns += 1;
+        self.tool_calls += tool_calls as u32;
+        self.record_usage(usage);
+    }
+
+    /// Record tokens and cost spent outside of a turn, such as on sampling for an extension
+    pub fn record_usage(&mut self, usage: &ProviderUsage) {
+        let tokens = usage.usage.total_tokens.or_else(|| {
+            match (usage.usage.input_tokens, usage.usage.output_tokens) {
+                (None, None) => None,
+                (input, output) => Some(input.unwrap_or(0) + output.unwrap_or(0)),
+            }
+        });
+        self.tokens += tokens.unwrap_or(0).max(0) as u64;
+        self.cost += pricing::estimate_cost(&usage.model, &usage.usage).unwrap_or(0.0);
+    }
+
+    /// How many tokens are left before `budget` is reached, `None` when there is no limit
+    pub fn remaining_tokens(&self, budget: &Budget) -> Option<u64> {
+        budget.max_tokens.map(|max| max.saturating_sub(self.tokens))
+    }
+
+    /// The first limit in `budget` that has been reached, if any
+    pub fn exceeded(&self, budget: &Budget) -> Option<BudgetExceeded> {
+        let limits = [
+            (
+                BudgetLimit::Turns,
+                budget.max_turns.map(f64::from),
+                self.turns as f64,
+                "GOOSE_MAX_TURNS",
+            ),
+            (
+                BudgetLimit::ToolCalls,
+                budget.max_tool_calls.map(f64::from),
+                self.tool_calls as f64,
+                "GOOSE_MAX_TOOL_CALLS",
+            ),
+            (
+                BudgetLimit::Tokens,
+                budget.max_tokens.map(|max| max as f64),
+                self.tokens as f64,
+                "GOOSE_MAX_TOKENS",
+            ),
+            (
+                BudgetLimit::Cost,
+                budget.max_cost,
+                self.cost,
+                "GOOSE_MAX_COST",
+            ),
+        ];
+
+        limits
+            .into_iter()
+            .find_map(|(limit, max, used, key)| match max {
+                Some(max) if used >= max => Some(BudgetExceeded {
+                    limit,
+                    max,
+                    used,
+                    msg: match limit {
+                        BudgetLimit::Cost => format!(
+                            "Stopped after spending an estimated ${:.4}, the limit is ${:.4} ({}).",
+                            used, max, key
+                        ),
+                        _ => format!(
+                            "Stopped after using {} {}, the limit is {} ({}).",
+                            used, limit, max, key
+                        ),
+                    },
+                }),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    fn usage(model: &str, input: i32, output: i32) -> ProviderUsage {
+        ProviderUsage::new(
+            model.to_string(),
+            Usage::new(Some(input), Some(output), Some(input + output)),
+        )
+    }
+
+    #[test]
+    fn test_unlimited_budget_is_never_exceeded() {
+        let mut used = BudgetUsage::default();
+        for _ in 0..100 {
+            used.record_turn(&usage("gpt-4o", 1_000_000, 1_000_000), 10);
+        }
+        assert_eq!(used.exceeded(&Budget::default()), None);
+    }
+
+    #[test]
+    fn test_turn_and_tool_call_limits() {
+        let budget = Budget {
+            max_turns: Some(3),
+            max_tool_calls: Some(4),
+            ..Default::default()
+        };
+        let mut used = BudgetUsage::default();
+
+        used.record_turn(&usage("mock", 1, 1), 2);
+        assert_eq!(used.exceeded(&budget), None);
+
+        used.record_turn(&usage("mock", 1, 1), 2);
+        let exceeded = used.exceeded(&budget).unwrap();
+        assert_eq!(exceeded.limit, BudgetLimit::ToolCalls);
+        assert_eq!(exceeded.max, 4.0);
+        assert_eq!(exceeded.used, 4.0);
+        assert!(exceeded.msg.contains("4 tool calls"));
+    }
+
+    #[test]
+    fn test_token_and_cost_limits() {
+        let mut used = BudgetUsage::default();
+        // 1M input and 100k output tokens of gpt-4o cost $2.5 + $1
+        used.record_turn(&usage("gpt-4o", 1_000_000, 100_000), 0);
+
+        let budget = Budget {
+            max_tokens: Some(2_000_000),
+            max_cost: Some(3.0),
+            ..Default::default()
+        };
+        assert_eq!(used.remaining_tokens(&budget), Some(900_000));
+        let exceeded = used.exceeded(&budget).unwrap();
+        assert_eq!(exceeded.limit, BudgetLimit::Cost);
+        assert!((exceeded.used - 3.5).abs() < 1e-9);
+
+        let budget = Budget {
+            max_tokens: Some(1_000_000),
+            ..Default::default()
+        };
+        assert_eq!(used.exceeded(&budget).unwrap().limit, BudgetLimit::Tokens);
+    }
+
+    #[test]
+    fn test_budget_or_prefers_own_limits() {
+        let recipe = Budget {
+            max_turns: Some(5),
+            ..Default::default()
+        };
+        let config = Budget {
+            max_turns: Some(50),
+            max_cost: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            recipe.or(config),
+            Budget {
+                max_turns: Some(5),
+                max_cost: Some(1.0),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_session_usage_carries_over() {
+        let budget = Budget {
+            max_tokens: Some(1000),
+            max_turns: Some(1),
+            ..Default::default()
+        };
+        let metadata = SessionMetadata {
+            accumulated_total_tokens: Some(1200),
+            ..Default::default()
+        };
+        let used = BudgetUsage::from_session(&metadata);
+        let exceeded = used.exceeded(&budget).unwrap();
+        assert_eq!(exceeded.limit, BudgetLimit::Tokens);
+
+        // Continuing grants another 1000 tokens on top of what was used
+        let budget = budget.extended(&exceeded);
+        assert_eq!(budget.max_tokens, Some(2200));
+        assert_eq!(budget.max_turns, Some(1));
+        assert_eq!(used.exceeded(&budget), None);
+    }
+}
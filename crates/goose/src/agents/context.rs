@@ -1,11 +1,14 @@
-use anyhow::Ok;
+use anyhow::{anyhow, Ok};
+use mcp_core::Role;
 
 use crate::message::Message;
 use crate::token_counter::TokenCounter;
 
 use crate::context_mgmt::summarize::summarize_messages;
-use crate::context_mgmt::truncate::{truncate_messages, OldestFirstTruncation};
-use crate::context_mgmt::{estimate_target_context_limit, get_messages_token_counts};
+use crate::context_mgmt::truncate::{drop_tool_output, truncate_messages, OldestFirstTruncation};
+use crate::context_mgmt::{
+    estimate_target_context_limit, get_messages_token_counts, ContextStrategy,
+};
 
 use super::super::agents::Agent;
 
@@ -60,4 +63,239 @@ impl Agent {
 
         Ok((new_messages, new_token_counts))
     }
+
+    /// Compact the conversation with the given strategy so that it fits within the context limit again.
+    ///
+    /// Unlike `truncate_context` and `summarize_context` this keeps the turn in progress, from the
+    /// latest user prompt onwards, so the agent can retry it. Tool output is dropped as a last resort.
+    pub async fn compact_context(
+        &self,
+        messages: &[Message],
+        strategy: ContextStrategy,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        let provider = self.provider().await?;
+        let token_counter = TokenCounter::new(provider.get_model_config().tokenizer_name());
+        let target_context_limit = estimate_target_context_limit(provider.clone());
+        let count_tokens =
+            |msg: &Message| token_counter.count_chat_tokens("", std::slice::from_ref(msg), &[]);
+
+        // Everything from the prompt that started the current turn onwards
+        let turn_start = messages
+            .iter()
+            .rposition(|m| m.role == Role::User && m.has_only_text_content())
+            .unwrap_or(0);
+
+        let compacted = match strategy {
+            ContextStrategy::Off => return Err(anyhow!("Context compaction is disabled")),
+            ContextStrategy::DropToolOutput => messages.to_vec(),
+            ContextStrategy::Truncate => {
+                // Truncation has to end on a user prompt, so the prompt stays with the history
+                let (history, turn) = messages.split_at(turn_start + 1);
+                let turn_tokens: usize = turn.iter().map(count_tokens).sum();
+                let token_counts = get_messages_token_counts(&token_counter, history);
+                match truncate_messages(
+                    history,
+                    &token_counts,
+                    target_context_limit.saturating_sub(turn_tokens),
+                    &OldestFirstTruncation,
+                ) {
+                    Result::Ok((mut truncated, _)) => {
+                        truncated.extend_from_slice(turn);
+                        truncated
+                    }
+                    Err(e) => {
+                        tracing::warn!("Falling back to dropping tool output: {}", e);
+                        messages.to_vec()
+                    }
+                }
+            }
+            ContextStrategy::Summarize => {
+                let (history, turn) = messages.split_at(turn_start);
+                if history.is_empty() {
+                    messages.to_vec()
+                } else {
+                    let (mut summarized, _) = summarize_messages(
+                        provider.clone(),
+                        history,
+                        &token_counter,
+                        target_context_limit,
+                    )
+                    .await?;
+                    // Keep the roles alternating before the current prompt
+                    summarized.push(Message::assistant().with_text(
+                        "I summarized our earlier conversation to stay within the context limit.",
+                    ));
+                    summarized.extend_from_slice(turn);
+                    summarized
+                }
+            }
+        };
+
+        let token_counts = get_messages_token_counts(&token_counter, &compacted);
+        let (compacted, token_counts) = if token_counts.iter().sum::<usize>() > target_context_limit
+        {
+            drop_tool_output(
+                &compacted,
+                &token_counts,
+                target_context_limit,
+                count_tokens,
+            )?
+        } else {
+            (compacted, token_counts)
+        };
+
+        if compacted == messages {
+            return Err(anyhow!(
+                "There is nothing left to compact in the conversation"
+            ));
+        }
+        if token_counts.iter().sum::<usize>() > target_context_limit {
+            return Err(anyhow!(
+                "Unable to compact the conversation within the context limit"
+            ));
+        }
+
+        Ok(compacted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AgentEvent;
+    use crate::context_mgmt::truncate::DROPPED_TOOL_OUTPUT;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use futures::StreamExt;
+    use mcp_core::{tool::Tool, Content, ToolCall};
+    use serde_json::json;
+    use std::result::Result::Ok;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Rejects the first `overflows` calls as too long, then answers with a fixed text
+    struct MockProvider {
+        model_config: ModelConfig,
+        overflows: usize,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.overflows {
+                return Err(ProviderError::ContextLengthExceeded("too long".to_string()));
+            }
+            Ok((
+                Message::assistant().with_text("Summarized content"),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    async fn agent_with_mock_provider(overflows: usize) -> Agent {
+        let agent = Agent::new();
+        let provider = MockProvider {
+            model_config: ModelConfig::new("test-model".to_string())
+                .with_context_limit(Some(20_000)),
+            overflows,
+            calls: AtomicUsize::new(0),
+        };
+        agent.update_provider(Arc::new(provider)).await.unwrap();
+        agent
+    }
+
+    #[tokio::test]
+    async fn test_compact_context_truncate_keeps_current_turn() -> anyhow::Result<()> {
+        let agent = agent_with_mock_provider(0).await;
+        let tool_call = ToolCall::new("read_file", json!({"path": "a.txt"}));
+        let messages = vec![
+            Message::user().with_text("hello ".repeat(20_000)),
+            Message::assistant().with_text("That's a lot of hellos"),
+            Message::user().with_text("read a.txt"),
+            Message::assistant().with_tool_request("tool1", Ok(tool_call)),
+            Message::user().with_tool_response("tool1", Ok(vec![Content::text("contents")])),
+        ];
+
+        let compacted = agent
+            .compact_context(&messages, ContextStrategy::Truncate)
+            .await?;
+        assert_eq!(compacted, messages[2..].to_vec());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compact_context_drop_tool_output() -> anyhow::Result<()> {
+        let agent = agent_with_mock_provider(0).await;
+        let tool_call = ToolCall::new("read_file", json!({"path": "a.txt"}));
+        let messages = vec![
+            Message::user().with_text("read a.txt"),
+            Message::assistant().with_tool_request("tool1", Ok(tool_call)),
+            Message::user()
+                .with_tool_response("tool1", Ok(vec![Content::text("hello ".repeat(20_000))])),
+        ];
+
+        let compacted = agent
+            .compact_context(&messages, ContextStrategy::DropToolOutput)
+            .await?;
+        assert_eq!(compacted.len(), messages.len());
+        let output = compacted[2].content[0].as_tool_response().unwrap();
+        assert_eq!(
+            output.tool_result.as_ref().unwrap()[0].as_text(),
+            Some(DROPPED_TOOL_OUTPUT)
+        );
+
+        // Nothing left to drop the second time around
+        assert!(agent
+            .compact_context(&compacted, ContextStrategy::DropToolOutput)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reply_compacts_and_retries_after_context_length_exceeded() -> anyhow::Result<()> {
+        let agent = agent_with_mock_provider(1).await;
+        let messages = vec![
+            Message::user().with_text("hi there"),
+            Message::assistant().with_text("hey!"),
+            Message::user().with_text("what's the meaning of life?"),
+        ];
+
+        let mut events = Vec::new();
+        let mut stream = agent.reply(&messages, None, None).await?;
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            AgentEvent::HistoryReplaced(history) => {
+                assert_eq!(history.len(), 3);
+                assert_eq!(history.last(), messages.last());
+            }
+            other => panic!("Expected the history to be replaced, got {:?}", other),
+        }
+        match &events[1] {
+            AgentEvent::Message(message) => {
+                assert_eq!(message.as_concat_text(), "Summarized content")
+            }
+            other => panic!("Expected a message, got {:?}", other),
+        }
+        Ok(())
+    }
 }
@@ -5,19 +5,26 @@ use futures::stream::{FuturesUnordered, StreamExt};
 use mcp_client::McpService;
 use mcp_core::protocol::GetPromptResult;
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::LazyLock;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::mpsc;
 use tokio::task;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 
 use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ToolInfo};
+use super::sampling::{sampling_handler, SamplingSender};
 use crate::agents::extension::Envs;
 use crate::config::{Config, ExtensionConfigManager};
+use crate::message::ToolProgress;
 use crate::prompt_template;
-use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
-use mcp_client::transport::{SseTransport, StdioTransport, Transport};
+use mcp_client::client::{
+    ClientCapabilities, ClientInfo, McpClient, McpClientTrait, ProgressHandler, SamplingCapability,
+};
+use mcp_client::transport::{
+    SseTransport, StdioTransport, StreamableHttpTransport, Transport, TransportHandle,
+};
 use mcp_core::{prompt::Prompt, Content, Tool, ToolCall, ToolError, ToolResult};
 use serde_json::Value;
 
@@ -26,13 +33,52 @@ use serde_json::Value;
 static DEFAULT_TIMESTAMP: LazyLock<DateTime<Utc>> =
     LazyLock::new(|| Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
 
-type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
+type McpClientBox = Arc<dyn McpClientTrait>;
+
+pub(crate) type ToolProgressSender = mpsc::UnboundedSender<ToolProgress>;
+pub(crate) type ToolProgressReceiver = mpsc::UnboundedReceiver<ToolProgress>;
 
 /// Manages Goose extensions / MCP clients and their interactions
 pub struct ExtensionManager {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
+    tools: Arc<ToolsCache>,
+    /// Where the sampling requests of extensions go, when something serves them
+    sampling: Option<SamplingSender>,
+    /// Where the progress of running tool calls goes, when something shows it
+    tool_progress: Option<ToolProgressSender>,
+}
+
+/// The tools of each extension, listed once and again after the extension says they changed
+#[derive(Default)]
+struct ToolsCache {
+    tools: RwLock<HashMap<String, Vec<Tool>>>,
+    /// Bumped whenever the tools of an extension change
+    version: AtomicU64,
+}
+
+impl ToolsCache {
+    fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    fn get(&self, name: &str) -> Option<Vec<Tool>> {
+        self.tools.read().unwrap().get(name).cloned()
+    }
+
+    /// Keep the tools listed when the cache was at `version`, unless they changed since
+    fn insert(&self, name: &str, tools: Vec<Tool>, version: u64) {
+        let mut cached = self.tools.write().unwrap();
+        if self.version() == version {
+            cached.insert(name.to_string(), tools);
+        }
+    }
+
+    fn invalidate(&self, name: &str) {
+        self.tools.write().unwrap().remove(name);
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -82,6 +128,54 @@ fn normalize(input: String) -> String {
     result.to_lowercase()
 }
 
+/// Wraps a started transport in a client, which also takes the messages the server sends on
+/// its own
+fn new_client<T: TransportHandle>(handle: T, timeout: Option<u64>) -> Box<dyn McpClientTrait> {
+    let server_messages = handle.server_messages();
+    let service = McpService::with_timeout(
+        handle,
+        Duration::from_secs(timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT)),
+    );
+    let client = McpClient::new(service);
+    match server_messages {
+        Some(messages) => Box::new(client.with_server_messages(messages)),
+        None => Box::new(client),
+    }
+}
+
+/// Surfaces a log message an extension sent with `notifications/message` in goose's own logs
+fn log_extension_message(extension: &str, params: Option<Value>) {
+    let params = params.unwrap_or_default();
+    let level = params
+        .get("level")
+        .and_then(Value::as_str)
+        .unwrap_or("info");
+    let logger = params
+        .get("logger")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let data = match params.get("data") {
+        Some(Value::String(text)) => text.clone(),
+        Some(data) => data.to_string(),
+        None => String::new(),
+    };
+    match level {
+        "debug" => debug!(extension, logger, "{}", data),
+        "info" | "notice" => info!(extension, logger, "{}", data),
+        "warning" => warn!(extension, logger, "{}", data),
+        // error, critical, alert and emergency
+        _ => error!(extension, logger, "{}", data),
+    }
+}
+
+/// Fills `${KEY}` placeholders in a header value from the extension's environment, so a
+/// token can be kept in the keychain instead of the config file
+fn substitute_envs(value: &str, envs: &HashMap<String, String>) -> String {
+    envs.iter().fold(value.to_string(), |value, (key, env)| {
+        value.replace(&format!("${{{}}}", key), env)
+    })
+}
+
 pub fn get_parameter_names(tool: &Tool) -> Vec<String> {
     tool.input_schema
         .get("properties")
@@ -96,6 +190,19 @@ impl Default for ExtensionManager {
     }
 }
 
+/// Tags the progress an extension reports for a tool call with the id of its tool request
+fn progress_handler(request_id: &str, sender: ToolProgressSender) -> ProgressHandler {
+    let id = request_id.to_string();
+    Arc::new(move |params| {
+        let _ = sender.send(ToolProgress {
+            id: id.clone(),
+            progress: params.progress,
+            total: params.total,
+            message: params.message,
+        });
+    })
+}
+
 impl ExtensionManager {
     /// Create a new ExtensionManager instance
     pub fn new() -> Self {
@@ -103,9 +210,30 @@ impl ExtensionManager {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            tools: Arc::new(ToolsCache::default()),
+            sampling: None,
+            tool_progress: None,
         }
     }
 
+    /// Let extensions ask for completions, handing their requests to `sender`
+    pub(crate) fn with_sampling(mut self, sender: SamplingSender) -> Self {
+        self.sampling = Some(sender);
+        self
+    }
+
+    /// Ask extensions for the progress of tool calls, and send what they report to `sender`
+    pub(crate) fn with_tool_progress(mut self, sender: ToolProgressSender) -> Self {
+        self.tool_progress = Some(sender);
+        self
+    }
+
+    /// Changes whenever the tools of an extension change, so callers can tell their list of
+    /// tools is out of date
+    pub fn tools_version(&self) -> u64 {
+        self.tools.version()
+    }
+
     pub fn supports_resources(&self) -> bool {
         !self.resource_capable_extensions.is_empty()
     }
@@ -182,14 +310,23 @@ impl ExtensionManager {
             } => {
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
                 let transport = SseTransport::new(uri, all_envs);
-                let handle = transport.start().await?;
-                let service = McpService::with_timeout(
-                    handle,
-                    Duration::from_secs(
-                        timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                    ),
-                );
-                Box::new(McpClient::new(service))
+                new_client(transport.start().await?, *timeout)
+            }
+            ExtensionConfig::StreamableHttp {
+                uri,
+                envs,
+                env_keys,
+                headers,
+                timeout,
+                ..
+            } => {
+                let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
+                let headers = headers
+                    .iter()
+                    .map(|(key, value)| (key.clone(), substitute_envs(value, &all_envs)))
+                    .collect();
+                let transport = StreamableHttpTransport::new(uri, all_envs, headers);
+                new_client(transport.start().await?, *timeout)
             }
             ExtensionConfig::Stdio {
                 cmd,
@@ -201,14 +338,7 @@ impl ExtensionManager {
             } => {
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
                 let transport = StdioTransport::new(cmd, args.to_vec(), all_envs);
-                let handle = transport.start().await?;
-                let service = McpService::with_timeout(
-                    handle,
-                    Duration::from_secs(
-                        timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                    ),
-                );
-                Box::new(McpClient::new(service))
+                new_client(transport.start().await?, *timeout)
             }
             ExtensionConfig::Builtin {
                 name,
@@ -226,29 +356,29 @@ impl ExtensionManager {
                     vec!["mcp".to_string(), name.clone()],
                     HashMap::new(),
                 );
-                let handle = transport.start().await?;
-                let service = McpService::with_timeout(
-                    handle,
-                    Duration::from_secs(
-                        timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                    ),
-                );
-                Box::new(McpClient::new(service))
+                new_client(transport.start().await?, *timeout)
             }
             _ => unreachable!(),
         };
 
-        // Initialize the client with default capabilities
+        self.watch_extension(&sanitized_name, client.as_ref());
+
+        // Initialize the client, advertising sampling when the requests are served
         let info = ClientInfo {
             name: "goose".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
         };
-        let capabilities = ClientCapabilities::default();
+        let capabilities = ClientCapabilities {
+            sampling: self
+                .sampling
+                .as_ref()
+                .map(|_| SamplingCapability::default()),
+        };
 
         let init_result = client
             .initialize(info, capabilities)
             .await
-            .map_err(|e| ExtensionError::Initialization(config.clone(), e))?;
+            .map_err(|e| ExtensionError::Initialization(Box::new(config.clone()), e))?;
 
         if let Some(instructions) = init_result.instructions {
             self.instructions
@@ -261,11 +391,53 @@ impl ExtensionManager {
         }
 
         self.clients
-            .insert(sanitized_name.clone(), Arc::new(Mutex::new(client)));
+            .insert(sanitized_name.clone(), Arc::from(client));
 
         Ok(())
     }
 
+    /// Follow what an extension says about itself: changes to its tools and its log messages,
+    /// and take its sampling requests
+    fn watch_extension(&self, name: &str, client: &dyn McpClientTrait) {
+        if let Some(sampling) = &self.sampling {
+            client.on_request(
+                "sampling/createMessage",
+                sampling_handler(name, sampling.clone()),
+            );
+        }
+
+        let tools = Arc::clone(&self.tools);
+        let extension = name.to_string();
+        client.on_notification(
+            "notifications/tools/list_changed",
+            Arc::new(move |_| {
+                debug!(extension = %extension, "Extension tools changed");
+                tools.invalidate(&extension);
+            }),
+        );
+
+        let extension = name.to_string();
+        client.on_notification(
+            "notifications/message",
+            Arc::new(move |notification| log_extension_message(&extension, notification.params)),
+        );
+
+        let extension = name.to_string();
+        client.on_notification(
+            "notifications/resources/updated",
+            Arc::new(move |notification| {
+                let uri = notification
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("uri"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                debug!(extension = %extension, uri, "Extension resource updated");
+            }),
+        );
+    }
+
     /// Get extensions info
     pub async fn get_extensions_info(&self) -> Vec<ExtensionInfo> {
         self.clients
@@ -285,6 +457,7 @@ impl ExtensionManager {
         self.clients.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        self.tools.invalidate(&sanitized_name);
         Ok(())
     }
 
@@ -341,30 +514,42 @@ impl ExtensionManager {
         let client_futures = filtered_clients.map(|(name, client)| {
             let name = name.clone();
             let client = client.clone();
+            let cache = Arc::clone(&self.tools);
 
             task::spawn(async move {
-                let mut tools = Vec::new();
-                let client_guard = client.lock().await;
-                let mut client_tools = client_guard.list_tools(None).await?;
+                let tools = match cache.get(&name) {
+                    Some(tools) => tools,
+                    None => {
+                        let version = cache.version();
+                        let mut tools = Vec::new();
+                        let mut client_tools = client.list_tools(None).await?;
+
+                        loop {
+                            tools.extend(client_tools.tools);
+
+                            // Exit loop when there are no more pages
+                            if client_tools.next_cursor.is_none() {
+                                break;
+                            }
+
+                            client_tools = client.list_tools(client_tools.next_cursor).await?;
+                        }
+                        cache.insert(&name, tools.clone(), version);
+                        tools
+                    }
+                };
 
-                loop {
-                    for tool in client_tools.tools {
-                        tools.push(Tool::new(
+                let tools = tools
+                    .into_iter()
+                    .map(|tool| {
+                        Tool::new(
                             format!("{}__{}", name, tool.name),
                             &tool.description,
                             tool.input_schema,
                             tool.annotations,
-                        ));
-                    }
-
-                    // Exit loop when there are no more pages
-                    if client_tools.next_cursor.is_none() {
-                        break;
-                    }
-
-                    client_tools = client_guard.list_tools(client_tools.next_cursor).await?;
-                }
-
+                        )
+                    })
+                    .collect();
                 Ok::<Vec<Tool>, ExtensionError>(tools)
             })
         });
@@ -390,8 +575,7 @@ impl ExtensionManager {
         let mut result: Vec<ResourceItem> = Vec::new();
 
         for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let resources = client_guard.list_resources(None).await?;
+            let resources = client.list_resources(None).await?;
 
             for resource in resources.resources {
                 // Skip reading the resource if it's not marked active
@@ -400,7 +584,7 @@ impl ExtensionManager {
                     continue;
                 }
 
-                if let Ok(contents) = client_guard.read_resource(&resource.uri).await {
+                if let Ok(contents) = client.read_resource(&resource.uri).await {
                     for content in contents.contents {
                         let (uri, content_str) = match content {
                             mcp_core::resource::ResourceContents::TextResourceContents {
@@ -511,8 +695,7 @@ impl ExtensionManager {
             .get(extension_name)
             .ok_or(ToolError::InvalidParameters(error_msg))?;
 
-        let client_guard = client.lock().await;
-        let read_result = client_guard.read_resource(uri).await.map_err(|_| {
+        let read_result = client.read_resource(uri).await.map_err(|_| {
             ToolError::ExecutionError(format!("Could not read resource with uri: {}", uri))
         })?;
 
@@ -537,8 +720,7 @@ impl ExtensionManager {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .list_resources(None)
             .await
             .map_err(|e| {
@@ -609,7 +791,13 @@ impl ExtensionManager {
         }
     }
 
-    pub async fn dispatch_tool_call(&self, tool_call: ToolCall) -> ToolResult<Vec<Content>> {
+    /// Call a tool of an extension, passing on the progress the extension reports under the id
+    /// of the tool request
+    pub async fn dispatch_tool_call(
+        &self,
+        tool_call: ToolCall,
+        request_id: &str,
+    ) -> ToolResult<Vec<Content>> {
         // Dispatch tool call based on the prefix naming convention
         let (client_name, client) = self
             .get_client_for_tool(&tool_call.name)
@@ -622,11 +810,17 @@ impl ExtensionManager {
             .and_then(|s| s.strip_prefix("__"))
             .ok_or_else(|| ToolError::NotFound(tool_call.name.clone()))?;
 
-        let client_guard = client.lock().await;
-
-        let result = client_guard
-            .call_tool(tool_name, tool_call.clone().arguments)
-            .await
+        let arguments = tool_call.arguments.clone();
+        let result = match &self.tool_progress {
+            Some(sender) => {
+                let on_progress = progress_handler(request_id, sender.clone());
+                client
+                    .call_tool_with_progress(tool_name, arguments, on_progress)
+                    .await
+            }
+            None => client.call_tool(tool_name, arguments).await,
+        };
+        let result = result
             .map(|result| result.content)
             .map_err(|e| ToolError::ExecutionError(e.to_string()));
 
@@ -646,8 +840,7 @@ impl ExtensionManager {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .list_prompts(None)
             .await
             .map_err(|e| {
@@ -712,8 +905,7 @@ impl ExtensionManager {
             .get(extension_name)
             .ok_or_else(|| anyhow::anyhow!("Extension {} not found", extension_name))?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .get_prompt(name, arguments)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get prompt: {}", e))
@@ -740,10 +932,13 @@ impl ExtensionManager {
                     ExtensionConfig::Sse {
                         description, name, ..
                     }
+                    | ExtensionConfig::StreamableHttp {
+                        description, name, ..
+                    }
                     | ExtensionConfig::Stdio {
                         description, name, ..
                     } => {
-                        // For SSE/Stdio, use description if available
+                        // For SSE/Streamable HTTP/Stdio, use description if available
                         description
                             .as_ref()
                             .map(|s| s.to_string())
@@ -794,7 +989,7 @@ mod tests {
     use mcp_client::client::McpClientTrait;
     use mcp_core::protocol::{
         CallToolResult, GetPromptResult, InitializeResult, ListPromptsResult, ListResourcesResult,
-        ListToolsResult, ReadResourceResult,
+        ListToolsResult, ProgressNotificationParams, ProgressToken, ReadResourceResult,
     };
     use serde_json::json;
 
@@ -835,6 +1030,21 @@ mod tests {
             }
         }
 
+        async fn call_tool_with_progress(
+            &self,
+            name: &str,
+            arguments: Value,
+            on_progress: ProgressHandler,
+        ) -> Result<CallToolResult, Error> {
+            on_progress(ProgressNotificationParams {
+                progress_token: ProgressToken::Number(0),
+                progress: 1.0,
+                total: Some(2.0),
+                message: Some("Halfway".to_string()),
+            });
+            self.call_tool(name, arguments).await
+        }
+
         async fn list_prompts(
             &self,
             _next_cursor: Option<String>,
@@ -849,6 +1059,29 @@ mod tests {
         ) -> Result<GetPromptResult, Error> {
             Err(Error::NotInitialized)
         }
+
+        fn on_notification(&self, _method: &str, _handler: mcp_client::NotificationHandler) {}
+
+        fn on_request(&self, _method: &str, _handler: mcp_client::RequestHandler) {}
+    }
+
+    #[test]
+    fn test_tools_cache_invalidation() {
+        let cache = ToolsCache::default();
+        let tool = Tool::new("tool", "A tool", json!({"type": "object"}), None);
+
+        let version = cache.version();
+        cache.insert("test", vec![tool.clone()], version);
+        assert_eq!(cache.get("test").map(|tools| tools.len()), Some(1));
+
+        // The extension says its tools changed, so they have to be listed again
+        cache.invalidate("test");
+        assert!(cache.version() > version);
+        assert!(cache.get("test").is_none());
+
+        // A listing that started before the change is stale and not kept
+        cache.insert("test", vec![tool], version);
+        assert!(cache.get("test").is_none());
     }
 
     #[test]
@@ -858,23 +1091,21 @@ mod tests {
         // Add some mock clients
         extension_manager.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("__client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("__client".to_string()), Arc::new(MockClient {}));
 
         extension_manager.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // Test basic case
         assert!(extension_manager
@@ -897,6 +1128,16 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_substitute_envs() {
+        let envs = HashMap::from([("API_TOKEN".to_string(), "secret".to_string())]);
+        assert_eq!(
+            substitute_envs("Bearer ${API_TOKEN}", &envs),
+            "Bearer secret"
+        );
+        assert_eq!(substitute_envs("${OTHER}", &envs), "${OTHER}");
+    }
+
     #[tokio::test]
     async fn test_dispatch_tool_call() {
         // test that dispatch_tool_call parses out the sanitized name correctly, and extracts
@@ -906,18 +1147,17 @@ mod tests {
         // Add some mock clients
         extension_manager.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
         extension_manager.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // verify a normal tool call
         let tool_call = ToolCall {
@@ -925,7 +1165,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = extension_manager.dispatch_tool_call(tool_call).await;
+        let result = extension_manager.dispatch_tool_call(tool_call, "1").await;
         assert!(result.is_ok());
 
         let tool_call = ToolCall {
@@ -933,7 +1173,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = extension_manager.dispatch_tool_call(tool_call).await;
+        let result = extension_manager.dispatch_tool_call(tool_call, "1").await;
         assert!(result.is_ok());
 
         // verify a multiple underscores dispatch
@@ -942,7 +1182,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = extension_manager.dispatch_tool_call(tool_call).await;
+        let result = extension_manager.dispatch_tool_call(tool_call, "1").await;
         assert!(result.is_ok());
 
         // Test unicode in tool name, "client 🚀" should become "client_"
@@ -951,7 +1191,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = extension_manager.dispatch_tool_call(tool_call).await;
+        let result = extension_manager.dispatch_tool_call(tool_call, "1").await;
         assert!(result.is_ok());
 
         let tool_call = ToolCall {
@@ -959,7 +1199,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = extension_manager.dispatch_tool_call(tool_call).await;
+        let result = extension_manager.dispatch_tool_call(tool_call, "1").await;
         assert!(result.is_ok());
 
         // this should error out, specifically for an ToolError::ExecutionError
@@ -969,7 +1209,7 @@ mod tests {
         };
 
         let result = extension_manager
-            .dispatch_tool_call(invalid_tool_call)
+            .dispatch_tool_call(invalid_tool_call, "1")
             .await;
         assert!(matches!(
             result.err().unwrap(),
@@ -984,8 +1224,38 @@ mod tests {
         };
 
         let result = extension_manager
-            .dispatch_tool_call(invalid_tool_call)
+            .dispatch_tool_call(invalid_tool_call, "1")
             .await;
         assert!(matches!(result.err().unwrap(), ToolError::NotFound(_)));
     }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_progress() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut extension_manager = ExtensionManager::new().with_tool_progress(tx);
+        extension_manager.clients.insert(
+            normalize("test_client".to_string()),
+            Arc::new(MockClient {}),
+        );
+
+        let tool_call = ToolCall {
+            name: "test_client__tool".to_string(),
+            arguments: json!({}),
+        };
+        let result = extension_manager
+            .dispatch_tool_call(tool_call, "request_1")
+            .await;
+        assert!(result.is_ok());
+
+        // The progress is tagged with the tool request it belongs to
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            ToolProgress {
+                id: "request_1".to_string(),
+                progress: 1.0,
+                total: Some(2.0),
+                message: Some("Halfway".to_string()),
+            }
+        );
+    }
 }
@@ -1,15 +1,19 @@
 mod agent;
+mod budget;
 mod context;
 pub mod extension;
 pub mod extension_manager;
 pub mod platform_tools;
 pub mod prompt_manager;
 mod reply_parts;
+mod sampling;
 mod tool_execution;
 mod types;
 
 pub use agent::Agent;
+pub use budget::Budget;
 pub use extension::ExtensionConfig;
 pub use extension_manager::ExtensionManager;
 pub use prompt_manager::PromptManager;
-pub use types::{FrontendTool, SessionConfig};
+pub use sampling::SAMPLING_CONFIRMATION_PREFIX;
+pub use types::{AgentEvent, FrontendTool, SessionConfig};
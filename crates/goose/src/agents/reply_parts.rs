@@ -1,12 +1,14 @@
 use anyhow::Result;
+use futures::StreamExt;
 use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::message::{Message, MessageContent, ToolRequest};
-use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::base::{Provider, ProviderStream, ProviderStreamEvent};
 use crate::providers::errors::ProviderError;
+use crate::providers::pricing;
 use crate::providers::toolshim::{
-    augment_message_with_tool_calls, modify_system_prompt_for_tool_json, OllamaInterpreter,
+    augment_message_with_tool_calls, create_interpreter, modify_system_prompt_for_tool_json,
 };
 use crate::session;
 use mcp_core::tool::Tool;
@@ -80,37 +82,57 @@ impl Agent {
             })
     }
 
-    /// Generate a response from the LLM provider
-    /// Handles toolshim transformations if needed
-    pub(crate) async fn generate_response_from_provider(
+    /// Stream a response from the LLM provider
+    /// Handles toolshim transformations on the final message if needed
+    pub(crate) async fn stream_response_from_provider(
         provider: Arc<dyn Provider>,
         system_prompt: &str,
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
-    ) -> Result<(Message, ProviderUsage), ProviderError> {
+    ) -> Result<ProviderStream, ProviderError> {
         let config = provider.get_model_config();
+        let toolshim_tools = toolshim_tools.to_vec();
 
-        // Call the provider to get a response
-        let (mut response, usage) = provider.complete(system_prompt, messages, tools).await?;
+        // Call the provider to start a response
+        let mut stream = provider.stream(system_prompt, messages, tools).await?;
 
-        // Store the model information in the global store
-        crate::providers::base::set_current_model(&usage.model);
+        Ok(Box::pin(async_stream::try_stream! {
+            while let Some(event) = stream.next().await {
+                match event? {
+                    ProviderStreamEvent::Complete(mut response, usage) => {
+                        // Store the model information in the global store
+                        crate::providers::base::set_current_model(&usage.model);
 
-        // Post-process / structure the response only if tool interpretation is enabled
-        if config.toolshim {
-            let interpreter = OllamaInterpreter::new().map_err(|e| {
-                ProviderError::ExecutionError(format!("Failed to create OllamaInterpreter: {}", e))
-            })?;
+                        // Post-process / structure the response only if tool interpretation is enabled
+                        if config.toolshim {
+                            let interpreter = create_interpreter().map_err(|e| {
+                                ProviderError::ExecutionError(format!(
+                                    "Failed to create tool interpreter: {}",
+                                    e
+                                ))
+                            })?;
 
-            response = augment_message_with_tool_calls(&interpreter, response, toolshim_tools)
-                .await
-                .map_err(|e| {
-                    ProviderError::ExecutionError(format!("Failed to augment message: {}", e))
-                })?;
-        }
+                            response = augment_message_with_tool_calls(
+                                interpreter.as_ref(),
+                                response,
+                                &toolshim_tools,
+                            )
+                            .await
+                            .map_err(|e| {
+                                ProviderError::ExecutionError(format!(
+                                    "Failed to augment message: {}",
+                                    e
+                                ))
+                            })?;
+                        }
 
-        Ok((response, usage))
+                        yield ProviderStreamEvent::Complete(response, usage);
+                    }
+                    delta => yield delta,
+                }
+            }
+        }))
     }
 
     /// Categorize tool requests from the response into different types
@@ -214,6 +236,19 @@ impl Agent {
             metadata.accumulated_output_tokens,
             usage.usage.output_tokens,
         );
+        let cost = pricing::estimate_cost(&usage.model, &usage.usage);
+        if let Some(cost) = cost {
+            metadata.accumulated_cost = Some(metadata.accumulated_cost.unwrap_or(0.0) + cost);
+        }
+
+        // Attribute the response to the model that produced it
+        let model_usage = metadata.model_usage.entry(usage.model.clone()).or_default();
+        model_usage.responses += 1;
+        model_usage.input_tokens += usage.usage.input_tokens.unwrap_or(0);
+        model_usage.output_tokens += usage.usage.output_tokens.unwrap_or(0);
+        if let Some(cost) = cost {
+            model_usage.cost = Some(model_usage.cost.unwrap_or(0.0) + cost);
+        }
         session::update_metadata(&session_file, &metadata).await?;
 
         Ok(())
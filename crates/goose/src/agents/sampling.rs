@@ -0,0 +1,372 @@
+use std::sync::Arc;
+
+use mcp_client::RequestHandler;
+use mcp_core::protocol::{
+    CreateMessageParams, CreateMessageResult, ErrorData, INTERNAL_ERROR, INVALID_PARAMS,
+    INVALID_REQUEST,
+};
+use mcp_core::{Content, Role};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::agents::budget::{Budget, BudgetUsage};
+use crate::agents::Agent;
+use crate::config::Config;
+use crate::message::Message;
+use crate::permission::Permission;
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::create_with_retries;
+use crate::token_counter::TokenCounter;
+
+/// Confirmations for sampling requests have ids starting with this, which sets them apart from
+/// the confirmations for tool calls
+pub const SAMPLING_CONFIRMATION_PREFIX: &str = "sampling_";
+
+/// The most tokens a single sampling request may ask for, unless `GOOSE_SAMPLING_MAX_TOKENS`
+/// says otherwise
+const DEFAULT_SAMPLING_MAX_TOKENS: u32 = 4096;
+
+const DEFAULT_SAMPLING_SYSTEM_PROMPT: &str = "You are a helpful assistant.";
+
+/// An extension asking for a completion from the agent's model, answered through `respond`
+pub(crate) struct SamplingRequest {
+    pub extension: String,
+    pub params: CreateMessageParams,
+    pub respond: oneshot::Sender<Result<CreateMessageResult, ErrorData>>,
+}
+
+pub(crate) type SamplingSender = mpsc::UnboundedSender<SamplingRequest>;
+pub(crate) type SamplingReceiver = mpsc::UnboundedReceiver<SamplingRequest>;
+
+fn sampling_error(code: i32, message: impl Into<String>) -> ErrorData {
+    ErrorData {
+        code,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// Hands the `sampling/createMessage` requests of an extension to the agent, and answers them
+/// with what it responds
+pub(crate) fn sampling_handler(extension: &str, sender: SamplingSender) -> RequestHandler {
+    let extension = extension.to_string();
+    Arc::new(move |request| {
+        let extension = extension.clone();
+        let sender = sender.clone();
+        Box::pin(async move {
+            let params = serde_json::from_value(request.params.unwrap_or_default())
+                .map_err(|e| sampling_error(INVALID_PARAMS, e.to_string()))?;
+            let (respond, response) = oneshot::channel();
+            sender
+                .send(SamplingRequest {
+                    extension,
+                    params,
+                    respond,
+                })
+                .map_err(|_| sampling_error(INTERNAL_ERROR, "The agent is no longer running"))?;
+            let result = response.await.map_err(|_| {
+                sampling_error(INTERNAL_ERROR, "The agent stopped before answering")
+            })??;
+            serde_json::to_value(result).map_err(|e| sampling_error(INTERNAL_ERROR, e.to_string()))
+        })
+    })
+}
+
+/// The conversation a sampling request asks to continue, as goose messages
+fn to_messages(params: &CreateMessageParams) -> Result<Vec<Message>, ErrorData> {
+    params
+        .messages
+        .iter()
+        .map(|message| {
+            let base = match message.role {
+                Role::User => Message::user(),
+                Role::Assistant => Message::assistant(),
+            };
+            match &message.content {
+                Content::Text(text) => Ok(base.with_text(&text.text)),
+                Content::Image(image) => Ok(base.with_image(&image.data, &image.mime_type)),
+                Content::Resource(_) => Err(sampling_error(
+                    INVALID_PARAMS,
+                    "Sampling messages can only hold text and images",
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Refuse requests asking for more than `limit` tokens, or for more than the run has left
+fn check_token_limits(
+    max_tokens: u32,
+    prompt_tokens: usize,
+    limit: u32,
+    remaining: Option<u64>,
+) -> Result<(), ErrorData> {
+    if max_tokens > limit {
+        return Err(sampling_error(
+            INVALID_REQUEST,
+            format!(
+                "Sampling requests may ask for up to {} tokens, this one asked for {} \
+                (GOOSE_SAMPLING_MAX_TOKENS)",
+                limit, max_tokens
+            ),
+        ));
+    }
+    let needed = prompt_tokens as u64 + max_tokens as u64;
+    match remaining {
+        Some(remaining) if needed > remaining => Err(sampling_error(
+            INVALID_REQUEST,
+            format!(
+                "The request needs up to {} tokens, only {} are left (GOOSE_MAX_TOKENS)",
+                needed, remaining
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A provider for the agent's model that writes at most `max_tokens`, at the requested
+/// temperature
+///
+/// Providers fix their settings when created, so one is created for the request from
+/// `GOOSE_PROVIDER`. If that fails, the agent's own provider is only used when its limit
+/// already fits the request.
+fn sampling_provider(
+    current: Arc<dyn Provider>,
+    params: &CreateMessageParams,
+) -> Result<Arc<dyn Provider>, ErrorData> {
+    let current_config = current.get_model_config();
+    let temperature = params.temperature.or(current_config.temperature);
+    let model_config = current_config
+        .clone()
+        .with_max_tokens(Some(params.max_tokens as i32))
+        .with_temperature(temperature);
+    let created = Config::global()
+        .get_param::<String>("GOOSE_PROVIDER")
+        .map_err(anyhow::Error::from)
+        .and_then(|name| create_with_retries(&name, model_config));
+    match created {
+        Ok(provider) => Ok(provider),
+        Err(e) => {
+            let fits = current_config
+                .max_tokens
+                .is_some_and(|max| max <= params.max_tokens as i32);
+            if fits && temperature == current_config.temperature {
+                return Ok(current);
+            }
+            Err(sampling_error(
+                INTERNAL_ERROR,
+                format!("Can't limit the completion as requested: {}", e),
+            ))
+        }
+    }
+}
+
+/// Cut `text` before the first of the stop sequences in it, if any
+fn apply_stop_sequences(text: &str, stop_sequences: &[String]) -> Option<String> {
+    stop_sequences
+        .iter()
+        .filter(|stop| !stop.is_empty())
+        .filter_map(|stop| text.find(stop.as_str()))
+        .min()
+        .map(|end| text[..end].to_string())
+}
+
+impl Agent {
+    /// The confirmation the user answers before an extension gets to use the model
+    pub(crate) fn sampling_confirmation(id: &str, request: &SamplingRequest) -> Message {
+        Message::user().with_tool_confirmation_request(
+            id.to_string(),
+            request.extension.clone(),
+            serde_json::to_value(&request.params).unwrap_or_default(),
+            Some(format!(
+                "The {} extension would like to ask the model for a completion. Allow? (y/n):",
+                request.extension
+            )),
+        )
+    }
+
+    /// Whether the user already allowed an extension to sample for the rest of the session
+    pub(crate) async fn is_sampling_allowed(&self, extension: &str) -> bool {
+        self.sampling_allowed.lock().await.contains(extension)
+    }
+
+    /// Wait for the user's answer to the confirmation with `id`
+    pub(crate) async fn wait_for_confirmation(&self, id: &str) -> Option<Permission> {
+        let mut rx = self.confirmation_rx.lock().await;
+        while let Some((req_id, confirmation)) = rx.recv().await {
+            if req_id == id {
+                return Some(confirmation.permission);
+            }
+        }
+        None
+    }
+
+    /// Answer a sampling request the user has or has not approved, counting the tokens used
+    /// against the run's budget
+    pub(crate) async fn answer_sampling_request(
+        &self,
+        request: SamplingRequest,
+        permission: Option<Permission>,
+        budget_usage: &mut BudgetUsage,
+        budget: &Budget,
+    ) {
+        let result = match permission {
+            Some(Permission::AllowOnce | Permission::AlwaysAllow) => {
+                if permission == Some(Permission::AlwaysAllow) {
+                    self.sampling_allowed
+                        .lock()
+                        .await
+                        .insert(request.extension.clone());
+                }
+                self.sample(&request.params, budget_usage.remaining_tokens(budget))
+                    .await
+                    .map(|(result, usage)| {
+                        budget_usage.record_usage(&usage);
+                        result
+                    })
+            }
+            _ => Err(sampling_error(
+                INVALID_REQUEST,
+                "The user declined the sampling request",
+            )),
+        };
+        if let Err(e) = &result {
+            tracing::warn!(extension = %request.extension, error = %e.message, "Sampling failed");
+        }
+        let _ = request.respond.send(result);
+    }
+
+    /// Get a completion from the agent's current provider
+    async fn sample(
+        &self,
+        params: &CreateMessageParams,
+        remaining_tokens: Option<u64>,
+    ) -> Result<(CreateMessageResult, ProviderUsage), ErrorData> {
+        let provider = self
+            .provider()
+            .await
+            .map_err(|e| sampling_error(INTERNAL_ERROR, e.to_string()))?;
+        let messages = to_messages(params)?;
+        let system = params
+            .system_prompt
+            .as_deref()
+            .unwrap_or(DEFAULT_SAMPLING_SYSTEM_PROMPT);
+
+        let model_config = provider.get_model_config();
+        let prompt_tokens = TokenCounter::new(model_config.tokenizer_name()).count_chat_tokens(
+            system,
+            &messages,
+            &[],
+        );
+        let limit = Config::global()
+            .get_param("GOOSE_SAMPLING_MAX_TOKENS")
+            .unwrap_or(DEFAULT_SAMPLING_MAX_TOKENS);
+        check_token_limits(params.max_tokens, prompt_tokens, limit, remaining_tokens)?;
+
+        let provider = sampling_provider(provider, params)?;
+        let (message, usage) = provider
+            .complete(system, &messages, &[])
+            .await
+            .map_err(|e| sampling_error(INTERNAL_ERROR, e.to_string()))?;
+
+        // Providers don't take stop sequences, so the text is cut at the first one instead
+        let text = message.as_concat_text();
+        let stopped = apply_stop_sequences(&text, params.stop_sequences.as_deref().unwrap_or(&[]));
+        let hit_limit = usage
+            .usage
+            .output_tokens
+            .is_some_and(|tokens| tokens >= params.max_tokens as i32);
+        let stop_reason = if stopped.is_some() {
+            "stopSequence"
+        } else if hit_limit {
+            "maxTokens"
+        } else {
+            "endTurn"
+        };
+        let result = CreateMessageResult {
+            role: Role::Assistant,
+            content: Content::text(stopped.unwrap_or(text)),
+            model: usage.model.clone(),
+            stop_reason: Some(stop_reason.to_string()),
+        };
+        Ok((result, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::protocol::SamplingMessage;
+
+    fn params(messages: Vec<SamplingMessage>) -> CreateMessageParams {
+        CreateMessageParams {
+            messages,
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens: 100,
+            stop_sequences: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_to_messages() {
+        let messages = to_messages(&params(vec![
+            SamplingMessage {
+                role: Role::User,
+                content: Content::text("What is 2 + 2?"),
+            },
+            SamplingMessage {
+                role: Role::Assistant,
+                content: Content::text("4"),
+            },
+        ]))
+        .unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, Role::User);
+        assert_eq!(messages[0].as_concat_text(), "What is 2 + 2?");
+        assert_eq!(messages[1].role, Role::Assistant);
+
+        let resource = Content::resource(mcp_core::ResourceContents::TextResourceContents {
+            uri: "file:///notes.txt".to_string(),
+            mime_type: None,
+            text: "notes".to_string(),
+        });
+        let error = to_messages(&params(vec![SamplingMessage {
+            role: Role::User,
+            content: resource,
+        }]))
+        .unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_check_token_limits() {
+        assert!(check_token_limits(100, 50, 4096, None).is_ok());
+        assert!(check_token_limits(100, 50, 4096, Some(150)).is_ok());
+
+        // Asking for more than a single request may
+        let error = check_token_limits(5000, 50, 4096, None).unwrap_err();
+        assert!(error.message.contains("GOOSE_SAMPLING_MAX_TOKENS"));
+
+        // The prompt and the completion must fit in what the run has left
+        let error = check_token_limits(100, 50, 4096, Some(149)).unwrap_err();
+        assert!(error.message.contains("GOOSE_MAX_TOKENS"));
+    }
+
+    #[test]
+    fn test_apply_stop_sequences() {
+        let stops = vec!["END".to_string(), "\n\n".to_string()];
+        assert_eq!(
+            apply_stop_sequences("one\n\ntwo END", &stops),
+            Some("one".to_string())
+        );
+        assert_eq!(
+            apply_stop_sequences("one END\n\ntwo", &stops),
+            Some("one ".to_string())
+        );
+        assert_eq!(apply_stop_sequences("one two", &stops), None);
+        assert_eq!(apply_stop_sequences("one", &[String::new()]), None);
+    }
+}
@@ -33,6 +33,30 @@ pub const CHAT_MODE_TOOL_SKIPPED_RESPONSE: &str = "Let the user know the tool ca
                                         2. **Outline Steps** - Break down the steps.\n \
                                         If needed, adjust the explanation based on user preferences or questions.";
 
+pub const CANCELLED_RESPONSE: &str = "The user cancelled this tool call before it finished. \
+    Do not assume it had any effect.";
+
+/// Respond to every request in `tool_requests` that has no response yet with [`CANCELLED_RESPONSE`]
+pub(crate) fn cancel_pending_tool_requests<'a>(
+    mut message_tool_response: Message,
+    tool_requests: impl IntoIterator<Item = &'a ToolRequest>,
+) -> Message {
+    for request in tool_requests {
+        let responded = message_tool_response
+            .content
+            .iter()
+            .filter_map(|content| content.as_tool_response())
+            .any(|response| response.id == request.id);
+        if !responded {
+            message_tool_response = message_tool_response.with_tool_response(
+                request.id.clone(),
+                Ok(vec![Content::text(CANCELLED_RESPONSE)]),
+            );
+        }
+    }
+    message_tool_response
+}
+
 impl Agent {
     pub(crate) fn handle_approval_tool_requests<'a>(
         &'a self,
@@ -1,3 +1,4 @@
+use crate::message::{Message, MessageDelta};
 use crate::session;
 use mcp_core::{Content, Tool, ToolResult};
 use serde::{Deserialize, Serialize};
@@ -23,3 +24,14 @@ pub struct SessionConfig {
     /// Working directory for the session
     pub working_dir: PathBuf,
 }
+
+/// An event yielded by `Agent::reply`
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// A complete message, to be rendered and kept in the conversation history
+    Message(Message),
+    /// A partial update to the assistant message currently being generated
+    MessageDelta(MessageDelta),
+    /// The conversation was compacted, frontends should replace their history with these messages
+    HistoryReplaced(Vec<Message>),
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use mcp_core::Tool;
+use serde::{Deserialize, Serialize};
 
 use crate::{message::Message, providers::base::Provider, token_counter::TokenCounter};
 
@@ -8,6 +9,21 @@ const ESTIMATE_FACTOR: f32 = 0.7;
 const SYSTEM_PROMPT_TOKEN_OVERHEAD: usize = 3_000;
 const TOOLS_TOKEN_OVERHEAD: usize = 5_000;
 
+/// How the agent shrinks the conversation once it no longer fits the model's context
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Replace older messages with a summary written by the model
+    #[default]
+    Summarize,
+    /// Remove the oldest messages
+    Truncate,
+    /// Replace the output of older tool calls with a placeholder
+    DropToolOutput,
+    /// Don't compact, report that the context length was exceeded instead
+    Off,
+}
+
 pub fn estimate_target_context_limit(provider: Arc<dyn Provider>) -> usize {
     let model_context_limit = provider.get_model_config().context_limit();
 
@@ -16,7 +32,7 @@ pub fn estimate_target_context_limit(provider: Arc<dyn Provider>) -> usize {
     let target_limit = (model_context_limit as f32 * ESTIMATE_FACTOR) as usize;
 
     // subtract out overhead for system prompt and tools
-    target_limit - (SYSTEM_PROMPT_TOKEN_OVERHEAD + TOOLS_TOKEN_OVERHEAD)
+    target_limit.saturating_sub(SYSTEM_PROMPT_TOKEN_OVERHEAD + TOOLS_TOKEN_OVERHEAD)
 }
 
 pub fn get_messages_token_counts(token_counter: &TokenCounter, messages: &[Message]) -> Vec<usize> {
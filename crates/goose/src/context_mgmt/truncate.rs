@@ -1,6 +1,6 @@
-use crate::message::Message;
+use crate::message::{Message, MessageContent};
 use anyhow::{anyhow, Result};
-use mcp_core::Role;
+use mcp_core::{Content, Role};
 use std::collections::HashSet;
 use tracing::debug;
 
@@ -109,6 +109,68 @@ pub fn truncate_messages(
     Ok((messages, token_counts))
 }
 
+/// Placeholder left in place of tool output that was dropped to save context
+pub const DROPPED_TOOL_OUTPUT: &str = "[Tool output removed to reduce the context length]";
+
+/// Replaces the output of tool responses, oldest first, until the conversation fits
+/// within the context limit.
+/// The tool requests and responses themselves are kept so every call stays paired.
+/// Returns the new messages and token counts, which may still exceed the limit if
+/// dropping every tool output wasn't enough.
+/// - messages: The vector of messages in the conversation.
+/// - token_counts: A parallel vector containing the token count for each message.
+/// - context_limit: The maximum allowed context length in tokens.
+/// - count_tokens: Counts the tokens of a message after its tool output was replaced.
+pub fn drop_tool_output(
+    messages: &[Message],
+    token_counts: &[usize],
+    context_limit: usize,
+    count_tokens: impl Fn(&Message) -> usize,
+) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
+    if messages.len() != token_counts.len() {
+        return Err(anyhow!(
+            "The vector for messages and token_counts must have same length"
+        ));
+    }
+
+    let mut messages = messages.to_owned();
+    let mut token_counts = token_counts.to_owned();
+    let mut total_tokens: usize = token_counts.iter().sum();
+
+    for (message, tokens) in messages.iter_mut().zip(token_counts.iter_mut()) {
+        if total_tokens <= context_limit {
+            break;
+        }
+
+        let mut dropped = false;
+        for content in message.content.iter_mut() {
+            if let MessageContent::ToolResponse(response) = content {
+                let already_dropped = matches!(
+                    &response.tool_result,
+                    Ok(output) if output.len() == 1
+                        && output[0].as_text() == Some(DROPPED_TOOL_OUTPUT)
+                );
+                if !already_dropped {
+                    response.tool_result = Ok(vec![Content::text(DROPPED_TOOL_OUTPUT)]);
+                    dropped = true;
+                }
+            }
+        }
+
+        if dropped {
+            let new_tokens = count_tokens(message);
+            debug!(
+                "Dropped tool output. Tokens removed: {}",
+                tokens.saturating_sub(new_tokens)
+            );
+            total_tokens = total_tokens - *tokens + new_tokens;
+            *tokens = new_tokens;
+        }
+    }
+
+    Ok((messages, token_counts))
+}
+
 /// Trait representing a truncation strategy
 pub trait TruncationStrategy {
     /// Determines the indices of messages to remove to fit within the context limit.
@@ -439,6 +501,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_drop_tool_output_oldest_first() -> Result<()> {
+        let tool_call = ToolCall::new("read_file", json!({"path": "a.txt"}));
+        let (messages, token_counts): (Vec<Message>, Vec<usize>) = vec![
+            user_text(0, 10),
+            assistant_tool_request("tool1", tool_call.clone(), 10),
+            user_tool_response("tool1", vec![Content::text("first output")], 100),
+            assistant_tool_request("tool2", tool_call, 10),
+            user_tool_response("tool2", vec![Content::text("second output")], 100),
+        ]
+        .into_iter()
+        .unzip();
+
+        // Dropping only the first output is enough to fit
+        let (new_messages, new_counts) = drop_tool_output(&messages, &token_counts, 150, |_| 5)?;
+        assert_eq!(new_counts, vec![10, 10, 5, 10, 100]);
+        assert_eq!(new_messages.len(), messages.len());
+
+        let outputs: Vec<_> = new_messages
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .filter_map(|c| c.as_tool_response())
+            .map(|r| r.tool_result.as_ref().unwrap()[0].as_text().unwrap())
+            .collect();
+        assert_eq!(outputs, vec![DROPPED_TOOL_OUTPUT, "second output"]);
+
+        // Still too large after dropping everything, the best effort is returned
+        let (_, new_counts) = drop_tool_output(&messages, &token_counts, 10, |_| 5)?;
+        assert_eq!(new_counts, vec![10, 10, 5, 10, 5]);
+        Ok(())
+    }
+
     #[test]
     fn test_error_cases() -> Result<()> {
         // Test impossibly small context window
@@ -86,17 +86,83 @@ pub struct FrontendToolRequest {
     pub tool_call: ToolResult<ToolCall>,
 }
 
+/// A document such as a PDF, base64 encoded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentContent {
+    pub data: String,
+    pub mime_type: String,
+    /// File name shown to the model, some providers require one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// An audio clip such as a voice note, base64 encoded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioContent {
+    pub data: String,
+    pub mime_type: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ContextLengthExceeded {
     pub msg: String,
 }
 
+/// The budget limit that stopped the agent loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetLimit {
+    Turns,
+    ToolCalls,
+    Tokens,
+    Cost,
+}
+
+impl std::fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BudgetLimit::Turns => "turns",
+            BudgetLimit::ToolCalls => "tool calls",
+            BudgetLimit::Tokens => "tokens",
+            BudgetLimit::Cost => "cost",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BudgetExceeded {
+    pub limit: BudgetLimit,
+    /// The configured maximum, in turns, tool calls, tokens or USD depending on the limit
+    pub max: f64,
+    /// How much had been used when the agent stopped, in the same unit as `max`
+    pub used: f64,
+    pub msg: String,
+}
+
+/// How far along a running tool call is, as its extension reported it
+///
+/// Progress is only meant for display while the tool runs, it never belongs in the history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ToolProgress {
+    /// The id of the tool request that is running
+    pub id: String,
+    pub progress: f64,
+    /// What `progress` counts up to, when the extension knows
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 /// Content passed inside a message, which can be both simple content and tool content
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum MessageContent {
     Text(TextContent),
     Image(ImageContent),
+    Document(DocumentContent),
+    Audio(AudioContent),
     ToolRequest(ToolRequest),
     ToolResponse(ToolResponse),
     ToolConfirmationRequest(ToolConfirmationRequest),
@@ -104,6 +170,8 @@ pub enum MessageContent {
     Thinking(ThinkingContent),
     RedactedThinking(RedactedThinkingContent),
     ContextLengthExceeded(ContextLengthExceeded),
+    BudgetExceeded(BudgetExceeded),
+    ToolProgress(ToolProgress),
 }
 
 impl MessageContent {
@@ -122,6 +190,25 @@ impl MessageContent {
         })
     }
 
+    pub fn document<S: Into<String>, T: Into<String>>(
+        data: S,
+        mime_type: T,
+        name: Option<String>,
+    ) -> Self {
+        MessageContent::Document(DocumentContent {
+            data: data.into(),
+            mime_type: mime_type.into(),
+            name,
+        })
+    }
+
+    pub fn audio<S: Into<String>, T: Into<String>>(data: S, mime_type: T) -> Self {
+        MessageContent::Audio(AudioContent {
+            data: data.into(),
+            mime_type: mime_type.into(),
+        })
+    }
+
     pub fn tool_request<S: Into<String>>(id: S, tool_call: ToolResult<ToolCall>) -> Self {
         MessageContent::ToolRequest(ToolRequest {
             id: id.into(),
@@ -172,6 +259,34 @@ impl MessageContent {
         MessageContent::ContextLengthExceeded(ContextLengthExceeded { msg: msg.into() })
     }
 
+    pub fn budget_exceeded<S: Into<String>>(
+        limit: BudgetLimit,
+        max: f64,
+        used: f64,
+        msg: S,
+    ) -> Self {
+        MessageContent::BudgetExceeded(BudgetExceeded {
+            limit,
+            max,
+            used,
+            msg: msg.into(),
+        })
+    }
+
+    pub fn tool_progress<S: Into<String>>(
+        id: S,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<String>,
+    ) -> Self {
+        MessageContent::ToolProgress(ToolProgress {
+            id: id.into(),
+            progress,
+            total,
+            message,
+        })
+    }
+
     pub fn as_tool_request(&self) -> Option<&ToolRequest> {
         if let MessageContent::ToolRequest(ref tool_request) = self {
             Some(tool_request)
@@ -236,6 +351,30 @@ impl MessageContent {
     }
 }
 
+/// A partial update to an assistant message that is still being generated
+///
+/// Deltas are only meant for display while a response streams in. The complete
+/// message is always delivered afterwards and is what belongs in the history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MessageDelta {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ToolCall {
+        /// Position of the tool call within the message, stable across deltas
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        /// A fragment of the JSON encoded arguments
+        arguments: String,
+    },
+}
+
 impl From<Content> for MessageContent {
     fn from(content: Content) -> Self {
         match content {
@@ -324,6 +463,21 @@ impl Message {
         self.with_content(MessageContent::image(data, mime_type))
     }
 
+    /// Add document content to the message
+    pub fn with_document<S: Into<String>, T: Into<String>>(
+        self,
+        data: S,
+        mime_type: T,
+        name: Option<String>,
+    ) -> Self {
+        self.with_content(MessageContent::document(data, mime_type, name))
+    }
+
+    /// Add audio content to the message
+    pub fn with_audio<S: Into<String>, T: Into<String>>(self, data: S, mime_type: T) -> Self {
+        self.with_content(MessageContent::audio(data, mime_type))
+    }
+
     /// Add a tool request to the message
     pub fn with_tool_request<S: Into<String>>(
         self,
@@ -382,6 +536,16 @@ impl Message {
         self.with_content(MessageContent::context_length_exceeded(msg))
     }
 
+    /// Add budget exceeded content to the message
+    pub fn with_budget_exceeded(self, exceeded: BudgetExceeded) -> Self {
+        self.with_content(MessageContent::BudgetExceeded(exceeded))
+    }
+
+    /// Add the progress of a running tool call to the message
+    pub fn with_tool_progress(self, progress: ToolProgress) -> Self {
+        self.with_content(MessageContent::ToolProgress(progress))
+    }
+
     /// Get the concatenated text content of the message, separated by newlines
     pub fn as_concat_text(&self) -> String {
         self.content
@@ -405,6 +569,14 @@ impl Message {
             .any(|c| matches!(c, MessageContent::ToolResponse(_)))
     }
 
+    /// Check if the message reports the progress of a running tool call, which is only shown
+    /// while the tool runs and never kept in the history
+    pub fn is_tool_progress(&self) -> bool {
+        self.content
+            .iter()
+            .any(|c| matches!(c, MessageContent::ToolProgress(_)))
+    }
+
     /// Retrieves all tool `id` from the message
     pub fn get_tool_ids(&self) -> HashSet<&str> {
         self.content
@@ -502,6 +674,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_document_and_audio_serialization() {
+        let message = Message::user()
+            .with_document("JVBERi0=", "application/pdf", Some("spec.pdf".to_string()))
+            .with_audio("UklGRg==", "audio/wav");
+
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            value["content"][0],
+            json!({"type": "document", "data": "JVBERi0=", "mimeType": "application/pdf", "name": "spec.pdf"})
+        );
+        assert_eq!(
+            value["content"][1],
+            json!({"type": "audio", "data": "UklGRg==", "mimeType": "audio/wav"})
+        );
+
+        let parsed: Message = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, message);
+    }
+
     #[test]
     fn test_error_serialization() {
         let message = Message::assistant().with_tool_request(
@@ -1,10 +1,103 @@
 use serde::{Deserialize, Serialize};
 
+use crate::config::Config;
+
 const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 
+/// Thinking budget used by the legacy CLAUDE_THINKING_ENABLED setting
+const DEFAULT_THINKING_BUDGET: u32 = 16_000;
+/// Anthropic rejects thinking budgets below this
+pub const MIN_THINKING_BUDGET: u32 = 1024;
+
 // Tokenizer names, used to infer from model name
 pub const GPT_4O_TOKENIZER: &str = "Xenova--gpt-4o";
 pub const CLAUDE_TOKENIZER: &str = "Xenova--claude-tokenizer";
+pub const GEMMA_TOKENIZER: &str = "Xenova--gemma-tokenizer";
+pub const LLAMA_TOKENIZER: &str = "Xenova--llama3-tokenizer";
+pub const QWEN_TOKENIZER: &str = "Qwen--Qwen2.5-Coder-32B-Instruct";
+pub const MISTRAL_TOKENIZER: &str = "Xenova--mistral-tokenizer-v3";
+pub const DEEPSEEK_TOKENIZER: &str = "deepseek-ai--DeepSeek-V3";
+
+/// Tokenizers by model family, matched in order against the lowercased model name
+///
+/// Gemini's tokenizer is not published, Gemma's shares its vocabulary. Distilled models
+/// such as `deepseek-r1-distill-llama` use the tokenizer of the model they were distilled into,
+/// so the base families come before DeepSeek.
+const TOKENIZER_FAMILIES: &[(&[&str], &str)] = &[
+    (&["claude"], CLAUDE_TOKENIZER),
+    (&["gemini", "gemma"], GEMMA_TOKENIZER),
+    (&["llama"], LLAMA_TOKENIZER),
+    (&["qwen", "qwq"], QWEN_TOKENIZER),
+    (
+        &["mistral", "mixtral", "codestral", "devstral", "ministral"],
+        MISTRAL_TOKENIZER,
+    ),
+    (&["deepseek"], DEEPSEEK_TOKENIZER),
+];
+
+/// How much a model should reason before it answers
+///
+/// Each provider format translates this into its own parameters: `reasoning_effort` for
+/// OpenAI o-series models, a `thinking` budget for Claude and a `thinkingBudget` for Gemini.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    /// No extended thinking, or as little reasoning as the model allows
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// Thinking budget in tokens for models that take a budget rather than an effort
+    pub fn default_budget(self) -> u32 {
+        match self {
+            ReasoningEffort::Off => 0,
+            ReasoningEffort::Low => 4_096,
+            ReasoningEffort::Medium => DEFAULT_THINKING_BUDGET,
+            ReasoningEffort::High => 32_000,
+        }
+    }
+
+    /// The `reasoning_effort` value for OpenAI o-series models, which always reason
+    pub fn as_openai_effort(self) -> &'static str {
+        match self {
+            ReasoningEffort::Off | ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+impl std::fmt::Display for ReasoningEffort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ReasoningEffort::Off => "off",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for ReasoningEffort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "off" | "none" | "false" => Ok(ReasoningEffort::Off),
+            "low" => Ok(ReasoningEffort::Low),
+            "medium" | "on" | "true" => Ok(ReasoningEffort::Medium),
+            "high" => Ok(ReasoningEffort::High),
+            other => Err(format!(
+                "Invalid reasoning effort '{}', expected one of: off, low, medium, high",
+                other
+            )),
+        }
+    }
+}
 
 /// Configuration for model-specific settings and limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,8 +105,8 @@ pub struct ModelConfig {
     /// The name of the model to use
     pub model_name: String,
     // Optional tokenizer name (corresponds to the sanitized HuggingFace tokenizer name)
-    // "Xenova/gpt-4o" -> "Xenova/gpt-4o"
-    // If not provided, best attempt will be made to infer from model name or default
+    // "Xenova/gpt-4o" -> "Xenova--gpt-4o", or a path to a local tokenizer.json
+    // If not set with GOOSE_TOKENIZER, best attempt will be made to infer from model name or default
     pub tokenizer_name: String,
     /// Optional explicit context limit that overrides any defaults
     pub context_limit: Option<usize>,
@@ -25,6 +118,12 @@ pub struct ModelConfig {
     pub toolshim: bool,
     /// Model to use for toolshim (optional as a default exists)
     pub toolshim_model: Option<String>,
+    /// How much the model should reason, None leaves it to the model's default
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Tokens the model may spend thinking, overriding the effort's default budget
+    #[serde(default)]
+    pub thinking_budget: Option<u32>,
 }
 
 impl ModelConfig {
@@ -32,11 +131,12 @@ impl ModelConfig {
     ///
     /// The context limit is set with the following precedence:
     /// 1. Explicit context_limit if provided in config
-    /// 2. Model-specific default based on model name
-    /// 3. Global default (128_000) (in get_context_limit)
+    /// 2. The limit reported by the provider's API, if the model list has been cached
+    /// 3. Model-specific default based on model name
+    /// 4. Global default (128_000) (in get_context_limit)
     pub fn new(model_name: String) -> Self {
-        let context_limit = Self::get_model_specific_limit(&model_name);
-        let tokenizer_name = Self::infer_tokenizer_name(&model_name);
+        let context_limit = crate::providers::models::cached_context_limit(&model_name)
+            .or_else(|| Self::get_model_specific_limit(&model_name));
 
         let toolshim = std::env::var("GOOSE_TOOLSHIM")
             .map(|val| val == "1" || val.to_lowercase() == "true")
@@ -48,24 +148,37 @@ impl ModelConfig {
             .ok()
             .and_then(|val| val.parse::<f32>().ok());
 
+        let config = Config::global();
+        let reasoning_effort = config
+            .get_param::<String>("GOOSE_REASONING_EFFORT")
+            .ok()
+            .and_then(|val| val.parse().ok());
+        let thinking_budget = config.get_param::<u32>("GOOSE_THINKING_BUDGET").ok();
+        let tokenizer_name = config
+            .get_param::<String>("GOOSE_TOKENIZER")
+            .unwrap_or_else(|_| Self::infer_tokenizer_name(&model_name).to_string());
+
         Self {
             model_name,
-            tokenizer_name: tokenizer_name.to_string(),
+            tokenizer_name,
             context_limit,
             temperature,
             max_tokens: None,
             toolshim,
             toolshim_model,
+            reasoning_effort,
+            thinking_budget,
         }
     }
 
     fn infer_tokenizer_name(model_name: &str) -> &'static str {
-        if model_name.contains("claude") {
-            CLAUDE_TOKENIZER
-        } else {
+        let model_name = model_name.to_lowercase();
+        TOKENIZER_FAMILIES
+            .iter()
+            .find(|(families, _)| families.iter().any(|family| model_name.contains(family)))
+            .map(|(_, tokenizer)| *tokenizer)
             // Default tokenizer
-            GPT_4O_TOKENIZER
-        }
+            .unwrap_or(GPT_4O_TOKENIZER)
     }
 
     /// Get model-specific context limit based on model name
@@ -130,6 +243,38 @@ impl ModelConfig {
         self
     }
 
+    /// Set how much the model should reason
+    pub fn with_reasoning_effort(mut self, effort: Option<ReasoningEffort>) -> Self {
+        self.reasoning_effort = effort;
+        self
+    }
+
+    /// Set the thinking budget in tokens
+    pub fn with_thinking_budget(mut self, budget: Option<u32>) -> Self {
+        self.thinking_budget = budget;
+        self
+    }
+
+    /// The thinking budget for models with extended thinking, None if thinking is off
+    ///
+    /// Without an explicit effort, thinking is on when a budget is set or when the legacy
+    /// CLAUDE_THINKING_ENABLED and CLAUDE_THINKING_BUDGET variables ask for it.
+    pub fn thinking_budget(&self) -> Option<u32> {
+        let budget = match self.reasoning_effort {
+            Some(ReasoningEffort::Off) => None,
+            Some(effort) => Some(self.thinking_budget.unwrap_or(effort.default_budget())),
+            None => self.thinking_budget.or_else(|| {
+                std::env::var("CLAUDE_THINKING_ENABLED").ok().map(|_| {
+                    std::env::var("CLAUDE_THINKING_BUDGET")
+                        .ok()
+                        .and_then(|val| val.parse().ok())
+                        .unwrap_or(DEFAULT_THINKING_BUDGET)
+                })
+            }),
+        };
+        budget.map(|budget| budget.max(MIN_THINKING_BUDGET))
+    }
+
     /// Get the tokenizer name
     pub fn tokenizer_name(&self) -> &str {
         &self.tokenizer_name
@@ -165,6 +310,29 @@ mod tests {
         assert_eq!(config.context_limit(), DEFAULT_CONTEXT_LIMIT);
     }
 
+    #[test]
+    fn test_infer_tokenizer_name() {
+        let cases = [
+            ("claude-3-5-sonnet-latest", CLAUDE_TOKENIZER),
+            ("anthropic/claude-3.7-sonnet", CLAUDE_TOKENIZER),
+            ("gemini-2.5-pro", GEMMA_TOKENIZER),
+            ("meta-llama/Llama-3.3-70B-Instruct", LLAMA_TOKENIZER),
+            ("qwen2.5-coder:32b", QWEN_TOKENIZER),
+            ("mistral-large-latest", MISTRAL_TOKENIZER),
+            ("deepseek-chat", DEEPSEEK_TOKENIZER),
+            ("deepseek-r1-distill-llama-70b", LLAMA_TOKENIZER),
+            ("gpt-4o", GPT_4O_TOKENIZER),
+            ("unknown-model", GPT_4O_TOKENIZER),
+        ];
+        for (model_name, tokenizer) in cases {
+            assert_eq!(
+                ModelConfig::infer_tokenizer_name(model_name),
+                tokenizer,
+                "{model_name}"
+            );
+        }
+    }
+
     #[test]
     fn test_model_config_settings() {
         let config = ModelConfig::new("test-model".to_string())
@@ -215,4 +383,53 @@ mod tests {
         let config = ModelConfig::new("test-model".to_string());
         assert_eq!(config.temperature, None);
     }
+
+    #[test]
+    fn test_reasoning_effort_parsing() {
+        assert_eq!("HIGH".parse(), Ok(ReasoningEffort::High));
+        assert_eq!("on".parse(), Ok(ReasoningEffort::Medium));
+        assert_eq!("off".parse(), Ok(ReasoningEffort::Off));
+        assert!("extreme".parse::<ReasoningEffort>().is_err());
+        assert_eq!(ReasoningEffort::Low.to_string(), "low");
+    }
+
+    #[test]
+    fn test_thinking_budget() {
+        temp_env::with_vars(
+            [
+                ("CLAUDE_THINKING_ENABLED", None::<&str>),
+                ("GOOSE_REASONING_EFFORT", None),
+                ("GOOSE_THINKING_BUDGET", None),
+            ],
+            || {
+                let config = ModelConfig::new("test-model".to_string())
+                    .with_reasoning_effort(Some(ReasoningEffort::High));
+                assert_eq!(config.thinking_budget(), Some(32_000));
+
+                let config = config.with_thinking_budget(Some(100));
+                assert_eq!(config.thinking_budget(), Some(MIN_THINKING_BUDGET));
+
+                let config = config.with_reasoning_effort(Some(ReasoningEffort::Off));
+                assert_eq!(config.thinking_budget(), None);
+            },
+        );
+
+        temp_env::with_vars(
+            [
+                ("CLAUDE_THINKING_ENABLED", Some("true")),
+                ("CLAUDE_THINKING_BUDGET", Some("2048")),
+                ("GOOSE_REASONING_EFFORT", None),
+            ],
+            || {
+                let config = ModelConfig::new("test-model".to_string());
+                assert_eq!(config.thinking_budget(), Some(2048));
+            },
+        );
+
+        temp_env::with_var("GOOSE_REASONING_EFFORT", Some("low"), || {
+            let config = ModelConfig::new("test-model".to_string());
+            assert_eq!(config.reasoning_effort, Some(ReasoningEffort::Low));
+            assert_eq!(config.thinking_budget(), Some(4_096));
+        });
+    }
 }
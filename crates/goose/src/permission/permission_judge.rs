@@ -5,67 +5,57 @@ use crate::message::{Message, MessageContent, ToolRequest};
 use crate::providers::base::Provider;
 use chrono::Utc;
 use indoc::indoc;
-use mcp_core::tool::ToolAnnotations;
-use mcp_core::{tool::Tool, TextContent};
+use mcp_core::TextContent;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashSet;
 use std::sync::Arc;
 
-/// Creates the tool definition for checking read-only permissions.
-fn create_read_only_tool() -> Tool {
-    Tool::new(
-        "platform__tool_by_tool_permission".to_string(),
-        indoc! {r#"
-            Analyze the tool requests and determine which ones perform read-only operations.
-
-            What constitutes a read-only operation:
-            - A read-only operation retrieves information without modifying any data or state.
-            - Examples include:
-                - Reading a file without writing to it.
-                - Querying a database without making updates.
-                - Retrieving information from APIs without performing POST, PUT, or DELETE operations.
-
-            Examples of read vs. write operations:
-            - Read Operations:
-                - `SELECT` query in SQL.
-                - Reading file metadata or content.
-                - Listing directory contents.
-            - Write Operations:
-                - `INSERT`, `UPDATE`, or `DELETE` in SQL.
-                - Writing or appending to a file.
-                - Modifying system configurations.
-                - Sending messages to Slack channel.
-
-            How to analyze tool requests:
-            - Inspect each tool request to identify its purpose based on its name and arguments.
-            - Categorize the operation as read-only if it does not involve any state or data modification.
-            - Return a list of tool names that are strictly read-only. If you cannot make the decision, then it is not read-only.
-
-            Use this analysis to generate the list of tools performing read-only operations from the provided tool requests.
-        "#}
-        .to_string(),
-        json!({
-            "type": "object",
-            "properties": {
-                "read_only_tools": {
-                    "type": "array",
-                    "items": {
-                        "type": "string"
-                    },
-                    "description": "Optional list of tool names which has read-only operations."
-                }
-            },
-            "required": []
-        }),
-        Some(ToolAnnotations {
-                title: Some("Check tool operation".to_string()),
-                read_only_hint: true,
-                destructive_hint: false,
-                idempotent_hint: false,
-                open_world_hint: false,
-            }),
-    )
+/// System prompt for checking read-only permissions.
+const READ_ONLY_SYSTEM_PROMPT: &str = indoc! {r#"
+    You are a good analyst and can detect operations whether they have read-only operations.
+
+    Analyze the tool requests and determine which ones perform read-only operations.
+
+    What constitutes a read-only operation:
+    - A read-only operation retrieves information without modifying any data or state.
+    - Examples include:
+        - Reading a file without writing to it.
+        - Querying a database without making updates.
+        - Retrieving information from APIs without performing POST, PUT, or DELETE operations.
+
+    Examples of read vs. write operations:
+    - Read Operations:
+        - `SELECT` query in SQL.
+        - Reading file metadata or content.
+        - Listing directory contents.
+    - Write Operations:
+        - `INSERT`, `UPDATE`, or `DELETE` in SQL.
+        - Writing or appending to a file.
+        - Modifying system configurations.
+        - Sending messages to Slack channel.
+
+    How to analyze tool requests:
+    - Inspect each tool request to identify its purpose based on its name and arguments.
+    - Categorize the operation as read-only if it does not involve any state or data modification.
+    - Return a list of tool names that are strictly read-only. If you cannot make the decision, then it is not read-only.
+"#};
+
+/// Schema of the response listing the read-only tools.
+fn read_only_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "read_only_tools": {
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Optional list of tool names which has read-only operations."
+            }
+        },
+        "required": []
+    })
 }
 
 /// Builds the message to be sent to the LLM for detecting read-only operations.
@@ -101,28 +91,16 @@ fn create_check_messages(tool_requests: Vec<&ToolRequest>) -> Vec<Message> {
 }
 
 /// Processes the response to extract the list of tools with read-only operations.
-fn extract_read_only_tools(response: &Message) -> Option<Vec<String>> {
-    for content in &response.content {
-        if let MessageContent::ToolRequest(tool_request) = content {
-            if let Ok(tool_call) = &tool_request.tool_call {
-                if tool_call.name == "platform__tool_by_tool_permission" {
-                    if let Value::Object(arguments) = &tool_call.arguments {
-                        if let Some(Value::Array(read_only_tools)) =
-                            arguments.get("read_only_tools")
-                        {
-                            return Some(
-                                read_only_tools
-                                    .iter()
-                                    .filter_map(|tool| tool.as_str().map(String::from))
-                                    .collect(),
-                            );
-                        }
-                    }
-                }
-            }
-        }
-    }
-    None
+fn extract_read_only_tools(response: &Value) -> Vec<String> {
+    response["read_only_tools"]
+        .as_array()
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|tool| tool.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// Executes the read-only tools detection and returns the list of tools with read-only operations.
@@ -133,22 +111,23 @@ pub async fn detect_read_only_tools(
     if tool_requests.is_empty() {
         return vec![];
     }
-    let tool = create_read_only_tool();
     let check_messages = create_check_messages(tool_requests);
 
     let res = provider
-        .complete(
-            "You are a good analyst and can detect operations whether they have read-only operations.",
+        .complete_structured(
+            READ_ONLY_SYSTEM_PROMPT,
             &check_messages,
-            &[tool.clone()],
+            &read_only_schema(),
         )
         .await;
 
     // Process the response and return an empty vector if the response is invalid
-    if let Ok((message, _usage)) = res {
-        extract_read_only_tools(&message).unwrap_or_default()
-    } else {
-        vec![]
+    match res {
+        Ok((response, _usage)) => extract_read_only_tools(&response),
+        Err(e) => {
+            tracing::warn!("Failed to detect read-only tools: {}", e);
+            vec![]
+        }
     }
 }
 
@@ -268,6 +247,7 @@ mod tests {
     use crate::model::ModelConfig;
     use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
     use crate::providers::errors::ProviderError;
+    use crate::providers::structured;
     use chrono::Utc;
     use mcp_core::ToolCall;
     use mcp_core::{tool::Tool, Role, ToolResult};
@@ -302,7 +282,7 @@ mod tests {
                     content: vec![MessageContent::ToolRequest(ToolRequest {
                         id: "mock_tool_request".to_string(),
                         tool_call: ToolResult::Ok(ToolCall {
-                            name: "platform__tool_by_tool_permission".to_string(),
+                            name: structured::RESPONSE_TOOL_NAME.to_string(),
                             arguments: json!({
                                 "read_only_tools": ["file_reader", "data_fetcher"]
                             }),
@@ -322,11 +302,12 @@ mod tests {
         })
     }
 
-    #[tokio::test]
-    async fn test_create_read_only_tool() {
-        let tool = create_read_only_tool();
-        assert_eq!(tool.name, "platform__tool_by_tool_permission");
-        assert!(tool.description.contains("read-only operation"));
+    #[test]
+    fn test_read_only_schema() {
+        let schema = read_only_schema();
+        let response = json!({"read_only_tools": ["file_reader"]});
+        assert!(structured::validate(&schema, &response).is_ok());
+        assert!(structured::validate(&schema, &json!({"read_only_tools": "file_reader"})).is_err());
     }
 
     #[test]
@@ -354,24 +335,12 @@ mod tests {
 
     #[test]
     fn test_extract_read_only_tools() {
-        let message = Message {
-            role: Role::Assistant,
-            created: Utc::now().timestamp(),
-            content: vec![MessageContent::ToolRequest(ToolRequest {
-                id: "tool_2".to_string(),
-                tool_call: ToolResult::Ok(ToolCall {
-                    name: "platform__tool_by_tool_permission".to_string(),
-                    arguments: json!({
-                        "read_only_tools": ["file_reader", "data_fetcher"]
-                    }),
-                }),
-            })],
-        };
-
-        let result = extract_read_only_tools(&message);
-        assert!(result.is_some());
-        let tools = result.unwrap();
-        assert_eq!(tools, vec!["file_reader", "data_fetcher"]);
+        let response = json!({"read_only_tools": ["file_reader", "data_fetcher"]});
+        assert_eq!(
+            extract_read_only_tools(&response),
+            vec!["file_reader", "data_fetcher"]
+        );
+        assert!(extract_read_only_tools(&json!({})).is_empty());
     }
 
     #[tokio::test]
@@ -1,15 +1,21 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use axum::http::HeaderMap;
-use reqwest::{Client, StatusCode};
-use serde_json::Value;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Response, StatusCode};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::time::Duration;
 use tokio::time::sleep;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderStream, ProviderUsage,
+};
+use super::custom::CustomEndpoint;
 use super::errors::ProviderError;
-use super::formats::anthropic::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model};
+use super::formats::anthropic::{create_request, get_usage, response_to_message, stream_response};
+use super::models::parse_anthropic_models;
+use super::structured;
+use super::utils::{emit_debug_trace, get_model, retry_delay_from_headers};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -92,6 +98,8 @@ pub struct AnthropicProvider {
     model: ModelConfig,
     #[serde(skip)]
     retry_config: RetryConfig,
+    #[serde(skip)]
+    custom_headers: HashMap<String, String>,
 }
 
 impl Default for AnthropicProvider {
@@ -122,6 +130,23 @@ impl AnthropicProvider {
             api_key,
             model,
             retry_config,
+            custom_headers: HashMap::new(),
+        })
+    }
+
+    /// Create a provider for an Anthropic compatible endpoint declared in `custom_providers`
+    pub fn from_custom(model: ModelConfig, endpoint: CustomEndpoint) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let client = Client::builder().timeout(endpoint.timeout).build()?;
+
+        Ok(Self {
+            client,
+            host: endpoint.host,
+            // The API key, if any, is already one of the endpoint's headers
+            api_key: String::new(),
+            model,
+            retry_config: Self::load_retry_config(config),
+            custom_headers: endpoint.headers,
         })
     }
 
@@ -159,7 +184,44 @@ impl AnthropicProvider {
         }
     }
 
-    async fn post(&self, headers: HeaderMap, payload: Value) -> Result<Value, ProviderError> {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if !self.api_key.is_empty() {
+            headers.insert("x-api-key", self.api_key.parse().unwrap());
+        }
+        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+
+        let is_thinking_enabled = self.model.thinking_budget().is_some();
+        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
+            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
+            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
+        }
+
+        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
+            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
+            headers.insert(
+                "anthropic-beta",
+                "token-efficient-tools-2025-02-19".parse().unwrap(),
+            );
+        }
+
+        for (key, value) in &self.custom_headers {
+            match (
+                HeaderName::from_bytes(key.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    headers.insert(name, value);
+                }
+                _ => tracing::warn!("Skipping invalid header {}", key),
+            }
+        }
+
+        headers
+    }
+
+    /// Send a request, retrying with backoff while the API is rate limited or overloaded
+    async fn send(&self, headers: HeaderMap, payload: &Value) -> Result<Response, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let url = base_url.join("v1/messages").map_err(|e| {
@@ -178,7 +240,10 @@ impl AnthropicProvider {
                     self.retry_config.max_retries
                 );
                 tracing::error!("{}", error_msg);
-                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg)));
+                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded {
+                    details: error_msg,
+                    retry_delay: None,
+                }));
             }
 
             // Make the request
@@ -186,16 +251,16 @@ impl AnthropicProvider {
                 .client
                 .post(url.clone())
                 .headers(headers.clone())
-                .json(&payload)
+                .json(payload)
                 .send()
                 .await
                 .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
 
             let status = response.status();
-            let response_payload: Option<Value> = response.json().await.ok();
 
             // Handle 429 Too Many Requests or 503 Service Unavailable
-            if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+            {
                 // Handle rate limit or server error with retry logic
                 attempts += 1;
 
@@ -214,48 +279,87 @@ impl AnthropicProvider {
                     error_message
                 );
 
+                // Prefer the delay the server asked for over our own backoff
+                let retry_delay = retry_delay_from_headers(response.headers());
+
                 // Store the error in case we need to return it after max retries
-                last_error = Some(ProviderError::RateLimitExceeded(error_message));
+                last_error = Some(ProviderError::RateLimitExceeded {
+                    details: error_message,
+                    retry_delay,
+                });
 
                 // Calculate and apply the backoff delay
-                let delay = self.retry_config.delay_for_attempt(attempts);
+                let delay =
+                    retry_delay.unwrap_or_else(|| self.retry_config.delay_for_attempt(attempts));
                 tracing::info!("Backing off for {:?} before retry", delay);
                 sleep(delay).await;
                 continue;
             }
 
-            // https://docs.anthropic.com/en/api/errors
-            return match status {
-                StatusCode::OK => response_payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
-                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                    Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
-                        Status: {}. Response: {:?}", status, response_payload)))
-                }
-                StatusCode::BAD_REQUEST => {
-                    let mut error_msg = "Unknown error".to_string();
-                    if let Some(payload) = &response_payload {
-                        if let Some(error) = payload.get("error") {
-                        tracing::debug!("Bad Request Error: {error:?}");
-                        error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
-                        if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
-                            return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
-                        }
-                    }}
-                    tracing::debug!(
-                        "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, response_payload)
-                    );
-                    Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
-                }
-                StatusCode::INTERNAL_SERVER_ERROR => {
-                    Err(ProviderError::ServerError(format!("{:?}", response_payload)))
-                }
-                _ => {
-                    tracing::debug!(
-                        "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, response_payload)
-                    );
-                    Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
-                }
-            };
+            return Ok(response);
+        }
+    }
+
+    async fn post(&self, headers: HeaderMap, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(headers, &payload).await?;
+        let status = response.status();
+        let response_payload: Option<Value> = response.json().await.ok();
+        Self::handle_response(status, response_payload)
+    }
+
+    async fn post_stream(
+        &self,
+        headers: HeaderMap,
+        payload: Value,
+    ) -> Result<Response, ProviderError> {
+        let response = self.send(headers, &payload).await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let response_payload: Option<Value> = response.json().await.ok();
+        Err(Self::handle_response(status, response_payload)
+            .err()
+            .unwrap_or_else(|| {
+                ProviderError::RequestFailed(format!("Request failed with status: {}", status))
+            }))
+    }
+
+    // https://docs.anthropic.com/en/api/errors
+    fn handle_response(
+        status: StatusCode,
+        response_payload: Option<Value>,
+    ) -> Result<Value, ProviderError> {
+        match status {
+            StatusCode::OK => response_payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                    Status: {}. Response: {:?}", status, response_payload)))
+            }
+            StatusCode::BAD_REQUEST => {
+                let mut error_msg = "Unknown error".to_string();
+                if let Some(payload) = &response_payload {
+                    if let Some(error) = payload.get("error") {
+                    tracing::debug!("Bad Request Error: {error:?}");
+                    error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
+                    if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
+                        return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
+                    }
+                }}
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, response_payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
+            }
+            StatusCode::INTERNAL_SERVER_ERROR => {
+                Err(ProviderError::ServerError(format!("{:?}", response_payload)))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, response_payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+            }
         }
     }
 }
@@ -322,26 +426,8 @@ impl Provider for AnthropicProvider {
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let payload = create_request(&self.model, system, messages, tools)?;
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("x-api-key", self.api_key.parse().unwrap());
-        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
-
-        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
-            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
-        }
-
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
-            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
-            headers.insert(
-                "anthropic-beta",
-                "token-efficient-tools-2025-02-19".parse().unwrap(),
-            );
-        }
-
         // Make request
-        let response = self.post(headers, payload.clone()).await?;
+        let response = self.post(self.headers(), payload.clone()).await?;
 
         // Parse response
         let message = response_to_message(response.clone())?;
@@ -351,4 +437,56 @@ impl Provider for AnthropicProvider {
         emit_debug_trace(&self.model, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        // Force a call to the response tool, whose input is the schema
+        let tools = [structured::response_tool(schema)];
+        let mut payload = create_request(&self.model, system, messages, &tools)?;
+        payload["tool_choice"] = json!({"type": "tool", "name": structured::RESPONSE_TOOL_NAME});
+        // Extended thinking can't be combined with a forced tool choice
+        if let Some(payload) = payload.as_object_mut() {
+            payload.remove("thinking");
+        }
+
+        let response = self.post(self.headers(), payload.clone()).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = get_usage(&response)?;
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        let value = structured::extract_structured(&message, schema)?;
+        Ok((value, ProviderUsage::new(model, usage)))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        payload["stream"] = json!(true);
+
+        let response = self.post_stream(self.headers(), payload.clone()).await?;
+
+        Ok(stream_response(response, self.model.clone(), payload))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let url = format!("{}/v1/models?limit=1000", self.host.trim_end_matches('/'));
+        let response = self.client.get(&url).headers(self.headers()).send().await?;
+        let status = response.status();
+        let response_payload: Option<Value> = response.json().await.ok();
+        let response = Self::handle_response(status, response_payload)?;
+        Ok(Some(parse_anthropic_models(&response)))
+    }
 }
@@ -118,7 +118,10 @@ impl AzureProvider {
                     DEFAULT_MAX_RETRIES
                 );
                 tracing::error!("{}", error_msg);
-                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg)));
+                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded {
+                    details: error_msg,
+                    retry_delay: None,
+                }));
             }
 
             // Get a fresh auth token for each attempt
@@ -148,9 +151,15 @@ impl AzureProvider {
                     Ok(result) => {
                         return Ok(result);
                     }
-                    Err(ProviderError::RateLimitExceeded(msg)) => {
+                    Err(ProviderError::RateLimitExceeded {
+                        details: msg,
+                        retry_delay,
+                    }) => {
                         attempts += 1;
-                        last_error = Some(ProviderError::RateLimitExceeded(msg.clone()));
+                        last_error = Some(ProviderError::RateLimitExceeded {
+                            details: msg.clone(),
+                            retry_delay,
+                        });
 
                         let retry_after =
                             if let Some(secs) = msg.to_lowercase().find("try again in ") {
@@ -163,7 +172,9 @@ impl AzureProvider {
                                 0
                             };
 
-                        let delay = if retry_after > 0 {
+                        let delay = if let Some(delay) = retry_delay {
+                            delay
+                        } else if retry_after > 0 {
                             Duration::from_secs(retry_after)
                         } else {
                             let delay = current_delay.min(DEFAULT_MAX_RETRY_INTERVAL_MS);
@@ -2,9 +2,11 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use super::errors::ProviderError;
-use crate::message::Message;
+use super::structured;
+use crate::message::{Message, MessageDelta};
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
+use serde_json::Value;
 use utoipa::ToSchema;
 
 use once_cell::sync::Lazy;
@@ -26,12 +28,53 @@ pub fn get_current_model() -> Option<String> {
 }
 
 /// Information about a model's capabilities
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
 pub struct ModelInfo {
     /// The name of the model
     pub name: String,
     /// The maximum context length this model supports
     pub context_limit: usize,
+    /// Whether the model supports tool calling, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+    /// Whether the model accepts images, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_vision: Option<bool>,
+    /// Whether the model can reason before answering, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_reasoning: Option<bool>,
+}
+
+impl ModelInfo {
+    /// Model info with the context limit guessed from the name and unknown capabilities
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let context_limit = ModelConfig::new(name.clone()).context_limit();
+        Self {
+            name,
+            context_limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_context_limit(mut self, context_limit: Option<usize>) -> Self {
+        if let Some(limit) = context_limit {
+            self.context_limit = limit;
+        }
+        self
+    }
+
+    pub fn with_capabilities(
+        mut self,
+        tools: Option<bool>,
+        vision: Option<bool>,
+        reasoning: Option<bool>,
+    ) -> Self {
+        self.supports_tools = tools;
+        self.supports_vision = vision;
+        self.supports_reasoning = reasoning;
+        self
+    }
 }
 
 /// Metadata about a provider's configuration requirements and capabilities
@@ -45,8 +88,8 @@ pub struct ProviderMetadata {
     pub description: String,
     /// The default/recommended model for this provider
     pub default_model: String,
-    /// A list of currently known models with their capabilities
-    /// TODO: eventually query the apis directly
+    /// A list of currently known models with their capabilities, providers that implement
+    /// `Provider::fetch_models` can list the live models instead
     pub known_models: Vec<ModelInfo>,
     /// Link to the docs where models can be found
     pub model_doc_link: String,
@@ -69,13 +112,7 @@ impl ProviderMetadata {
             display_name: display_name.to_string(),
             description: description.to_string(),
             default_model: default_model.to_string(),
-            known_models: model_names
-                .iter()
-                .map(|&name| ModelInfo {
-                    name: name.to_string(),
-                    context_limit: ModelConfig::new(name.to_string()).context_limit(),
-                })
-                .collect(),
+            known_models: model_names.into_iter().map(ModelInfo::new).collect(),
             model_doc_link: model_doc_link.to_string(),
             config_keys,
         }
@@ -127,9 +164,20 @@ impl ProviderUsage {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Usage {
+    /// All input tokens, including the ones read from or written to the prompt cache
     pub input_tokens: Option<i32>,
+    /// All output tokens, including reasoning tokens
     pub output_tokens: Option<i32>,
     pub total_tokens: Option<i32>,
+    /// Input tokens served from the prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: Option<i32>,
+    /// Input tokens written to the prompt cache
+    #[serde(default)]
+    pub cache_write_input_tokens: Option<i32>,
+    /// Output tokens the model spent reasoning before it answered
+    #[serde(default)]
+    pub reasoning_tokens: Option<i32>,
 }
 
 impl Usage {
@@ -142,11 +190,35 @@ impl Usage {
             input_tokens,
             output_tokens,
             total_tokens,
+            ..Default::default()
         }
     }
+
+    pub fn with_cache_tokens(mut self, read: Option<i32>, write: Option<i32>) -> Self {
+        self.cache_read_input_tokens = read;
+        self.cache_write_input_tokens = write;
+        self
+    }
+
+    pub fn with_reasoning_tokens(mut self, reasoning_tokens: Option<i32>) -> Self {
+        self.reasoning_tokens = reasoning_tokens;
+        self
+    }
+}
+
+/// An event produced while streaming a completion from a provider
+#[derive(Debug, Clone)]
+pub enum ProviderStreamEvent {
+    /// A partial piece of the response, for display as it arrives
+    Delta(MessageDelta),
+    /// The final message and usage, always the last event of a successful stream
+    Complete(Message, ProviderUsage),
 }
 
+pub type ProviderStream = BoxStream<'static, Result<ProviderStreamEvent, ProviderError>>;
+
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 
 /// Base trait for AI providers (OpenAI, Anthropic, etc)
 #[async_trait]
@@ -176,8 +248,56 @@ pub trait Provider: Send + Sync {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError>;
 
+    /// Generate the next message, yielding partial deltas as the model produces them
+    ///
+    /// The stream ends with a single `ProviderStreamEvent::Complete` carrying the same
+    /// message `complete` would have returned. The default implementation does not
+    /// stream at all and just wraps `complete`.
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let (message, usage) = self.complete(system, messages, tools).await?;
+        Ok(Box::pin(futures::stream::once(async move {
+            Ok(ProviderStreamEvent::Complete(message, usage))
+        })))
+    }
+
+    /// Whether `stream` yields deltas before the final message
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    /// Generate a response that is JSON matching `schema`, returned parsed and validated
+    ///
+    /// Providers that can constrain generation to a schema override this. The default
+    /// offers a response tool and asks for it through the system prompt, see
+    /// `structured` for how the response is extracted.
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let system = structured::structured_system_prompt(system, schema);
+        let tools = [structured::response_tool(schema)];
+        let (message, usage) = self.complete(&system, messages, &tools).await?;
+        Ok((structured::extract_structured(&message, schema)?, usage))
+    }
+
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
+
+    /// List the models available from the provider's API
+    ///
+    /// Returns `Ok(None)` for providers that can't list their models, in which case
+    /// callers fall back to `ProviderMetadata::known_models`. Use `models::list_models`
+    /// to go through the on-disk cache instead of calling the API every time.
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        Ok(None)
+    }
 }
 
 #[cfg(test)]
@@ -270,6 +390,7 @@ mod tests {
         let info = ModelInfo {
             name: "test-model".to_string(),
             context_limit: 1000,
+            ..Default::default()
         };
         assert_eq!(info.context_limit, 1000);
 
@@ -277,6 +398,7 @@ mod tests {
         let info2 = ModelInfo {
             name: "test-model".to_string(),
             context_limit: 1000,
+            ..Default::default()
         };
         assert_eq!(info, info2);
 
@@ -284,6 +406,7 @@ mod tests {
         let info3 = ModelInfo {
             name: "test-model".to_string(),
             context_limit: 2000,
+            ..Default::default()
         };
         assert_ne!(info, info3);
     }
@@ -1,14 +1,13 @@
 use std::collections::HashMap;
-use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use aws_sdk_bedrockruntime::config::ProvideCredentials;
-use aws_sdk_bedrockruntime::operation::converse::ConverseError;
+use aws_sdk_bedrockruntime::error::ProvideErrorMetadata;
+use aws_sdk_bedrockruntime::primitives::Blob;
 use aws_sdk_bedrockruntime::{types as bedrock, Client};
 use mcp_core::Tool;
 use serde_json::Value;
-use tokio::time::sleep;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
@@ -17,9 +16,12 @@ use crate::model::ModelConfig;
 use crate::providers::utils::emit_debug_trace;
 
 // Import the migrated helper functions from providers/formats/bedrock.rs
+use super::formats::anthropic::{self, supports_extended_thinking};
 use super::formats::bedrock::{
-    from_bedrock_message, from_bedrock_usage, to_bedrock_message, to_bedrock_tool_config,
+    from_bedrock_message, from_bedrock_usage, is_bedrock_claude, to_bedrock_anthropic_request,
+    to_bedrock_forced_tool_config, to_bedrock_json, to_bedrock_message, to_bedrock_tool_config,
 };
+use super::structured;
 
 pub const BEDROCK_DOC_LINK: &str =
     "https://docs.aws.amazon.com/bedrock/latest/userguide/models-supported.html";
@@ -77,6 +79,155 @@ impl Default for BedrockProvider {
     }
 }
 
+impl BedrockProvider {
+    /// Send a conversation to Bedrock, retrying while it is throttled
+    async fn converse(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        tool_config: Option<bedrock::ToolConfiguration>,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model_name = &self.model.model_name;
+
+        let mut request = self
+            .client
+            .converse()
+            .system(bedrock::SystemContentBlock::Text(system.to_string()))
+            .model_id(model_name.to_string())
+            .set_messages(Some(
+                messages
+                    .iter()
+                    .map(to_bedrock_message)
+                    .collect::<Result<_>>()?,
+            ));
+
+        // Extended thinking can't be combined with a forced tool choice
+        let forces_tool = matches!(
+            tool_config.as_ref().and_then(|config| config.tool_choice()),
+            Some(bedrock::ToolChoice::Tool(_))
+        );
+        let thinking_budget = self
+            .model
+            .thinking_budget()
+            .filter(|_| !forces_tool && supports_extended_thinking(model_name));
+        if let Some(budget_tokens) = thinking_budget {
+            // The budget counts towards max_tokens
+            let max_tokens = self.model.max_tokens.unwrap_or(8192) + budget_tokens as i32;
+            request = request
+                .inference_config(
+                    bedrock::InferenceConfiguration::builder()
+                        .max_tokens(max_tokens)
+                        .build(),
+                )
+                .additional_model_request_fields(to_bedrock_json(&serde_json::json!({
+                    "thinking": {"type": "enabled", "budget_tokens": budget_tokens}
+                })));
+        }
+
+        if let Some(tool_config) = tool_config {
+            request = request.tool_config(tool_config);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| bedrock_error(err.into_service_error()))?;
+
+        match response.output {
+            Some(bedrock::ConverseOutput::Message(message)) => {
+                let usage = response
+                    .usage
+                    .as_ref()
+                    .map(from_bedrock_usage)
+                    .unwrap_or_default();
+
+                let message = from_bedrock_message(&message)?;
+
+                // Add debug trace with input context
+                let debug_payload = serde_json::json!({
+                    "system": system,
+                    "messages": messages,
+                    "tools": tools
+                });
+                emit_debug_trace(
+                    &self.model,
+                    &debug_payload,
+                    &serde_json::to_value(&message).unwrap_or_default(),
+                    &usage,
+                );
+
+                let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
+                Ok((message, provider_usage))
+            }
+            _ => Err(ProviderError::RequestFailed(
+                "No output from Bedrock".to_string(),
+            )),
+        }
+    }
+
+    /// Send a conversation to Claude in the Anthropic format, which unlike Converse
+    /// supports prompt caching
+    async fn invoke_claude(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = to_bedrock_anthropic_request(&self.model, system, messages, tools)?;
+        let body = serde_json::to_vec(&payload)
+            .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+
+        let response = self
+            .client
+            .invoke_model()
+            .model_id(self.model.model_name.clone())
+            .content_type("application/json")
+            .accept("application/json")
+            .body(Blob::new(body))
+            .send()
+            .await
+            .map_err(|err| bedrock_error(err.into_service_error()))?;
+
+        let response: Value = serde_json::from_slice(response.body().as_ref()).map_err(|e| {
+            ProviderError::RequestFailed(format!("Invalid response from Bedrock: {}", e))
+        })?;
+        let message = anthropic::response_to_message(response.clone())?;
+        let usage = anthropic::get_usage(&response)?;
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+
+        let provider_usage = ProviderUsage::new(self.model.model_name.clone(), usage);
+        Ok((message, provider_usage))
+    }
+}
+
+/// The provider error for a failed Bedrock request
+///
+/// Throttling is reported as `RateLimitExceeded`, which `RetryProvider` backs off from.
+fn bedrock_error<E: ProvideErrorMetadata + std::fmt::Debug>(err: E) -> ProviderError {
+    match err.code() {
+        Some("ThrottlingException") => ProviderError::RateLimitExceeded {
+            details: format!("Bedrock is throttling requests: {:?}", err),
+            retry_delay: None,
+        },
+        Some("AccessDeniedException") => {
+            ProviderError::Authentication(format!("Failed to call Bedrock: {:?}", err))
+        }
+        Some("ValidationException")
+            if err
+                .message()
+                .unwrap_or_default()
+                .contains("Input is too long for requested model.") =>
+        {
+            ProviderError::ContextLengthExceeded(format!("Failed to call Bedrock: {:?}", err))
+        }
+        Some("ModelErrorException") => {
+            ProviderError::ExecutionError(format!("Failed to call Bedrock: {:?}", err))
+        }
+        _ => ProviderError::ServerError(format!("Failed to call Bedrock: {:?}", err)),
+    }
+}
+
 #[async_trait]
 impl Provider for BedrockProvider {
     fn metadata() -> ProviderMetadata {
@@ -105,134 +256,30 @@ impl Provider for BedrockProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let model_name = &self.model.model_name;
-
-        let mut request = self
-            .client
-            .converse()
-            .system(bedrock::SystemContentBlock::Text(system.to_string()))
-            .model_id(model_name.to_string())
-            .set_messages(Some(
-                messages
-                    .iter()
-                    .map(to_bedrock_message)
-                    .collect::<Result<_>>()?,
-            ));
-
-        if !tools.is_empty() {
-            request = request.tool_config(to_bedrock_tool_config(tools)?);
+        if is_bedrock_claude(&self.model.model_name) {
+            return self.invoke_claude(system, messages, tools).await;
         }
 
-        // Retry configuration
-        const MAX_RETRIES: u32 = 10;
-        const INITIAL_BACKOFF_MS: u64 = 20_000; // 20 seconds
-        const MAX_BACKOFF_MS: u64 = 120_000; // 120 seconds (2 minutes)
-
-        let mut attempts = 0;
-        let mut backoff_ms = INITIAL_BACKOFF_MS;
-
-        loop {
-            attempts += 1;
-
-            match request.clone().send().await {
-                Ok(response) => {
-                    // Successful response, process it and return
-                    return match response.output {
-                        Some(bedrock::ConverseOutput::Message(message)) => {
-                            let usage = response
-                                .usage
-                                .as_ref()
-                                .map(from_bedrock_usage)
-                                .unwrap_or_default();
-
-                            let message = from_bedrock_message(&message)?;
-
-                            // Add debug trace with input context
-                            let debug_payload = serde_json::json!({
-                                "system": system,
-                                "messages": messages,
-                                "tools": tools
-                            });
-                            emit_debug_trace(
-                                &self.model,
-                                &debug_payload,
-                                &serde_json::to_value(&message).unwrap_or_default(),
-                                &usage,
-                            );
-
-                            let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
-                            Ok((message, provider_usage))
-                        }
-                        _ => Err(ProviderError::RequestFailed(
-                            "No output from Bedrock".to_string(),
-                        )),
-                    };
-                }
-                Err(err) => {
-                    match err.into_service_error() {
-                        ConverseError::ThrottlingException(throttle_err) => {
-                            if attempts > MAX_RETRIES {
-                                // We've exhausted our retries
-                                tracing::error!(
-                                    "Failed after {MAX_RETRIES} retries: {:?}",
-                                    throttle_err
-                                );
-                                return Err(ProviderError::RateLimitExceeded(format!(
-                                    "Failed to call Bedrock after {MAX_RETRIES} retries: {:?}",
-                                    throttle_err
-                                )));
-                            }
-
-                            // Log retry attempt
-                            tracing::warn!(
-                                "Bedrock throttling error (attempt {}/{}), retrying in {} ms: {:?}",
-                                attempts,
-                                MAX_RETRIES,
-                                backoff_ms,
-                                throttle_err
-                            );
-
-                            // Wait before retry with exponential backoff
-                            sleep(Duration::from_millis(backoff_ms)).await;
-
-                            // Calculate next backoff with exponential growth, capped at max
-                            backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
-
-                            // Continue to the next retry attempt
-                            continue;
-                        }
-                        ConverseError::AccessDeniedException(err) => {
-                            return Err(ProviderError::Authentication(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                        ConverseError::ValidationException(err)
-                            if err
-                                .message()
-                                .unwrap_or_default()
-                                .contains("Input is too long for requested model.") =>
-                        {
-                            return Err(ProviderError::ContextLengthExceeded(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                        ConverseError::ModelErrorException(err) => {
-                            return Err(ProviderError::ExecutionError(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                        err => {
-                            return Err(ProviderError::ServerError(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                    }
-                }
-            }
-        }
+        let tool_config = if tools.is_empty() {
+            None
+        } else {
+            Some(to_bedrock_tool_config(tools)?)
+        };
+        self.converse(system, messages, tools, tool_config).await
+    }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        // Force a call to the response tool, whose input is the schema
+        let tool = structured::response_tool(schema);
+        let tool_config = to_bedrock_forced_tool_config(&tool)?;
+        let (message, usage) = self
+            .converse(system, messages, &[tool], Some(tool_config))
+            .await?;
+        Ok((structured::extract_structured(&message, schema)?, usage))
     }
 }
@@ -0,0 +1,237 @@
+//! Prompt caching, decided once for every provider.
+//!
+//! Claude caches the prefix of a request up to each breakpoint it is given, and later
+//! requests that share the prefix pay a fraction of the input price for it. The policy picks
+//! the prefixes that stay stable across turns: the system prompt, the tool definitions and the
+//! most recent user messages, so each turn reads what the turn before it wrote. Formats emit the
+//! hints in the shape their API takes. OpenAI, Gemini and DeepSeek cache on their own and need
+//! no hints. Every format reports cache hits and writes through `Usage`.
+
+use serde_json::{json, Value};
+
+use crate::config::Config;
+
+/// Anthropic accepts at most four breakpoints per request
+const MAX_BREAKPOINTS: usize = 4;
+/// Default number of recent user messages marked
+const DEFAULT_HISTORY_BREAKPOINTS: usize = 2;
+
+/// Which parts of a request are marked for caching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Mark the system prompt
+    pub system: bool,
+    /// Mark the end of the tool definitions, which caches all of them
+    pub tools: bool,
+    /// Number of the most recent user messages to mark
+    pub history: usize,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            system: true,
+            tools: true,
+            history: DEFAULT_HISTORY_BREAKPOINTS,
+        }
+    }
+}
+
+impl CachePolicy {
+    /// A policy that marks nothing
+    pub fn disabled() -> Self {
+        Self {
+            system: false,
+            tools: false,
+            history: 0,
+        }
+    }
+
+    /// Load the policy from config, `GOOSE_PROMPT_CACHING: false` turns caching off
+    pub fn from_config(config: &Config) -> Self {
+        if config
+            .get_param::<bool>("GOOSE_PROMPT_CACHING")
+            .unwrap_or(true)
+        {
+            Self::default()
+        } else {
+            Self::disabled()
+        }
+    }
+
+    /// Recent user messages to mark, within what is left of the breakpoint limit
+    fn history_breakpoints(&self) -> usize {
+        let fixed = usize::from(self.system) + usize::from(self.tools);
+        self.history.min(MAX_BREAKPOINTS - fixed)
+    }
+
+    /// Add `cache_control` hints to an Anthropic messages request
+    ///
+    /// Also used for Claude on Bedrock and GCP Vertex AI, which take the same request body.
+    pub fn apply_anthropic(&self, payload: &mut Value) {
+        if self.system {
+            mark_last_block(payload.get_mut("system"));
+        }
+        if self.tools {
+            mark_last_block(payload.get_mut("tools"));
+        }
+        if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+            for message in recent_user_messages(messages, self.history_breakpoints()) {
+                mark_last_block(message.get_mut("content"));
+            }
+        }
+    }
+
+    /// Add Anthropic `cache_control` hints to an OpenAI chat completions request
+    ///
+    /// For Claude served behind OpenAI compatible APIs such as OpenRouter and Databricks,
+    /// which pass the hints on. Plain text content is turned into a text block to carry them.
+    pub fn apply_openai(&self, payload: &mut Value) {
+        if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+            if self.system {
+                if let Some(system) = messages
+                    .iter_mut()
+                    .find(|message| matches!(role(message), Some("system" | "developer")))
+                {
+                    mark_last_block(system.get_mut("content").map(text_to_blocks));
+                }
+            }
+            for message in recent_user_messages(messages, self.history_breakpoints()) {
+                mark_last_block(message.get_mut("content").map(text_to_blocks));
+            }
+        }
+        if self.tools {
+            if let Some(function) = payload
+                .get_mut("tools")
+                .and_then(Value::as_array_mut)
+                .and_then(|tools| tools.last_mut())
+                .and_then(|tool| tool.get_mut("function"))
+                .and_then(Value::as_object_mut)
+            {
+                function.insert("cache_control".to_string(), ephemeral());
+            }
+        }
+    }
+}
+
+fn ephemeral() -> Value {
+    json!({ "type": "ephemeral" })
+}
+
+fn role(message: &Value) -> Option<&str> {
+    message.get("role").and_then(Value::as_str)
+}
+
+fn recent_user_messages(messages: &mut [Value], count: usize) -> impl Iterator<Item = &mut Value> {
+    messages
+        .iter_mut()
+        .rev()
+        .filter(|message| role(message) == Some("user"))
+        .take(count)
+}
+
+fn text_to_blocks(content: &mut Value) -> &mut Value {
+    if let Value::String(text) = content {
+        *content = json!([{ "type": "text", "text": text }]);
+    }
+    content
+}
+
+/// Put a breakpoint on the last block of a list, which caches everything up to it
+fn mark_last_block(blocks: Option<&mut Value>) {
+    if let Some(block) = blocks
+        .and_then(Value::as_array_mut)
+        .and_then(|blocks| blocks.last_mut())
+        .and_then(Value::as_object_mut)
+    {
+        block.insert("cache_control".to_string(), ephemeral());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anthropic_request() -> Value {
+        json!({
+            "system": [{"type": "text", "text": "You are goose"}],
+            "tools": [{"name": "shell"}, {"name": "edit"}],
+            "messages": [
+                {"role": "user", "content": [{"type": "text", "text": "one"}]},
+                {"role": "assistant", "content": [{"type": "text", "text": "two"}]},
+                {"role": "user", "content": [{"type": "text", "text": "three"}]},
+                {"role": "assistant", "content": [{"type": "text", "text": "four"}]},
+                {"role": "user", "content": [{"type": "text", "text": "five"}, {"type": "text", "text": "six"}]}
+            ]
+        })
+    }
+
+    fn is_marked(block: &Value) -> bool {
+        block.get("cache_control") == Some(&ephemeral())
+    }
+
+    #[test]
+    fn test_apply_anthropic() {
+        let mut payload = anthropic_request();
+        CachePolicy::default().apply_anthropic(&mut payload);
+
+        assert!(is_marked(&payload["system"][0]));
+        assert!(!is_marked(&payload["tools"][0]));
+        assert!(is_marked(&payload["tools"][1]));
+        let messages = &payload["messages"];
+        assert!(!is_marked(&messages[0]["content"][0]));
+        assert!(is_marked(&messages[2]["content"][0]));
+        assert!(!is_marked(&messages[3]["content"][0]));
+        assert!(!is_marked(&messages[4]["content"][0]));
+        assert!(is_marked(&messages[4]["content"][1]));
+    }
+
+    #[test]
+    fn test_disabled_and_breakpoint_limit() {
+        let mut payload = anthropic_request();
+        CachePolicy::disabled().apply_anthropic(&mut payload);
+        assert_eq!(payload, anthropic_request());
+
+        // History gets what the system prompt and tools leave of the four breakpoints
+        let mut payload = anthropic_request();
+        let policy = CachePolicy {
+            history: 5,
+            ..Default::default()
+        };
+        policy.apply_anthropic(&mut payload);
+        let marked = payload["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|message| message["content"].as_array().unwrap().iter().any(is_marked))
+            .count();
+        assert_eq!(marked, 2);
+    }
+
+    #[test]
+    fn test_apply_openai() {
+        let mut payload = json!({
+            "messages": [
+                {"role": "system", "content": "You are goose"},
+                {"role": "user", "content": "one"},
+                {"role": "assistant", "content": "two"},
+                {"role": "tool", "content": "three", "tool_call_id": "1"},
+                {"role": "user", "content": [{"type": "text", "text": "four"}, {"type": "image_url"}]}
+            ],
+            "tools": [{"type": "function", "function": {"name": "shell"}}]
+        });
+        CachePolicy::default().apply_openai(&mut payload);
+
+        let messages = &payload["messages"];
+        assert_eq!(
+            messages[0]["content"],
+            json!([{"type": "text", "text": "You are goose", "cache_control": {"type": "ephemeral"}}])
+        );
+        assert!(is_marked(&messages[1]["content"][0]));
+        assert_eq!(messages[2]["content"], "two");
+        assert_eq!(messages[3]["content"], "three");
+        assert!(!is_marked(&messages[4]["content"][0]));
+        assert!(is_marked(&messages[4]["content"][1]));
+        assert!(is_marked(&payload["tools"][0]["function"]));
+    }
+}
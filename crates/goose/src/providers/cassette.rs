@@ -0,0 +1,512 @@
+//! Record the exchanges with a provider to a cassette file and replay them later.
+//!
+//! `GOOSE_PROVIDER=record` wraps the provider named by `GOOSE_RECORD_PROVIDER` and writes
+//! every request and response to the cassette at `GOOSE_CASSETTE`. `GOOSE_PROVIDER=replay`
+//! then serves the recorded responses from that cassette without any network access or
+//! credentials, which lets `goose run` and `goose bench` run recorded sessions offline.
+//!
+//! `GOOSE_CASSETTE_MATCH` decides how a request is matched against the recording:
+//! - `messages` (default): the conversation must be the same, ignoring message timestamps
+//! - `strict`: the system prompt and tool definitions must also be the same
+//! - `sequence`: responses are served in the recorded order, whatever the request
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the path of the cassette file
+pub const CASSETTE_CONFIG_KEY: &str = "GOOSE_CASSETTE";
+/// Config key holding the name of the provider to record
+pub const RECORD_PROVIDER_CONFIG_KEY: &str = "GOOSE_RECORD_PROVIDER";
+/// Config key holding how replayed requests are matched, see `CassetteMatch`
+pub const CASSETTE_MATCH_CONFIG_KEY: &str = "GOOSE_CASSETTE_MATCH";
+
+pub const RECORD_DEFAULT_MODEL: &str = "gpt-4o";
+pub const REPLAY_DEFAULT_MODEL: &str = "replay";
+
+/// A request sent to a provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub system: String,
+    pub messages: Vec<Message>,
+    pub tools: Vec<Tool>,
+}
+
+/// A request and the response the provider gave to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: CassetteRequest,
+    pub message: Message,
+    pub usage: ProviderUsage,
+}
+
+/// The recorded exchanges with a provider, in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read cassette {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn with_interaction(
+        mut self,
+        request: CassetteRequest,
+        message: Message,
+        usage: ProviderUsage,
+    ) -> Self {
+        self.interactions.push(Interaction {
+            request,
+            message,
+            usage,
+        });
+        self
+    }
+}
+
+/// How a replayed request is matched against the recorded ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMatch {
+    /// Same conversation, ignoring message timestamps
+    #[default]
+    Messages,
+    /// Same system prompt, tool definitions and conversation
+    Strict,
+    /// The next recorded response, whatever the request
+    Sequence,
+}
+
+impl CassetteMatch {
+    fn matches(self, recorded: &CassetteRequest, request: &CassetteRequest) -> bool {
+        match self {
+            CassetteMatch::Sequence => true,
+            CassetteMatch::Messages => {
+                first_difference(&recorded.messages, &request.messages).is_none()
+            }
+            CassetteMatch::Strict => {
+                recorded.system == request.system
+                    && recorded.tools == request.tools
+                    && first_difference(&recorded.messages, &request.messages).is_none()
+            }
+        }
+    }
+}
+
+/// A message without its timestamp, which differs between a recording and its replay
+fn normalize(message: &Message) -> Value {
+    let mut value = serde_json::to_value(message).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.remove("created");
+    }
+    value
+}
+
+/// Index of the first message that differs between the two conversations
+fn first_difference(recorded: &[Message], messages: &[Message]) -> Option<usize> {
+    (0..recorded.len().max(messages.len()))
+        .find(|&i| recorded.get(i).map(normalize) != messages.get(i).map(normalize))
+}
+
+fn describe(message: Option<&Message>) -> String {
+    const MAX_LEN: usize = 300;
+    match message {
+        None => "no message".to_string(),
+        Some(message) => {
+            let json = normalize(message).to_string();
+            if json.chars().count() > MAX_LEN {
+                format!("{}...", json.chars().take(MAX_LEN).collect::<String>())
+            } else {
+                json
+            }
+        }
+    }
+}
+
+fn cassette_path(config: &Config) -> Result<PathBuf> {
+    let path: String = config.get_param(CASSETTE_CONFIG_KEY)?;
+    Ok(PathBuf::from(path))
+}
+
+/// Passes requests through to another provider and records them to a cassette
+pub struct RecordProvider {
+    inner: Arc<dyn Provider>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordProvider {
+    /// Record to a new cassette at `path`, replacing any existing one
+    pub fn new(inner: Arc<dyn Provider>, path: PathBuf) -> Self {
+        Self {
+            inner,
+            path,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let provider_name: String = config.get_param(RECORD_PROVIDER_CONFIG_KEY)?;
+        if provider_name == "record" || provider_name == "replay" {
+            return Err(anyhow::anyhow!(
+                "{} must name the provider to record, got {}",
+                RECORD_PROVIDER_CONFIG_KEY,
+                provider_name
+            ));
+        }
+        let inner = super::factory::create_provider(&provider_name, model)?;
+        Ok(Self::new(inner, cassette_path(config)?))
+    }
+}
+
+#[async_trait]
+impl Provider for RecordProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "record",
+            "Record",
+            "Record the responses of another provider to a cassette file for replay",
+            RECORD_DEFAULT_MODEL,
+            vec![],
+            "",
+            vec![
+                ConfigKey::new(RECORD_PROVIDER_CONFIG_KEY, true, false, None),
+                ConfigKey::new(CASSETTE_CONFIG_KEY, true, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let (message, usage) = self.inner.complete(system, messages, tools).await?;
+
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(Interaction {
+            request: CassetteRequest {
+                system: system.to_string(),
+                messages: messages.to_vec(),
+                tools: tools.to_vec(),
+            },
+            message: message.clone(),
+            usage: usage.clone(),
+        });
+        // Saved after every exchange so an interrupted session still leaves a usable cassette
+        cassette.save(&self.path).map_err(|e| {
+            ProviderError::ExecutionError(format!(
+                "Failed to write cassette {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+
+        Ok((message, usage))
+    }
+}
+
+/// Serves the responses recorded in a cassette
+pub struct ReplayProvider {
+    model: ModelConfig,
+    path: PathBuf,
+    mode: CassetteMatch,
+    interactions: Vec<Interaction>,
+    /// Which of the interactions have been served, each is served once
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayProvider {
+    pub fn new(model: ModelConfig, path: PathBuf, cassette: Cassette, mode: CassetteMatch) -> Self {
+        Self {
+            model,
+            path,
+            mode,
+            used: Mutex::new(vec![false; cassette.interactions.len()]),
+            interactions: cassette.interactions,
+        }
+    }
+
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let path = cassette_path(config)?;
+        let cassette = Cassette::load(&path)?;
+        let mode = config
+            .get_param(CASSETTE_MATCH_CONFIG_KEY)
+            .unwrap_or_default();
+        Ok(Self::new(model, path, cassette, mode))
+    }
+
+    fn mismatch(&self, request: &CassetteRequest, used: &[bool]) -> ProviderError {
+        let Some(next) = used.iter().position(|used| !used) else {
+            return ProviderError::ExecutionError(format!(
+                "Cassette {} has no recorded responses left, all {} were replayed",
+                self.path.display(),
+                self.interactions.len()
+            ));
+        };
+
+        let recorded = &self.interactions[next].request;
+        let detail = match first_difference(&recorded.messages, &request.messages) {
+            Some(i) => format!(
+                "message {} differs from the next recorded request (#{}):\n  recorded: {}\n  received: {}",
+                i,
+                next,
+                describe(recorded.messages.get(i)),
+                describe(request.messages.get(i))
+            ),
+            None if recorded.system != request.system => format!(
+                "the system prompt differs from the next recorded request (#{})",
+                next
+            ),
+            None => format!(
+                "the tools differ from the next recorded request (#{})",
+                next
+            ),
+        };
+        ProviderError::ExecutionError(format!(
+            "No recorded response in {} matches the request ({} matching): {}",
+            self.path.display(),
+            format!("{:?}", self.mode).to_lowercase(),
+            detail
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "replay",
+            "Replay",
+            "Serve the responses recorded in a cassette file, without network access",
+            REPLAY_DEFAULT_MODEL,
+            vec![],
+            "",
+            vec![
+                ConfigKey::new(CASSETTE_CONFIG_KEY, true, false, None),
+                ConfigKey::new(CASSETTE_MATCH_CONFIG_KEY, false, false, Some("messages")),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let request = CassetteRequest {
+            system: system.to_string(),
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+        };
+
+        let mut used = self.used.lock().unwrap();
+        let found = self
+            .interactions
+            .iter()
+            .enumerate()
+            .find(|(i, interaction)| {
+                !used[*i] && self.mode.matches(&interaction.request, &request)
+            });
+
+        match found {
+            Some((i, interaction)) => {
+                used[i] = true;
+                Ok((interaction.message.clone(), interaction.usage.clone()))
+            }
+            None => Err(self.mismatch(&request, &used)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use tempfile::tempdir;
+
+    /// Answers every request with the number of messages it was sent
+    struct CountingProvider;
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("counting".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(format!("{} messages", messages.len())),
+                ProviderUsage::new(
+                    "counting".to_string(),
+                    Usage::new(Some(1), Some(1), Some(2)),
+                ),
+            ))
+        }
+    }
+
+    fn text(message: &Message) -> String {
+        message.as_concat_text()
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("session.json");
+
+        let recorder = RecordProvider::new(Arc::new(CountingProvider), path.clone());
+        let first = vec![Message::user().with_text("hello")];
+        let second = vec![
+            Message::user().with_text("hello"),
+            Message::assistant().with_text("1 messages"),
+            Message::user().with_text("again"),
+        ];
+        recorder.complete("system", &first, &[]).await?;
+        recorder.complete("system", &second, &[]).await?;
+
+        let cassette = Cassette::load(&path)?;
+        assert_eq!(cassette.interactions.len(), 2);
+
+        // Recreated messages get new timestamps, which matching ignores
+        let replay = ReplayProvider::new(
+            ModelConfig::new("replay".to_string()),
+            path,
+            cassette,
+            CassetteMatch::Messages,
+        );
+        let again = vec![Message::user().with_text("hello")];
+        let (message, usage) = replay.complete("a different system", &again, &[]).await?;
+        assert_eq!(text(&message), "1 messages");
+        assert_eq!(usage.model, "counting");
+
+        let (message, _) = replay.complete("system", &second, &[]).await?;
+        assert_eq!(text(&message), "3 messages");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_mismatch_explains_the_difference() {
+        let recorded = CassetteRequest {
+            system: "system".to_string(),
+            messages: vec![Message::user().with_text("hello")],
+            tools: vec![],
+        };
+        let cassette = Cassette::default().with_interaction(
+            recorded,
+            Message::assistant().with_text("hi"),
+            ProviderUsage::new("mock".to_string(), Usage::default()),
+        );
+        let replay = ReplayProvider::new(
+            ModelConfig::new("replay".to_string()),
+            PathBuf::from("session.json"),
+            cassette,
+            CassetteMatch::Strict,
+        );
+
+        let err = replay
+            .complete("system", &[Message::user().with_text("goodbye")], &[])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("message 0 differs"), "{err}");
+        assert!(err.contains("goodbye"), "{err}");
+
+        let err = replay
+            .complete("other", &[Message::user().with_text("hello")], &[])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("system prompt differs"), "{err}");
+
+        replay
+            .complete("system", &[Message::user().with_text("hello")], &[])
+            .await
+            .unwrap();
+        let err = replay
+            .complete("system", &[Message::user().with_text("hello")], &[])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no recorded responses left"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_sequence_matching_ignores_the_request() -> Result<()> {
+        let request = |text: &str| CassetteRequest {
+            system: String::new(),
+            messages: vec![Message::user().with_text(text)],
+            tools: vec![],
+        };
+        let usage = ProviderUsage::new("mock".to_string(), Usage::default());
+        let cassette = Cassette::default()
+            .with_interaction(
+                request("a"),
+                Message::assistant().with_text("first"),
+                usage.clone(),
+            )
+            .with_interaction(
+                request("b"),
+                Message::assistant().with_text("second"),
+                usage,
+            );
+        let replay = ReplayProvider::new(
+            ModelConfig::new("replay".to_string()),
+            PathBuf::from("session.json"),
+            cassette,
+            CassetteMatch::Sequence,
+        );
+
+        let anything = [Message::user().with_text("anything")];
+        assert_eq!(text(&replay.complete("", &anything, &[]).await?.0), "first");
+        assert_eq!(
+            text(&replay.complete("", &anything, &[]).await?.0),
+            "second"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+//! Providers declared in the config file rather than built into goose.
+//!
+//! Any number of OpenAI, Anthropic or Ollama compatible endpoints can be added under
+//! `custom_providers`, keyed by the name used for `GOOSE_PROVIDER`:
+//!
+//! ```yaml
+//! custom_providers:
+//!   vllm:
+//!     display_name: Local vLLM
+//!     base_url: http://localhost:8000
+//!     auth:
+//!       type: none
+//!     models: [meta-llama/Llama-3.1-8B-Instruct]
+//!   gateway:
+//!     format: anthropic
+//!     base_url: https://llm-gateway.internal.example.com
+//!     api_key: GATEWAY_TOKEN
+//!     auth:
+//!       type: header
+//!       name: X-Gateway-Token
+//!     headers:
+//!       X-Team: goose
+//!     models: [claude-3-7-sonnet-latest]
+//! ```
+//!
+//! The API key is a secret named by `api_key`, `<NAME>_API_KEY` by default, so it can be
+//! set through `goose configure` like the key of any built-in provider.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::anthropic::AnthropicProvider;
+use super::base::{ConfigKey, Provider, ProviderMetadata};
+use super::ollama::OllamaProvider;
+use super::openai::OpenAiProvider;
+use crate::config::Config;
+use crate::model::ModelConfig;
+
+/// Config key holding the custom providers, by name
+pub const CUSTOM_PROVIDERS_CONFIG_KEY: &str = "custom_providers";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// The API a custom provider speaks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomProviderFormat {
+    /// OpenAI chat completions, as served by vLLM, LM Studio, LiteLLM and most gateways
+    #[default]
+    OpenAi,
+    /// Anthropic messages
+    Anthropic,
+    /// Ollama, which needs no API key
+    Ollama,
+}
+
+/// How a custom provider expects the API key to be sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CustomProviderAuth {
+    /// `Authorization: Bearer <key>`
+    Bearer,
+    /// The key as the value of the named header, e.g. `x-api-key`
+    Header { name: String },
+    /// No API key
+    None,
+}
+
+impl CustomProviderFormat {
+    fn default_auth(self) -> CustomProviderAuth {
+        match self {
+            CustomProviderFormat::OpenAi => CustomProviderAuth::Bearer,
+            CustomProviderFormat::Anthropic => CustomProviderAuth::Header {
+                name: "x-api-key".to_string(),
+            },
+            CustomProviderFormat::Ollama => CustomProviderAuth::None,
+        }
+    }
+}
+
+/// A provider declared under `custom_providers` in the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomProviderConfig {
+    /// Name shown when picking a provider, defaults to the provider's name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub format: CustomProviderFormat,
+    /// Scheme, host and port of the endpoint, e.g. `http://localhost:8000`
+    pub base_url: String,
+    /// Path of the chat completions endpoint for the openai format, `v1/chat/completions` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
+    /// How the API key is sent, defaults to what the format's API expects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<CustomProviderAuth>,
+    /// Name of the secret holding the API key, `<NAME>_API_KEY` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Extra headers sent with every request
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Models to offer, the first is the default unless `default_model` is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    /// Request timeout in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// Where and how to reach a custom provider, with its API key resolved
+#[derive(Debug, Clone)]
+pub struct CustomEndpoint {
+    pub host: String,
+    pub base_path: Option<String>,
+    /// The configured headers plus the one carrying the API key
+    pub headers: HashMap<String, String>,
+    pub timeout: Duration,
+}
+
+impl CustomProviderConfig {
+    fn auth(&self) -> CustomProviderAuth {
+        self.auth
+            .clone()
+            .unwrap_or_else(|| self.format.default_auth())
+    }
+
+    /// Name of the secret holding the API key, None if the provider takes no key
+    pub fn api_key_name(&self, name: &str) -> Option<String> {
+        match self.auth() {
+            CustomProviderAuth::None => None,
+            _ => Some(self.api_key.clone().unwrap_or_else(|| {
+                format!(
+                    "{}_API_KEY",
+                    name.to_uppercase().replace(['-', ' ', '.'], "_")
+                )
+            })),
+        }
+    }
+
+    pub fn default_model(&self) -> String {
+        self.default_model
+            .clone()
+            .or_else(|| self.models.first().cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn metadata(&self, name: &str) -> ProviderMetadata {
+        let display_name = self.display_name.as_deref().unwrap_or(name);
+        let description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("Custom provider at {}", self.base_url));
+        let config_keys = self
+            .api_key_name(name)
+            .map(|key| vec![ConfigKey::new(&key, true, true, None)])
+            .unwrap_or_default();
+
+        ProviderMetadata::new(
+            name,
+            display_name,
+            &description,
+            &self.default_model(),
+            self.models.iter().map(String::as_str).collect(),
+            &self.base_url,
+            config_keys,
+        )
+    }
+
+    /// Resolve the API key and build the headers sent with every request
+    pub fn endpoint(&self, name: &str) -> Result<CustomEndpoint> {
+        let mut headers = self.headers.clone();
+        if let Some(key_name) = self.api_key_name(name) {
+            let api_key: String = Config::global().get_secret(&key_name)?;
+            match self.auth() {
+                CustomProviderAuth::Bearer => {
+                    headers.insert("Authorization".to_string(), format!("Bearer {}", api_key));
+                }
+                CustomProviderAuth::Header { name } => {
+                    headers.insert(name, api_key);
+                }
+                CustomProviderAuth::None => {}
+            }
+        }
+
+        Ok(CustomEndpoint {
+            host: self.base_url.clone(),
+            base_path: self.base_path.clone(),
+            headers,
+            timeout: Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS)),
+        })
+    }
+
+    pub fn create(&self, name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+        let endpoint = self.endpoint(name)?;
+        Ok(match self.format {
+            CustomProviderFormat::OpenAi => Arc::new(OpenAiProvider::from_custom(model, endpoint)?),
+            CustomProviderFormat::Anthropic => {
+                Arc::new(AnthropicProvider::from_custom(model, endpoint)?)
+            }
+            CustomProviderFormat::Ollama => Arc::new(OllamaProvider::from_custom(model, endpoint)?),
+        })
+    }
+}
+
+/// The custom providers in the config file, by name
+pub fn custom_providers() -> BTreeMap<String, CustomProviderConfig> {
+    match Config::global().get_param(CUSTOM_PROVIDERS_CONFIG_KEY) {
+        Ok(providers) => providers,
+        Err(crate::config::ConfigError::NotFound(_)) => BTreeMap::new(),
+        Err(e) => {
+            tracing::warn!("Ignoring invalid {}: {}", CUSTOM_PROVIDERS_CONFIG_KEY, e);
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Look up a custom provider by name
+pub fn custom_provider(name: &str) -> Result<CustomProviderConfig> {
+    custom_providers()
+        .remove(name)
+        .ok_or_else(|| anyhow!("Unknown provider: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> CustomProviderConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_defaults_follow_the_format() {
+        let openai = parse("base_url: http://localhost:8000\nmodels: [a, b]");
+        assert_eq!(openai.format, CustomProviderFormat::OpenAi);
+        assert_eq!(openai.auth(), CustomProviderAuth::Bearer);
+        assert_eq!(
+            openai.api_key_name("lm-studio"),
+            Some("LM_STUDIO_API_KEY".to_string())
+        );
+        assert_eq!(openai.default_model(), "a");
+
+        let anthropic = parse("format: anthropic\nbase_url: https://gateway\napi_key: TOKEN");
+        assert_eq!(
+            anthropic.auth(),
+            CustomProviderAuth::Header {
+                name: "x-api-key".to_string()
+            }
+        );
+        assert_eq!(anthropic.api_key_name("gw"), Some("TOKEN".to_string()));
+
+        let ollama = parse("format: ollama\nbase_url: http://gpu-box:11434");
+        assert_eq!(ollama.api_key_name("gpu"), None);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let config = parse(
+            r#"
+display_name: Team Gateway
+base_url: https://gateway
+auth:
+  type: header
+  name: X-Token
+models: [small, large]
+default_model: large
+"#,
+        );
+        let metadata = config.metadata("team");
+        assert_eq!(metadata.name, "team");
+        assert_eq!(metadata.display_name, "Team Gateway");
+        assert_eq!(metadata.default_model, "large");
+        assert_eq!(metadata.known_models.len(), 2);
+        assert_eq!(metadata.config_keys.len(), 1);
+        assert_eq!(metadata.config_keys[0].name, "TEAM_API_KEY");
+        assert!(metadata.config_keys[0].secret);
+
+        let keyless = parse("base_url: http://localhost:1234\nauth:\n  type: none");
+        assert!(keyless.metadata("local").config_keys.is_empty());
+    }
+
+    #[test]
+    fn test_endpoint_without_key() {
+        let config = parse(
+            "base_url: http://localhost:1234\nauth:\n  type: none\nheaders:\n  X-Team: goose\ntimeout: 30",
+        );
+        let endpoint = config.endpoint("local").unwrap();
+        assert_eq!(endpoint.host, "http://localhost:1234");
+        assert_eq!(endpoint.headers.len(), 1);
+        assert_eq!(endpoint.headers["X-Team"], "goose");
+        assert_eq!(endpoint.timeout, Duration::from_secs(30));
+    }
+}
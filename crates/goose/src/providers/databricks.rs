@@ -1,15 +1,16 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderStream, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::databricks::{create_request, get_usage, response_to_message};
+use super::formats::openai::stream_response;
 use super::oauth;
-use super::utils::{get_model, ImageFormat};
+use super::utils::{get_model, retry_delay_from_headers, ImageFormat};
 use crate::config::ConfigError;
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -163,7 +164,7 @@ impl DatabricksProvider {
         }
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, payload: &Value) -> Result<Response, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let path = format!("serving-endpoints/{}/invocations", self.model.model_name);
@@ -176,11 +177,35 @@ impl DatabricksProvider {
             .client
             .post(url)
             .header("Authorization", auth_header)
-            .json(&payload)
+            .json(payload)
             .send()
             .await?;
 
+        Ok(response)
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(&payload).await?;
+        Self::handle_response(response).await
+    }
+
+    async fn post_stream(&self, payload: Value) -> Result<Response, ProviderError> {
+        let response = self.send(&payload).await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
         let status = response.status();
+        Err(Self::handle_response(response)
+            .await
+            .err()
+            .unwrap_or_else(|| {
+                ProviderError::RequestFailed(format!("Request failed with status: {}", status))
+            }))
+    }
+
+    async fn handle_response(response: Response) -> Result<Value, ProviderError> {
+        let status = response.status();
+        let retry_delay = retry_delay_from_headers(response.headers());
         let payload: Option<Value> = response.json().await.ok();
 
         match status {
@@ -225,7 +250,10 @@ impl DatabricksProvider {
                 Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
             }
             StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+                Err(ProviderError::RateLimitExceeded {
+                    details: format!("{:?}", payload),
+                    retry_delay,
+                })
             }
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
                 Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -295,4 +323,33 @@ impl Provider for DatabricksProvider {
 
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, tools, &self.image_format)?;
+        // Remove the model key which is part of the url with databricks
+        payload
+            .as_object_mut()
+            .expect("payload should have model key")
+            .remove("model");
+        payload["stream"] = json!(true);
+
+        let response = self.post_stream(payload.clone()).await?;
+
+        // Databricks streams OpenAI style chunks, but reasoning content needs its own parsing
+        Ok(stream_response(
+            response,
+            self.model.clone(),
+            payload,
+            response_to_message,
+        ))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 }
@@ -5,19 +5,25 @@ use super::{
     azure::AzureProvider,
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
+    cassette::{RecordProvider, ReplayProvider},
+    custom::{custom_provider, custom_providers},
     databricks::DatabricksProvider,
     gcpvertexai::GcpVertexAIProvider,
     google::GoogleProvider,
     groq::GroqProvider,
+    lead_worker::{LeadWorkerConfig, LeadWorkerProvider},
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
+    retry::{FallbackConfig, RetryConfig, RetryProvider},
 };
+use crate::config::Config;
 use crate::model::ModelConfig;
 use anyhow::Result;
 
+/// Metadata of the built-in providers followed by the custom providers in the config file
 pub fn providers() -> Vec<ProviderMetadata> {
-    vec![
+    let mut providers = vec![
         AnthropicProvider::metadata(),
         AzureProvider::metadata(),
         BedrockProvider::metadata(),
@@ -28,10 +34,92 @@ pub fn providers() -> Vec<ProviderMetadata> {
         OllamaProvider::metadata(),
         OpenAiProvider::metadata(),
         OpenRouterProvider::metadata(),
-    ]
+        RecordProvider::metadata(),
+        ReplayProvider::metadata(),
+    ];
+    #[cfg(feature = "local-gguf")]
+    providers.push(super::gguf::GgufProvider::metadata());
+
+    // Custom providers can't take the name of a built-in one
+    for (name, custom) in custom_providers() {
+        if providers.iter().any(|metadata| metadata.name == name) {
+            tracing::warn!("Ignoring custom provider {}, the name is taken", name);
+            continue;
+        }
+        providers.push(custom.metadata(&name));
+    }
+    providers
 }
 
+/// Create the named provider for a session
+///
+/// When `GOOSE_LEAD_MODEL` is set, turns are routed between that lead model, on
+/// `GOOSE_LEAD_PROVIDER` or the same provider, and the given model as the worker. See
+/// `LeadWorkerProvider` for the routing rules.
 pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let config = Config::global();
+    let Ok(lead_model) = config.get_param::<String>("GOOSE_LEAD_MODEL") else {
+        return create_with_retries(name, model);
+    };
+    let lead_provider = config
+        .get_param::<String>("GOOSE_LEAD_PROVIDER")
+        .unwrap_or_else(|_| name.to_string());
+
+    // The lead shares the session's sampling and reasoning settings
+    let mut lead_config = ModelConfig::new(lead_model);
+    lead_config.temperature = model.temperature;
+    lead_config.reasoning_effort = model.reasoning_effort;
+    lead_config.thinking_budget = model.thinking_budget;
+
+    let lead = create_with_retries(&lead_provider, lead_config)?;
+    let worker = create_with_retries(name, model)?;
+    Ok(Arc::new(LeadWorkerProvider::new(
+        lead,
+        worker,
+        LeadWorkerConfig::from_config(config),
+    )))
+}
+
+/// Create the named provider, wrapped so transient failures are retried
+///
+/// Fallbacks listed under `GOOSE_PROVIDER_FALLBACKS` are tried in order once the
+/// primary provider keeps failing. A fallback that can't be created (e.g. missing
+/// credentials) is skipped with a warning rather than failing the whole session.
+/// Unlike `create`, this never routes to a lead model, so it suits helpers that
+/// pick their own model.
+pub fn create_with_retries(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let config = Config::global();
+    let primary = create_provider(name, model)?;
+
+    let fallbacks: Vec<FallbackConfig> = config
+        .get_param("GOOSE_PROVIDER_FALLBACKS")
+        .unwrap_or_default();
+    let fallbacks = fallbacks
+        .into_iter()
+        .filter_map(|fallback| {
+            match create_provider(&fallback.provider, ModelConfig::new(fallback.model.clone())) {
+                Ok(provider) => Some(provider),
+                Err(e) => {
+                    tracing::warn!(
+                        "Skipping fallback {}/{}: {}",
+                        fallback.provider,
+                        fallback.model,
+                        e
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
+
+    Ok(Arc::new(RetryProvider::new(
+        primary,
+        fallbacks,
+        RetryConfig::from_config(config),
+    )))
+}
+
+pub(super) fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     // We use Arc instead of Box to be able to clone for multiple async tasks
     match name {
         "openai" => Ok(Arc::new(OpenAiProvider::from_env(model)?)),
@@ -44,6 +132,10 @@ pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
         "openrouter" => Ok(Arc::new(OpenRouterProvider::from_env(model)?)),
         "gcp_vertex_ai" => Ok(Arc::new(GcpVertexAIProvider::from_env(model)?)),
         "google" => Ok(Arc::new(GoogleProvider::from_env(model)?)),
-        _ => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+        "record" => Ok(Arc::new(RecordProvider::from_env(model)?)),
+        "replay" => Ok(Arc::new(ReplayProvider::from_env(model)?)),
+        #[cfg(feature = "local-gguf")]
+        "gguf" => Ok(Arc::new(super::gguf::GgufProvider::from_env(model)?)),
+        _ => custom_provider(name)?.create(name, model),
     }
 }
@@ -3,13 +3,20 @@ use std::path::Path;
 
 use anyhow::{anyhow, bail, Result};
 use aws_sdk_bedrockruntime::types as bedrock;
-use aws_smithy_types::{Document, Number};
+use aws_smithy_types::{Blob, Document, Number};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use chrono::Utc;
 use mcp_core::{Content, ResourceContents, Role, Tool, ToolCall, ToolError, ToolResult};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use super::super::base::Usage;
+use super::anthropic;
 use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+/// Version of the Anthropic messages API that Bedrock's InvokeModel accepts
+const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
 
 pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
     bedrock::Message::builder()
@@ -25,6 +32,48 @@ pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
         .map_err(|err| anyhow!("Failed to construct Bedrock message: {}", err))
 }
 
+fn to_bedrock_document_format(mime_type: &str) -> Result<bedrock::DocumentFormat> {
+    Ok(match mime_type {
+        "application/pdf" => bedrock::DocumentFormat::Pdf,
+        "text/plain" => bedrock::DocumentFormat::Txt,
+        "text/csv" => bedrock::DocumentFormat::Csv,
+        "text/markdown" => bedrock::DocumentFormat::Md,
+        "text/html" => bedrock::DocumentFormat::Html,
+        _ => bail!(
+            "Documents of type {} are not supported by Bedrock",
+            mime_type
+        ),
+    })
+}
+
+/// Bedrock only allows alphanumerics, single spaces, hyphens, parentheses and brackets
+fn to_bedrock_document_name(name: Option<&str>) -> String {
+    let stem = name
+        .map(|name| {
+            Path::new(name)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+        })
+        .unwrap_or_default();
+    let sanitized = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-()[]".contains(c) {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>();
+    let name = sanitized.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        "document".to_string()
+    } else {
+        name
+    }
+}
+
 pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::ContentBlock> {
     Ok(match content {
         MessageContent::Text(text) => bedrock::ContentBlock::Text(text.text.to_string()),
@@ -34,17 +83,49 @@ pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::C
         MessageContent::Image(_) => {
             bail!("Image content is not supported by Bedrock provider yet")
         }
-        MessageContent::Thinking(_) => {
-            // Thinking blocks are not supported in Bedrock - skip
-            bedrock::ContentBlock::Text("".to_string())
+        MessageContent::Document(document) => {
+            let data = BASE64_STANDARD
+                .decode(&document.data)
+                .map_err(|err| anyhow!("Invalid document data: {}", err))?;
+            bedrock::ContentBlock::Document(
+                bedrock::DocumentBlock::builder()
+                    .format(to_bedrock_document_format(&document.mime_type)?)
+                    .name(to_bedrock_document_name(document.name.as_deref()))
+                    .source(bedrock::DocumentSource::Bytes(Blob::new(data)))
+                    .build()?,
+            )
         }
-        MessageContent::RedactedThinking(_) => {
-            // Redacted thinking blocks are not supported in Bedrock - skip
+        MessageContent::Audio(_) => {
+            bail!("Audio content is not supported by Bedrock models")
+        }
+        MessageContent::Thinking(thinking) if thinking.signature.is_empty() => {
+            // Unsigned thinking from another provider can't be sent back - skip
             bedrock::ContentBlock::Text("".to_string())
         }
+        MessageContent::Thinking(thinking) => {
+            // Claude needs its thinking back alongside the tool use it led to
+            bedrock::ContentBlock::ReasoningContent(bedrock::ReasoningContentBlock::ReasoningText(
+                bedrock::ReasoningTextBlock::builder()
+                    .text(thinking.thinking.to_string())
+                    .signature(thinking.signature.to_string())
+                    .build()?,
+            ))
+        }
+        MessageContent::RedactedThinking(redacted) => {
+            let data = BASE64_STANDARD
+                .decode(&redacted.data)
+                .map_err(|err| anyhow!("Invalid redacted thinking data: {}", err))?;
+            bedrock::ContentBlock::ReasoningContent(
+                bedrock::ReasoningContentBlock::RedactedContent(Blob::new(data)),
+            )
+        }
         MessageContent::ContextLengthExceeded(_) => {
             bail!("ContextLengthExceeded should not get passed to the provider")
         }
+        MessageContent::BudgetExceeded(_) | MessageContent::ToolProgress(_) => {
+            // Frontends may keep these notices in the history they send back - skip
+            bedrock::ContentBlock::Text("".to_string())
+        }
         MessageContent::ToolRequest(tool_req) => {
             let tool_use_id = tool_req.id.to_string();
             let tool_use = if let Ok(call) = tool_req.tool_call.as_ref() {
@@ -141,6 +222,18 @@ pub fn to_bedrock_tool_config(tools: &[Tool]) -> Result<bedrock::ToolConfigurati
         .build()?)
 }
 
+/// A tool configuration that makes the model call `tool`
+pub fn to_bedrock_forced_tool_config(tool: &Tool) -> Result<bedrock::ToolConfiguration> {
+    Ok(bedrock::ToolConfiguration::builder()
+        .tools(to_bedrock_tool(tool)?)
+        .tool_choice(bedrock::ToolChoice::Tool(
+            bedrock::SpecificToolChoice::builder()
+                .name(tool.name.to_string())
+                .build()?,
+        ))
+        .build()?)
+}
+
 pub fn to_bedrock_tool(tool: &Tool) -> Result<bedrock::Tool> {
     Ok(bedrock::Tool::ToolSpec(
         bedrock::ToolSpecification::builder()
@@ -153,6 +246,34 @@ pub fn to_bedrock_tool(tool: &Tool) -> Result<bedrock::Tool> {
     ))
 }
 
+/// Whether a model id is Claude, including cross-region inference profiles like `us.anthropic.claude-*`
+pub fn is_bedrock_claude(model_name: &str) -> bool {
+    model_name.contains("anthropic.claude")
+}
+
+/// The Anthropic messages request that Claude takes through InvokeModel
+///
+/// Unlike Converse, this carries the prompt caching hints of the Anthropic format.
+pub fn to_bedrock_anthropic_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<Value> {
+    let mut payload = anthropic::create_request(model_config, system, messages, tools)?;
+    let object = payload
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Anthropic request is not an object"))?;
+    // The model is given by the model id and streaming by the operation
+    object.remove("model");
+    object.remove("stream");
+    object.insert(
+        "anthropic_version".to_string(),
+        json!(BEDROCK_ANTHROPIC_VERSION),
+    );
+    Ok(payload)
+}
+
 pub fn to_bedrock_json(value: &Value) -> Document {
     match value {
         Value::Null => Document::Null,
@@ -257,6 +378,15 @@ pub fn from_bedrock_content_block(block: &bedrock::ContentBlock) -> Result<Messa
                     .collect::<ToolResult<Vec<_>>>()
             },
         ),
+        bedrock::ContentBlock::ReasoningContent(reasoning) => match reasoning {
+            bedrock::ReasoningContentBlock::ReasoningText(text) => {
+                MessageContent::thinking(text.text(), text.signature().unwrap_or_default())
+            }
+            bedrock::ReasoningContentBlock::RedactedContent(data) => {
+                MessageContent::redacted_thinking(BASE64_STANDARD.encode(data.as_ref()))
+            }
+            _ => bail!("Unsupported reasoning content from Bedrock"),
+        },
         _ => bail!("Unsupported content block type from Bedrock"),
     })
 }
@@ -287,6 +417,7 @@ pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
         input_tokens: Some(usage.input_tokens),
         output_tokens: Some(usage.output_tokens),
         total_tokens: Some(usage.total_tokens),
+        ..Default::default()
     }
 }
 
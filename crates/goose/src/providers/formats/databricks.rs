@@ -1,10 +1,14 @@
+use crate::config::Config;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::base::Usage;
+use crate::providers::caching::CachePolicy;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::anthropic::supports_extended_thinking;
+use crate::providers::formats::openai::with_usage_details;
 use crate::providers::utils::{
-    convert_image, detect_image_path, is_valid_function_name, load_image_file,
-    sanitize_function_name, ImageFormat,
+    check_modalities, convert_image, detect_image_path, is_valid_function_name, load_image_file,
+    sanitize_function_name, ImageFormat, Modality,
 };
 use anyhow::{anyhow, Error};
 use mcp_core::ToolError;
@@ -110,7 +114,9 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                         }
                     }
                 }
-                MessageContent::ContextLengthExceeded(_) => {
+                MessageContent::ContextLengthExceeded(_)
+                | MessageContent::BudgetExceeded(_)
+                | MessageContent::ToolProgress(_) => {
                     continue;
                 }
                 MessageContent::ToolResponse(response) => {
@@ -191,6 +197,9 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                         }
                     }));
                 }
+                MessageContent::Document(_) | MessageContent::Audio(_) => {
+                    // Rejected by create_request, the serving endpoints take images only
+                }
                 MessageContent::FrontendToolRequest(req) => {
                     // Frontend tool requests are converted to text messages
                     if let Ok(tool_call) = &req.tool_call {
@@ -389,7 +398,10 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
             _ => None,
         });
 
-    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+    Ok(with_usage_details(
+        Usage::new(input_tokens, output_tokens, total_tokens),
+        usage,
+    ))
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -451,24 +463,30 @@ pub fn create_request(
         ));
     }
 
+    check_modalities(&model_config.model_name, messages, &[Modality::Image])?;
+
     let model_name = model_config.model_name.to_string();
     let is_o1 = model_name.starts_with("o1") || model_name.starts_with("goose-o1");
     let is_o3 = model_name.starts_with("o3") || model_name.starts_with("goose-o3");
-    let is_claude_3_7_sonnet = model_name.contains("claude-3-7-sonnet"); // can be goose- or databricks-
+    let supports_thinking = supports_extended_thinking(&model_name); // can be goose- or databricks-
 
     // Only extract reasoning effort for O1/O3 models
     let (model_name, reasoning_effort) = if is_o1 || is_o3 {
         let parts: Vec<&str> = model_config.model_name.split('-').collect();
         let last_part = parts.last().unwrap();
+        // An effort set in the model config takes precedence over one in the model name
+        let configured = model_config
+            .reasoning_effort
+            .map(|effort| effort.as_openai_effort().to_string());
 
         match *last_part {
             "low" | "medium" | "high" => {
                 let base_name = parts[..parts.len() - 1].join("-");
-                (base_name, Some(last_part.to_string()))
+                (base_name, configured.or(Some(last_part.to_string())))
             }
             _ => (
                 model_config.model_name.to_string(),
-                Some("medium".to_string()),
+                configured.or(Some("medium".to_string())),
             ),
         }
     } else {
@@ -513,14 +531,10 @@ pub fn create_request(
             .insert("tools".to_string(), json!(tools_spec));
     }
 
-    // Add thinking parameters for Claude 3.7 Sonnet model when requested
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if is_claude_3_7_sonnet && is_thinking_enabled {
-        // Minimum budget_tokens is 1024
-        let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
-            .unwrap_or_else(|_| "16000".to_string())
-            .parse()
-            .unwrap_or(16000);
+    // Add thinking parameters for Claude models when requested
+    let thinking_budget = model_config.thinking_budget().filter(|_| supports_thinking);
+    if let Some(budget_tokens) = thinking_budget {
+        let budget_tokens = budget_tokens as i32;
 
         // For Claude models with thinking enabled, we need to add max_tokens + budget_tokens
         // Default to 8192 (Claude max output) + budget if not specified
@@ -568,6 +582,11 @@ pub fn create_request(
         }
     }
 
+    // Claude served by Databricks takes Anthropic's cache hints
+    if model_name.contains("claude") {
+        CachePolicy::from_config(Config::global()).apply_openai(&mut payload);
+    }
+
     Ok(payload)
 }
 
@@ -889,6 +908,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_streamed_reasoning_response_to_message() -> anyhow::Result<()> {
+        use crate::providers::formats::openai::StreamAccumulator;
+
+        let mut accumulator = StreamAccumulator::new();
+        accumulator.push_chunk(&json!({"choices": [{"delta": {"content": [
+            {"type": "reasoning", "summary": [{"type": "summary_text", "text": "Hmm", "signature": ""}]}
+        ]}}]}))?;
+        accumulator.push_chunk(&json!({"choices": [{"delta": {"content": [
+            {"type": "reasoning", "summary": [{"type": "summary_text", "text": "...", "signature": "sig"}]}
+        ]}}]}))?;
+        accumulator.push_chunk(&json!({"choices": [{"delta": {"content": [
+            {"type": "text", "text": "Done"}
+        ]}}]}))?;
+
+        let message = response_to_message(accumulator.into_response())?;
+        assert_eq!(
+            message.content[0],
+            MessageContent::thinking("Hmm...", "sig")
+        );
+        assert_eq!(message.content[1], MessageContent::text("Done"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_valid_toolrequest() -> anyhow::Result<()> {
         let response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -980,6 +1024,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1012,6 +1058,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1045,6 +1093,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
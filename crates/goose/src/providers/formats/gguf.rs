@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use mcp_core::role::Role;
+use serde_json::{json, Value};
+
+use crate::message::{Message, MessageContent};
+
+/// "GGUF" as a little endian u32
+const GGUF_MAGIC: u32 = 0x4655_4747;
+/// Longest string read from a model file, anything longer means the file is corrupt
+const MAX_STRING_LEN: u64 = 64 * 1024 * 1024;
+
+/// What goose needs from the header of a GGUF model file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GgufMetadata {
+    /// Model architecture, e.g. "llama", "qwen2" or "gemma3"
+    pub architecture: Option<String>,
+    /// Human readable model name
+    pub name: Option<String>,
+    /// Context window the model was trained with
+    pub context_length: Option<usize>,
+    /// Jinja chat template shipped with the model, used to recognize its family
+    pub chat_template: Option<String>,
+}
+
+impl GgufMetadata {
+    /// Read the metadata from the header of a model file, without loading the weights
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| anyhow!("Failed to open model {}: {}", path.display(), e))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        if read_u32(&mut reader)? != GGUF_MAGIC {
+            bail!("Not a GGUF model file");
+        }
+        let version = read_u32(&mut reader)?;
+        if version < 2 {
+            bail!("GGUF version {} is not supported", version);
+        }
+        let _tensor_count = read_u64(&mut reader)?;
+        let kv_count = read_u64(&mut reader)?;
+
+        let mut metadata = Self::default();
+        let mut context_lengths = HashMap::new();
+        for _ in 0..kv_count {
+            let key = read_string(&mut reader)?;
+            let value_type = read_u32(&mut reader)?;
+            let value = read_value(&mut reader, value_type)?;
+            match key.as_str() {
+                "general.architecture" => metadata.architecture = value.as_str().map(String::from),
+                "general.name" => metadata.name = value.as_str().map(String::from),
+                "tokenizer.chat_template" => {
+                    metadata.chat_template = value.as_str().map(String::from)
+                }
+                key => {
+                    if let Some(architecture) = key.strip_suffix(".context_length") {
+                        if let Some(length) = value.as_u64() {
+                            context_lengths.insert(architecture.to_string(), length as usize);
+                        }
+                    }
+                }
+            }
+        }
+
+        // The context length is stored under the architecture's name
+        metadata.context_length = metadata
+            .architecture
+            .as_ref()
+            .and_then(|architecture| context_lengths.get(architecture).copied());
+        Ok(metadata)
+    }
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| anyhow!("Truncated GGUF header: {}", e))?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String> {
+    let len = read_u64(reader)?;
+    if len > MAX_STRING_LEN {
+        bail!("GGUF string of {} bytes is too long", len);
+    }
+    let mut bytes = vec![0; len as usize];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| anyhow!("Truncated GGUF header: {}", e))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Read a metadata value of the given GGUF type
+///
+/// Arrays, such as the tokenizer's vocabulary, are skipped and read as null.
+fn read_value<R: Read>(reader: &mut R, value_type: u32) -> Result<Value> {
+    Ok(match value_type {
+        0 => json!(u8::from_le_bytes(read_bytes(reader)?)),
+        1 => json!(i8::from_le_bytes(read_bytes(reader)?)),
+        2 => json!(u16::from_le_bytes(read_bytes(reader)?)),
+        3 => json!(i16::from_le_bytes(read_bytes(reader)?)),
+        4 => json!(read_u32(reader)?),
+        5 => json!(i32::from_le_bytes(read_bytes(reader)?)),
+        6 => json!(f32::from_le_bytes(read_bytes(reader)?)),
+        7 => json!(read_bytes::<R, 1>(reader)?[0] != 0),
+        8 => json!(read_string(reader)?),
+        9 => {
+            let item_type = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            for _ in 0..len {
+                read_value(reader, item_type)?;
+            }
+            Value::Null
+        }
+        10 => json!(read_u64(reader)?),
+        11 => json!(i64::from_le_bytes(read_bytes(reader)?)),
+        12 => json!(f64::from_le_bytes(read_bytes(reader)?)),
+        other => bail!("Unknown GGUF value type {}", other),
+    })
+}
+
+/// Prompt format of a model family
+///
+/// Each family was trained on its own markers for the turns of a conversation, and answers
+/// poorly when prompted with another's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatTemplate {
+    /// Llama 3 and later
+    Llama3,
+    /// `<|im_start|>` turns, used by Qwen, Hermes, Phi and many fine-tunes
+    ChatMl,
+    /// `[INST]` turns of Mistral and Mixtral
+    Mistral,
+    /// Gemma's `<start_of_turn>` turns, which have no system role
+    Gemma,
+    /// DeepSeek V3 and R1
+    DeepSeek,
+}
+
+impl FromStr for ChatTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "llama3" => Ok(Self::Llama3),
+            "chatml" => Ok(Self::ChatMl),
+            "mistral" => Ok(Self::Mistral),
+            "gemma" => Ok(Self::Gemma),
+            "deepseek" => Ok(Self::DeepSeek),
+            _ => Err(anyhow!(
+                "Unknown chat template '{}', expected llama3, chatml, mistral, gemma or deepseek",
+                s
+            )),
+        }
+    }
+}
+
+impl ChatTemplate {
+    /// Recognize the family from the markers in the model's own template, or else its
+    /// architecture. ChatML is the most common format among other models.
+    pub fn detect(metadata: &GgufMetadata) -> Self {
+        if let Some(template) = &metadata.chat_template {
+            let markers = [
+                ("<|start_header_id|>", Self::Llama3),
+                ("<|im_start|>", Self::ChatMl),
+                ("[INST]", Self::Mistral),
+                ("<start_of_turn>", Self::Gemma),
+                ("<｜User｜>", Self::DeepSeek),
+            ];
+            if let Some((_, template)) =
+                markers.iter().find(|(marker, _)| template.contains(marker))
+            {
+                return *template;
+            }
+        }
+
+        match metadata.architecture.as_deref().unwrap_or_default() {
+            "llama" => Self::Llama3,
+            architecture if architecture.starts_with("gemma") => Self::Gemma,
+            architecture if architecture.starts_with("deepseek") => Self::DeepSeek,
+            _ => Self::ChatMl,
+        }
+    }
+
+    /// The prompt for a conversation, ending where the assistant's next answer starts
+    ///
+    /// Special tokens such as the beginning of text marker are included, so the prompt is
+    /// tokenized without adding them again.
+    pub fn render(&self, system: &str, messages: &[Message]) -> String {
+        let turns: Vec<(Role, String)> = messages
+            .iter()
+            .map(|message| (message.role.clone(), message_text(message)))
+            .filter(|(_, text)| !text.is_empty())
+            .collect();
+
+        let mut prompt = String::new();
+        match self {
+            Self::Llama3 => {
+                prompt.push_str("<|begin_of_text|>");
+                let turn = |role: &str, text: &str| {
+                    format!(
+                        "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                        role, text
+                    )
+                };
+                if !system.is_empty() {
+                    prompt.push_str(&turn("system", system));
+                }
+                for (role, text) in &turns {
+                    prompt.push_str(&turn(role_name(role), text));
+                }
+                prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+            Self::ChatMl => {
+                let turn =
+                    |role: &str, text: &str| format!("<|im_start|>{}\n{}<|im_end|>\n", role, text);
+                if !system.is_empty() {
+                    prompt.push_str(&turn("system", system));
+                }
+                for (role, text) in &turns {
+                    prompt.push_str(&turn(role_name(role), text));
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            Self::Mistral => {
+                prompt.push_str("<s>");
+                for (i, (role, text)) in turns.iter().enumerate() {
+                    match role {
+                        Role::User => {
+                            let text = with_system(system, text, i == 0);
+                            prompt.push_str(&format!("[INST] {} [/INST]", text));
+                        }
+                        Role::Assistant => prompt.push_str(&format!("{}</s>", text)),
+                    }
+                }
+            }
+            Self::Gemma => {
+                prompt.push_str("<bos>");
+                for (i, (role, text)) in turns.iter().enumerate() {
+                    let (role, text) = match role {
+                        Role::User => ("user", with_system(system, text, i == 0)),
+                        Role::Assistant => ("model", text.clone()),
+                    };
+                    prompt.push_str(&format!("<start_of_turn>{}\n{}<end_of_turn>\n", role, text));
+                }
+                prompt.push_str("<start_of_turn>model\n");
+            }
+            Self::DeepSeek => {
+                prompt.push_str("<｜begin▁of▁sentence｜>");
+                prompt.push_str(system);
+                for (role, text) in &turns {
+                    match role {
+                        Role::User => prompt.push_str(&format!("<｜User｜>{}", text)),
+                        Role::Assistant => prompt
+                            .push_str(&format!("<｜Assistant｜>{}<｜end▁of▁sentence｜>", text)),
+                    }
+                }
+                prompt.push_str("<｜Assistant｜>");
+            }
+        }
+        prompt
+    }
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    }
+}
+
+/// Families without a system role get the system prompt with the first user turn
+fn with_system(system: &str, text: &str, first: bool) -> String {
+    if first && !system.is_empty() {
+        format!("{}\n\n{}", system, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// A message as plain text, with tool calls in the JSON format the toolshim prompt asks for
+fn message_text(message: &Message) -> String {
+    let parts: Vec<String> = message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::Text(text) => Some(text.text.clone()),
+            MessageContent::ToolRequest(request) => request
+                .tool_call
+                .as_ref()
+                .ok()
+                .map(|call| json!({"name": call.name, "arguments": call.arguments}).to_string()),
+            MessageContent::ToolResponse(response) => Some(match &response.tool_result {
+                Ok(_) => format!(
+                    "Tool result:\n{}",
+                    content.as_tool_response_text().unwrap_or_default()
+                ),
+                Err(e) => format!("Tool error: {}", e),
+            }),
+            _ => None,
+        })
+        .collect();
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::content::Content;
+    use mcp_core::tool::ToolCall;
+
+    fn gguf_string(bytes: &mut Vec<u8>, text: &str) {
+        bytes.extend((text.len() as u64).to_le_bytes());
+        bytes.extend(text.as_bytes());
+    }
+
+    fn gguf_header() -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend(GGUF_MAGIC.to_le_bytes());
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(5u64.to_le_bytes());
+
+        gguf_string(&mut bytes, "general.architecture");
+        bytes.extend(8u32.to_le_bytes());
+        gguf_string(&mut bytes, "qwen2");
+
+        // An array of strings, which is skipped
+        gguf_string(&mut bytes, "tokenizer.ggml.tokens");
+        bytes.extend(9u32.to_le_bytes());
+        bytes.extend(8u32.to_le_bytes());
+        bytes.extend(2u64.to_le_bytes());
+        gguf_string(&mut bytes, "<|im_start|>");
+        gguf_string(&mut bytes, "hello");
+
+        gguf_string(&mut bytes, "qwen2.context_length");
+        bytes.extend(4u32.to_le_bytes());
+        bytes.extend(32768u32.to_le_bytes());
+
+        gguf_string(&mut bytes, "qwen2.rope.freq_base");
+        bytes.extend(6u32.to_le_bytes());
+        bytes.extend(1_000_000f32.to_le_bytes());
+
+        gguf_string(&mut bytes, "tokenizer.chat_template");
+        bytes.extend(8u32.to_le_bytes());
+        gguf_string(
+            &mut bytes,
+            "{% for message in messages %}<|im_start|>{{ message.role }}{% endfor %}",
+        );
+        bytes
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let metadata = GgufMetadata::from_reader(gguf_header().as_slice()).unwrap();
+        assert_eq!(metadata.architecture.as_deref(), Some("qwen2"));
+        assert_eq!(metadata.context_length, Some(32768));
+        assert_eq!(ChatTemplate::detect(&metadata), ChatTemplate::ChatMl);
+
+        let truncated = &gguf_header()[..60];
+        assert!(GgufMetadata::from_reader(truncated).is_err());
+        assert!(GgufMetadata::from_reader(&b"GGML"[..]).is_err());
+    }
+
+    #[test]
+    fn test_detect_by_architecture() {
+        let metadata = |architecture: &str| GgufMetadata {
+            architecture: Some(architecture.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            ChatTemplate::detect(&metadata("llama")),
+            ChatTemplate::Llama3
+        );
+        assert_eq!(
+            ChatTemplate::detect(&metadata("gemma3")),
+            ChatTemplate::Gemma
+        );
+        assert_eq!(
+            ChatTemplate::detect(&metadata("deepseek2")),
+            ChatTemplate::DeepSeek
+        );
+        assert_eq!(
+            ChatTemplate::detect(&metadata("phi3")),
+            ChatTemplate::ChatMl
+        );
+        assert_eq!(
+            "Mistral".parse::<ChatTemplate>().unwrap(),
+            ChatTemplate::Mistral
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let messages = vec![
+            Message::user().with_text("List files"),
+            Message::assistant()
+                .with_tool_request("1", Ok(ToolCall::new("shell", json!({"command": "ls"})))),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("a.txt")])),
+        ];
+
+        assert_eq!(
+            ChatTemplate::ChatMl.render("Be brief", &messages),
+            "<|im_start|>system\nBe brief<|im_end|>\n\
+             <|im_start|>user\nList files<|im_end|>\n\
+             <|im_start|>assistant\n{\"arguments\":{\"command\":\"ls\"},\"name\":\"shell\"}<|im_end|>\n\
+             <|im_start|>user\nTool result:\na.txt<|im_end|>\n\
+             <|im_start|>assistant\n"
+        );
+        assert_eq!(
+            ChatTemplate::Mistral.render("Be brief", &messages[..1]),
+            "<s>[INST] Be brief\n\nList files [/INST]"
+        );
+        assert_eq!(
+            ChatTemplate::Gemma.render("", &messages[..1]),
+            "<bos><start_of_turn>user\nList files<end_of_turn>\n<start_of_turn>model\n"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::message::{Message, MessageContent};
-use crate::model::ModelConfig;
+use crate::model::{ModelConfig, ReasoningEffort};
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
 use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
@@ -10,6 +10,16 @@ use mcp_core::tool::{Tool, ToolCall};
 use rand::{distributions::Alphanumeric, Rng};
 use serde_json::{json, Map, Value};
 
+/// Media sent inline, Gemini accepts images, PDFs and audio alike
+fn inline_data(mime_type: &str, data: &str) -> Value {
+    json!({
+        "inline_data": {
+            "mime_type": mime_type,
+            "data": data,
+        }
+    })
+}
+
 /// Convert internal Message format to Google's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
     messages
@@ -73,12 +83,7 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                                 for content in abridged {
                                     match content {
                                         Content::Image(image) => {
-                                            parts.push(json!({
-                                                "inline_data": {
-                                                    "mime_type": image.mime_type,
-                                                    "data": image.data,
-                                                }
-                                            }));
+                                            parts.push(inline_data(&image.mime_type, &image.data));
                                         }
                                         _ => {
                                             tool_content.push(content);
@@ -110,6 +115,15 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                             }
                         }
                     }
+                    MessageContent::Image(image) => {
+                        parts.push(inline_data(&image.mime_type, &image.data));
+                    }
+                    MessageContent::Document(document) => {
+                        parts.push(inline_data(&document.mime_type, &document.data));
+                    }
+                    MessageContent::Audio(audio) => {
+                        parts.push(inline_data(&audio.mime_type, &audio.data));
+                    }
 
                     _ => {}
                 }
@@ -161,6 +175,29 @@ pub fn format_tools(tools: &[Tool]) -> Vec<Value> {
         .collect()
 }
 
+/// Convert a JSON schema to the subset Gemini accepts as `responseSchema`
+pub fn format_response_schema(schema: &Value) -> Value {
+    let accepted_schema_attributes: Vec<String> = [
+        "type",
+        "format",
+        "description",
+        "nullable",
+        "enum",
+        "maxItems",
+        "minItems",
+        "properties",
+        "required",
+        "items",
+    ]
+    .iter()
+    .map(|attribute| attribute.to_string())
+    .collect();
+    match schema.as_object() {
+        Some(map) => process_map(map, &accepted_schema_attributes, None),
+        None => schema.clone(),
+    }
+}
+
 /// Process a JSON map to filter out unsupported attributes
 fn process_map(
     map: &Map<String, Value>,
@@ -266,15 +303,26 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             .get("promptTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
+        // Thinking tokens are billed as output but not included in the candidates count
+        let reasoning_tokens = usage_meta_data
+            .get("thoughtsTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
         let output_tokens = usage_meta_data
             .get("candidatesTokenCount")
             .and_then(|v| v.as_u64())
-            .map(|v| v as i32);
+            .map(|v| v as i32 + reasoning_tokens.unwrap_or(0));
         let total_tokens = usage_meta_data
             .get("totalTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        let cache_read_tokens = usage_meta_data
+            .get("cachedContentTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
+        Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cache_read_tokens, None)
+            .with_reasoning_tokens(reasoning_tokens))
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -285,6 +333,19 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// The thinking budget for Gemini 2.5 models, 0 turns thinking off where the model allows it
+fn thinking_budget(model_config: &ModelConfig) -> Option<u32> {
+    let name = &model_config.model_name;
+    if !(name.contains("gemini-2.5") || name.contains("gemini-2-5")) {
+        return None;
+    }
+    match model_config.reasoning_effort {
+        Some(ReasoningEffort::Off) => Some(0),
+        Some(_) => model_config.thinking_budget(),
+        None => model_config.thinking_budget,
+    }
+}
+
 /// Create a complete request payload for Google's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -311,6 +372,12 @@ pub fn create_request(
     if let Some(tokens) = model_config.max_tokens {
         generation_config.insert("maxOutputTokens".to_string(), json!(tokens));
     }
+    if let Some(budget) = thinking_budget(model_config) {
+        generation_config.insert(
+            "thinkingConfig".to_string(),
+            json!({"thinkingBudget": budget}),
+        );
+    }
     if !generation_config.is_empty() {
         payload.insert("generationConfig".to_string(), json!(generation_config));
     }
@@ -389,6 +456,24 @@ mod tests {
         assert_eq!(usage.total_tokens, Some(3));
     }
 
+    #[test]
+    fn test_get_usage_with_thoughts_and_cache() {
+        let data = json!({
+            "usageMetadata": {
+                "promptTokenCount": 100,
+                "cachedContentTokenCount": 60,
+                "candidatesTokenCount": 20,
+                "thoughtsTokenCount": 30,
+                "totalTokenCount": 150
+            }
+        });
+        let usage = get_usage(&data).unwrap();
+        assert_eq!(usage.input_tokens, Some(100));
+        assert_eq!(usage.output_tokens, Some(50));
+        assert_eq!(usage.reasoning_tokens, Some(30));
+        assert_eq!(usage.cache_read_input_tokens, Some(60));
+    }
+
     #[test]
     fn test_message_to_google_spec_text_message() {
         let messages = vec![
@@ -403,6 +488,25 @@ mod tests {
         assert_eq!(payload[1]["parts"][0]["text"], "World");
     }
 
+    #[test]
+    fn test_message_to_google_spec_media_message() {
+        let messages = vec![Message::user()
+            .with_text("What do these say?")
+            .with_document("JVBERi0=", "application/pdf", None)
+            .with_audio("SUQz", "audio/mpeg")];
+        let payload = format_messages(&messages);
+        let parts = &payload[0]["parts"];
+        assert_eq!(parts[0]["text"], "What do these say?");
+        assert_eq!(
+            parts[1],
+            json!({"inline_data": {"mime_type": "application/pdf", "data": "JVBERi0="}})
+        );
+        assert_eq!(
+            parts[2],
+            json!({"inline_data": {"mime_type": "audio/mpeg", "data": "SUQz"}})
+        );
+    }
+
     #[test]
     fn test_message_to_google_spec_tool_request_message() {
         let arguments = json!({
@@ -628,4 +732,28 @@ mod tests {
 
         assert_eq!(payload, expected_payload);
     }
+
+    #[test]
+    fn test_create_request_thinking_budget() {
+        let messages = vec![set_up_text_message("Hello", Role::User)];
+        let model_config = ModelConfig::new("gemini-2.5-flash".to_string())
+            .with_reasoning_effort(Some(ReasoningEffort::High));
+        let payload = create_request(&model_config, "system", &messages, &[]).unwrap();
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            32_000
+        );
+
+        let model_config = model_config.with_reasoning_effort(Some(ReasoningEffort::Off));
+        let payload = create_request(&model_config, "system", &messages, &[]).unwrap();
+        assert_eq!(
+            payload["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            0
+        );
+
+        let model_config = ModelConfig::new("gemini-2.0-flash".to_string())
+            .with_reasoning_effort(Some(ReasoningEffort::High));
+        let payload = create_request(&model_config, "system", &messages, &[]).unwrap();
+        assert!(payload.get("generationConfig").is_none());
+    }
 }
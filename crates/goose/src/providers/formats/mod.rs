@@ -2,5 +2,6 @@ pub mod anthropic;
 pub mod bedrock;
 pub mod databricks;
 pub mod gcpvertexai;
+pub mod gguf;
 pub mod google;
 pub mod openai;
@@ -1,12 +1,14 @@
-use crate::message::{Message, MessageContent};
+use crate::message::{Message, MessageContent, MessageDelta};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
-use crate::providers::errors::ProviderError;
+use crate::providers::base::{ProviderStream, ProviderStreamEvent, ProviderUsage, Usage};
+use crate::providers::errors::{OpenAIError, ProviderError};
 use crate::providers::utils::{
-    convert_image, detect_image_path, is_valid_function_name, load_image_file,
-    sanitize_function_name, ImageFormat,
+    check_modalities, convert_image, detect_image_path, emit_debug_trace, get_model,
+    is_valid_function_name, load_image_file, sanitize_function_name, sse_json_stream, ImageFormat,
+    Modality,
 };
 use anyhow::{anyhow, Error};
+use futures::StreamExt;
 use mcp_core::ToolError;
 use mcp_core::{Content, Role, Tool, ToolCall};
 use serde_json::{json, Value};
@@ -27,20 +29,23 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
             match content {
                 MessageContent::Text(text) => {
                     if !text.text.is_empty() {
-                        // Check for image paths in the text
+                        // A message with nothing but text keeps it as a plain string
+                        if converted.get("content").is_none() {
+                            converted["content"] = json!(text.text);
+                        } else {
+                            push_content_part(
+                                &mut converted,
+                                json!({"type": "text", "text": text.text}),
+                            );
+                        }
+                        // Check for image paths in the text, and attach the image if it loads
                         if let Some(image_path) = detect_image_path(&text.text) {
-                            // Try to load and convert the image
                             if let Ok(image) = load_image_file(image_path) {
-                                converted["content"] = json!([
-                                    {"type": "text", "text": text.text},
-                                    convert_image(&image, image_format)
-                                ]);
-                            } else {
-                                // If image loading fails, just use the text
-                                converted["content"] = json!(text.text);
+                                push_content_part(
+                                    &mut converted,
+                                    convert_image(&image, image_format),
+                                );
                             }
-                        } else {
-                            converted["content"] = json!(text.text);
                         }
                     }
                 }
@@ -52,7 +57,9 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                     // Redacted thinking blocks are not directly used in OpenAI format
                     continue;
                 }
-                MessageContent::ContextLengthExceeded(_) => {
+                MessageContent::ContextLengthExceeded(_)
+                | MessageContent::BudgetExceeded(_)
+                | MessageContent::ToolProgress(_) => {
                     continue;
                 }
                 MessageContent::ToolRequest(request) => match &request.tool_call {
@@ -151,8 +158,32 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                     // Skip tool confirmation requests
                 }
                 MessageContent::Image(image) => {
-                    // Handle direct image content
-                    converted["content"] = json!([convert_image(image, image_format)]);
+                    push_content_part(&mut converted, convert_image(image, image_format));
+                }
+                MessageContent::Document(document) => {
+                    let filename = document.name.as_deref().unwrap_or("document.pdf");
+                    push_content_part(
+                        &mut converted,
+                        json!({
+                            "type": "file",
+                            "file": {
+                                "filename": filename,
+                                "file_data": format!("data:{};base64,{}", document.mime_type, document.data),
+                            }
+                        }),
+                    );
+                }
+                MessageContent::Audio(audio) => {
+                    push_content_part(
+                        &mut converted,
+                        json!({
+                            "type": "input_audio",
+                            "input_audio": {
+                                "data": audio.data,
+                                "format": openai_audio_format(&audio.mime_type).unwrap_or_default(),
+                            }
+                        }),
+                    );
                 }
                 MessageContent::FrontendToolRequest(request) => match &request.tool_call {
                     Ok(tool_call) => {
@@ -192,6 +223,54 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
     messages_spec
 }
 
+/// Add a part to a message's content, turning plain text content into a text part first
+fn push_content_part(converted: &mut Value, part: Value) {
+    let parts = match converted.get("content").cloned() {
+        Some(Value::String(text)) => vec![json!({"type": "text", "text": text}), part],
+        Some(Value::Array(mut parts)) => {
+            parts.push(part);
+            parts
+        }
+        _ => vec![part],
+    };
+    converted["content"] = json!(parts);
+}
+
+/// The `input_audio` format for a media type, OpenAI only accepts wav and mp3
+fn openai_audio_format(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "audio/wav" | "audio/x-wav" => Some("wav"),
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        _ => None,
+    }
+}
+
+/// Reject media the model can't take, only the audio models accept audio input
+fn check_media(model_name: &str, messages: &[Message]) -> anyhow::Result<()> {
+    let mut supported = vec![Modality::Image, Modality::Document];
+    if model_name.contains("audio") {
+        supported.push(Modality::Audio);
+    }
+    check_modalities(model_name, messages, &supported)?;
+
+    let unsupported_audio = messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .find_map(|content| match content {
+            MessageContent::Audio(audio) if openai_audio_format(&audio.mime_type).is_none() => {
+                Some(audio.mime_type.as_str())
+            }
+            _ => None,
+        });
+    if let Some(mime_type) = unsupported_audio {
+        return Err(anyhow!(
+            "Audio of type {} can't be sent, OpenAI compatible APIs only accept wav and mp3",
+            mime_type
+        ));
+    }
+    Ok(())
+}
+
 /// Convert internal Tool format to OpenAI's API tool specification
 pub fn format_tools(tools: &[Tool]) -> anyhow::Result<Vec<Value>> {
     let mut tool_names = std::collections::HashSet::new();
@@ -305,7 +384,249 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
             _ => None,
         });
 
-    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+    Ok(with_usage_details(
+        Usage::new(input_tokens, output_tokens, total_tokens),
+        usage,
+    ))
+}
+
+/// Add the cache and reasoning token counts of an OpenAI style `usage` object to `base`
+pub fn with_usage_details(base: Usage, usage: &Value) -> Usage {
+    // Cached tokens are reported as part of the prompt tokens, DeepSeek and Claude report them
+    // separately
+    let cache_read_tokens = usage
+        .pointer("/prompt_tokens_details/cached_tokens")
+        .or_else(|| usage.get("prompt_cache_hit_tokens"))
+        .or_else(|| usage.get("cache_read_input_tokens"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+    // Claude behind an OpenAI compatible API reports cache writes as Anthropic does
+    let cache_write_tokens = usage
+        .get("cache_creation_input_tokens")
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    let reasoning_tokens = usage
+        .pointer("/completion_tokens_details/reasoning_tokens")
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    base.with_cache_tokens(cache_read_tokens, cache_write_tokens)
+        .with_reasoning_tokens(reasoning_tokens)
+}
+
+#[derive(Debug, Default)]
+struct StreamedToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Accumulates the chunks of a streamed chat completion
+///
+/// Each chunk is turned into deltas for display, and once the stream is done the
+/// chunks are reassembled into the shape of a regular, non-streamed response so the
+/// existing response parsing can be reused unchanged.
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    model: Option<String>,
+    text: String,
+    reasoning: String,
+    reasoning_signature: String,
+    tool_calls: Vec<StreamedToolCall>,
+    usage: Option<Value>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold a single `chat.completion.chunk` into the accumulated state
+    pub fn push_chunk(&mut self, chunk: &Value) -> Result<Vec<MessageDelta>, ProviderError> {
+        if let Some(error) = chunk.get("error") {
+            return Err(stream_error(error));
+        }
+        if let Some(model) = chunk.get("model").and_then(|m| m.as_str()) {
+            self.model = Some(model.to_string());
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = Some(usage.clone());
+        }
+
+        let mut deltas = Vec::new();
+        let Some(delta) = chunk.pointer("/choices/0/delta") else {
+            return Ok(deltas);
+        };
+
+        match delta.get("content") {
+            Some(Value::String(text)) if !text.is_empty() => {
+                self.text.push_str(text);
+                deltas.push(MessageDelta::Text { text: text.clone() });
+            }
+            // Some OpenAI compatible endpoints (e.g. Databricks) stream typed content parts
+            Some(Value::Array(parts)) => {
+                for part in parts {
+                    match part.get("type").and_then(|t| t.as_str()) {
+                        Some("text") => {
+                            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                self.text.push_str(text);
+                                deltas.push(MessageDelta::Text {
+                                    text: text.to_string(),
+                                });
+                            }
+                        }
+                        Some("reasoning") => {
+                            let summaries = part.get("summary").and_then(|s| s.as_array());
+                            for summary in summaries.into_iter().flatten() {
+                                if let Some(text) = summary.get("text").and_then(|t| t.as_str()) {
+                                    self.reasoning.push_str(text);
+                                    deltas.push(MessageDelta::Thinking {
+                                        thinking: text.to_string(),
+                                    });
+                                }
+                                if let Some(signature) =
+                                    summary.get("signature").and_then(|s| s.as_str())
+                                {
+                                    self.reasoning_signature.push_str(signature);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let tool_calls = delta.get("tool_calls").and_then(|t| t.as_array());
+        for (position, tool_call) in tool_calls.into_iter().flatten().enumerate() {
+            let index = tool_call
+                .get("index")
+                .and_then(|i| i.as_u64())
+                .map(|i| i as usize)
+                .unwrap_or(position);
+            if self.tool_calls.len() <= index {
+                self.tool_calls
+                    .resize_with(index + 1, StreamedToolCall::default);
+            }
+            let entry = &mut self.tool_calls[index];
+
+            let id = tool_call.get("id").and_then(|i| i.as_str());
+            let name = tool_call.pointer("/function/name").and_then(|n| n.as_str());
+            let arguments = tool_call
+                .pointer("/function/arguments")
+                .and_then(|a| a.as_str())
+                .unwrap_or_default();
+            if let Some(id) = id {
+                entry.id = id.to_string();
+            }
+            if let Some(name) = name {
+                entry.name.push_str(name);
+            }
+            entry.arguments.push_str(arguments);
+
+            deltas.push(MessageDelta::ToolCall {
+                index,
+                id: id.map(String::from),
+                name: name.map(String::from),
+                arguments: arguments.to_string(),
+            });
+        }
+
+        Ok(deltas)
+    }
+
+    /// Reassemble the accumulated chunks into a non-streamed chat completion response
+    pub fn into_response(self) -> Value {
+        let content = if self.reasoning.is_empty() {
+            json!(self.text)
+        } else {
+            json!([
+                {
+                    "type": "reasoning",
+                    "summary": [{
+                        "type": "summary_text",
+                        "text": self.reasoning,
+                        "signature": self.reasoning_signature,
+                    }]
+                },
+                { "type": "text", "text": self.text }
+            ])
+        };
+
+        let mut message = json!({
+            "role": "assistant",
+            "content": content,
+        });
+        if !self.tool_calls.is_empty() {
+            message["tool_calls"] = self
+                .tool_calls
+                .into_iter()
+                .map(|call| {
+                    json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": { "name": call.name, "arguments": call.arguments }
+                    })
+                })
+                .collect();
+        }
+
+        let mut response = json!({ "choices": [{ "index": 0, "message": message }] });
+        if let Some(model) = self.model {
+            response["model"] = json!(model);
+        }
+        if let Some(usage) = self.usage {
+            response["usage"] = usage;
+        }
+        response
+    }
+}
+
+fn stream_error(error: &Value) -> ProviderError {
+    match serde_json::from_value::<OpenAIError>(error.clone()) {
+        Ok(err) if err.is_context_length_exceeded() => ProviderError::ContextLengthExceeded(
+            err.message.unwrap_or_else(|| "Unknown error".to_string()),
+        ),
+        Ok(err) => ProviderError::ServerError(err.to_string()),
+        Err(_) => ProviderError::ServerError(error.to_string()),
+    }
+}
+
+/// Stream a chat completion from an OpenAI compatible endpoint
+///
+/// `to_message` converts the reassembled response, so formats that only differ in how
+/// they parse the final message (like Databricks) can share the streaming logic.
+pub fn stream_response(
+    response: reqwest::Response,
+    model_config: ModelConfig,
+    payload: Value,
+    to_message: fn(Value) -> anyhow::Result<Message>,
+) -> ProviderStream {
+    Box::pin(async_stream::try_stream! {
+        let mut accumulator = StreamAccumulator::new();
+        let mut events = Box::pin(sse_json_stream(response));
+        while let Some(chunk) = events.next().await {
+            for delta in accumulator.push_chunk(&chunk?)? {
+                yield ProviderStreamEvent::Delta(delta);
+            }
+        }
+
+        let response = accumulator.into_response();
+        let message = to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => Err(e)?,
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&model_config, &payload, &response, &usage);
+        yield ProviderStreamEvent::Complete(message, ProviderUsage::new(model, usage));
+    })
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -367,21 +688,27 @@ pub fn create_request(
         ));
     }
 
+    check_media(&model_config.model_name, messages)?;
+
     let is_ox_model = model_config.model_name.starts_with("o");
 
     // Only extract reasoning effort for O1/O3 models
     let (model_name, reasoning_effort) = if is_ox_model {
         let parts: Vec<&str> = model_config.model_name.split('-').collect();
         let last_part = parts.last().unwrap();
+        // An effort set in the model config takes precedence over one in the model name
+        let configured = model_config
+            .reasoning_effort
+            .map(|effort| effort.as_openai_effort().to_string());
 
         match *last_part {
             "low" | "medium" | "high" => {
                 let base_name = parts[..parts.len() - 1].join("-");
-                (base_name, Some(last_part.to_string()))
+                (base_name, configured.or(Some(last_part.to_string())))
             }
             _ => (
                 model_config.model_name.to_string(),
-                Some("medium".to_string()),
+                configured.or(Some("medium".to_string())),
             ),
         }
     } else {
@@ -552,6 +879,42 @@ mod tests {
         }
     }"#;
 
+    #[test]
+    fn test_get_usage_cached_and_reasoning_tokens() -> anyhow::Result<()> {
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 50,
+                "total_tokens": 150,
+                "prompt_tokens_details": {"cached_tokens": 80},
+                "completion_tokens_details": {"reasoning_tokens": 40}
+            }
+        });
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(100));
+        assert_eq!(usage.output_tokens, Some(50));
+        assert_eq!(usage.cache_read_input_tokens, Some(80));
+        assert_eq!(usage.cache_write_input_tokens, None);
+        assert_eq!(usage.reasoning_tokens, Some(40));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_usage_anthropic_cache_tokens() -> anyhow::Result<()> {
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 50,
+                "cache_read_input_tokens": 60,
+                "cache_creation_input_tokens": 30
+            }
+        });
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.cache_read_input_tokens, Some(60));
+        assert_eq!(usage.cache_write_input_tokens, Some(30));
+        Ok(())
+    }
+
     #[test]
     fn test_format_messages() -> anyhow::Result<()> {
         let message = Message::user().with_text("Hello");
@@ -740,6 +1103,86 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_messages_keeps_every_part() -> anyhow::Result<()> {
+        let message = Message::user()
+            .with_document("JVBERi0=", "application/pdf", Some("spec.pdf".to_string()))
+            .with_image("iVBORw0KGgo=", "image/png")
+            .with_text("What does the diagram show?")
+            .with_text("Answer briefly.");
+        let spec = format_messages(&[message], &ImageFormat::OpenAi);
+
+        let content = spec[0]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 4);
+        assert_eq!(content[0]["type"], "file");
+        assert_eq!(content[1]["type"], "image_url");
+        assert_eq!(
+            content[2],
+            json!({"type": "text", "text": "What does the diagram show?"})
+        );
+        assert_eq!(
+            content[3],
+            json!({"type": "text", "text": "Answer briefly."})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_messages_with_document_and_audio() -> anyhow::Result<()> {
+        let message = Message::user()
+            .with_text("Compare the spec with the voice note")
+            .with_document("JVBERi0=", "application/pdf", Some("spec.pdf".to_string()))
+            .with_audio("UklGRg==", "audio/wav");
+        let messages = [message];
+        let spec = format_messages(&messages, &ImageFormat::OpenAi);
+
+        let content = spec[0]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[0]["type"], "text");
+        assert_eq!(
+            content[1],
+            json!({"type": "file", "file": {"filename": "spec.pdf", "file_data": "data:application/pdf;base64,JVBERi0="}})
+        );
+        assert_eq!(
+            content[2],
+            json!({"type": "input_audio", "input_audio": {"data": "UklGRg==", "format": "wav"}})
+        );
+
+        // Only the audio models take audio
+        let model_config = ModelConfig::new("gpt-4o-audio-preview".to_string());
+        assert!(create_request(
+            &model_config,
+            "system",
+            &messages,
+            &[],
+            &ImageFormat::OpenAi
+        )
+        .is_ok());
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        let err = create_request(
+            &model_config,
+            "system",
+            &messages,
+            &[],
+            &ImageFormat::OpenAi,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("does not accept audio input"));
+
+        let messages = [Message::user().with_audio("T2dnUw==", "audio/ogg")];
+        let model_config = ModelConfig::new("gpt-4o-audio-preview".to_string());
+        let err = create_request(
+            &model_config,
+            "system",
+            &messages,
+            &[],
+            &ImageFormat::OpenAi,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("only accept wav and mp3"));
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_text() -> anyhow::Result<()> {
         let response = json!({
@@ -848,6 +1291,82 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_stream_accumulator_text_and_tool_calls() -> anyhow::Result<()> {
+        let chunks = [
+            json!({"model": "gpt-4o", "choices": [{"index": 0, "delta": {"role": "assistant", "content": "Hel"}}]}),
+            json!({"model": "gpt-4o", "choices": [{"index": 0, "delta": {"content": "lo"}}]}),
+            json!({"model": "gpt-4o", "choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "id": "call_1", "type": "function", "function": {"name": "example_fn", "arguments": ""}}
+            ]}}]}),
+            json!({"model": "gpt-4o", "choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "function": {"arguments": "{\"param\": "}}
+            ]}}]}),
+            json!({"model": "gpt-4o", "choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "function": {"arguments": "\"value\"}"}}
+            ]}}]}),
+            json!({"model": "gpt-4o", "choices": [], "usage": {"prompt_tokens": 12, "completion_tokens": 5, "total_tokens": 17}}),
+        ];
+
+        let mut accumulator = StreamAccumulator::new();
+        let mut deltas = Vec::new();
+        for chunk in &chunks {
+            deltas.extend(accumulator.push_chunk(chunk)?);
+        }
+
+        assert_eq!(
+            deltas[0],
+            MessageDelta::Text {
+                text: "Hel".to_string()
+            }
+        );
+        assert_eq!(
+            deltas[1],
+            MessageDelta::Text {
+                text: "lo".to_string()
+            }
+        );
+        assert_eq!(
+            deltas[2],
+            MessageDelta::ToolCall {
+                index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("example_fn".to_string()),
+                arguments: "".to_string(),
+            }
+        );
+        assert_eq!(deltas.len(), 5);
+
+        let response = accumulator.into_response();
+        assert_eq!(get_model(&response), "gpt-4o");
+        assert_eq!(get_usage(&response)?.total_tokens, Some(17));
+
+        let message = response_to_message(response)?;
+        assert_eq!(message.content[0], MessageContent::text("Hello"));
+        if let MessageContent::ToolRequest(request) = &message.content[1] {
+            let tool_call = request.tool_call.as_ref().unwrap();
+            assert_eq!(request.id, "call_1");
+            assert_eq!(tool_call.name, "example_fn");
+            assert_eq!(tool_call.arguments, json!({"param": "value"}));
+        } else {
+            panic!("Expected ToolRequest content");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_accumulator_error_chunk() {
+        let mut accumulator = StreamAccumulator::new();
+        let result = accumulator.push_chunk(&json!({
+            "error": {"message": "too long", "code": "context_length_exceeded"}
+        }));
+        assert!(matches!(
+            result,
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+    }
+
     #[test]
     fn test_create_request_gpt_4o() -> anyhow::Result<()> {
         // Test default medium reasoning effort for O3 model
@@ -859,6 +1378,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -891,6 +1412,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -924,6 +1447,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -945,4 +1470,22 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_create_request_configured_reasoning_effort() -> anyhow::Result<()> {
+        // The configured effort wins over the one in the model name
+        let model_config = ModelConfig::new("o3-mini-high".to_string())
+            .with_reasoning_effort(Some(crate::model::ReasoningEffort::Low));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["model"], "o3-mini");
+        assert_eq!(request["reasoning_effort"], "low");
+
+        // Models that don't reason ignore it
+        let model_config = ModelConfig::new("gpt-4o".to_string())
+            .with_reasoning_effort(Some(crate::model::ReasoningEffort::High));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert!(request.get("reasoning_effort").is_none());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use etcetera::{choose_app_strategy, AppStrategy};
+use mcp_core::tool::Tool;
+use tokio::sync::Mutex;
+
+use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::gguf::{ChatTemplate, GgufMetadata};
+use super::llama_cpp::LlamaModel;
+use super::toolshim::{
+    augment_message_with_tool_calls, modify_system_prompt_for_tool_json, TextFormatInterpreter,
+};
+use super::utils::emit_debug_trace;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+pub const GGUF_DEFAULT_MODEL: &str = "qwen2.5-coder-7b-instruct-q4_k_m.gguf";
+pub const GGUF_DOC_URL: &str = "https://huggingface.co/models?library=gguf";
+/// Tokens generated per response when no max tokens are configured
+const DEFAULT_MAX_TOKENS: usize = 4096;
+
+/// Runs a GGUF model file in-process on the CPU, without a server
+///
+/// The model is a path to a `.gguf` file or the name of one in `GGUF_MODEL_DIR`. Its family's
+/// chat template and its context length are read from the file. Local models rarely call
+/// tools natively, so tools are described in the system prompt and the calls are parsed out
+/// of the text, as the toolshim does.
+pub struct GgufProvider {
+    model: ModelConfig,
+    /// Completions run one at a time, they already use every core
+    llama: Arc<Mutex<LlamaModel>>,
+    template: ChatTemplate,
+    context_size: u32,
+}
+
+impl GgufProvider {
+    pub fn from_env(mut model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let path = model_path(&model.model_name);
+        let metadata = GgufMetadata::read(&path)?;
+
+        let template = match config.get_param::<String>("GGUF_CHAT_TEMPLATE") {
+            Ok(name) => name.parse()?,
+            Err(_) => ChatTemplate::detect(&metadata),
+        };
+
+        // The whole context is allocated up front, so it can be set lower to save memory
+        let context_size = config
+            .get_param::<usize>("GGUF_CONTEXT_SIZE")
+            .ok()
+            .or(metadata.context_length)
+            .unwrap_or_else(|| model.context_limit());
+        model.context_limit = Some(context_size);
+
+        tracing::info!(
+            "Loading {} with the {:?} chat template and a context of {} tokens",
+            path.display(),
+            template,
+            context_size
+        );
+        let llama = LlamaModel::load(&path)?;
+
+        Ok(Self {
+            model,
+            llama: Arc::new(Mutex::new(llama)),
+            template,
+            context_size: u32::try_from(context_size)?,
+        })
+    }
+}
+
+fn model_dir() -> PathBuf {
+    Config::global()
+        .get_param::<String>("GGUF_MODEL_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            // choose_app_strategy().data_dir()
+            // - macOS/Linux: ~/.local/share/goose/models
+            // - Windows:     ~\AppData\Roaming\Block\goose\data\models
+            choose_app_strategy(crate::config::APP_STRATEGY.clone())
+                .map(|strategy| strategy.in_data_dir("models"))
+                .unwrap_or_else(|_| PathBuf::from(".local/share/goose/models"))
+        })
+}
+
+/// A model name is a path to a model file, or the name of one in the model directory
+fn model_path(model_name: &str) -> PathBuf {
+    let path = Path::new(model_name);
+    if path.is_absolute() || path.exists() {
+        return path.to_path_buf();
+    }
+    let path = model_dir().join(model_name);
+    if path.extension().is_none() {
+        path.with_extension("gguf")
+    } else {
+        path
+    }
+}
+
+#[async_trait]
+impl Provider for GgufProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "gguf",
+            "Local GGUF",
+            "Run a GGUF model file in-process on the CPU with llama.cpp, without a server",
+            GGUF_DEFAULT_MODEL,
+            vec![GGUF_DEFAULT_MODEL],
+            GGUF_DOC_URL,
+            vec![
+                ConfigKey::new("GGUF_MODEL_DIR", false, false, None),
+                ConfigKey::new("GGUF_CONTEXT_SIZE", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let system = if tools.is_empty() {
+            system.to_string()
+        } else {
+            modify_system_prompt_for_tool_json(system, tools)
+        };
+        let prompt = self.template.render(&system, messages);
+
+        let max_tokens = self
+            .model
+            .max_tokens
+            .map(|tokens| tokens.max(1) as usize)
+            .unwrap_or(DEFAULT_MAX_TOKENS);
+        let temperature = self.model.temperature;
+        let context_size = self.context_size;
+        let llama = self.llama.clone().lock_owned().await;
+
+        let prompt_for_model = prompt.clone();
+        let generation = tokio::task::spawn_blocking(move || {
+            llama.generate(&prompt_for_model, context_size, max_tokens, temperature)
+        })
+        .await
+        .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+        .map_err(|e| {
+            let message = e.to_string();
+            if message.contains("Input is too long") {
+                ProviderError::ContextLengthExceeded(message)
+            } else {
+                ProviderError::ExecutionError(message)
+            }
+        })?;
+
+        let message = Message::assistant().with_text(generation.text.trim());
+        let message =
+            augment_message_with_tool_calls(&TextFormatInterpreter, message, tools).await?;
+
+        let input_tokens = generation.input_tokens as i32;
+        let output_tokens = generation.output_tokens as i32;
+        let usage = Usage::new(
+            Some(input_tokens),
+            Some(output_tokens),
+            Some(input_tokens + output_tokens),
+        );
+        emit_debug_trace(
+            &self.model,
+            &serde_json::json!({ "prompt": prompt }),
+            &serde_json::to_value(&message).unwrap_or_default(),
+            &usage,
+        );
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+
+    /// The model files in the model directory
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let Ok(entries) = std::fs::read_dir(model_dir()) else {
+            return Ok(None);
+        };
+        let mut models: Vec<ModelInfo> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "gguf"))
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                let context_length = GgufMetadata::read(&path).ok()?.context_length;
+                Some(ModelInfo::new(name).with_context_limit(context_length))
+            })
+            .collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Some(models))
+    }
+}
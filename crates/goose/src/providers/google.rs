@@ -1,8 +1,12 @@
 use super::errors::ProviderError;
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
-use crate::providers::formats::google::{create_request, get_usage, response_to_message};
+use crate::providers::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use crate::providers::formats::google::{
+    create_request, format_response_schema, get_usage, response_to_message,
+};
+use crate::providers::models::parse_google_models;
+use crate::providers::structured;
 use crate::providers::utils::{
     emit_debug_trace, handle_response_google_compat, unescape_json_values,
 };
@@ -10,7 +14,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::tool::Tool;
 use reqwest::Client;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::time::Duration;
 use url::Url;
 
@@ -93,12 +97,14 @@ impl GoogleProvider {
                 Ok(res) => {
                     match handle_response_google_compat(res).await {
                         Ok(result) => return Ok(result),
-                        Err(ProviderError::RateLimitExceeded(_)) => {
+                        Err(ProviderError::RateLimitExceeded { .. }) => {
                             retries += 1;
                             if retries > max_retries {
-                                return Err(ProviderError::RateLimitExceeded(
-                                    "Max retries exceeded for rate limit error".to_string(),
-                                ));
+                                return Err(ProviderError::RateLimitExceeded {
+                                    details: "Max retries exceeded for rate limit error"
+                                        .to_string(),
+                                    retry_delay: None,
+                                });
                             }
 
                             let delay = 2u64.pow(retries);
@@ -169,4 +175,41 @@ impl Provider for GoogleProvider {
         let provider_usage = ProviderUsage::new(model, usage);
         Ok((message, provider_usage))
     }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, &[])?;
+        payload["generationConfig"]["responseMimeType"] = json!("application/json");
+        payload["generationConfig"]["responseSchema"] = format_response_schema(schema);
+
+        let response = self.post(payload.clone()).await?;
+
+        let message = response_to_message(unescape_json_values(&response))?;
+        let usage = get_usage(&response)?;
+        let model = match response.get("modelVersion") {
+            Some(model_version) => model_version.as_str().unwrap_or_default().to_string(),
+            None => self.model.model_name.clone(),
+        };
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        let value = structured::extract_structured(&message, schema)?;
+        Ok((value, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url
+            .join(&format!("v1beta/models?key={}&pageSize=1000", self.api_key))
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })?;
+
+        let response = self.client.get(url).send().await?;
+        let response = handle_response_google_compat(response).await?;
+        Ok(Some(parse_google_models(&response)))
+    }
 }
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::base::{ModelInfo, Provider, ProviderMetadata, ProviderStream, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use mcp_core::role::Role;
+use mcp_core::tool::Tool;
+
+/// Default number of turns at the start of a session that go to the lead model
+const DEFAULT_LEAD_TURNS: usize = 3;
+/// Default number of turns after a failed tool call that go back to the lead model
+const DEFAULT_FALLBACK_TURNS: usize = 2;
+
+/// When turns are routed to the lead model rather than the worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeadWorkerConfig {
+    /// Turns at the start of a session, while the plan is made, that go to the lead
+    pub lead_turns: usize,
+    /// Turns after a tool call failed that go to the lead, so it can recover
+    pub fallback_turns: usize,
+}
+
+impl Default for LeadWorkerConfig {
+    fn default() -> Self {
+        Self {
+            lead_turns: DEFAULT_LEAD_TURNS,
+            fallback_turns: DEFAULT_FALLBACK_TURNS,
+        }
+    }
+}
+
+impl LeadWorkerConfig {
+    /// Load the routing rules from config, falling back to defaults for unset keys
+    pub fn from_config(config: &Config) -> Self {
+        let defaults = Self::default();
+        Self {
+            lead_turns: config
+                .get_param("GOOSE_LEAD_TURNS")
+                .unwrap_or(defaults.lead_turns),
+            fallback_turns: config
+                .get_param("GOOSE_LEAD_FALLBACK_TURNS")
+                .unwrap_or(defaults.fallback_turns),
+        }
+    }
+}
+
+/// Routes each turn of a session to a strong lead model or a cheaper worker model
+///
+/// The lead takes the first `lead_turns` turns and any turn within `fallback_turns`
+/// of a failed tool call. Every other turn, typically routine tool calling, goes to
+/// the worker. Routing only looks at the conversation, so a resumed session picks up
+/// where it left off. Usage is reported by whichever model answered.
+pub struct LeadWorkerProvider {
+    lead: Arc<dyn Provider>,
+    worker: Arc<dyn Provider>,
+    config: LeadWorkerConfig,
+}
+
+impl LeadWorkerProvider {
+    pub fn new(
+        lead: Arc<dyn Provider>,
+        worker: Arc<dyn Provider>,
+        config: LeadWorkerConfig,
+    ) -> Self {
+        Self {
+            lead,
+            worker,
+            config,
+        }
+    }
+
+    /// Whether the next turn of the conversation should go to the lead model
+    pub fn use_lead(&self, messages: &[Message]) -> bool {
+        let turns = messages
+            .iter()
+            .filter(|message| message.role == Role::Assistant)
+            .count();
+        if turns < self.config.lead_turns {
+            return true;
+        }
+
+        // Tool results come back in user messages, one per turn that called tools
+        messages
+            .iter()
+            .rev()
+            .filter(|message| message.role == Role::User && message.is_tool_response())
+            .take(self.config.fallback_turns)
+            .any(|message| {
+                message.content.iter().any(|content| {
+                    matches!(content, MessageContent::ToolResponse(response) if response.tool_result.is_err())
+                })
+            })
+    }
+
+    fn route(&self, messages: &[Message]) -> &Arc<dyn Provider> {
+        let provider = if self.use_lead(messages) {
+            &self.lead
+        } else {
+            &self.worker
+        };
+        tracing::debug!("Routing turn to {}", provider.get_model_config().model_name);
+        provider
+    }
+}
+
+#[async_trait]
+impl Provider for LeadWorkerProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.route(messages).complete(system, messages, tools).await
+    }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        self.route(messages)
+            .complete_structured(system, messages, schema)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        self.route(messages).stream(system, messages, tools).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.worker.supports_streaming()
+    }
+
+    /// The worker's config, limited to the smaller of the two context windows so the
+    /// conversation still fits when a turn goes back to the lead
+    fn get_model_config(&self) -> ModelConfig {
+        let mut config = self.worker.get_model_config();
+        let lead_limit = self.lead.get_model_config().context_limit();
+        config.context_limit = Some(config.context_limit().min(lead_limit));
+        config
+    }
+
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        self.worker.fetch_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use mcp_core::content::Content;
+    use mcp_core::handler::ToolError;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    /// Answers with its own model name
+    struct NamedProvider {
+        model: ModelConfig,
+    }
+
+    impl NamedProvider {
+        fn new(model: &str, context_limit: usize) -> Arc<Self> {
+            let mut model = ModelConfig::new(model.to_string());
+            model.context_limit = Some(context_limit);
+            Arc::new(Self { model })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for NamedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(&self.model.model_name),
+                ProviderUsage::new(self.model.model_name.clone(), Usage::default()),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model.clone()
+        }
+    }
+
+    fn provider(lead_turns: usize, fallback_turns: usize) -> LeadWorkerProvider {
+        LeadWorkerProvider::new(
+            NamedProvider::new("lead", 200_000),
+            NamedProvider::new("worker", 128_000),
+            LeadWorkerConfig {
+                lead_turns,
+                fallback_turns,
+            },
+        )
+    }
+
+    /// One turn in which the model called a tool, with the tool's result
+    fn tool_turn(id: &str, ok: bool) -> Vec<Message> {
+        let result = if ok {
+            Ok(vec![Content::text("done")])
+        } else {
+            Err(ToolError::ExecutionError("failed".to_string()))
+        };
+        vec![
+            Message::assistant().with_tool_request(id, Ok(ToolCall::new("shell", json!({})))),
+            Message::user().with_tool_response(id, result),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_first_turns_go_to_lead() {
+        let provider = provider(2, 1);
+        let mut messages = vec![Message::user().with_text("build it")];
+
+        let (_, usage) = provider.complete("", &messages, &[]).await.unwrap();
+        assert_eq!(usage.model, "lead");
+
+        messages.extend(tool_turn("1", true));
+        let (_, usage) = provider.complete("", &messages, &[]).await.unwrap();
+        assert_eq!(usage.model, "lead");
+
+        messages.extend(tool_turn("2", true));
+        let (_, usage) = provider.complete("", &messages, &[]).await.unwrap();
+        assert_eq!(usage.model, "worker");
+    }
+
+    #[test]
+    fn test_tool_failure_goes_back_to_lead() {
+        let provider = provider(0, 2);
+        let mut messages = vec![Message::user().with_text("build it")];
+        messages.extend(tool_turn("1", true));
+        assert!(!provider.use_lead(&messages));
+
+        messages.extend(tool_turn("2", false));
+        assert!(provider.use_lead(&messages));
+        messages.extend(tool_turn("3", true));
+        assert!(provider.use_lead(&messages));
+        messages.extend(tool_turn("4", true));
+        assert!(!provider.use_lead(&messages));
+    }
+
+    #[test]
+    fn test_context_limit_fits_both_models() {
+        let provider = provider(1, 1);
+        let config = provider.get_model_config();
+        assert_eq!(config.model_name, "worker");
+        assert_eq!(config.context_limit(), 128_000);
+    }
+}
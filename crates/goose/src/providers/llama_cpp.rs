@@ -0,0 +1,426 @@
+//! In-process inference with llama.cpp.
+//!
+//! Binds the C API of a system `libllama`, so no server and no C++ toolchain are needed at
+//! build time. The declarations follow `llama.h` of llama.cpp release b5200, and other
+//! releases change the structs passed by value, so the build links the library its `llama.pc`
+//! names, refuses any other release and checks the structs of its `llama.h`. Point
+//! `LLAMA_CPP_LIB_DIR` at the directory with the library when pkg-config can't find it.
+
+use std::ffi::{c_char, c_void, CString};
+use std::path::Path;
+use std::sync::Once;
+
+use anyhow::{anyhow, bail, Result};
+
+#[allow(non_camel_case_types)]
+type llama_token = i32;
+
+#[repr(C)]
+struct LlamaModelHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct LlamaContextHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct LlamaVocabHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct LlamaSamplerHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct LlamaModelParams {
+    devices: *mut c_void,
+    tensor_buft_overrides: *const c_void,
+    n_gpu_layers: i32,
+    split_mode: i32,
+    main_gpu: i32,
+    tensor_split: *const f32,
+    progress_callback: *const c_void,
+    progress_callback_user_data: *mut c_void,
+    kv_overrides: *const c_void,
+    vocab_only: bool,
+    use_mmap: bool,
+    use_mlock: bool,
+    check_tensors: bool,
+}
+
+#[repr(C)]
+struct LlamaContextParams {
+    n_ctx: u32,
+    n_batch: u32,
+    n_ubatch: u32,
+    n_seq_max: u32,
+    n_threads: i32,
+    n_threads_batch: i32,
+    rope_scaling_type: i32,
+    pooling_type: i32,
+    attention_type: i32,
+    rope_freq_base: f32,
+    rope_freq_scale: f32,
+    yarn_ext_factor: f32,
+    yarn_attn_factor: f32,
+    yarn_beta_fast: f32,
+    yarn_beta_slow: f32,
+    yarn_orig_ctx: u32,
+    defrag_thold: f32,
+    cb_eval: *const c_void,
+    cb_eval_user_data: *mut c_void,
+    type_k: i32,
+    type_v: i32,
+    logits_all: bool,
+    embeddings: bool,
+    offload_kqv: bool,
+    flash_attn: bool,
+    no_perf: bool,
+    abort_callback: *const c_void,
+    abort_callback_data: *mut c_void,
+}
+
+#[repr(C)]
+struct LlamaSamplerChainParams {
+    no_perf: bool,
+}
+
+#[repr(C)]
+struct LlamaBatch {
+    n_tokens: i32,
+    token: *mut llama_token,
+    embd: *mut f32,
+    pos: *mut i32,
+    n_seq_id: *mut i32,
+    seq_id: *mut *mut i32,
+    logits: *mut i8,
+}
+
+// The structs above are passed by value, so their layout has to match llama.h exactly. These
+// are the sizes and offsets the C compiler gives the b5200 structs on 64-bit targets, and the
+// build asserts the same numbers for the llama.h it links against in llama_cpp_layout.c.
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    use std::mem::{align_of, offset_of, size_of};
+
+    assert!(size_of::<LlamaModelParams>() == 72);
+    assert!(align_of::<LlamaModelParams>() == 8);
+    assert!(offset_of!(LlamaModelParams, n_gpu_layers) == 16);
+    assert!(offset_of!(LlamaModelParams, tensor_split) == 32);
+    assert!(offset_of!(LlamaModelParams, vocab_only) == 64);
+
+    assert!(size_of::<LlamaContextParams>() == 120);
+    assert!(align_of::<LlamaContextParams>() == 8);
+    assert!(offset_of!(LlamaContextParams, n_threads) == 16);
+    assert!(offset_of!(LlamaContextParams, defrag_thold) == 64);
+    assert!(offset_of!(LlamaContextParams, cb_eval) == 72);
+    assert!(offset_of!(LlamaContextParams, type_k) == 88);
+    assert!(offset_of!(LlamaContextParams, logits_all) == 96);
+    assert!(offset_of!(LlamaContextParams, abort_callback) == 104);
+
+    assert!(size_of::<LlamaSamplerChainParams>() == 1);
+    assert!(align_of::<LlamaSamplerChainParams>() == 1);
+
+    assert!(size_of::<LlamaBatch>() == 56);
+    assert!(align_of::<LlamaBatch>() == 8);
+    assert!(offset_of!(LlamaBatch, token) == 8);
+    assert!(offset_of!(LlamaBatch, logits) == 48);
+};
+
+#[link(name = "llama")]
+extern "C" {
+    fn llama_backend_init();
+    fn llama_model_default_params() -> LlamaModelParams;
+    fn llama_context_default_params() -> LlamaContextParams;
+    fn llama_model_load_from_file(
+        path_model: *const c_char,
+        params: LlamaModelParams,
+    ) -> *mut LlamaModelHandle;
+    fn llama_model_free(model: *mut LlamaModelHandle);
+    fn llama_model_get_vocab(model: *const LlamaModelHandle) -> *const LlamaVocabHandle;
+    fn llama_init_from_model(
+        model: *mut LlamaModelHandle,
+        params: LlamaContextParams,
+    ) -> *mut LlamaContextHandle;
+    fn llama_free(ctx: *mut LlamaContextHandle);
+    fn llama_tokenize(
+        vocab: *const LlamaVocabHandle,
+        text: *const c_char,
+        text_len: i32,
+        tokens: *mut llama_token,
+        n_tokens_max: i32,
+        add_special: bool,
+        parse_special: bool,
+    ) -> i32;
+    fn llama_token_to_piece(
+        vocab: *const LlamaVocabHandle,
+        token: llama_token,
+        buf: *mut c_char,
+        length: i32,
+        lstrip: i32,
+        special: bool,
+    ) -> i32;
+    fn llama_vocab_is_eog(vocab: *const LlamaVocabHandle, token: llama_token) -> bool;
+    fn llama_batch_get_one(tokens: *mut llama_token, n_tokens: i32) -> LlamaBatch;
+    fn llama_decode(ctx: *mut LlamaContextHandle, batch: LlamaBatch) -> i32;
+    fn llama_sampler_chain_default_params() -> LlamaSamplerChainParams;
+    fn llama_sampler_chain_init(params: LlamaSamplerChainParams) -> *mut LlamaSamplerHandle;
+    fn llama_sampler_chain_add(chain: *mut LlamaSamplerHandle, smpl: *mut LlamaSamplerHandle);
+    fn llama_sampler_init_greedy() -> *mut LlamaSamplerHandle;
+    fn llama_sampler_init_min_p(p: f32, min_keep: usize) -> *mut LlamaSamplerHandle;
+    fn llama_sampler_init_temp(t: f32) -> *mut LlamaSamplerHandle;
+    fn llama_sampler_init_dist(seed: u32) -> *mut LlamaSamplerHandle;
+    fn llama_sampler_sample(
+        smpl: *mut LlamaSamplerHandle,
+        ctx: *mut LlamaContextHandle,
+        idx: i32,
+    ) -> llama_token;
+    fn llama_sampler_free(smpl: *mut LlamaSamplerHandle);
+}
+
+/// Let llama.cpp pick a random seed for sampling
+const DEFAULT_SEED: u32 = 0xFFFF_FFFF;
+/// Contexts are sized in steps of this many tokens, which llama.cpp pads them to anyway
+const CONTEXT_STEP: usize = 256;
+
+static BACKEND_INIT: Once = Once::new();
+
+/// A model loaded into memory, shared by all completions
+pub struct LlamaModel {
+    model: *mut LlamaModelHandle,
+}
+
+// SAFETY: llama.cpp doesn't tie a model to the thread that loaded it, and the handle is owned
+// here and freed once, on drop. The model is not `Sync`: llama.h makes no promise about
+// using one model from several threads at once, so callers take turns.
+unsafe impl Send for LlamaModel {}
+
+/// The text a model generated, with the tokens it took
+pub struct Generation {
+    pub text: String,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl LlamaModel {
+    /// Load a GGUF model file for inference on the CPU
+    pub fn load(path: &Path) -> Result<Self> {
+        BACKEND_INIT.call_once(|| unsafe { llama_backend_init() });
+
+        let path_str = CString::new(path.to_string_lossy().as_bytes())?;
+        let mut params = unsafe { llama_model_default_params() };
+        params.n_gpu_layers = 0;
+        let model = unsafe { llama_model_load_from_file(path_str.as_ptr(), params) };
+        if model.is_null() {
+            bail!("Failed to load model {}", path.display());
+        }
+        Ok(Self { model })
+    }
+
+    fn vocab(&self) -> *const LlamaVocabHandle {
+        unsafe { llama_model_get_vocab(self.model) }
+    }
+
+    /// Tokenize a prompt whose special tokens are written out in the text
+    pub fn tokenize(&self, text: &str) -> Result<Vec<llama_token>> {
+        let vocab = self.vocab();
+        let len = i32::try_from(text.len())?;
+        let count = unsafe {
+            llama_tokenize(
+                vocab,
+                text.as_ptr() as *const c_char,
+                len,
+                std::ptr::null_mut(),
+                0,
+                false,
+                true,
+            )
+        };
+        // A negative count is the number of tokens that did not fit
+        let mut tokens = vec![0; count.unsigned_abs() as usize];
+        let written = unsafe {
+            llama_tokenize(
+                vocab,
+                text.as_ptr() as *const c_char,
+                len,
+                tokens.as_mut_ptr(),
+                tokens.len() as i32,
+                false,
+                true,
+            )
+        };
+        if written < 0 {
+            bail!("Failed to tokenize the prompt");
+        }
+        tokens.truncate(written as usize);
+        Ok(tokens)
+    }
+
+    fn token_bytes(&self, token: llama_token) -> Vec<u8> {
+        let mut buf = vec![0u8; 64];
+        let mut len = unsafe {
+            llama_token_to_piece(
+                self.vocab(),
+                token,
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len() as i32,
+                0,
+                false,
+            )
+        };
+        if len < 0 {
+            buf.resize(len.unsigned_abs() as usize, 0);
+            len = unsafe {
+                llama_token_to_piece(
+                    self.vocab(),
+                    token,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len() as i32,
+                    0,
+                    false,
+                )
+            };
+        }
+        buf.truncate(len.max(0) as usize);
+        buf
+    }
+
+    /// Continue a prompt until the model ends its turn or `max_tokens` are generated
+    ///
+    /// Blocks for as long as generation takes, so call it from a blocking task.
+    pub fn generate(
+        &self,
+        prompt: &str,
+        context_size: u32,
+        max_tokens: usize,
+        temperature: Option<f32>,
+    ) -> Result<Generation> {
+        let mut tokens = self.tokenize(prompt)?;
+        let input_tokens = tokens.len();
+        if input_tokens >= context_size as usize {
+            bail!(
+                "Input is too long for requested model: {} tokens for a context of {}",
+                input_tokens,
+                context_size
+            );
+        }
+
+        // The KV cache grows with the context, so allocate only what this completion can use
+        let n_ctx = context_tokens(input_tokens, max_tokens, context_size);
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get() as i32)
+            .unwrap_or(4);
+        let mut params = unsafe { llama_context_default_params() };
+        params.n_ctx = n_ctx;
+        // The prompt is evaluated in a single batch
+        params.n_batch = context_tokens(input_tokens, 0, context_size);
+        params.n_threads = threads;
+        params.n_threads_batch = threads;
+        let context = Context(unsafe { llama_init_from_model(self.model, params) });
+        if context.0.is_null() {
+            bail!("Failed to create a context of {} tokens", n_ctx);
+        }
+        let sampler = Sampler::new(temperature);
+
+        let status = unsafe {
+            llama_decode(
+                context.0,
+                llama_batch_get_one(tokens.as_mut_ptr(), tokens.len() as i32),
+            )
+        };
+        if status != 0 {
+            bail!("Failed to evaluate the prompt: {}", status);
+        }
+
+        let max_tokens = max_tokens.min(n_ctx as usize - input_tokens);
+        let mut output = Vec::new();
+        let mut output_tokens = 0;
+        while output_tokens < max_tokens {
+            let mut token = unsafe { llama_sampler_sample(sampler.0, context.0, -1) };
+            if unsafe { llama_vocab_is_eog(self.vocab(), token) } {
+                break;
+            }
+            output.extend(self.token_bytes(token));
+            output_tokens += 1;
+
+            let status = unsafe { llama_decode(context.0, llama_batch_get_one(&mut token, 1)) };
+            if status != 0 {
+                return Err(anyhow!("Failed to generate: {}", status));
+            }
+        }
+
+        Ok(Generation {
+            // Tokens may split a character, so the text is only decoded at the end
+            text: String::from_utf8_lossy(&output).into_owned(),
+            input_tokens,
+            output_tokens,
+        })
+    }
+}
+
+/// The context for `input_tokens` of prompt and up to `max_tokens` of output, rounded up to a
+/// whole step and at most `limit`
+fn context_tokens(input_tokens: usize, max_tokens: usize, limit: u32) -> u32 {
+    let needed = (input_tokens + max_tokens).div_ceil(CONTEXT_STEP) * CONTEXT_STEP;
+    needed.min(limit as usize) as u32
+}
+
+impl Drop for LlamaModel {
+    fn drop(&mut self) {
+        unsafe { llama_model_free(self.model) }
+    }
+}
+
+struct Context(*mut LlamaContextHandle);
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { llama_free(self.0) }
+        }
+    }
+}
+
+struct Sampler(*mut LlamaSamplerHandle);
+
+impl Sampler {
+    /// Greedy without a temperature or at zero, otherwise min-p sampling at the temperature
+    fn new(temperature: Option<f32>) -> Self {
+        unsafe {
+            let chain = llama_sampler_chain_init(llama_sampler_chain_default_params());
+            match temperature.filter(|t| *t > 0.0) {
+                Some(temperature) => {
+                    llama_sampler_chain_add(chain, llama_sampler_init_min_p(0.05, 1));
+                    llama_sampler_chain_add(chain, llama_sampler_init_temp(temperature));
+                    llama_sampler_chain_add(chain, llama_sampler_init_dist(DEFAULT_SEED));
+                }
+                None => llama_sampler_chain_add(chain, llama_sampler_init_greedy()),
+            }
+            Self(chain)
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe { llama_sampler_free(self.0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_tokens() {
+        assert_eq!(context_tokens(100, 50, 8192), 256);
+        assert_eq!(context_tokens(1000, 1024, 8192), 2048);
+        assert_eq!(context_tokens(1000, 1025, 8192), 2304);
+        assert_eq!(context_tokens(7000, 4096, 8192), 8192);
+        assert_eq!(context_tokens(300, 0, 8192), 512);
+    }
+}
@@ -0,0 +1,38 @@
+// Compiled by build.rs against the llama.h of the libllama being linked, so the build fails
+// when its structs differ from the declarations in llama_cpp.rs. The numbers are the same ones
+// llama_cpp.rs asserts for its own structs.
+
+#include <stddef.h>
+#include <stdint.h>
+
+#include "llama.h"
+
+#if UINTPTR_MAX == 0xffffffffffffffffu
+
+_Static_assert(sizeof(struct llama_model_params) == 72, "llama_model_params changed");
+_Static_assert(_Alignof(struct llama_model_params) == 8, "llama_model_params changed");
+_Static_assert(offsetof(struct llama_model_params, n_gpu_layers) == 16, "llama_model_params changed");
+_Static_assert(offsetof(struct llama_model_params, tensor_split) == 32, "llama_model_params changed");
+_Static_assert(offsetof(struct llama_model_params, vocab_only) == 64, "llama_model_params changed");
+
+_Static_assert(sizeof(struct llama_context_params) == 120, "llama_context_params changed");
+_Static_assert(_Alignof(struct llama_context_params) == 8, "llama_context_params changed");
+_Static_assert(offsetof(struct llama_context_params, n_threads) == 16, "llama_context_params changed");
+_Static_assert(offsetof(struct llama_context_params, defrag_thold) == 64, "llama_context_params changed");
+_Static_assert(offsetof(struct llama_context_params, cb_eval) == 72, "llama_context_params changed");
+_Static_assert(offsetof(struct llama_context_params, type_k) == 88, "llama_context_params changed");
+_Static_assert(offsetof(struct llama_context_params, logits_all) == 96, "llama_context_params changed");
+_Static_assert(offsetof(struct llama_context_params, abort_callback) == 104, "llama_context_params changed");
+
+_Static_assert(sizeof(struct llama_sampler_chain_params) == 1, "llama_sampler_chain_params changed");
+_Static_assert(_Alignof(struct llama_sampler_chain_params) == 1, "llama_sampler_chain_params changed");
+
+_Static_assert(sizeof(struct llama_batch) == 56, "llama_batch changed");
+_Static_assert(_Alignof(struct llama_batch) == 8, "llama_batch changed");
+_Static_assert(offsetof(struct llama_batch, token) == 8, "llama_batch changed");
+_Static_assert(offsetof(struct llama_batch, logits) == 48, "llama_batch changed");
+
+#endif
+
+// Keeps the translation unit from being empty
+int goose_llama_cpp_layout_checked(void) { return 1; }
@@ -3,19 +3,31 @@ pub mod azure;
 pub mod azureauth;
 pub mod base;
 pub mod bedrock;
+pub mod caching;
+pub mod cassette;
+pub mod custom;
 pub mod databricks;
 pub mod errors;
 mod factory;
 pub mod formats;
 mod gcpauth;
 pub mod gcpvertexai;
+#[cfg(feature = "local-gguf")]
+pub mod gguf;
 pub mod google;
 pub mod groq;
+pub mod lead_worker;
+#[cfg(feature = "local-gguf")]
+pub mod llama_cpp;
+pub mod models;
 pub mod oauth;
 pub mod ollama;
 pub mod openai;
 pub mod openrouter;
+pub mod pricing;
+pub mod retry;
+pub mod structured;
 pub mod toolshim;
 pub mod utils;
 
-pub use factory::{create, providers};
+pub use factory::{create, create_with_retries, providers};
@@ -0,0 +1,433 @@
+//! Live model listings from provider APIs, cached on disk
+//!
+//! `Provider::fetch_models` asks the provider's API which models it serves. Since that is a
+//! network round trip (and some APIs are slow), `list_models` keeps the result in the cache
+//! dir for a day. The cached context limits are also what `ModelConfig` uses for models whose
+//! limit it can't otherwise know.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::base::ModelInfo;
+use super::{create, providers};
+use crate::model::ModelConfig;
+
+/// How long a cached listing is used before the provider is asked again
+const CACHE_TTL_HOURS: i64 = 24;
+
+/// Model ids served by OpenAI compatible APIs that can't be used for chat
+const NON_CHAT_MODEL_MARKERS: &[&str] = &[
+    "embedding",
+    "moderation",
+    "whisper",
+    "tts",
+    "dall-e",
+    "davinci",
+    "babbage",
+    "transcribe",
+    "realtime",
+    "image",
+];
+
+/// Claude models released before extended thinking
+const PRE_THINKING_CLAUDE_MODELS: &[&str] = &[
+    "claude-2",
+    "claude-3-opus",
+    "claude-3-sonnet",
+    "claude-3-haiku",
+    "claude-3-5",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedModels {
+    pub fetched_at: DateTime<Utc>,
+    pub models: Vec<ModelInfo>,
+}
+
+impl CachedModels {
+    pub fn is_fresh(&self) -> bool {
+        Utc::now() - self.fetched_at < Duration::hours(CACHE_TTL_HOURS)
+    }
+}
+
+/// On-disk cache of model listings, one JSON file per provider
+pub struct ModelCache {
+    dir: PathBuf,
+}
+
+impl Default for ModelCache {
+    fn default() -> Self {
+        // choose_app_strategy().cache_dir()
+        // - macOS/Linux: ~/.cache/goose/models
+        // - Windows:     ~\AppData\Local\Block\goose\cache\models
+        let dir = choose_app_strategy(crate::config::APP_STRATEGY.clone())
+            .map(|strategy| strategy.in_cache_dir("models"))
+            .unwrap_or_else(|_| PathBuf::from(".cache/goose/models"));
+        Self::new(dir)
+    }
+}
+
+impl ModelCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, provider: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", provider))
+    }
+
+    /// The last listing stored for the provider, fresh or not
+    pub fn load(&self, provider: &str) -> Option<CachedModels> {
+        let contents = fs::read_to_string(self.path(provider)).ok()?;
+        serde_json::from_str(&contents)
+            .map_err(|e| tracing::warn!("Ignoring unreadable model cache for {}: {}", provider, e))
+            .ok()
+    }
+
+    pub fn store(&self, provider: &str, models: &[ModelInfo]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let cached = CachedModels {
+            fetched_at: Utc::now(),
+            models: models.to_vec(),
+        };
+        fs::write(self.path(provider), serde_json::to_string_pretty(&cached)?)?;
+        Ok(())
+    }
+
+    /// Context limits of every cached model, across providers
+    fn context_limits(&self) -> HashMap<String, usize> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return HashMap::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let provider = path.file_stem()?.to_str()?.to_string();
+                self.load(&provider)
+            })
+            .flat_map(|cached| cached.models)
+            .map(|model| (model.name, model.context_limit))
+            .collect()
+    }
+}
+
+static CONTEXT_LIMITS: Lazy<RwLock<HashMap<String, usize>>> =
+    Lazy::new(|| RwLock::new(ModelCache::default().context_limits()));
+
+/// Context limit reported by a provider for the model, if it is in the model cache
+pub fn cached_context_limit(model_name: &str) -> Option<usize> {
+    CONTEXT_LIMITS
+        .read()
+        .ok()
+        .and_then(|limits| limits.get(model_name).copied())
+}
+
+/// List the models of a provider by name, from the cache when it is fresh
+///
+/// Returns `Ok(None)` if the provider can't list its models. `refresh` skips the cache.
+pub async fn list_models(provider_name: &str, refresh: bool) -> Result<Option<Vec<ModelInfo>>> {
+    let cache = ModelCache::default();
+    if !refresh {
+        if let Some(cached) = cache.load(provider_name).filter(CachedModels::is_fresh) {
+            return Ok(Some(cached.models));
+        }
+    }
+
+    let metadata = providers()
+        .into_iter()
+        .find(|metadata| metadata.name == provider_name)
+        .ok_or_else(|| anyhow!("Unknown provider: {}", provider_name))?;
+    let provider = create(provider_name, ModelConfig::new(metadata.default_model))?;
+    let Some(models) = provider.fetch_models().await? else {
+        return Ok(None);
+    };
+
+    if let Err(e) = cache.store(provider_name, &models) {
+        tracing::warn!("Failed to cache the models of {}: {}", provider_name, e);
+    }
+    if let Ok(mut limits) = CONTEXT_LIMITS.write() {
+        limits.extend(
+            models
+                .iter()
+                .map(|model| (model.name.clone(), model.context_limit)),
+        );
+    }
+    Ok(Some(models))
+}
+
+fn is_reasoning_model(name: &str) -> bool {
+    ["o1", "o3", "o4"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Parse the response of an OpenAI compatible `GET /v1/models`
+pub fn parse_openai_models(response: &Value) -> Vec<ModelInfo> {
+    response["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["id"].as_str())
+        .filter(|id| {
+            !NON_CHAT_MODEL_MARKERS
+                .iter()
+                .any(|marker| id.contains(marker))
+        })
+        .map(|id| {
+            let reasoning = is_reasoning_model(id).then_some(true);
+            ModelInfo::new(id).with_capabilities(None, None, reasoning)
+        })
+        .collect()
+}
+
+/// Parse the response of Anthropic's `GET /v1/models`
+pub fn parse_anthropic_models(response: &Value) -> Vec<ModelInfo> {
+    response["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["id"].as_str())
+        .map(|id| {
+            // Every model the API still serves takes tools and images, extended thinking
+            // arrived with Claude 3.7
+            let reasoning = !PRE_THINKING_CLAUDE_MODELS
+                .iter()
+                .any(|prefix| id.starts_with(prefix));
+            ModelInfo::new(id)
+                .with_context_limit(Some(200_000))
+                .with_capabilities(Some(true), Some(true), Some(reasoning))
+        })
+        .collect()
+}
+
+/// Model names from Ollama's `GET /api/tags`
+pub fn parse_ollama_tags(response: &Value) -> Vec<String> {
+    response["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["name"].as_str().map(String::from))
+        .collect()
+}
+
+/// Model info from Ollama's `POST /api/show`, which reports the capabilities of newer models
+pub fn parse_ollama_show(name: &str, response: &Value) -> ModelInfo {
+    // The context length is keyed by architecture, e.g. "llama.context_length"
+    let context_limit = response["model_info"].as_object().and_then(|info| {
+        info.iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|limit| limit as usize)
+    });
+    let capabilities: Option<Vec<&str>> = response["capabilities"]
+        .as_array()
+        .map(|caps| caps.iter().filter_map(|c| c.as_str()).collect());
+    let has = |capability: &str| capabilities.as_ref().map(|caps| caps.contains(&capability));
+
+    ModelInfo::new(name)
+        .with_context_limit(context_limit)
+        .with_capabilities(has("tools"), has("vision"), has("thinking"))
+}
+
+/// Parse the response of OpenRouter's `GET /api/v1/models`
+pub fn parse_openrouter_models(response: &Value) -> Vec<ModelInfo> {
+    response["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            let id = model["id"].as_str()?;
+            let context_limit = model["context_length"].as_u64().map(|l| l as usize);
+            let contains = |list: &Value, item: &str| {
+                list.as_array()
+                    .map(|values| values.iter().any(|v| v.as_str() == Some(item)))
+            };
+            Some(
+                ModelInfo::new(id)
+                    .with_context_limit(context_limit)
+                    .with_capabilities(
+                        contains(&model["supported_parameters"], "tools"),
+                        contains(&model["architecture"]["input_modalities"], "image"),
+                        contains(&model["supported_parameters"], "reasoning"),
+                    ),
+            )
+        })
+        .collect()
+}
+
+/// Parse the response of Google's `GET v1beta/models`, keeping only models that can chat
+pub fn parse_google_models(response: &Value) -> Vec<ModelInfo> {
+    response["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|model| {
+            model["supportedGenerationMethods"]
+                .as_array()
+                .is_some_and(|methods| methods.iter().any(|m| m == "generateContent"))
+        })
+        .filter_map(|model| {
+            let name = model["name"].as_str()?;
+            let name = name.strip_prefix("models/").unwrap_or(name);
+            let context_limit = model["inputTokenLimit"].as_u64().map(|l| l as usize);
+            let gemini = name.starts_with("gemini").then_some(true);
+            Some(
+                ModelInfo::new(name)
+                    .with_context_limit(context_limit)
+                    .with_capabilities(gemini, gemini, model["thinking"].as_bool()),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_openai_models_skips_non_chat_models() {
+        let response = json!({
+            "object": "list",
+            "data": [
+                {"id": "gpt-4o", "object": "model", "owned_by": "system"},
+                {"id": "o3-mini", "object": "model", "owned_by": "system"},
+                {"id": "text-embedding-3-small", "object": "model", "owned_by": "system"},
+                {"id": "whisper-1", "object": "model", "owned_by": "system"}
+            ]
+        });
+        let models = parse_openai_models(&response);
+        let names: Vec<_> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["gpt-4o", "o3-mini"]);
+        assert_eq!(models[0].context_limit, 128_000);
+        assert_eq!(models[0].supports_reasoning, None);
+        assert_eq!(models[1].supports_reasoning, Some(true));
+    }
+
+    #[test]
+    fn test_parse_anthropic_models() {
+        let response = json!({
+            "data": [
+                {"id": "claude-3-7-sonnet-20250219", "display_name": "Claude 3.7 Sonnet", "type": "model"},
+                {"id": "claude-3-5-haiku-20241022", "display_name": "Claude 3.5 Haiku", "type": "model"}
+            ],
+            "has_more": false
+        });
+        let models = parse_anthropic_models(&response);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].context_limit, 200_000);
+        assert_eq!(models[0].supports_reasoning, Some(true));
+        assert_eq!(models[1].supports_reasoning, Some(false));
+        assert_eq!(models[1].supports_tools, Some(true));
+    }
+
+    #[test]
+    fn test_parse_ollama() {
+        let tags = json!({
+            "models": [
+                {"name": "qwen3:8b", "model": "qwen3:8b", "details": {"family": "qwen3"}},
+                {"name": "llava:latest", "model": "llava:latest", "details": {"family": "llama"}}
+            ]
+        });
+        assert_eq!(parse_ollama_tags(&tags), vec!["qwen3:8b", "llava:latest"]);
+
+        let show = json!({
+            "capabilities": ["completion", "tools", "thinking"],
+            "model_info": {"general.architecture": "qwen3", "qwen3.context_length": 40960}
+        });
+        let model = parse_ollama_show("qwen3:8b", &show);
+        assert_eq!(model.context_limit, 40_960);
+        assert_eq!(model.supports_tools, Some(true));
+        assert_eq!(model.supports_vision, Some(false));
+        assert_eq!(model.supports_reasoning, Some(true));
+
+        // Older Ollama versions don't report capabilities
+        let model = parse_ollama_show("llama3.2", &json!({"model_info": {}}));
+        assert_eq!(model.context_limit, 128_000);
+        assert_eq!(model.supports_tools, None);
+    }
+
+    #[test]
+    fn test_parse_openrouter_models() {
+        let response = json!({
+            "data": [{
+                "id": "anthropic/claude-3.7-sonnet",
+                "context_length": 200000,
+                "architecture": {"input_modalities": ["text", "image"], "output_modalities": ["text"]},
+                "supported_parameters": ["tools", "tool_choice", "reasoning", "max_tokens"]
+            }, {
+                "id": "some/text-model",
+                "context_length": 32768,
+                "architecture": {"input_modalities": ["text"]},
+                "supported_parameters": ["max_tokens"]
+            }]
+        });
+        let models = parse_openrouter_models(&response);
+        assert_eq!(
+            models[0],
+            ModelInfo {
+                name: "anthropic/claude-3.7-sonnet".to_string(),
+                context_limit: 200_000,
+                supports_tools: Some(true),
+                supports_vision: Some(true),
+                supports_reasoning: Some(true),
+            }
+        );
+        assert_eq!(models[1].context_limit, 32_768);
+        assert_eq!(models[1].supports_tools, Some(false));
+        assert_eq!(models[1].supports_vision, Some(false));
+    }
+
+    #[test]
+    fn test_parse_google_models() {
+        let response = json!({
+            "models": [{
+                "name": "models/gemini-2.5-flash",
+                "inputTokenLimit": 1048576,
+                "supportedGenerationMethods": ["generateContent", "countTokens"],
+                "thinking": true
+            }, {
+                "name": "models/text-embedding-004",
+                "inputTokenLimit": 2048,
+                "supportedGenerationMethods": ["embedContent"]
+            }]
+        });
+        let models = parse_google_models(&response);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "gemini-2.5-flash");
+        assert_eq!(models[0].context_limit, 1_048_576);
+        assert_eq!(models[0].supports_reasoning, Some(true));
+    }
+
+    #[test]
+    fn test_model_cache_round_trip() -> Result<()> {
+        let dir = tempdir()?;
+        let cache = ModelCache::new(dir.path().join("models"));
+        assert!(cache.load("openrouter").is_none());
+
+        let models = vec![ModelInfo::new("new-model").with_context_limit(Some(2_000_000))];
+        cache.store("openrouter", &models)?;
+
+        let cached = cache.load("openrouter").unwrap();
+        assert!(cached.is_fresh());
+        assert_eq!(cached.models, models);
+        assert_eq!(cache.context_limits().get("new-model"), Some(&2_000_000));
+
+        let stale = CachedModels {
+            fetched_at: Utc::now() - Duration::hours(CACHE_TTL_HOURS + 1),
+            models,
+        };
+        assert!(!stale.is_fresh());
+        Ok(())
+    }
+}
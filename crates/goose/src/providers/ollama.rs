@@ -1,14 +1,24 @@
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderStream, ProviderUsage, Usage,
+};
+use super::custom::CustomEndpoint;
 use super::errors::ProviderError;
-use super::utils::{get_model, handle_response_openai_compat};
+use super::models::{parse_ollama_show, parse_ollama_tags};
+use super::structured;
+use super::utils::{
+    check_streaming_response_openai_compat, get_model, handle_response_openai_compat,
+};
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::formats::openai::{
+    create_request, get_usage, response_to_message, stream_response,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::tool::Tool;
-use reqwest::Client;
-use serde_json::Value;
+use reqwest::{Client, RequestBuilder, Response};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::time::Duration;
 use url::Url;
 
@@ -18,6 +28,9 @@ pub const OLLAMA_DEFAULT_MODEL: &str = "qwen2.5";
 // Ollama can run many models, we only provide the default
 pub const OLLAMA_KNOWN_MODELS: &[&str] = &[OLLAMA_DEFAULT_MODEL];
 pub const OLLAMA_DOC_URL: &str = "https://ollama.com/library";
+/// How long each request listing the installed models may take, so a server that isn't
+/// running is noticed quickly
+const OLLAMA_LIST_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(serde::Serialize)]
 pub struct OllamaProvider {
@@ -25,6 +38,8 @@ pub struct OllamaProvider {
     client: Client,
     host: String,
     model: ModelConfig,
+    #[serde(skip)]
+    custom_headers: HashMap<String, String>,
 }
 
 impl Default for OllamaProvider {
@@ -49,10 +64,72 @@ impl OllamaProvider {
             client,
             host,
             model,
+            custom_headers: HashMap::new(),
         })
     }
 
+    /// Create a provider for an Ollama server declared in `custom_providers`
+    pub fn from_custom(model: ModelConfig, endpoint: CustomEndpoint) -> Result<Self> {
+        let client = Client::builder().timeout(endpoint.timeout).build()?;
+
+        Ok(Self {
+            client,
+            host: endpoint.host,
+            model,
+            custom_headers: endpoint.headers,
+        })
+    }
+
+    fn with_headers(&self, mut request: RequestBuilder) -> RequestBuilder {
+        for (key, value) in &self.custom_headers {
+            request = request.header(key, value);
+        }
+        request
+    }
+
     /// Get the base URL for Ollama API calls
+    /// The models installed on the Ollama server, with what api/show reports about each
+    async fn fetch_installed_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let base_url = self.get_base_url()?;
+        let endpoint = |path: &str| {
+            base_url.join(path).map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })
+        };
+
+        let response = self
+            .with_headers(self.client.get(endpoint("api/tags")?))
+            .timeout(OLLAMA_LIST_TIMEOUT)
+            .send()
+            .await?;
+        let tags = handle_response_openai_compat(response).await?;
+
+        // The tags only list names, the context length and capabilities come from api/show,
+        // asked for every model at once. A model it can't describe is still listed.
+        let show_endpoint = endpoint("api/show")?;
+        let models = futures::future::join_all(parse_ollama_tags(&tags).into_iter().map(|name| {
+            let request = self
+                .with_headers(self.client.post(show_endpoint.clone()))
+                .json(&json!({ "model": name }))
+                .timeout(OLLAMA_LIST_TIMEOUT);
+            async move {
+                let details = match request.send().await {
+                    Ok(response) => handle_response_openai_compat(response).await,
+                    Err(e) => Err(e.into()),
+                };
+                match details {
+                    Ok(details) => parse_ollama_show(&name, &details),
+                    Err(e) => {
+                        tracing::debug!("Could not describe the Ollama model {}: {}", name, e);
+                        ModelInfo::new(name)
+                    }
+                }
+            }
+        }))
+        .await;
+        Ok(models)
+    }
+
     fn get_base_url(&self) -> Result<Url, ProviderError> {
         // OLLAMA_HOST is sometimes just the 'host' or 'host:port' without a scheme
         let base = if self.host.starts_with("http://") || self.host.starts_with("https://") {
@@ -80,18 +157,65 @@ impl OllamaProvider {
         Ok(base_url)
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    fn get_completions_url(&self) -> Result<Url, ProviderError> {
         // TODO: remove this later when the UI handles provider config refresh
         let base_url = self.get_base_url()?;
 
-        let url = base_url.join("v1/chat/completions").map_err(|e| {
+        base_url.join("v1/chat/completions").map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
-        })?;
+        })
+    }
 
-        let response = self.client.post(url).json(&payload).send().await?;
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let url = self.get_completions_url()?;
+        let response = self
+            .with_headers(self.client.post(url))
+            .json(&payload)
+            .send()
+            .await?;
 
         handle_response_openai_compat(response).await
     }
+
+    async fn post_stream(&self, payload: Value) -> Result<Response, ProviderError> {
+        let url = self.get_completions_url()?;
+        let response = self
+            .with_headers(self.client.post(url))
+            .json(&payload)
+            .send()
+            .await?;
+
+        check_streaming_response_openai_compat(response).await
+    }
+}
+
+/// Convert OpenAI formatted messages to the native Ollama chat format, which takes plain
+/// text content and tool call arguments as objects
+fn to_native_messages(messages: &Value) -> Value {
+    let messages = messages.as_array().cloned().unwrap_or_default();
+    Value::Array(
+        messages
+            .into_iter()
+            .map(|mut message| {
+                if let Some(parts) = message["content"].as_array() {
+                    let text: Vec<&str> = parts
+                        .iter()
+                        .filter_map(|part| part["text"].as_str())
+                        .collect();
+                    message["content"] = json!(text.join("\n"));
+                }
+                if let Some(calls) = message["tool_calls"].as_array_mut() {
+                    for call in calls {
+                        if let Some(arguments) = call["function"]["arguments"].as_str() {
+                            call["function"]["arguments"] =
+                                serde_json::from_str(arguments).unwrap_or_else(|_| json!({}));
+                        }
+                    }
+                }
+                message
+            })
+            .collect(),
+    )
 }
 
 #[async_trait]
@@ -150,4 +274,122 @@ impl Provider for OllamaProvider {
         super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        // The OpenAI compatible endpoint has no equivalent of the native `format`
+        let request = create_request(
+            &self.model,
+            system,
+            messages,
+            &[],
+            &super::utils::ImageFormat::OpenAi,
+        )?;
+        let mut payload = json!({
+            "model": self.model.model_name,
+            "messages": to_native_messages(&request["messages"]),
+            "format": schema,
+            "stream": false,
+        });
+        if let Some(temperature) = self.model.temperature {
+            payload["options"] = json!({ "temperature": temperature });
+        }
+
+        let url = self.get_base_url()?.join("api/chat").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        let response = self
+            .with_headers(self.client.post(url))
+            .json(&payload)
+            .send()
+            .await?;
+        let response = handle_response_openai_compat(response).await?;
+
+        let text = response["message"]["content"].as_str().unwrap_or_default();
+        let message = Message::assistant().with_text(text);
+        let input_tokens = response["prompt_eval_count"].as_i64().map(|n| n as i32);
+        let output_tokens = response["eval_count"].as_i64().map(|n| n as i32);
+        let total_tokens = input_tokens.zip(output_tokens).map(|(i, o)| i + o);
+        let usage = Usage::new(input_tokens, output_tokens, total_tokens);
+        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+
+        let value = structured::extract_structured(&message, schema)?;
+        Ok((
+            value,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let mut payload = create_request(
+            &self.model,
+            system,
+            messages,
+            tools,
+            &super::utils::ImageFormat::OpenAi,
+        )?;
+        payload["stream"] = json!(true);
+        payload["stream_options"] = json!({ "include_usage": true });
+
+        let response = self.post_stream(payload.clone()).await?;
+
+        Ok(stream_response(
+            response,
+            self.model.clone(),
+            payload,
+            response_to_message,
+        ))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        match self.fetch_installed_models().await {
+            Ok(models) => Ok(Some(models)),
+            Err(e) => {
+                tracing::debug!("Could not list the installed Ollama models: {}", e);
+                Ok(Some(
+                    OLLAMA_KNOWN_MODELS
+                        .iter()
+                        .map(|name| ModelInfo::new(*name))
+                        .collect(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_models_falls_back_to_known_models() {
+        // Nothing listens on the discard port, so listing the installed models fails
+        let provider = OllamaProvider::from_custom(
+            ModelConfig::new(OLLAMA_DEFAULT_MODEL.to_string()),
+            CustomEndpoint {
+                host: "http://127.0.0.1:9".to_string(),
+                base_path: None,
+                headers: HashMap::new(),
+                timeout: Duration::from_secs(5),
+            },
+        )
+        .unwrap();
+
+        let models = provider.fetch_models().await.unwrap().unwrap();
+        let names: Vec<&str> = models.iter().map(|model| model.name.as_str()).collect();
+        assert_eq!(names, OLLAMA_KNOWN_MODELS);
+    }
 }
@@ -1,14 +1,22 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::Client;
-use serde_json::Value;
+use reqwest::{Client, RequestBuilder, Response};
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderStream, ProviderUsage, Usage,
+};
+use super::custom::CustomEndpoint;
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::formats::openai::{create_request, get_usage, response_to_message, stream_response};
+use super::models::parse_openai_models;
+use super::structured;
+use super::utils::{
+    check_streaming_response_openai_compat, emit_debug_trace, get_model,
+    handle_response_openai_compat, ImageFormat,
+};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -80,17 +88,37 @@ impl OpenAiProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    /// Create a provider for an OpenAI compatible endpoint declared in `custom_providers`
+    pub fn from_custom(model: ModelConfig, endpoint: CustomEndpoint) -> Result<Self> {
+        let client = Client::builder().timeout(endpoint.timeout).build()?;
+
+        Ok(Self {
+            client,
+            host: endpoint.host,
+            base_path: endpoint
+                .base_path
+                .unwrap_or_else(|| "v1/chat/completions".to_string()),
+            // The API key, if any, is already one of the endpoint's headers
+            api_key: String::new(),
+            organization: None,
+            project: None,
+            model,
+            custom_headers: Some(endpoint.headers),
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<url::Url, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
-        let url = base_url.join(&self.base_path).map_err(|e| {
+        base_url.join(path).map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
-        })?;
+        })
+    }
 
-        let mut request = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.api_key));
+    fn with_headers(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if !self.api_key.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", self.api_key));
+        }
 
         // Add organization header if present
         if let Some(org) = &self.organization {
@@ -108,10 +136,25 @@ impl OpenAiProvider {
             }
         }
 
-        let response = request.json(&payload).send().await?;
+        request
+    }
+
+    fn request(&self) -> Result<RequestBuilder, ProviderError> {
+        let url = self.url(&self.base_path)?;
+        Ok(self.with_headers(self.client.post(url)))
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.request()?.json(&payload).send().await?;
 
         handle_response_openai_compat(response).await
     }
+
+    async fn post_stream(&self, payload: Value) -> Result<Response, ProviderError> {
+        let response = self.request()?.json(&payload).send().await?;
+
+        check_streaming_response_openai_compat(response).await
+    }
 }
 
 #[async_trait]
@@ -169,6 +212,67 @@ impl Provider for OpenAiProvider {
         emit_debug_trace(&self.model, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, &[], &ImageFormat::OpenAi)?;
+        payload["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "response",
+                "schema": structured::object_schema(schema),
+            }
+        });
+
+        let response = self.post(payload.clone()).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = get_usage(&response).unwrap_or_default();
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        let value = structured::extract_structured(&message, schema)?;
+        Ok((value, ProviderUsage::new(model, usage)))
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        payload["stream"] = json!(true);
+        payload["stream_options"] = json!({ "include_usage": true });
+
+        let response = self.post_stream(payload.clone()).await?;
+
+        Ok(stream_response(
+            response,
+            self.model.clone(),
+            payload,
+            response_to_message,
+        ))
+    }
+
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        // The models endpoint sits next to the chat completions one, e.g. v1/models
+        let path = self.base_path.replace("chat/completions", "models");
+        let response = self
+            .with_headers(self.client.get(self.url(&path)?))
+            .send()
+            .await?;
+        let response = handle_response_openai_compat(response).await?;
+        Ok(Some(parse_openai_models(&response)))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 }
 
 fn parse_custom_headers(s: String) -> HashMap<String, String> {
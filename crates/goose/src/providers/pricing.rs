@@ -0,0 +1,274 @@
+//! Per-model token pricing used to estimate what a session has cost.
+//!
+//! Prices are in USD per million tokens. The built-in table covers the models goose
+//! knows about; anything else (or a price that has changed) can be set through the
+//! `GOOSE_PRICING` config key, which maps a model name prefix to its pricing:
+//!
+//! ```yaml
+//! GOOSE_PRICING:
+//!   my-finetuned-model:
+//!     input: 0.5
+//!     output: 1.5
+//!     cache_read: 0.05
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::base::Usage;
+use crate::config::Config;
+
+/// Config key holding user-defined pricing that takes precedence over the built-in table
+pub const PRICING_CONFIG_KEY: &str = "GOOSE_PRICING";
+
+const PER_TOKENS: f64 = 1_000_000.0;
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price of uncached input tokens
+    pub input: f64,
+    /// Price of output tokens, including reasoning tokens
+    pub output: f64,
+    /// Price of input tokens read from the prompt cache, defaults to the input price
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+    /// Price of input tokens written to the prompt cache, defaults to the input price
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+}
+
+impl ModelPricing {
+    pub const fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read: None,
+            cache_write: None,
+        }
+    }
+
+    pub const fn with_cache(mut self, read: f64, write: Option<f64>) -> Self {
+        self.cache_read = Some(read);
+        self.cache_write = write;
+        self
+    }
+
+    /// Estimated cost of the usage in USD, None if the usage carries no token counts
+    pub fn cost(&self, usage: &Usage) -> Option<f64> {
+        if usage.input_tokens.is_none() && usage.output_tokens.is_none() {
+            return None;
+        }
+
+        let tokens = |count: Option<i32>| count.unwrap_or(0).max(0) as f64;
+        let cache_read = tokens(usage.cache_read_input_tokens);
+        let cache_write = tokens(usage.cache_write_input_tokens);
+        // input_tokens includes the cached tokens, which are billed at their own rates
+        let uncached_input = (tokens(usage.input_tokens) - cache_read - cache_write).max(0.0);
+
+        let cost = uncached_input * self.input
+            + cache_read * self.cache_read.unwrap_or(self.input)
+            + cache_write * self.cache_write.unwrap_or(self.input)
+            + tokens(usage.output_tokens) * self.output;
+        Some(cost / PER_TOKENS)
+    }
+}
+
+/// Built-in pricing keyed by model name prefix, the longest matching prefix wins
+const BUILTIN_PRICING: &[(&str, ModelPricing)] = &[
+    // OpenAI
+    (
+        "gpt-4o",
+        ModelPricing::new(2.5, 10.0).with_cache(1.25, None),
+    ),
+    (
+        "gpt-4o-mini",
+        ModelPricing::new(0.15, 0.6).with_cache(0.075, None),
+    ),
+    ("gpt-4.1", ModelPricing::new(2.0, 8.0).with_cache(0.5, None)),
+    (
+        "gpt-4.1-mini",
+        ModelPricing::new(0.4, 1.6).with_cache(0.1, None),
+    ),
+    (
+        "gpt-4.1-nano",
+        ModelPricing::new(0.1, 0.4).with_cache(0.025, None),
+    ),
+    ("gpt-4-turbo", ModelPricing::new(10.0, 30.0)),
+    ("o1", ModelPricing::new(15.0, 60.0).with_cache(7.5, None)),
+    (
+        "o1-mini",
+        ModelPricing::new(1.1, 4.4).with_cache(0.55, None),
+    ),
+    ("o3", ModelPricing::new(10.0, 40.0).with_cache(2.5, None)),
+    (
+        "o3-mini",
+        ModelPricing::new(1.1, 4.4).with_cache(0.55, None),
+    ),
+    (
+        "o4-mini",
+        ModelPricing::new(1.1, 4.4).with_cache(0.275, None),
+    ),
+    // Anthropic
+    (
+        "claude-opus-4",
+        ModelPricing::new(15.0, 75.0).with_cache(1.5, Some(18.75)),
+    ),
+    (
+        "claude-sonnet-4",
+        ModelPricing::new(3.0, 15.0).with_cache(0.3, Some(3.75)),
+    ),
+    (
+        "claude-3-7-sonnet",
+        ModelPricing::new(3.0, 15.0).with_cache(0.3, Some(3.75)),
+    ),
+    (
+        "claude-3-5-sonnet",
+        ModelPricing::new(3.0, 15.0).with_cache(0.3, Some(3.75)),
+    ),
+    (
+        "claude-3-5-haiku",
+        ModelPricing::new(0.8, 4.0).with_cache(0.08, Some(1.0)),
+    ),
+    (
+        "claude-3-opus",
+        ModelPricing::new(15.0, 75.0).with_cache(1.5, Some(18.75)),
+    ),
+    (
+        "claude-3-haiku",
+        ModelPricing::new(0.25, 1.25).with_cache(0.03, Some(0.3)),
+    ),
+    // Google
+    ("gemini-2.5-pro", ModelPricing::new(1.25, 10.0)),
+    ("gemini-2.5-flash", ModelPricing::new(0.15, 0.6)),
+    (
+        "gemini-2.0-flash",
+        ModelPricing::new(0.1, 0.4).with_cache(0.025, None),
+    ),
+    ("gemini-2.0-flash-lite", ModelPricing::new(0.075, 0.3)),
+    ("gemini-1.5-pro", ModelPricing::new(1.25, 5.0)),
+    ("gemini-1.5-flash", ModelPricing::new(0.075, 0.3)),
+];
+
+/// Strip the routing prefix some providers put in front of the model name,
+/// e.g. `anthropic/claude-3-5-sonnet` on OpenRouter or `models/gemini-1.5-pro` on Google
+fn normalize_model_name(model: &str) -> String {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    name.to_lowercase()
+}
+
+fn longest_prefix_match<'a, I>(model: &str, entries: I) -> Option<ModelPricing>
+where
+    I: IntoIterator<Item = (&'a str, ModelPricing)>,
+{
+    entries
+        .into_iter()
+        .filter(|(prefix, _)| model.starts_with(&prefix.to_lowercase()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, pricing)| pricing)
+}
+
+/// Look up the pricing of a model, checking `GOOSE_PRICING` before the built-in table
+pub fn model_pricing(model: &str) -> Option<ModelPricing> {
+    let overrides: HashMap<String, ModelPricing> = Config::global()
+        .get_param(PRICING_CONFIG_KEY)
+        .unwrap_or_default();
+    lookup_pricing(model, &overrides)
+}
+
+fn lookup_pricing(model: &str, overrides: &HashMap<String, ModelPricing>) -> Option<ModelPricing> {
+    // An override for the exact, unnormalized name (e.g. "openrouter/some-model") wins outright
+    if let Some(pricing) = overrides.get(model) {
+        return Some(*pricing);
+    }
+
+    let name = normalize_model_name(model);
+    longest_prefix_match(&name, overrides.iter().map(|(k, v)| (k.as_str(), *v)))
+        .or_else(|| longest_prefix_match(&name, BUILTIN_PRICING.iter().copied()))
+}
+
+/// Estimated cost in USD of the usage reported for a model, None if its pricing is unknown
+pub fn estimate_cost(model: &str, usage: &Usage) -> Option<f64> {
+    model_pricing(model).and_then(|pricing| pricing.cost(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_cost_without_cache() {
+        let pricing = ModelPricing::new(3.0, 15.0);
+        let usage = Usage::new(Some(1_000_000), Some(100_000), Some(1_100_000));
+        assert_close(pricing.cost(&usage).unwrap(), 3.0 + 1.5);
+    }
+
+    #[test]
+    fn test_cost_bills_cached_tokens_at_cache_rates() {
+        let pricing = ModelPricing::new(3.0, 15.0).with_cache(0.3, Some(3.75));
+        let usage = Usage::new(Some(1_000_000), Some(0), Some(1_000_000))
+            .with_cache_tokens(Some(600_000), Some(200_000));
+        // 200k uncached at 3.0, 600k read at 0.3, 200k written at 3.75
+        assert_close(pricing.cost(&usage).unwrap(), 0.6 + 0.18 + 0.75);
+
+        // Without cache prices the cached tokens fall back to the input price
+        let pricing = ModelPricing::new(3.0, 15.0);
+        assert_close(pricing.cost(&usage).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_cost_without_token_counts() {
+        let pricing = ModelPricing::new(3.0, 15.0);
+        assert_eq!(pricing.cost(&Usage::default()), None);
+    }
+
+    #[test]
+    fn test_lookup_uses_longest_prefix() {
+        let overrides = HashMap::new();
+        assert_eq!(
+            lookup_pricing("gpt-4o-mini-2024-07-18", &overrides),
+            Some(ModelPricing::new(0.15, 0.6).with_cache(0.075, None))
+        );
+        assert_eq!(
+            lookup_pricing("gpt-4o-2024-08-06", &overrides),
+            Some(ModelPricing::new(2.5, 10.0).with_cache(1.25, None))
+        );
+        assert!(lookup_pricing("anthropic/claude-3-5-sonnet-latest", &overrides).is_some());
+        assert!(lookup_pricing("models/gemini-1.5-pro-002", &overrides).is_some());
+        assert_eq!(lookup_pricing("llama3.2", &overrides), None);
+    }
+
+    #[test]
+    fn test_lookup_prefers_overrides() {
+        let mut overrides = HashMap::new();
+        overrides.insert("gpt-4o".to_string(), ModelPricing::new(1.0, 2.0));
+        overrides.insert("llama3.2".to_string(), ModelPricing::new(0.0, 0.0));
+        overrides.insert("openrouter/custom".to_string(), ModelPricing::new(5.0, 5.0));
+
+        assert_eq!(
+            lookup_pricing("gpt-4o-2024-08-06", &overrides),
+            Some(ModelPricing::new(1.0, 2.0))
+        );
+        // An override applies to every model name it prefixes, ahead of the built-in table
+        assert_eq!(
+            lookup_pricing("gpt-4o-mini", &overrides),
+            Some(ModelPricing::new(1.0, 2.0))
+        );
+        assert_eq!(
+            lookup_pricing("llama3.2:latest", &overrides),
+            Some(ModelPricing::new(0.0, 0.0))
+        );
+        assert_eq!(
+            lookup_pricing("openrouter/custom", &overrides),
+            Some(ModelPricing::new(5.0, 5.0))
+        );
+    }
+}
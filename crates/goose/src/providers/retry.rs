@@ -0,0 +1,407 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use super::base::{ModelInfo, Provider, ProviderMetadata, ProviderStream, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+use serde_json::Value;
+
+/// Default maximum number of retries against a single provider
+const DEFAULT_MAX_RETRIES: usize = 3;
+/// Default initial interval for retry (in milliseconds)
+const DEFAULT_INITIAL_RETRY_INTERVAL_MS: u64 = 1000;
+/// Default retry backoff multiplier
+const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
+/// Default maximum interval for retry (in milliseconds)
+const DEFAULT_MAX_RETRY_INTERVAL_MS: u64 = 30_000;
+
+/// Retry policy applied to every provider call made through `RetryProvider`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Maximum number of retries against one provider before falling back to the next
+    pub max_retries: usize,
+    /// Initial interval between retries in milliseconds
+    pub initial_interval_ms: u64,
+    /// Multiplier for backoff (exponential)
+    pub backoff_multiplier: f64,
+    /// Maximum interval between retries in milliseconds
+    pub max_interval_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_interval_ms: DEFAULT_INITIAL_RETRY_INTERVAL_MS,
+            backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
+            max_interval_ms: DEFAULT_MAX_RETRY_INTERVAL_MS,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Load the retry policy from config, falling back to defaults for unset keys
+    pub fn from_config(config: &Config) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_retries: config
+                .get_param("GOOSE_MAX_RETRIES")
+                .unwrap_or(defaults.max_retries),
+            initial_interval_ms: config
+                .get_param("GOOSE_INITIAL_RETRY_INTERVAL_MS")
+                .unwrap_or(defaults.initial_interval_ms),
+            backoff_multiplier: config
+                .get_param("GOOSE_BACKOFF_MULTIPLIER")
+                .unwrap_or(defaults.backoff_multiplier),
+            max_interval_ms: config
+                .get_param("GOOSE_MAX_RETRY_INTERVAL_MS")
+                .unwrap_or(defaults.max_interval_ms),
+        }
+    }
+
+    /// Calculate the delay for a specific retry attempt (with jitter)
+    pub fn delay_for_attempt(&self, attempt: usize) -> Duration {
+        if attempt == 0 {
+            return Duration::from_millis(0);
+        }
+
+        let exponent = (attempt - 1) as i32;
+        let base_delay_ms =
+            (self.initial_interval_ms as f64 * self.backoff_multiplier.powi(exponent)) as u64;
+        let capped_delay_ms = std::cmp::min(base_delay_ms, self.max_interval_ms);
+
+        // Add jitter (+/-20% randomness) so concurrent sessions don't retry in lockstep
+        let jitter_factor = 0.8 + (rand::random::<f64>() * 0.4);
+        Duration::from_millis((capped_delay_ms as f64 * jitter_factor) as u64)
+    }
+
+    /// The delay before the given retry, preferring the one the provider asked for as long as
+    /// it is within `max_interval_ms`
+    fn delay_for(&self, attempt: usize, error: &ProviderError) -> Duration {
+        match error {
+            ProviderError::RateLimitExceeded {
+                retry_delay: Some(delay),
+                ..
+            } => (*delay).min(Duration::from_millis(self.max_interval_ms)),
+            _ => self.delay_for_attempt(attempt),
+        }
+    }
+}
+
+/// A provider/model pair to switch to when the ones before it keep failing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Wraps an ordered list of providers, retrying transient failures with backoff
+///
+/// Rate limits and server errors are retried against the active provider up to
+/// `max_retries` times. Once those are used up the next provider in the list becomes
+/// active and stays active for the rest of the session. Any other error is returned
+/// immediately.
+pub struct RetryProvider {
+    providers: Vec<Arc<dyn Provider>>,
+    retry_config: RetryConfig,
+    active: AtomicUsize,
+}
+
+impl RetryProvider {
+    pub fn new(
+        primary: Arc<dyn Provider>,
+        fallbacks: Vec<Arc<dyn Provider>>,
+        retry_config: RetryConfig,
+    ) -> Self {
+        let mut providers = vec![primary];
+        providers.extend(fallbacks);
+        Self {
+            providers,
+            retry_config,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    fn active_provider(&self) -> &Arc<dyn Provider> {
+        &self.providers[self.active.load(Ordering::SeqCst)]
+    }
+
+    async fn with_retries<'a, T>(
+        &'a self,
+        call: impl Fn(&'a Arc<dyn Provider>) -> BoxFuture<'a, Result<T, ProviderError>>,
+    ) -> Result<T, ProviderError> {
+        let mut index = self.active.load(Ordering::SeqCst);
+        loop {
+            let provider = &self.providers[index];
+            let mut attempts = 0;
+            let error = loop {
+                match call(provider).await {
+                    Ok(result) => return Ok(result),
+                    Err(error) if !error.is_retryable() => return Err(error),
+                    Err(error) if attempts >= self.retry_config.max_retries => break error,
+                    Err(error) => {
+                        attempts += 1;
+                        let delay = self.retry_config.delay_for(attempts, &error);
+                        tracing::warn!(
+                            "Provider call failed (attempt {}/{}): {}. Retrying in {:?}",
+                            attempts,
+                            self.retry_config.max_retries,
+                            error,
+                            delay
+                        );
+                        sleep(delay).await;
+                    }
+                }
+            };
+
+            if index + 1 >= self.providers.len() {
+                return Err(error);
+            }
+            index += 1;
+            self.active.store(index, Ordering::SeqCst);
+            tracing::warn!(
+                "Falling back to model {} after repeated failures: {}",
+                self.providers[index].get_model_config().model_name,
+                error
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RetryProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.with_retries(|provider| provider.complete(system, messages, tools))
+            .await
+    }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        self.with_retries(|provider| provider.complete_structured(system, messages, schema))
+            .await
+    }
+
+    /// Only failures before the first event are retried: once deltas have been
+    /// handed to the caller the request can't be replayed transparently.
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderStream, ProviderError> {
+        self.with_retries(|provider| {
+            Box::pin(async move {
+                let mut stream = provider.stream(system, messages, tools).await?;
+                match stream.next().await {
+                    Some(Err(error)) => Err(error),
+                    Some(Ok(first)) => Ok(Box::pin(
+                        futures::stream::once(async move { Ok(first) }).chain(stream),
+                    ) as ProviderStream),
+                    None => Ok(stream),
+                }
+            })
+        })
+        .await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.active_provider().supports_streaming()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.active_provider().get_model_config()
+    }
+
+    async fn fetch_models(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        self.providers[0].fetch_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use std::sync::Mutex;
+
+    /// Replays a scripted list of results, recording how many calls were made
+    struct ScriptedProvider {
+        model: ModelConfig,
+        results: Mutex<Vec<Result<(), ProviderError>>>,
+        calls: AtomicUsize,
+    }
+
+    impl ScriptedProvider {
+        fn new(model: &str, results: Vec<Result<(), ProviderError>>) -> Arc<Self> {
+            Arc::new(Self {
+                model: ModelConfig::new(model.to_string()),
+                results: Mutex::new(results),
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let next = self.results.lock().unwrap().remove(0);
+            next.map(|_| {
+                (
+                    Message::assistant().with_text(&self.model.model_name),
+                    ProviderUsage::new(self.model.model_name.clone(), Usage::default()),
+                )
+            })
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model.clone()
+        }
+    }
+
+    fn fast_retries(max_retries: usize) -> RetryConfig {
+        RetryConfig {
+            max_retries,
+            initial_interval_ms: 1,
+            backoff_multiplier: 1.0,
+            max_interval_ms: 1,
+        }
+    }
+
+    fn rate_limited() -> ProviderError {
+        ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_delay: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let primary = ScriptedProvider::new(
+            "primary",
+            vec![
+                Err(rate_limited()),
+                Err(ProviderError::ServerError("overloaded".to_string())),
+                Ok(()),
+            ],
+        );
+        let provider = RetryProvider::new(primary.clone(), vec![], fast_retries(2));
+
+        let (_, usage) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(usage.model, "primary");
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_other_errors() {
+        let primary = ScriptedProvider::new(
+            "primary",
+            vec![Err(ProviderError::ContextLengthExceeded(
+                "too long".to_string(),
+            ))],
+        );
+        let fallback = ScriptedProvider::new("fallback", vec![Ok(())]);
+        let provider = RetryProvider::new(primary.clone(), vec![fallback], fast_retries(2));
+
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(
+            result,
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_and_stays_on_fallback() {
+        let primary =
+            ScriptedProvider::new("primary", vec![Err(rate_limited()), Err(rate_limited())]);
+        let fallback = ScriptedProvider::new("fallback", vec![Ok(()), Ok(())]);
+        let provider = RetryProvider::new(primary.clone(), vec![fallback.clone()], fast_retries(1));
+
+        let (_, usage) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(usage.model, "fallback");
+        assert_eq!(provider.get_model_config().model_name, "fallback");
+
+        provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(fallback.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_error_when_all_fail() {
+        let primary = ScriptedProvider::new("primary", vec![Err(rate_limited())]);
+        let fallback = ScriptedProvider::new(
+            "fallback",
+            vec![Err(ProviderError::ServerError("down".to_string()))],
+        );
+        let provider = RetryProvider::new(primary, vec![fallback], fast_retries(0));
+
+        let result = provider.complete("", &[], &[]).await;
+        assert!(matches!(result, Err(ProviderError::ServerError(_))));
+    }
+
+    #[test]
+    fn test_prefers_retry_after_delay() {
+        let config = RetryConfig {
+            max_interval_ms: 10_000,
+            ..fast_retries(3)
+        };
+        let asked_for = |secs| ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_delay: Some(Duration::from_secs(secs)),
+        };
+        assert_eq!(config.delay_for(1, &asked_for(7)), Duration::from_secs(7));
+        // A server asking for a longer wait does not get to stall the agent
+        assert_eq!(
+            config.delay_for(1, &asked_for(3600)),
+            Duration::from_secs(10)
+        );
+        assert!(config.delay_for(1, &rate_limited()) <= Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_delay_for_attempt_is_capped() {
+        let config = RetryConfig {
+            max_retries: 10,
+            initial_interval_ms: 1000,
+            backoff_multiplier: 2.0,
+            max_interval_ms: 4000,
+        };
+        assert_eq!(config.delay_for_attempt(0), Duration::ZERO);
+        let delay = config.delay_for_attempt(1);
+        assert!(delay >= Duration::from_millis(800) && delay <= Duration::from_millis(1200));
+        let delay = config.delay_for_attempt(8);
+        assert!(delay >= Duration::from_millis(3200) && delay <= Duration::from_millis(4800));
+    }
+}
@@ -0,0 +1,341 @@
+//! Responses constrained to a JSON schema.
+//!
+//! Providers that support it natively (OpenAI `response_format`, Gemini `responseSchema`,
+//! Ollama `format`) constrain generation to the schema. Anthropic and Bedrock are forced to
+//! call a tool whose input is the schema. Every other provider is asked for the same tool
+//! call, or JSON text, through the system prompt. Whatever the route, the response is parsed
+//! and validated here before it is returned.
+
+use mcp_core::tool::{Tool, ToolAnnotations};
+use regex::Regex;
+use serde_json::Value;
+
+use super::errors::ProviderError;
+use crate::message::{Message, MessageContent};
+
+/// Name of the tool the model calls with its structured response
+pub const RESPONSE_TOOL_NAME: &str = "structured_response";
+
+/// A tool whose input is the response, for providers that can be made to call it
+pub fn response_tool(schema: &Value) -> Tool {
+    Tool::new(
+        RESPONSE_TOOL_NAME.to_string(),
+        "Respond to the user with a result matching the input schema. Call this exactly once."
+            .to_string(),
+        object_schema(schema),
+        Some(ToolAnnotations {
+            title: Some("Structured response".to_string()),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: true,
+            open_world_hint: false,
+        }),
+    )
+}
+
+/// Tool inputs and some providers' response formats must be objects, so any other schema is
+/// wrapped in a `response` property, which `extract_structured` unwraps again
+pub fn object_schema(schema: &Value) -> Value {
+    if schema.get("type").and_then(Value::as_str) == Some("object") {
+        schema.clone()
+    } else {
+        serde_json::json!({
+            "type": "object",
+            "properties": { "response": schema },
+            "required": ["response"]
+        })
+    }
+}
+
+/// The system prompt extended with instructions to respond with the schema
+pub fn structured_system_prompt(system: &str, schema: &Value) -> String {
+    format!(
+        "{}\n\nRespond by calling the `{}` tool. If you can't call tools, reply with only a JSON \
+         value matching this JSON schema, without any other text:\n{}",
+        system,
+        RESPONSE_TOOL_NAME,
+        serde_json::to_string_pretty(schema).unwrap_or_default()
+    )
+}
+
+/// Parse JSON out of a model's text, which may wrap it in a code fence or prose
+pub fn parse_json_text(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+
+    let fence = Regex::new(r"(?s)```[^\n]*\n(.*?)\n?```").expect("Invalid regex");
+    if let Some(value) = fence
+        .captures(text)
+        .and_then(|caps| serde_json::from_str(caps[1].trim()).ok())
+    {
+        return Some(value);
+    }
+
+    // The outermost object or array in the text
+    [('{', '}'), ('[', ']')]
+        .iter()
+        .filter_map(|(open, close)| {
+            let start = text.find(*open)?;
+            let end = text.rfind(*close)?;
+            (start < end)
+                .then(|| serde_json::from_str(&text[start..=end]).ok())
+                .flatten()
+        })
+        .next()
+}
+
+/// The structured response in a message, from the response tool call or the text
+pub fn extract_structured(message: &Message, schema: &Value) -> Result<Value, ProviderError> {
+    let from_tool = message.content.iter().find_map(|content| match content {
+        MessageContent::ToolRequest(request) => request
+            .tool_call
+            .as_ref()
+            .ok()
+            .filter(|call| call.name == RESPONSE_TOOL_NAME)
+            .map(|call| call.arguments.clone()),
+        _ => None,
+    });
+
+    let mut value = match from_tool {
+        Some(arguments) => arguments,
+        None => parse_json_text(&message.as_concat_text()).ok_or_else(|| {
+            ProviderError::ExecutionError(format!(
+                "Expected a JSON response, got: {}",
+                message.as_concat_text()
+            ))
+        })?,
+    };
+
+    // Unwrap what object_schema wrapped
+    if object_schema(schema) != *schema {
+        if let Some(response) = value
+            .as_object()
+            .filter(|object| object.len() == 1)
+            .and_then(|object| object.get("response"))
+        {
+            value = response.clone();
+        }
+    }
+
+    validate(schema, &value).map_err(|e| {
+        ProviderError::ExecutionError(format!("Response does not match the schema: {}", e))
+    })?;
+    Ok(value)
+}
+
+/// Check a value against a JSON schema
+///
+/// Covers the parts of JSON schema used to describe model responses: `type`, `enum`,
+/// `const`, `properties`, `required`, `additionalProperties`, `items`, `minItems`,
+/// `maxItems`, `anyOf` and `oneOf`. Other keywords are ignored.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    validate_at(schema, value, "$")
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        return Ok(());
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|name| type_matches(name, value)) {
+            return Err(format!("{} should be of type {}", path, types.join(" or ")));
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return Err(format!(
+                "{} should be one of {}",
+                path,
+                Value::from(allowed.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{} should be {}", path, expected));
+        }
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(options)) = schema.get(keyword) {
+            if !options
+                .iter()
+                .any(|option| validate_at(option, value, path).is_ok())
+            {
+                return Err(format!("{} matches none of the allowed schemas", path));
+            }
+        }
+    }
+
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    return Err(format!("{} is missing required property '{}'", path, key));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, item) in object {
+            let item_path = format!("{}.{}", path, key);
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => validate_at(property, item, &item_path)?,
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        return Err(format!("{} is not an allowed property", item_path))
+                    }
+                    Some(additional @ Value::Object(_)) => {
+                        validate_at(additional, item, &item_path)?
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if let Value::Array(items) = value {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min {
+                return Err(format!("{} should have at least {} items", path, min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if items.len() as u64 > max {
+                return Err(format!("{} should have at most {} items", path, max));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                validate_at(item_schema, item, &format!("{}[{}]", path, i))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::ToolCall;
+    use serde_json::json;
+
+    fn recipe_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "instructions": {"type": "string"},
+                "activities": {"type": "array", "items": {"type": "string"}, "maxItems": 3}
+            },
+            "required": ["instructions", "activities"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = recipe_schema();
+        assert!(validate(&schema, &json!({"instructions": "a", "activities": ["b"]})).is_ok());
+
+        let err = validate(&schema, &json!({"instructions": "a"})).unwrap_err();
+        assert!(
+            err.contains("missing required property 'activities'"),
+            "{err}"
+        );
+
+        let err = validate(&schema, &json!({"instructions": "a", "activities": [1]})).unwrap_err();
+        assert_eq!(err, "$.activities[0] should be of type string");
+
+        let err = validate(
+            &schema,
+            &json!({"instructions": "a", "activities": [], "extra": true}),
+        )
+        .unwrap_err();
+        assert!(err.contains("$.extra is not an allowed property"), "{err}");
+
+        let err = validate(
+            &schema,
+            &json!({"instructions": "a", "activities": ["1", "2", "3", "4"]}),
+        )
+        .unwrap_err();
+        assert!(err.contains("at most 3 items"), "{err}");
+
+        let status = json!({"enum": ["pass", "fail"]});
+        assert!(validate(&status, &json!("pass")).is_ok());
+        assert!(validate(&status, &json!("maybe")).is_err());
+
+        let nullable = json!({"type": ["integer", "null"]});
+        assert!(validate(&nullable, &json!(null)).is_ok());
+        assert!(validate(&nullable, &json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_text() {
+        assert_eq!(parse_json_text(" {\"a\": 1} "), Some(json!({"a": 1})));
+        assert_eq!(
+            parse_json_text("Here you go:\n```json\n{\"a\": 1}\n```"),
+            Some(json!({"a": 1}))
+        );
+        assert_eq!(
+            parse_json_text("The result is {\"a\": [1, 2]}. Let me know!"),
+            Some(json!({"a": [1, 2]}))
+        );
+        assert_eq!(parse_json_text("[1, 2]"), Some(json!([1, 2])));
+        assert_eq!(parse_json_text("no json here"), None);
+    }
+
+    #[test]
+    fn test_extract_from_tool_call_or_text() {
+        let schema = recipe_schema();
+        let expected = json!({"instructions": "a", "activities": ["b"]});
+
+        let message = Message::assistant()
+            .with_tool_request("1", Ok(ToolCall::new(RESPONSE_TOOL_NAME, expected.clone())));
+        assert_eq!(extract_structured(&message, &schema).unwrap(), expected);
+
+        let message = Message::assistant().with_text(format!("```json\n{}\n```", expected));
+        assert_eq!(extract_structured(&message, &schema).unwrap(), expected);
+
+        let message = Message::assistant().with_text("{\"instructions\": \"a\"}");
+        let err = extract_structured(&message, &schema).unwrap_err();
+        assert!(err.to_string().contains("does not match the schema"));
+    }
+
+    #[test]
+    fn test_non_object_schemas_are_wrapped_for_tools() {
+        let schema = json!({"type": "array", "items": {"type": "string"}});
+        let tool = response_tool(&schema);
+        assert_eq!(tool.input_schema["properties"]["response"], schema);
+
+        let message = Message::assistant().with_tool_request(
+            "1",
+            Ok(ToolCall::new(
+                RESPONSE_TOOL_NAME,
+                json!({"response": ["a"]}),
+            )),
+        );
+        assert_eq!(extract_structured(&message, &schema).unwrap(), json!(["a"]));
+    }
+}
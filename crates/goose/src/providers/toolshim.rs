@@ -21,37 +21,54 @@
 //!
 //! ### Implementations
 //!
-//! The module provides an implementation for Ollama:
+//! The interpreter is picked with `GOOSE_TOOLSHIM_INTERPRETER`:
 //!
-//! - `OllamaInterpreter`: Uses Ollama's structured output API to interpret tool calls
+//! - `ollama` (default): `OllamaInterpreter` uses Ollama's structured output API to interpret tool calls
+//! - `parser`: `TextFormatInterpreter` needs no model, it parses the textual tool call formats
+//!   open models are trained on: Hermes `<tool_call>` tags, Llama 3 `<|python_tag|>` and JSON
+//!   code blocks
+//! - any other goose provider: `ProviderInterpreter` asks that provider for the tool calls with
+//!   its structured output support, using `GOOSE_TOOLSHIM_MODEL` or the provider's default model
 //!
 //! ### Helper Functions
 //!
 //! - `augment_message_with_tool_calls`: A utility function that takes any message, extracts text content, sends it to an interpreter, and adds any detected tool calls back to the message.
 //!
 
+use super::base::Provider;
 use super::errors::ProviderError;
 use super::ollama::OLLAMA_DEFAULT_PORT;
 use super::ollama::OLLAMA_HOST;
+use super::structured;
+use crate::config::Config;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::create_request;
 use anyhow::Result;
 use mcp_core::tool::{Tool, ToolCall};
+use regex::Regex;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
 /// Default model to use for tool interpretation
 pub const DEFAULT_INTERPRETER_MODEL_OLLAMA: &str = "mistral-nemo";
 
+/// Interpreter that parses tool calls out of the text without a model
+pub const PARSER_INTERPRETER: &str = "parser";
+/// Interpreter that asks a local Ollama model for the tool calls
+pub const OLLAMA_INTERPRETER: &str = "ollama";
+
 /// Environment variables that affect behavior:
 /// - GOOSE_TOOLSHIM: When set to "true" or "1", enables using the tool shim in the standard OllamaProvider (default: false)
+/// - GOOSE_TOOLSHIM_INTERPRETER: "ollama" (default), "parser" or the name of a goose provider
 /// - GOOSE_TOOLSHIM_OLLAMA_MODEL: Ollama model to use as the tool interpreter (default: DEFAULT_INTERPRETER_MODEL)
+/// - GOOSE_TOOLSHIM_MODEL: Model of the goose provider used as the tool interpreter (default: the provider's default)
 /// A trait for models that can interpret text into structured tool call JSON format
 #[async_trait::async_trait]
-pub trait ToolInterpreter {
+pub trait ToolInterpreter: Send + Sync {
     /// Interpret potential tool calls from text and convert them to proper tool call JSON format
     async fn interpret_to_tool_calls(
         &self,
@@ -112,29 +129,7 @@ impl OllamaInterpreter {
     }
 
     fn tool_structured_ouput_format_schema() -> Value {
-        json!({
-            "type": "object",
-            "properties": {
-                "tool_calls": {
-                    "type": "array",
-                    "items": {
-                        "type": "object",
-                        "properties": {
-                            "name": {
-                                "type": "string",
-                                "description": "The name of the tool to call"
-                            },
-                            "arguments": {
-                                "type": "object",
-                                "description": "The arguments to pass to the tool"
-                            }
-                        },
-                        "required": ["name", "arguments"]
-                    }
-                }
-            },
-            "required": ["tool_calls"]
-        })
+        tool_calls_schema()
     }
 
     async fn post_structured(
@@ -284,6 +279,206 @@ Otherwise, if no JSON tool requests are provided, use the no-op tool:
     }
 }
 
+/// Schema of the tool calls an interpreter model responds with
+fn tool_calls_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "tool_calls": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "description": "The name of the tool to call"
+                        },
+                        "arguments": {
+                            "type": "object",
+                            "description": "The arguments to pass to the tool"
+                        }
+                    },
+                    "required": ["name", "arguments"]
+                }
+            }
+        },
+        "required": ["tool_calls"]
+    })
+}
+
+/// Interpreter backed by any goose provider, through its structured output support
+pub struct ProviderInterpreter {
+    provider: Arc<dyn Provider>,
+}
+
+impl ProviderInterpreter {
+    pub fn new(provider: Arc<dyn Provider>) -> Self {
+        Self { provider }
+    }
+
+    /// Create the named provider with GOOSE_TOOLSHIM_MODEL, or the provider's default model
+    pub fn from_config(provider_name: &str) -> Result<Self, ProviderError> {
+        let model = Config::global()
+            .get_param::<String>("GOOSE_TOOLSHIM_MODEL")
+            .ok()
+            .or_else(|| {
+                super::factory::providers()
+                    .into_iter()
+                    .find(|metadata| metadata.name == provider_name)
+                    .map(|metadata| metadata.default_model)
+            })
+            .ok_or_else(|| {
+                ProviderError::ExecutionError(format!(
+                    "Unknown toolshim interpreter '{}'",
+                    provider_name
+                ))
+            })?;
+
+        let provider = super::factory::create_with_retries(provider_name, ModelConfig::new(model))
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
+        Ok(Self::new(provider))
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolInterpreter for ProviderInterpreter {
+    async fn interpret_to_tool_calls(
+        &self,
+        content: &str,
+        tools: &[Tool],
+    ) -> Result<Vec<ToolCall>, ProviderError> {
+        if tools.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let system_prompt = format!(
+            "You extract tool calls from an assistant's message. List each tool call the message \
+             asks for, with the tool's name and arguments. If it asks for none, list no tool \
+             calls. These are the available tools:\n\n{}",
+            format_tool_info(tools)
+        );
+        let messages = vec![Message::user().with_text(content)];
+
+        let (response, _usage) = self
+            .provider
+            .complete_structured(&system_prompt, &messages, &tool_calls_schema())
+            .await?;
+
+        Ok(response["tool_calls"]
+            .as_array()
+            .map(|calls| calls.iter().filter_map(parse_tool_call).collect())
+            .unwrap_or_default())
+    }
+}
+
+/// Interpreter that parses the textual tool call formats of open models, without a model
+///
+/// Recognizes Hermes `<tool_call>` tags, Llama 3 `<|python_tag|>` calls and JSON objects with
+/// a `name` and `arguments` (or `parameters`), in code blocks or on their own. Only calls to
+/// known tools are kept, so JSON that merely mentions a name isn't mistaken for a call.
+#[derive(Debug, Default)]
+pub struct TextFormatInterpreter;
+
+impl TextFormatInterpreter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// The tool calls in the text, in the first format that has any
+    pub fn parse(content: &str) -> Vec<ToolCall> {
+        let hermes = Regex::new(r"(?s)<tool_call>\s*(.*?)\s*(?:</tool_call>|$)").unwrap();
+        let python_tag =
+            Regex::new(r"(?s)<\|python_tag\|>\s*(.*?)\s*(?:<\|eom_id\|>|<\|eot_id\|>|$)").unwrap();
+        let code_block = Regex::new(r"(?s)```[a-zA-Z]*\s*\n(.*?)\n?```").unwrap();
+
+        let from_captures = |re: &Regex| -> Vec<ToolCall> {
+            re.captures_iter(content)
+                .flat_map(|caps| parse_tool_calls_text(&caps[1]))
+                .collect()
+        };
+
+        for re in [&hermes, &python_tag, &code_block] {
+            let calls = from_captures(re);
+            if !calls.is_empty() {
+                return calls;
+            }
+        }
+
+        // JSON on its own, as modify_system_prompt_for_tool_json asks for
+        structured::parse_json_text(content)
+            .map(|value| tool_calls_from_value(&value))
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolInterpreter for TextFormatInterpreter {
+    async fn interpret_to_tool_calls(
+        &self,
+        content: &str,
+        tools: &[Tool],
+    ) -> Result<Vec<ToolCall>, ProviderError> {
+        Ok(Self::parse(content)
+            .into_iter()
+            .filter(|call| tools.iter().any(|tool| tool.name == call.name))
+            .collect())
+    }
+}
+
+/// Tool calls in a snippet of text: one JSON value, or several separated by newlines or `;`
+fn parse_tool_calls_text(text: &str) -> Vec<ToolCall> {
+    if let Ok(value) = serde_json::from_str::<Value>(text.trim()) {
+        return tool_calls_from_value(&value);
+    }
+    text.split([';', '\n'])
+        .filter_map(|part| serde_json::from_str::<Value>(part.trim()).ok())
+        .flat_map(|value| tool_calls_from_value(&value))
+        .collect()
+}
+
+fn tool_calls_from_value(value: &Value) -> Vec<ToolCall> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(parse_tool_call).collect(),
+        Value::Object(object) => match object.get("tool_calls") {
+            Some(calls) => tool_calls_from_value(calls),
+            None => parse_tool_call(value).into_iter().collect(),
+        },
+        _ => vec![],
+    }
+}
+
+/// A single `{"name": ..., "arguments": ...}` call, also accepting `parameters`, arguments
+/// encoded as a JSON string, and the OpenAI `{"function": {...}}` wrapping
+fn parse_tool_call(value: &Value) -> Option<ToolCall> {
+    let value = value.get("function").unwrap_or(value);
+    let name = value.get("name")?.as_str()?;
+    let arguments = value
+        .get("arguments")
+        .or_else(|| value.get("parameters"))
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let arguments = match arguments {
+        Value::String(text) => serde_json::from_str(&text).ok()?,
+        arguments => arguments,
+    };
+    arguments
+        .is_object()
+        .then(|| ToolCall::new(name, arguments))
+}
+
+/// Create the tool interpreter selected by GOOSE_TOOLSHIM_INTERPRETER
+pub fn create_interpreter() -> Result<Box<dyn ToolInterpreter>, ProviderError> {
+    let interpreter = Config::global()
+        .get_param::<String>("GOOSE_TOOLSHIM_INTERPRETER")
+        .unwrap_or_else(|_| OLLAMA_INTERPRETER.to_string());
+
+    Ok(match interpreter.as_str() {
+        OLLAMA_INTERPRETER => Box::new(OllamaInterpreter::new()?),
+        PARSER_INTERPRETER => Box::new(TextFormatInterpreter::new()),
+        provider_name => Box::new(ProviderInterpreter::from_config(provider_name)?),
+    })
+}
+
 /// Creates a string containing formatted tool information
 pub fn format_tool_info(tools: &[Tool]) -> String {
     let mut tool_info = String::new();
@@ -310,7 +505,7 @@ pub fn modify_system_prompt_for_tool_json(system_prompt: &str, tools: &[Tool]) -
 }
 
 /// Helper function to augment a message with tool calls if any are detected
-pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
+pub async fn augment_message_with_tool_calls<T: ToolInterpreter + ?Sized>(
     interpreter: &T,
     message: Message,
     tools: &[Tool],
@@ -364,3 +559,73 @@ pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
 
     Ok(final_message)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools() -> Vec<Tool> {
+        ["developer__shell", "developer__text_editor"]
+            .iter()
+            .map(|name| Tool::new(*name, "", json!({"type": "object"}), None))
+            .collect()
+    }
+
+    async fn interpret(content: &str) -> Vec<ToolCall> {
+        TextFormatInterpreter::new()
+            .interpret_to_tool_calls(content, &tools())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hermes_tool_calls() {
+        let content = "I'll check both.\n<tool_call>\n{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}\n</tool_call>\n<tool_call>\n{\"name\": \"developer__shell\", \"arguments\": \"{\\\"command\\\": \\\"pwd\\\"}\"}\n</tool_call>";
+        let calls = interpret(content).await;
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].arguments, json!({"command": "ls"}));
+        assert_eq!(calls[1].arguments, json!({"command": "pwd"}));
+    }
+
+    #[tokio::test]
+    async fn test_llama_python_tag() {
+        let content = "<|python_tag|>{\"name\": \"developer__shell\", \"parameters\": {\"command\": \"ls\"}}<|eom_id|>";
+        let calls = interpret(content).await;
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "developer__shell");
+        assert_eq!(calls[0].arguments, json!({"command": "ls"}));
+    }
+
+    #[tokio::test]
+    async fn test_json_code_block_and_bare_json() {
+        let content = "Let me look.\n```json\n{\"name\": \"developer__text_editor\", \"arguments\": {\"command\": \"view\", \"path\": \"/tmp\"}}\n```";
+        let calls = interpret(content).await;
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "developer__text_editor");
+
+        let content = "{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}";
+        assert_eq!(interpret(content).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ignores_unknown_tools_and_plain_text() {
+        let content = "```json\n{\"name\": \"package\", \"arguments\": {}}\n```";
+        assert!(interpret(content).await.is_empty());
+        assert!(interpret("All done, nothing else to run.").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_augment_message_with_parser() {
+        let message = Message::assistant().with_text(
+            "<tool_call>{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}</tool_call>",
+        );
+        let message =
+            augment_message_with_tool_calls(&TextFormatInterpreter::new(), message, &tools())
+                .await
+                .unwrap();
+        assert!(message
+            .content
+            .iter()
+            .any(|content| matches!(content, MessageContent::ToolRequest(_))));
+    }
+}
@@ -1,15 +1,19 @@
 use super::base::Usage;
 use super::errors::GoogleErrorCode;
 use crate::model::ModelConfig;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use base64::Engine;
+use futures::{Stream, StreamExt};
 use regex::Regex;
+use reqwest::header::HeaderMap;
 use reqwest::{Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Map, Value};
 use std::io::Read;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 
+use crate::message::{Message, MessageContent};
 use crate::providers::errors::{OpenAIError, ProviderError};
 use mcp_core::content::ImageContent;
 
@@ -44,11 +48,43 @@ pub fn convert_image(image: &ImageContent, image_format: &ImageFormat) -> Value
     }
 }
 
+/// Read how long the server asked us to wait before retrying
+///
+/// Supports the non-standard `retry-after-ms` header sent by OpenAI and Anthropic as well as
+/// `Retry-After` given either in seconds or as an HTTP date.
+pub fn retry_delay_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+    };
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        if ms.is_finite() && ms >= 0.0 {
+            return Some(Duration::from_secs_f64(ms / 1000.0));
+        }
+    }
+
+    let value = header("retry-after")?;
+    if let Ok(secs) = value.parse::<f64>() {
+        return (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let until = SystemTime::from(date);
+    Some(
+        until
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
 /// Handle response from OpenAI compatible endpoints
 /// Error codes: https://platform.openai.com/docs/guides/error-codes
 /// Context window exceeded: https://community.openai.com/t/help-needed-tackling-context-length-limits-in-openai-models/617543
 pub async fn handle_response_openai_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_delay = retry_delay_from_headers(response.headers());
     // Try to parse the response body as JSON (if applicable)
     let payload = match response.json::<Value>().await {
         Ok(json) => json,
@@ -75,7 +111,10 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Unknown error (status {})", status)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::RateLimitExceeded {
+                details: format!("{:?}", payload),
+                retry_delay,
+            })
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
             Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -89,6 +128,113 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
     }
 }
 
+/// Incremental parser for `text/event-stream` bodies
+///
+/// Bytes can be pushed in arbitrary chunks; the `data` of each complete event is
+/// returned once its terminating blank line has been seen.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed more bytes into the parser and return the data of any completed events
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+            // Comments, `event:`, `id:` and `retry:` fields are not used by any provider
+        }
+        events
+    }
+
+    /// Flush an event that was not terminated by a blank line before the body ended
+    pub fn finish(&mut self) -> Option<String> {
+        if !self.buffer.is_empty() {
+            // Terminate the last line and the event it belongs to
+            if let Some(event) = self.push(b"\n\n").pop() {
+                return Some(event);
+            }
+        }
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.data).join("\n"))
+    }
+}
+
+/// Turn a streaming response into the JSON payloads of its server-sent events
+///
+/// Empty events and the OpenAI style `[DONE]` terminator are skipped.
+pub fn sse_json_stream(
+    response: Response,
+) -> impl Stream<Item = Result<Value, ProviderError>> + Send + 'static {
+    async_stream::try_stream! {
+        let mut body = response.bytes_stream();
+        let mut parser = SseParser::new();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            for data in parser.push(&chunk) {
+                if let Some(value) = parse_sse_data(&data)? {
+                    yield value;
+                }
+            }
+        }
+        if let Some(data) = parser.finish() {
+            if let Some(value) = parse_sse_data(&data)? {
+                yield value;
+            }
+        }
+    }
+}
+
+fn parse_sse_data(data: &str) -> Result<Option<Value>, ProviderError> {
+    let data = data.trim();
+    if data.is_empty() || data == "[DONE]" {
+        return Ok(None);
+    }
+    serde_json::from_str(data).map(Some).map_err(|e| {
+        ProviderError::RequestFailed(format!("Invalid event in response stream: {}", e))
+    })
+}
+
+/// Check the status of a streaming response from an OpenAI compatible endpoint
+///
+/// Successful responses are returned untouched so their body can be streamed, errors are
+/// mapped the same way as in `handle_response_openai_compat`.
+pub async fn check_streaming_response_openai_compat(
+    response: Response,
+) -> Result<Response, ProviderError> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    match handle_response_openai_compat(response).await {
+        Err(e) => Err(e),
+        Ok(payload) => Err(ProviderError::RequestFailed(format!(
+            "Request failed with status: {}. Payload: {:?}",
+            status, payload
+        ))),
+    }
+}
+
 /// Check if the model is a Google model based on the "model" field in the payload.
 ///
 /// ### Arguments
@@ -140,6 +286,7 @@ fn get_google_final_status(status: StatusCode, payload: Option<&Value>) -> Statu
 /// - `Err(ProviderError)`: Describes the failure reason.
 pub async fn handle_response_google_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_delay = retry_delay_from_headers(response.headers());
     let payload: Option<Value> = response.json().await.ok();
     let final_status = get_google_final_status(status, payload.as_ref());
 
@@ -166,7 +313,10 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", final_status, error_msg)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::RateLimitExceeded {
+                details: format!("{:?}", payload),
+                retry_delay,
+            })
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
             Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -288,6 +438,105 @@ pub fn load_image_file(path: &str) -> Result<ImageContent, ProviderError> {
     })
 }
 
+/// Media a message can carry besides text, which not every model accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modality {
+    Image,
+    Document,
+    Audio,
+}
+
+impl Modality {
+    pub fn of(content: &MessageContent) -> Option<Self> {
+        match content {
+            MessageContent::Image(_) => Some(Modality::Image),
+            MessageContent::Document(_) => Some(Modality::Document),
+            MessageContent::Audio(_) => Some(Modality::Audio),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Modality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Modality::Image => "image",
+            Modality::Document => "document",
+            Modality::Audio => "audio",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Fail with a clear error if the messages carry media the model does not accept
+pub fn check_modalities(
+    model_name: &str,
+    messages: &[Message],
+    supported: &[Modality],
+) -> Result<()> {
+    let unsupported = messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .filter_map(Modality::of)
+        .find(|modality| !supported.contains(modality));
+    if let Some(modality) = unsupported {
+        bail!(
+            "Model {} does not accept {} input, convert it to text or switch to a model that does",
+            model_name,
+            modality
+        );
+    }
+    Ok(())
+}
+
+/// Media type of a PDF or audio file, checked against its magic bytes
+fn attachment_mime_type(path: &Path) -> Option<&'static str> {
+    let mut buffer = [0u8; 12];
+    let read = std::fs::File::open(path).ok()?.read(&mut buffer).ok()?;
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match (extension.as_str(), &buffer[..read]) {
+        ("pdf", [b'%', b'P', b'D', b'F', ..]) => Some("application/pdf"),
+        ("wav", [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..]) => {
+            Some("audio/wav")
+        }
+        ("mp3", [b'I', b'D', b'3', ..] | [0xFF, 0xFB | 0xF3 | 0xF2, ..]) => Some("audio/mpeg"),
+        ("ogg", [b'O', b'g', b'g', b'S', ..]) => Some("audio/ogg"),
+        ("flac", [b'f', b'L', b'a', b'C', ..]) => Some("audio/flac"),
+        _ => None,
+    }
+}
+
+/// Detect absolute paths to PDF and audio files in a string
+pub fn detect_attachment_paths(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .filter(|word| {
+            let path = Path::new(word);
+            path.is_absolute() && path.is_file() && attachment_mime_type(path).is_some()
+        })
+        .collect()
+}
+
+/// Convert a local PDF or audio file to base64 encoded document or audio content
+pub fn load_attachment_file(path: &str) -> Result<MessageContent, ProviderError> {
+    let path = Path::new(path);
+    let mime_type = attachment_mime_type(path).ok_or_else(|| {
+        ProviderError::RequestFailed("File is not a PDF or a supported audio file".to_string())
+    })?;
+
+    let bytes = std::fs::read(path)
+        .map_err(|e| ProviderError::RequestFailed(format!("Failed to read file: {}", e)))?;
+    let data = base64::prelude::BASE64_STANDARD.encode(&bytes);
+
+    Ok(if mime_type.starts_with("audio/") {
+        MessageContent::audio(data, mime_type)
+    } else {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        MessageContent::document(data, mime_type, name)
+    })
+}
+
 pub fn unescape_json_values(value: &Value) -> Value {
     match value {
         Value::Object(map) => {
@@ -413,6 +662,55 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_load_attachment_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pdf_path = temp_dir.path().join("spec.pdf");
+        std::fs::write(&pdf_path, b"%PDF-1.7 rest of the document").unwrap();
+        let wav_path = temp_dir.path().join("note.wav");
+        std::fs::write(&wav_path, b"RIFF\x24\x00\x00\x00WAVEfmt ").unwrap();
+        let fake_path = temp_dir.path().join("fake.pdf");
+        std::fs::write(&fake_path, b"not a pdf").unwrap();
+
+        let pdf = pdf_path.to_str().unwrap();
+        let wav = wav_path.to_str().unwrap();
+        let text = format!(
+            "Summarize {} and {} but not {} or relative/spec.pdf",
+            pdf,
+            wav,
+            fake_path.to_str().unwrap()
+        );
+        assert_eq!(detect_attachment_paths(&text), vec![pdf, wav]);
+
+        match load_attachment_file(pdf).unwrap() {
+            MessageContent::Document(document) => {
+                assert_eq!(document.mime_type, "application/pdf");
+                assert_eq!(document.name.as_deref(), Some("spec.pdf"));
+            }
+            other => panic!("Expected a document, got {:?}", other),
+        }
+        match load_attachment_file(wav).unwrap() {
+            MessageContent::Audio(audio) => assert_eq!(audio.mime_type, "audio/wav"),
+            other => panic!("Expected audio, got {:?}", other),
+        }
+        assert!(load_attachment_file(fake_path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_check_modalities() {
+        let messages = vec![Message::user()
+            .with_text("Transcribe this")
+            .with_audio("UklGRg==", "audio/wav")];
+        assert!(check_modalities("gemini-2.0-flash", &messages, &[Modality::Audio]).is_ok());
+
+        let err = check_modalities("gpt-4o", &messages, &[Modality::Image, Modality::Document])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Model gpt-4o does not accept audio input, convert it to text or switch to a model that does"
+        );
+    }
+
     #[test]
     fn test_sanitize_function_name() {
         assert_eq!(sanitize_function_name("hello-world"), "hello-world");
@@ -478,6 +776,66 @@ mod tests {
         assert_eq!(unescaped_value, json!({"text": "Hello World"}));
     }
 
+    #[test]
+    fn test_retry_delay_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_delay_from_headers(&headers), None);
+
+        headers.insert("retry-after", "7".parse().unwrap());
+        assert_eq!(
+            retry_delay_from_headers(&headers),
+            Some(Duration::from_secs(7))
+        );
+
+        // The millisecond header is more precise, so it wins
+        headers.insert("retry-after-ms", "1500".parse().unwrap());
+        assert_eq!(
+            retry_delay_from_headers(&headers),
+            Some(Duration::from_millis(1500))
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "retry-after",
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_delay_from_headers(&headers), Some(Duration::ZERO));
+
+        headers.insert("retry-after", "soon".parse().unwrap());
+        assert_eq!(retry_delay_from_headers(&headers), None);
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"data: {\"a\":").is_empty());
+        assert!(parser.push(b" 1}\r\n").is_empty());
+        assert_eq!(
+            parser.push(b"\r\n: comment\n\ndata: [DONE]\n\n"),
+            vec!["{\"a\": 1}".to_string(), "[DONE]".to_string()]
+        );
+        assert_eq!(parser.finish(), None);
+    }
+
+    #[test]
+    fn test_sse_parser_joins_multiline_data_and_flushes() {
+        let mut parser = SseParser::new();
+        assert_eq!(
+            parser.push(b"event: message_start\ndata: one\ndata: two\n\n"),
+            vec!["one\ntwo".to_string()]
+        );
+        assert!(parser.push(b"data: tail").is_empty());
+        assert_eq!(parser.finish(), Some("tail".to_string()));
+    }
+
+    #[test]
+    fn test_parse_sse_data() {
+        assert_eq!(parse_sse_data("[DONE]").unwrap(), None);
+        assert_eq!(parse_sse_data("  ").unwrap(), None);
+        assert_eq!(parse_sse_data("{\"x\": 2}").unwrap(), Some(json!({"x": 2})));
+        assert!(parse_sse_data("{not json").is_err());
+    }
+
     #[test]
     fn test_is_google_model() {
         // Define the test cases as a vector of tuples
@@ -1,5 +1,8 @@
 use crate::agents::extension::ExtensionConfig;
+use crate::agents::Budget;
+use crate::model::ReasoningEffort;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 fn default_version() -> String {
     "1.0.0".to_string()
@@ -22,6 +25,10 @@ fn default_version() -> String {
 /// * `context` - Supplementary context information for the Recipe
 /// * `activities` - Activity labels that appear when loading the Recipe
 /// * `author` - Information about the Recipe's creator and metadata
+/// * `budget` - Limits on turns, tool calls, tokens and cost for each run of the agent
+/// * `reasoning_effort` - How much the model should reason: off, low, medium or high
+/// * `thinking_budget` - Tokens the model may spend thinking
+/// * `output_schema` - JSON schema of the result `goose run` prints when the recipe finishes
 ///
 /// # Example
 ///
@@ -47,6 +54,10 @@ fn default_version() -> String {
 ///     context: None,
 ///     activities: None,
 ///     author: None,
+///     budget: None,
+///     reasoning_effort: None,
+///     thinking_budget: None,
+///     output_schema: None,
 /// };
 /// ```
 #[derive(Serialize, Deserialize, Debug)]
@@ -78,6 +89,18 @@ pub struct Recipe {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub author: Option<Author>, // any additional author information
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>, // limits on how far the agent may go before asking to continue
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>, // how much the model should reason
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>, // tokens the model may spend thinking
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>, // JSON schema of the machine-readable result of a run
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -103,6 +126,10 @@ pub struct RecipeBuilder {
     context: Option<Vec<String>>,
     activities: Option<Vec<String>>,
     author: Option<Author>,
+    budget: Option<Budget>,
+    reasoning_effort: Option<ReasoningEffort>,
+    thinking_budget: Option<u32>,
+    output_schema: Option<Value>,
 }
 
 impl Recipe {
@@ -131,6 +158,10 @@ impl Recipe {
             context: None,
             activities: None,
             author: None,
+            budget: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            output_schema: None,
         }
     }
 }
@@ -189,6 +220,30 @@ impl RecipeBuilder {
         self
     }
 
+    /// Sets the budget for the Recipe
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Sets how much the model should reason
+    pub fn reasoning_effort(mut self, reasoning_effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(reasoning_effort);
+        self
+    }
+
+    /// Sets the thinking budget in tokens
+    pub fn thinking_budget(mut self, thinking_budget: u32) -> Self {
+        self.thinking_budget = Some(thinking_budget);
+        self
+    }
+
+    /// Sets the JSON schema of the result printed when the Recipe is run headless
+    pub fn output_schema(mut self, output_schema: Value) -> Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+
     /// Builds the Recipe instance
     ///
     /// Returns an error if any required fields are missing
@@ -210,6 +265,10 @@ impl RecipeBuilder {
             context: self.context,
             activities: self.activities,
             author: self.author,
+            budget: self.budget,
+            reasoning_effort: self.reasoning_effort,
+            thinking_budget: self.thinking_budget,
+            output_schema: self.output_schema,
         })
     }
 }
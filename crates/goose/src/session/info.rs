@@ -19,38 +19,13 @@ pub enum SortOrder {
 }
 
 pub fn get_session_info(sort_order: SortOrder) -> Result<Vec<SessionInfo>> {
-    let sessions = match session::list_sessions() {
+    let mut session_infos = match session::session_store().and_then(|store| store.list()) {
         Ok(sessions) => sessions,
         Err(e) => {
             tracing::error!("Failed to list sessions: {:?}", e);
             return Err(anyhow::anyhow!("Failed to list sessions"));
         }
     };
-    let mut session_infos = sessions
-        .into_iter()
-        .map(|(id, path)| {
-            // Get last modified time as string
-            let modified = path
-                .metadata()
-                .and_then(|m| m.modified())
-                .map(|time| {
-                    chrono::DateTime::<chrono::Utc>::from(time)
-                        .format("%Y-%m-%d %H:%M:%S UTC")
-                        .to_string()
-                })
-                .unwrap_or_else(|_| "Unknown".to_string());
-
-            // Get session description
-            let metadata = session::read_metadata(&path).expect("Failed to read session metadata");
-
-            SessionInfo {
-                id,
-                path: path.to_string_lossy().to_string(),
-                modified,
-                metadata,
-            }
-        })
-        .collect::<Vec<SessionInfo>>();
 
     // Sort sessions by modified date
     // Since all dates are in ISO format (YYYY-MM-DD HH:MM:SS UTC), we can just use string comparison
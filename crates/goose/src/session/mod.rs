@@ -1,11 +1,16 @@
 pub mod info;
 pub mod storage;
+pub mod store;
 
 // Re-export common session types and functions
 pub use storage::{
     ensure_session_dir, generate_description, generate_session_id, get_most_recent_session,
-    get_path, list_sessions, persist_messages, read_messages, read_metadata, update_metadata,
-    Identifier, SessionMetadata,
+    get_path, list_sessions, persist_messages, read_jsonl, read_messages, read_metadata,
+    update_metadata, write_jsonl, Identifier, ModelUsage, SessionMetadata, SessionParent,
 };
 
 pub use info::{get_session_info, SessionInfo};
+pub use store::{
+    is_valid_session_id, session_store, JsonlSessionStore, SessionSearchResult, SessionStore,
+    SqliteSessionStore,
+};
@@ -4,6 +4,7 @@ use anyhow::Result;
 use chrono::Local;
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
 use std::path::{Path, PathBuf};
@@ -17,8 +18,28 @@ fn get_home_dir() -> PathBuf {
         .to_path_buf()
 }
 
+/// Where a forked session branched off from its parent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SessionParent {
+    /// Id of the session this one was forked from
+    pub session_id: String,
+    /// Index of the parent's message where the fork diverges, all messages before it are shared
+    pub message_index: usize,
+}
+
+/// Usage attributed to one model over a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ModelUsage {
+    /// Number of responses the model produced
+    pub responses: usize,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    /// Estimated cost in USD, None when the model has no known pricing
+    pub cost: Option<f64>,
+}
+
 /// Metadata for a session, stored as the first line in the session file
-#[derive(Debug, Clone, Serialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
 pub struct SessionMetadata {
     /// Working directory for the session
     #[schema(value_type = String, example = "/home/user/sessions/session1")]
@@ -39,6 +60,15 @@ pub struct SessionMetadata {
     pub accumulated_input_tokens: Option<i32>,
     /// The number of output tokens used in the session. Accumulated across all messages.
     pub accumulated_output_tokens: Option<i32>,
+    /// Estimated cost of the session in USD, accumulated across all messages using the pricing table.
+    /// None when no usage with known pricing has been recorded.
+    pub accumulated_cost: Option<f64>,
+    /// The session this one was forked from, if any
+    pub parent: Option<SessionParent>,
+    /// Ids of the sessions forked from this one
+    pub branches: Vec<String>,
+    /// Usage accumulated per model, for sessions that switch between models
+    pub model_usage: BTreeMap<String, ModelUsage>,
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -58,6 +88,14 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_input_tokens: Option<i32>,
             accumulated_output_tokens: Option<i32>,
             working_dir: Option<PathBuf>,
+            #[serde(default)]
+            accumulated_cost: Option<f64>,
+            #[serde(default)]
+            parent: Option<SessionParent>,
+            #[serde(default)]
+            branches: Vec<String>,
+            #[serde(default)]
+            model_usage: BTreeMap<String, ModelUsage>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -71,7 +109,11 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: helper.accumulated_total_tokens,
             accumulated_input_tokens: helper.accumulated_input_tokens,
             accumulated_output_tokens: helper.accumulated_output_tokens,
+            accumulated_cost: helper.accumulated_cost,
             working_dir: helper.working_dir.unwrap_or_else(get_home_dir),
+            parent: helper.parent,
+            branches: helper.branches,
+            model_usage: helper.model_usage,
         })
     }
 }
@@ -88,6 +130,10 @@ impl SessionMetadata {
             accumulated_total_tokens: None,
             accumulated_input_tokens: None,
             accumulated_output_tokens: None,
+            accumulated_cost: None,
+            parent: None,
+            branches: Vec::new(),
+            model_usage: BTreeMap::new(),
         }
     }
 }
@@ -201,19 +247,26 @@ pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
         .truncate(false)
         .open(session_file)?;
 
-    let reader = io::BufReader::new(file);
+    let (_metadata, messages) = read_jsonl(io::BufReader::new(file))?;
+    Ok(messages)
+}
+
+/// Read a session in the JSONL format
+///
+/// The first line is metadata if it parses as such, every other line is a message.
+pub fn read_jsonl(reader: impl BufRead) -> Result<(Option<SessionMetadata>, Vec<Message>)> {
     let mut lines = reader.lines();
+    let mut metadata = None;
     let mut messages = Vec::new();
 
     // Read the first line as metadata or create default if empty/missing
     if let Some(line) = lines.next() {
         let line = line?;
         // Try to parse as metadata, but if it fails, treat it as a message
-        if let Ok(_metadata) = serde_json::from_str::<SessionMetadata>(&line) {
-            // Metadata successfully parsed, continue with the rest of the lines as messages
-        } else {
+        match serde_json::from_str::<SessionMetadata>(&line) {
+            Ok(parsed) => metadata = Some(parsed),
             // This is not metadata, it's a message
-            messages.push(serde_json::from_str::<Message>(&line)?);
+            Err(_) => messages.push(serde_json::from_str::<Message>(&line)?),
         }
     }
 
@@ -222,7 +275,7 @@ pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
         messages.push(serde_json::from_str::<Message>(&line?)?);
     }
 
-    Ok(messages)
+    Ok((metadata, messages))
 }
 
 /// Read session metadata from a session file
@@ -292,8 +345,15 @@ pub fn save_messages_with_metadata(
     messages: &[Message],
 ) -> Result<()> {
     let file = File::create(session_file).expect("The path specified does not exist");
-    let mut writer = io::BufWriter::new(file);
+    write_jsonl(io::BufWriter::new(file), metadata, messages)
+}
 
+/// Write a session in the JSONL format, metadata on the first line followed by one message per line
+pub fn write_jsonl(
+    mut writer: impl Write,
+    metadata: &SessionMetadata,
+    messages: &[Message],
+) -> Result<()> {
     // Write metadata as the first line
     serde_json::to_writer(&mut writer, &metadata)?;
     writeln!(writer)?;
@@ -563,4 +623,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_metadata_without_model_usage() -> Result<()> {
+        // Sessions written before usage was tracked per model
+        let metadata: SessionMetadata = serde_json::from_str(
+            r#"{"working_dir":"/tmp","description":"old","message_count":2,"total_tokens":null,"input_tokens":null,"output_tokens":null,"accumulated_total_tokens":null,"accumulated_input_tokens":null,"accumulated_output_tokens":null}"#,
+        )?;
+        assert!(metadata.model_usage.is_empty());
+
+        let mut metadata = SessionMetadata::default();
+        metadata.model_usage.insert(
+            "gpt-4o".to_string(),
+            ModelUsage {
+                responses: 2,
+                input_tokens: 100,
+                output_tokens: 20,
+                cost: Some(0.01),
+            },
+        );
+        let read: SessionMetadata = serde_json::from_str(&serde_json::to_string(&metadata)?)?;
+        assert_eq!(read.model_usage, metadata.model_usage);
+        Ok(())
+    }
 }
@@ -0,0 +1,823 @@
+use crate::message::{Message, MessageContent};
+use crate::session::info::SessionInfo;
+use crate::session::storage::{
+    ensure_session_dir, read_jsonl, read_messages, read_metadata, save_messages_with_metadata,
+    write_jsonl, SessionMetadata, SessionParent,
+};
+use anyhow::Result;
+use mcp_core::role::Role;
+use rusqlite::{params, Connection, Transaction};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+/// Number of characters of context kept on each side of a search match
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Whether `id` can name a session: ASCII letters, digits, `_` and `-`
+///
+/// Ids become file names, so anything else could reach outside the session directory.
+pub fn is_valid_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// A message that matched a session search
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionSearchResult {
+    /// Id of the session the message belongs to
+    pub session_id: String,
+    /// Description of the session the message belongs to
+    pub description: String,
+    /// Position of the message in the session
+    pub message_index: usize,
+    /// Who sent the message
+    pub role: Role,
+    /// The part of the message around the first match
+    pub snippet: String,
+}
+
+/// Backend that sessions are persisted to
+///
+/// Sessions are addressed by id. Every store can exchange sessions in the JSONL format,
+/// metadata on the first line followed by one message per line.
+pub trait SessionStore: Send + Sync {
+    /// All sessions in the store, in no particular order
+    fn list(&self) -> Result<Vec<SessionInfo>>;
+
+    /// Metadata of a session, or the default metadata if the session doesn't exist
+    fn read_metadata(&self, id: &str) -> Result<SessionMetadata>;
+
+    /// Messages of a session, or no messages if the session doesn't exist
+    fn read_messages(&self, id: &str) -> Result<Vec<Message>>;
+
+    /// Create or overwrite a session
+    fn save(&self, id: &str, metadata: &SessionMetadata, messages: &[Message]) -> Result<()>;
+
+    /// Delete a session
+    fn remove(&self, id: &str) -> Result<()>;
+
+    /// Check whether a session exists
+    fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.list()?.iter().any(|session| session.id == id))
+    }
+
+    /// Messages across all sessions containing every whitespace separated term of `query`,
+    /// ignoring case, most recently modified sessions first
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SessionSearchResult>> {
+        let terms = search_terms(query);
+        if terms.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = self.list()?;
+        sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+        let mut results = Vec::new();
+        for session in sessions {
+            for (message_index, message) in self.read_messages(&session.id)?.iter().enumerate() {
+                if let Some(snippet) = match_message(message, &terms) {
+                    results.push(SessionSearchResult {
+                        session_id: session.id.clone(),
+                        description: session.metadata.description.clone(),
+                        message_index,
+                        role: message.role.clone(),
+                        snippet,
+                    });
+                    if results.len() == limit {
+                        return Ok(results);
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Fork a session into a new session `new_id` holding the messages before `message_index`
+    ///
+    /// The fork records where it branched off and the parent lists the fork among its
+    /// branches, so the metadata of the sessions forms the branch tree. Forking at the index
+    /// of a user message and sending an edited version of it replaces that message on the
+    /// new branch. Returns the metadata of the new session.
+    fn fork(&self, id: &str, new_id: &str, message_index: usize) -> Result<SessionMetadata> {
+        if !self.exists(id)? {
+            return Err(anyhow::anyhow!("Session '{}' not found", id));
+        }
+        if self.exists(new_id)? {
+            return Err(anyhow::anyhow!("Session '{}' already exists", new_id));
+        }
+        let messages = self.read_messages(id)?;
+        if message_index > messages.len() {
+            return Err(anyhow::anyhow!(
+                "Session '{}' has only {} messages, cannot fork at message {}",
+                id,
+                messages.len(),
+                message_index
+            ));
+        }
+
+        let mut parent_metadata = self.read_metadata(id)?;
+        // Token usage is tracked per session, so the fork starts counting from scratch
+        let metadata = SessionMetadata {
+            description: parent_metadata.description.clone(),
+            message_count: message_index,
+            parent: Some(SessionParent {
+                session_id: id.to_string(),
+                message_index,
+            }),
+            ..SessionMetadata::new(parent_metadata.working_dir.clone())
+        };
+        self.save(new_id, &metadata, &messages[..message_index])?;
+
+        parent_metadata.branches.push(new_id.to_string());
+        self.save(id, &parent_metadata, &messages)?;
+        Ok(metadata)
+    }
+
+    /// Write a session in the JSONL format
+    fn export_jsonl(&self, id: &str, writer: &mut dyn Write) -> Result<()> {
+        if !self.exists(id)? {
+            return Err(anyhow::anyhow!("Session '{}' not found", id));
+        }
+        write_jsonl(writer, &self.read_metadata(id)?, &self.read_messages(id)?)
+    }
+
+    /// Read a session in the JSONL format into a new session `id`
+    fn import_jsonl(&self, id: &str, reader: &mut dyn BufRead) -> Result<()> {
+        if self.exists(id)? {
+            return Err(anyhow::anyhow!("Session '{}' already exists", id));
+        }
+        let (metadata, messages) = read_jsonl(reader)?;
+        let mut metadata = metadata.unwrap_or_default();
+        metadata.message_count = messages.len();
+        self.save(id, &metadata, &messages)
+    }
+}
+
+/// Stores each session as a JSONL file named after its id in a directory
+pub struct JsonlSessionStore {
+    dir: PathBuf,
+}
+
+impl JsonlSessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        if !is_valid_session_id(id) {
+            anyhow::bail!(
+                "Invalid session id '{}', use only letters, digits, '_' and '-'",
+                id
+            );
+        }
+        Ok(self.dir.join(format!("{}.jsonl", id)))
+    }
+}
+
+impl SessionStore for JsonlSessionStore {
+    fn list(&self) -> Result<Vec<SessionInfo>> {
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+
+            // Get last modified time as string
+            let modified = path
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(format_modified)
+                .unwrap_or_else(|_| "Unknown".to_string());
+
+            let metadata = match read_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable session {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            sessions.push(SessionInfo {
+                id,
+                path: path.to_string_lossy().to_string(),
+                modified,
+                metadata,
+            });
+        }
+        Ok(sessions)
+    }
+
+    fn read_metadata(&self, id: &str) -> Result<SessionMetadata> {
+        read_metadata(&self.path(id)?)
+    }
+
+    fn read_messages(&self, id: &str) -> Result<Vec<Message>> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        read_messages(&path)
+    }
+
+    fn save(&self, id: &str, metadata: &SessionMetadata, messages: &[Message]) -> Result<()> {
+        save_messages_with_metadata(&self.path(id)?, metadata, messages)
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        let path = self.path(id)?;
+        fs::remove_file(&path).map_err(|e| {
+            anyhow::anyhow!("Failed to remove session file '{}': {}", path.display(), e)
+        })
+    }
+
+    fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.path(id)?.exists())
+    }
+}
+
+/// Keeps sessions as JSONL files in a directory, indexed in an SQLite database for listing
+/// and full text search
+///
+/// The files stay the sessions' record, as the agent loop appends to them directly. The
+/// index is updated whenever a session is saved, forked or removed through the store, and
+/// sessions whose files changed outside of it are re-indexed before listing or searching.
+pub struct SqliteSessionStore {
+    files: JsonlSessionStore,
+    conn: Mutex<Connection>,
+}
+
+/// The index: metadata per session, and the searchable text of every message
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        modified TEXT NOT NULL,
+        file_modified INTEGER NOT NULL,
+        file_size INTEGER NOT NULL,
+        metadata TEXT NOT NULL
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+        session_id UNINDEXED,
+        message_index UNINDEXED,
+        role UNINDEXED,
+        text,
+        tokenize = 'unicode61 remove_diacritics 0'
+    );
+";
+
+/// How long to wait for another process writing to the index
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl SqliteSessionStore {
+    /// Open the index at `db_path` for the session files in `dir`, creating it if needed
+    pub fn open(dir: PathBuf, db_path: &Path) -> Result<Self> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            files: JsonlSessionStore::new(dir),
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Session index lock poisoned"))
+    }
+
+    /// Re-index the session files added, changed or removed since they were last indexed
+    fn sync(&self, conn: &mut Connection) -> Result<()> {
+        let mut indexed: HashMap<String, (i64, i64)> = HashMap::new();
+        {
+            let mut stmt = conn.prepare("SELECT id, file_modified, file_size FROM sessions")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?;
+            for row in rows {
+                let (id, stamp) = row?;
+                indexed.insert(id, stamp);
+            }
+        }
+
+        let tx = conn.transaction()?;
+        let mut seen = HashSet::new();
+        for entry in fs::read_dir(&self.files.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let Ok(stamp) = file_stamp(&path) else {
+                continue;
+            };
+            seen.insert(id.clone());
+            if indexed.get(&id) == Some(&stamp) {
+                continue;
+            }
+
+            let file = fs::File::open(&path)?;
+            match read_jsonl(BufReader::new(file)) {
+                Ok((metadata, messages)) => {
+                    let metadata = metadata.unwrap_or_default();
+                    index_session(&tx, &id, &path, stamp, &metadata, &messages)?;
+                }
+                // A session with a broken message is still listed, its messages can't be searched
+                Err(e) => match read_metadata(&path) {
+                    Ok(metadata) => {
+                        tracing::warn!("Not indexing the messages of {}: {}", path.display(), e);
+                        index_session(&tx, &id, &path, stamp, &metadata, &[])?;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Skipping unreadable session {}: {}", path.display(), e);
+                        unindex_session(&tx, &id)?;
+                    }
+                },
+            }
+        }
+        for id in indexed.keys().filter(|id| !seen.contains(*id)) {
+            unindex_session(&tx, id)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl SessionStore for SqliteSessionStore {
+    fn list(&self) -> Result<Vec<SessionInfo>> {
+        let mut conn = self.lock()?;
+        self.sync(&mut conn)?;
+
+        let mut stmt = conn.prepare("SELECT id, path, modified, metadata FROM sessions")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let mut sessions = Vec::new();
+        for row in rows {
+            let (id, path, modified, metadata) = row?;
+            sessions.push(SessionInfo {
+                id,
+                path,
+                modified,
+                metadata: serde_json::from_str(&metadata)?,
+            });
+        }
+        Ok(sessions)
+    }
+
+    fn read_metadata(&self, id: &str) -> Result<SessionMetadata> {
+        self.files.read_metadata(id)
+    }
+
+    fn read_messages(&self, id: &str) -> Result<Vec<Message>> {
+        self.files.read_messages(id)
+    }
+
+    fn save(&self, id: &str, metadata: &SessionMetadata, messages: &[Message]) -> Result<()> {
+        self.files.save(id, metadata, messages)?;
+
+        let path = self.files.path(id)?;
+        let stamp = file_stamp(&path)?;
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        index_session(&tx, id, &path, stamp, metadata, messages)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.files.remove(id)?;
+
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        unindex_session(&tx, id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn exists(&self, id: &str) -> Result<bool> {
+        self.files.exists(id)
+    }
+
+    /// Messages across all sessions with words starting with every whitespace separated term
+    /// of `query`, ignoring case, most recently modified sessions first
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SessionSearchResult>> {
+        let terms = search_terms(query);
+        if terms.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.lock()?;
+        self.sync(&mut conn)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT f.session_id, s.metadata, f.message_index, f.role, f.text
+             FROM messages_fts f JOIN sessions s ON s.id = f.session_id
+             WHERE messages_fts MATCH ?1
+             ORDER BY s.file_modified DESC, f.session_id, CAST(f.message_index AS INTEGER)
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![fts_query(&terms), limit as i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let (session_id, metadata, message_index, role, text) = row?;
+            let metadata: SessionMetadata = serde_json::from_str(&metadata)?;
+            results.push(SessionSearchResult {
+                session_id,
+                description: metadata.description,
+                message_index: message_index as usize,
+                role: serde_json::from_value(serde_json::Value::String(role))?,
+                snippet: snippet(&text, &terms[0]),
+            });
+        }
+        Ok(results)
+    }
+}
+
+/// Replace what the index holds for a session
+fn index_session(
+    tx: &Transaction,
+    id: &str,
+    path: &Path,
+    (file_modified, file_size): (i64, i64),
+    metadata: &SessionMetadata,
+    messages: &[Message],
+) -> Result<()> {
+    unindex_session(tx, id)?;
+    let modified = UNIX_EPOCH + Duration::from_nanos(file_modified as u64);
+    tx.execute(
+        "INSERT INTO sessions (id, path, modified, file_modified, file_size, metadata)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            path.to_string_lossy(),
+            format_modified(modified),
+            file_modified,
+            file_size,
+            serde_json::to_string(metadata)?,
+        ],
+    )?;
+
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO messages_fts (session_id, message_index, role, text) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for (message_index, message) in messages.iter().enumerate() {
+        let text = searchable_text(message);
+        if text.is_empty() {
+            continue;
+        }
+        let role = serde_json::to_value(&message.role)?;
+        insert.execute(params![
+            id,
+            message_index as i64,
+            role.as_str().unwrap_or_default(),
+            text
+        ])?;
+    }
+    Ok(())
+}
+
+fn unindex_session(tx: &Transaction, id: &str) -> Result<()> {
+    tx.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+    tx.execute(
+        "DELETE FROM messages_fts WHERE session_id = ?1",
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// When a session file was last written and how long it is, to tell if it changed
+fn file_stamp(path: &Path) -> Result<(i64, i64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?;
+    Ok((modified.as_nanos() as i64, metadata.len() as i64))
+}
+
+fn format_modified(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string()
+}
+
+/// An FTS5 query for words starting with every term, with the terms quoted so that
+/// punctuation in them isn't read as query syntax
+fn fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The store that sessions are kept in
+pub fn session_store() -> Result<Box<dyn SessionStore>> {
+    let dir = ensure_session_dir()?;
+    let db_path = dir.join("sessions.db");
+    Ok(Box::new(SqliteSessionStore::open(dir, &db_path)?))
+}
+
+fn search_terms(query: &str) -> Vec<String> {
+    query.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// The text of a message that search looks at: text, tool calls and tool output
+fn searchable_text(message: &Message) -> String {
+    let mut parts = Vec::new();
+    for content in &message.content {
+        match content {
+            MessageContent::Text(text) => parts.push(text.text.clone()),
+            MessageContent::ToolRequest(request) => {
+                if let Ok(tool_call) = &request.tool_call {
+                    parts.push(format!("{} {}", tool_call.name, tool_call.arguments));
+                }
+            }
+            MessageContent::ToolResponse(_) => {
+                if let Some(text) = content.as_tool_response_text() {
+                    parts.push(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.join("\n")
+}
+
+/// A snippet around the first term if the message contains all `terms`
+fn match_message(message: &Message, terms: &[String]) -> Option<String> {
+    let text = searchable_text(message);
+    let lowercase = text.to_lowercase();
+    if !terms.iter().all(|term| lowercase.contains(term.as_str())) {
+        return None;
+    }
+    Some(snippet(&text, &terms[0]))
+}
+
+/// The part of `text` around the first occurrence of the lowercase `term`, or its start
+fn snippet(text: &str, term: &str) -> String {
+    let lowercase = text.to_lowercase();
+
+    // Lowercasing can change byte offsets, so locate the match by characters instead
+    let chars: Vec<char> = text.chars().collect();
+    let lower_chars: Vec<char> = lowercase.chars().collect();
+    let term: Vec<char> = term.chars().collect();
+    let position = if lower_chars.len() == chars.len() {
+        lower_chars
+            .windows(term.len())
+            .position(|window| window == term.as_slice())
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    let start = position.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (position + term.len() + SNIPPET_CONTEXT_CHARS).min(chars.len());
+    let mut snippet: String = chars[start..end]
+        .iter()
+        .map(|c| if c.is_whitespace() { ' ' } else { *c })
+        .collect();
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{Content, ToolCall};
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn save_sessions(store: &dyn SessionStore) -> Result<()> {
+        let metadata = SessionMetadata {
+            description: "Fix the parser".to_string(),
+            ..Default::default()
+        };
+        store.save(
+            "first",
+            &metadata,
+            &[
+                Message::user().with_text("The Parser crashes on empty input"),
+                Message::assistant().with_tool_request(
+                    "tool1",
+                    Ok(ToolCall::new(
+                        "developer__shell",
+                        json!({"command": "cargo test parser"}),
+                    )),
+                ),
+                Message::user().with_tool_response(
+                    "tool1",
+                    Ok(vec![Content::text("test parser::empty_input ... FAILED")]),
+                ),
+            ],
+        )?;
+        store.save(
+            "second",
+            &SessionMetadata::default(),
+            &[Message::user().with_text("Write release notes")],
+        )
+    }
+
+    /// Every kind of store, each in its own directory and holding the same sessions
+    fn stores_with_sessions() -> Result<Vec<(tempfile::TempDir, Box<dyn SessionStore>)>> {
+        let jsonl_dir = tempdir()?;
+        let jsonl = JsonlSessionStore::new(jsonl_dir.path().to_path_buf());
+        let sqlite_dir = tempdir()?;
+        let sqlite = SqliteSessionStore::open(
+            sqlite_dir.path().to_path_buf(),
+            &sqlite_dir.path().join("sessions.db"),
+        )?;
+
+        let stores: Vec<(tempfile::TempDir, Box<dyn SessionStore>)> =
+            vec![(jsonl_dir, Box::new(jsonl)), (sqlite_dir, Box::new(sqlite))];
+        for (_, store) in &stores {
+            save_sessions(store.as_ref())?;
+        }
+        Ok(stores)
+    }
+
+    #[test]
+    fn test_search_matches_all_terms_ignoring_case() -> Result<()> {
+        for (_dir, store) in stores_with_sessions()? {
+            let results = store.search("parser EMPTY", 10)?;
+            let indexes: Vec<usize> = results.iter().map(|r| r.message_index).collect();
+            assert_eq!(indexes, vec![0, 2]);
+            assert!(results.iter().all(|r| r.session_id == "first"));
+            assert_eq!(results[0].description, "Fix the parser");
+            assert_eq!(results[0].snippet, "The Parser crashes on empty input");
+
+            // Tool calls are searchable too
+            let results = store.search("cargo test", 10)?;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].role, Role::Assistant);
+
+            assert_eq!(store.search("parser", 1)?.len(), 1);
+            assert!(store.search("   ", 10)?.is_empty());
+            assert!(store.search("deploy", 10)?.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqlite_index_follows_session_files() -> Result<()> {
+        let dir = tempdir()?;
+        let store =
+            SqliteSessionStore::open(dir.path().to_path_buf(), &dir.path().join("sessions.db"))?;
+        save_sessions(&store)?;
+        let files = JsonlSessionStore::new(dir.path().to_path_buf());
+
+        // Forks are indexed as they are saved
+        store.fork("first", "retry", 1)?;
+        let results = store.search("crashes", 10)?;
+        let mut ids: Vec<&str> = results.iter().map(|r| r.session_id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["first", "retry"]);
+
+        // Sessions written to their files directly, as the agent loop does, are picked up
+        files.save(
+            "third",
+            &SessionMetadata {
+                description: "Deploy".to_string(),
+                ..Default::default()
+            },
+            &[Message::user().with_text("Roll out the deployment")],
+        )?;
+        let results = store.search("deploy", 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].description, "Deploy");
+        assert_eq!(store.list()?.len(), 4);
+
+        // As are sessions whose files are gone
+        files.remove("third")?;
+        store.remove("retry")?;
+        assert!(store.search("deploy", 10)?.is_empty());
+        let mut ids: Vec<String> = store.list()?.into_iter().map(|s| s.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["first", "second"]);
+
+        // Query syntax in a search is taken literally
+        assert!(store.search("\"parser OR", 10).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_snippet_is_trimmed_around_match() {
+        let text = format!("{} needle {}", "a".repeat(200), "b".repeat(200));
+        let message = Message::user().with_text(text);
+
+        let snippet = match_message(&message, &["needle".to_string()]).unwrap();
+        assert!(snippet.starts_with("..."));
+        assert!(snippet.ends_with("..."));
+        assert!(snippet.contains(" needle "));
+        assert_eq!(
+            snippet.chars().count(),
+            "needle".len() + 2 * SNIPPET_CONTEXT_CHARS + 6
+        );
+    }
+
+    #[test]
+    fn test_export_import_round_trip() -> Result<()> {
+        for (_dir, store) in stores_with_sessions()? {
+            let mut exported = Vec::new();
+            store.export_jsonl("first", &mut exported)?;
+
+            store.import_jsonl("copy", &mut exported.as_slice())?;
+            assert_eq!(store.read_messages("copy")?, store.read_messages("first")?);
+            assert_eq!(store.read_metadata("copy")?.description, "Fix the parser");
+            assert_eq!(store.read_metadata("copy")?.message_count, 3);
+
+            // Imports never overwrite an existing session
+            assert!(store
+                .import_jsonl("second", &mut exported.as_slice())
+                .is_err());
+            assert!(store.export_jsonl("missing", &mut Vec::new()).is_err());
+
+            store.remove("copy")?;
+            assert!(!store.exists("copy")?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_ids_outside_session_dir() -> Result<()> {
+        assert!(is_valid_session_id("20250101_120000"));
+        assert!(is_valid_session_id("retry-again"));
+        for id in ["", "../escape", "a/b", "a.b", "..", "name with space"] {
+            assert!(!is_valid_session_id(id), "{:?}", id);
+        }
+
+        for (dir, store) in stores_with_sessions()? {
+            assert!(store.fork("first", "../escape", 1).is_err());
+            assert!(store
+                .import_jsonl("../escape", &mut b"".as_slice())
+                .is_err());
+            assert!(store.read_messages("../first").is_err());
+            assert!(!dir.path().join("../escape.jsonl").exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fork_records_branch_tree() -> Result<()> {
+        for (_dir, store) in stores_with_sessions()? {
+            let metadata = store.fork("first", "retry", 2)?;
+            assert_eq!(
+                metadata.parent,
+                Some(SessionParent {
+                    session_id: "first".to_string(),
+                    message_index: 2,
+                })
+            );
+            assert_eq!(metadata.description, "Fix the parser");
+            assert_eq!(
+                store.read_messages("retry")?,
+                store.read_messages("first")?[..2].to_vec()
+            );
+            assert_eq!(store.read_metadata("retry")?, metadata);
+
+            // Forking again from the fork extends the tree
+            store.fork("retry", "retry-again", 0)?;
+            assert!(store.read_messages("retry-again")?.is_empty());
+            assert_eq!(store.read_metadata("first")?.branches, vec!["retry"]);
+            assert_eq!(store.read_metadata("retry")?.branches, vec!["retry-again"]);
+
+            // The parent keeps all of its messages
+            assert_eq!(store.read_messages("first")?.len(), 3);
+
+            assert!(store.fork("first", "second", 1).is_err());
+            assert!(store.fork("first", "too-far", 4).is_err());
+            assert!(store.fork("missing", "other", 0).is_err());
+        }
+        Ok(())
+    }
+}
@@ -1,42 +1,102 @@
 use include_dir::{include_dir, Dir};
 use mcp_core::Tool;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tokenizers::tokenizer::Tokenizer;
 
+use crate::config::Config;
 use crate::message::Message;
 
 // The embedded directory with all possible tokenizer files.
 // If one of them doesn’t exist, we’ll download it at startup.
 static TOKENIZER_FILES: Dir = include_dir!("$CARGO_MANIFEST_DIR/../../tokenizer_files");
 
-/// The `TokenCounter` now stores exactly one `Tokenizer`.
+/// Rough number of characters per token, used when no tokenizer is available
+const CHARS_PER_TOKEN: f64 = 4.0;
+/// Calibration never scales the estimate further than this, in either direction
+const MAX_CALIBRATION: f64 = 4.0;
+
+/// Factors that correct the estimate, by tokenizer name, shared by all counters
+static CALIBRATION: Lazy<Mutex<HashMap<String, f64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The `TokenCounter` stores exactly one `Tokenizer`, or estimates when none could be loaded.
 pub struct TokenCounter {
-    tokenizer: Tokenizer,
+    tokenizer_name: String,
+    tokenizer: Option<Tokenizer>,
 }
 
 impl TokenCounter {
     /// Creates a new `TokenCounter` using the given HuggingFace tokenizer name.
     ///
     /// * `tokenizer_name` might look like "Xenova--gpt-4o"
-    ///   or "Qwen--Qwen2.5-Coder-32B-Instruct", etc.
+    ///   or "Qwen--Qwen2.5-Coder-32B-Instruct", etc., or be a path to a `tokenizer.json`.
+    ///
+    /// The tokenizer is looked up in this order: a local path, the tokenizers embedded at
+    /// build time, `GOOSE_TOKENIZER_DIR`, and finally Hugging Face unless
+    /// `GOOSE_TOKENIZER_OFFLINE` is set. If all of them fail, counts are estimated from
+    /// the length of the text and calibrated against what the provider reports.
     pub fn new(tokenizer_name: &str) -> Self {
-        match Self::load_from_embedded(tokenizer_name) {
-            Ok(tokenizer) => Self { tokenizer },
+        let tokenizer = match Self::load(tokenizer_name) {
+            Ok(tokenizer) => Some(tokenizer),
             Err(e) => {
-                println!(
-                    "Tokenizer '{}' not found in embedded dir: {}",
-                    tokenizer_name, e
+                tracing::warn!(
+                    "Tokenizer '{}' is not available, estimating token counts instead: {}",
+                    tokenizer_name,
+                    e
                 );
-                println!("Attempting to download tokenizer and load...");
-                // Fallback to download tokenizer and load from disk
-                match Self::download_and_load(tokenizer_name) {
-                    Ok(counter) => counter,
-                    Err(e) => panic!("Failed to initialize tokenizer: {}", e),
-                }
+                None
+            }
+        };
+        Self {
+            tokenizer_name: tokenizer_name.to_string(),
+            tokenizer,
+        }
+    }
+
+    fn load(tokenizer_name: &str) -> Result<Tokenizer, Box<dyn Error>> {
+        if tokenizer_name.ends_with(".json") {
+            return Self::load_from_file(Path::new(tokenizer_name));
+        }
+
+        if let Ok(tokenizer) = Self::load_from_embedded(tokenizer_name) {
+            return Ok(tokenizer);
+        }
+
+        let local_json_path = Self::tokenizer_dir()
+            .join(tokenizer_name)
+            .join("tokenizer.json");
+        if !local_json_path.exists() {
+            if Self::offline() {
+                return Err(format!(
+                    "{} not found and downloads are off",
+                    local_json_path.display()
+                )
+                .into());
             }
+            // e.g. "Xenova--llama3-tokenizer" -> "Xenova/llama3-tokenizer"
+            let repo_id = tokenizer_name.replace("--", "/");
+            Self::download_tokenizer(&repo_id, &local_json_path)?;
         }
+        Self::load_from_file(&local_json_path)
+    }
+
+    /// Directory with a `<tokenizer name>/tokenizer.json` for each tokenizer, where
+    /// downloads are kept too
+    fn tokenizer_dir() -> PathBuf {
+        Config::global()
+            .get_param::<String>("GOOSE_TOKENIZER_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::temp_dir())
+    }
+
+    fn offline() -> bool {
+        Config::global()
+            .get_param::<bool>("GOOSE_TOKENIZER_OFFLINE")
+            .unwrap_or(false)
     }
 
     /// Load tokenizer bytes from the embedded directory (via `include_dir!`).
@@ -56,60 +116,90 @@ impl TokenCounter {
         Ok(tokenizer)
     }
 
-    /// Fallback: If not found in embedded, we look in `base_dir` on disk.
-    /// If not on disk, we download from Hugging Face, then load from disk.
-    fn download_and_load(tokenizer_name: &str) -> Result<Self, Box<dyn Error>> {
-        let local_dir = std::env::temp_dir().join(tokenizer_name);
-        let local_json_path = local_dir.join("tokenizer.json");
-
-        // If the file doesn't already exist, we download from HF
-        if !Path::new(&local_json_path).exists() {
-            eprintln!("Tokenizer file not on disk, downloading…");
-            let repo_id = tokenizer_name.replace("--", "/");
-            // e.g. "Xenova--llama3-tokenizer" -> "Xenova/llama3-tokenizer"
-            Self::download_tokenizer(&repo_id, &local_dir)?;
-        }
-
-        // Load from disk
-        let file_content = fs::read(&local_json_path)?;
+    fn load_from_file(path: &Path) -> Result<Tokenizer, Box<dyn Error>> {
+        let file_content = fs::read(path)
+            .map_err(|e| format!("Failed to read tokenizer {}: {}", path.display(), e))?;
         let tokenizer = Tokenizer::from_bytes(&file_content)
-            .map_err(|e| format!("Failed to parse tokenizer after download: {}", e))?;
-
-        Ok(Self { tokenizer })
+            .map_err(|e| format!("Failed to parse tokenizer {}: {}", path.display(), e))?;
+        Ok(tokenizer)
     }
 
-    /// Download from Hugging Face into the local directory if not already present.
-    /// Synchronous version using a blocking runtime for simplicity.
-    fn download_tokenizer(repo_id: &str, download_dir: &Path) -> Result<(), Box<dyn Error>> {
-        fs::create_dir_all(download_dir)?;
+    /// Download `tokenizer.json` from Hugging Face to the given path.
+    ///
+    /// Runs on its own thread and runtime, since counters are also created from async code.
+    fn download_tokenizer(repo_id: &str, file_path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(download_dir) = file_path.parent() {
+            fs::create_dir_all(download_dir)?;
+        }
 
         let file_url = format!(
             "https://huggingface.co/{}/resolve/main/tokenizer.json",
             repo_id
         );
-        let file_path = download_dir.join("tokenizer.json");
-
-        // Blocking for example: just spawn a short-lived runtime
-        let content = tokio::runtime::Runtime::new()?.block_on(async {
-            let response = reqwest::get(&file_url).await?;
-            if !response.status().is_success() {
-                let error_msg =
-                    format!("Failed to download tokenizer: status {}", response.status());
-                return Err(Box::<dyn Error>::from(error_msg));
-            }
-            let bytes = response.bytes().await?;
-            Ok(bytes)
-        })?;
 
-        fs::write(&file_path, content)?;
+        let content = std::thread::spawn(move || -> Result<_, String> {
+            let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+            runtime.block_on(async {
+                let response = reqwest::get(&file_url).await.map_err(|e| e.to_string())?;
+                if !response.status().is_success() {
+                    return Err(format!(
+                        "Failed to download tokenizer: status {}",
+                        response.status()
+                    ));
+                }
+                response.bytes().await.map_err(|e| e.to_string())
+            })
+        })
+        .join()
+        .map_err(|_| "Tokenizer download panicked")??;
+
+        fs::write(file_path, content)?;
 
         Ok(())
     }
 
+    /// Whether counts are estimated, because no tokenizer could be loaded
+    pub fn is_estimate(&self) -> bool {
+        self.tokenizer.is_none()
+    }
+
+    fn calibration(&self) -> f64 {
+        CALIBRATION
+            .lock()
+            .unwrap()
+            .get(&self.tokenizer_name)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Correct estimates with the input tokens a provider reported for a request
+    ///
+    /// `estimated` is what this counter counted for the same request. Each report moves the
+    /// correction halfway towards the observed ratio, which smooths out differences between
+    /// requests. Counters with a tokenizer are exact and ignore this.
+    pub fn calibrate(&self, estimated: usize, reported: usize) {
+        if !self.is_estimate() || estimated == 0 || reported == 0 {
+            return;
+        }
+        let mut calibration = CALIBRATION.lock().unwrap();
+        let current = calibration
+            .get(&self.tokenizer_name)
+            .copied()
+            .unwrap_or(1.0);
+        let observed = current * reported as f64 / estimated as f64;
+        let updated = ((current + observed) / 2.0).clamp(1.0 / MAX_CALIBRATION, MAX_CALIBRATION);
+        calibration.insert(self.tokenizer_name.clone(), updated);
+    }
+
     /// Count tokens for a piece of text using our single tokenizer.
     pub fn count_tokens(&self, text: &str) -> usize {
-        let encoding = self.tokenizer.encode(text, false).unwrap();
-        encoding.len()
+        match &self.tokenizer {
+            Some(tokenizer) => tokenizer.encode(text, false).unwrap().len(),
+            None => {
+                let estimate = text.chars().count() as f64 / CHARS_PER_TOKEN;
+                (estimate * self.calibration()).ceil() as usize
+            }
+        }
     }
 
     pub fn count_tokens_for_tools(&self, tools: &[Tool]) -> usize {
@@ -325,12 +415,33 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn test_panic_if_provided_tokenizer_doesnt_exist() {
-        // This should panic because the tokenizer doesn't exist
-        // in the embedded directory and the download fails
+    fn test_estimate_if_tokenizer_doesnt_exist() {
+        // A missing local file is never downloaded
+        let counter = TokenCounter::new("/nonexistent/tokenizer.json");
+        assert!(counter.is_estimate());
+        assert_eq!(counter.count_tokens("12345678"), 2);
+    }
 
-        TokenCounter::new("nonexistent-tokenizer");
+    #[test]
+    fn test_calibrate_estimate() {
+        let counter = TokenCounter::new("/nonexistent/calibrated/tokenizer.json");
+        let text = "a".repeat(400);
+        assert_eq!(counter.count_tokens(&text), 100);
+
+        // The provider counted twice as many, the estimate moves halfway there
+        counter.calibrate(100, 200);
+        assert_eq!(counter.count_tokens(&text), 150);
+        counter.calibrate(150, 200);
+        assert_eq!(counter.count_tokens(&text), 175);
+
+        // Shared with other counters for the same tokenizer
+        let other = TokenCounter::new("/nonexistent/calibrated/tokenizer.json");
+        assert_eq!(other.count_tokens(&text), 175);
+
+        // Exact counters are left alone
+        let exact = TokenCounter::new(GPT_4O_TOKENIZER);
+        exact.calibrate(100, 200);
+        assert!(!exact.is_estimate());
     }
 
     // Optional test to confirm that fallback download works if not found in embedded:
@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use futures::StreamExt;
-use goose::agents::Agent;
+use goose::agents::{Agent, AgentEvent};
 use goose::message::Message;
 use goose::model::ModelConfig;
 use goose::providers::base::Provider;
@@ -126,13 +126,14 @@ async fn run_truncate_test(
         ),
     ];
 
-    let reply_stream = agent.reply(&messages, None).await?;
+    let reply_stream = agent.reply(&messages, None, None).await?;
     tokio::pin!(reply_stream);
 
     let mut responses = Vec::new();
     while let Some(response_result) = reply_stream.next().await {
         match response_result {
-            Ok(response) => responses.push(response),
+            Ok(AgentEvent::Message(response)) => responses.push(response),
+            Ok(AgentEvent::MessageDelta(_)) | Ok(AgentEvent::HistoryReplaced(_)) => {}
             Err(e) => {
                 println!("Error: {:?}", e);
                 return Err(e);
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
+use mcp_client::transport::{StreamableHttpTransport, Transport};
+use mcp_client::McpService;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+/// Talks to the counter server of the mcp-server crate, started with
+/// `cargo run -p mcp-server -- --http 127.0.0.1:8000`
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::from_default_env().add_directive("mcp_client=debug".parse().unwrap()),
+        )
+        .init();
+
+    // Create the base transport
+    let transport =
+        StreamableHttpTransport::new("http://localhost:8000/mcp", HashMap::new(), HashMap::new());
+
+    // Start transport
+    let handle = transport.start().await?;
+
+    // Create the service with timeout middleware
+    let service = McpService::with_timeout(handle, Duration::from_secs(3));
+
+    // Create client
+    let mut client = McpClient::new(service);
+    println!("Client created\n");
+
+    // Initialize, which starts the session
+    let server_info = client
+        .initialize(
+            ClientInfo {
+                name: "test-client".into(),
+                version: "1.0.0".into(),
+            },
+            ClientCapabilities::default(),
+        )
+        .await?;
+    println!("Connected to server: {server_info:?}\n");
+
+    // List tools
+    let tools = client.list_tools(None).await?;
+    println!("Available tools: {tools:?}\n");
+
+    // Call tool
+    let tool_result = client.call_tool("increment", serde_json::json!({})).await?;
+    println!("Tool result: {tool_result:?}\n");
+
+    // End the session
+    transport.close().await?;
+
+    Ok(())
+}
@@ -1,15 +1,21 @@
+use futures::future::BoxFuture;
 use mcp_core::protocol::{
-    CallToolResult, GetPromptResult, Implementation, InitializeResult, JsonRpcError,
+    CallToolResult, ErrorData, GetPromptResult, Implementation, InitializeResult, JsonRpcError,
     JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, ListPromptsResult,
-    ListResourcesResult, ListToolsResult, ReadResourceResult, ServerCapabilities, METHOD_NOT_FOUND,
+    ListResourcesResult, ListToolsResult, ProgressNotificationParams, ProgressToken,
+    ReadResourceResult, ServerCapabilities, METHOD_NOT_FOUND,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, RwLock};
 use thiserror::Error;
 use tokio::sync::Mutex;
 use tower::{Service, ServiceExt}; // for Service::ready()
 
+use crate::transport::ServerMessages;
+
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
 
 /// Error type for MCP client operations.
@@ -63,9 +69,14 @@ pub struct ClientInfo {
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct ClientCapabilities {
-    // Add fields as needed. For now, empty capabilities are fine.
+    /// Set when the client answers `sampling/createMessage` requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingCapability>,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+pub struct SamplingCapability {}
+
 #[derive(Serialize, Deserialize)]
 pub struct InitializeParams {
     #[serde(rename = "protocolVersion")]
@@ -75,6 +86,123 @@ pub struct InitializeParams {
     pub client_info: ClientInfo,
 }
 
+/// Called with each notification the server sends with the method it was registered for
+pub type NotificationHandler = Arc<dyn Fn(JsonRpcNotification) + Send + Sync>;
+
+/// Answers a request the server sends, with its result or the error to respond with
+pub type RequestHandler =
+    Arc<dyn Fn(JsonRpcRequest) -> BoxFuture<'static, Result<Value, ErrorData>> + Send + Sync>;
+
+/// Called with each progress notification the server sends about a request
+pub type ProgressHandler = Arc<dyn Fn(ProgressNotificationParams) + Send + Sync>;
+
+/// The progress handlers of the requests still waiting for their results, by progress token
+#[derive(Default)]
+struct ProgressHandlers {
+    handlers: StdMutex<HashMap<ProgressToken, ProgressHandler>>,
+    next_token: AtomicU64,
+}
+
+impl ProgressHandlers {
+    /// Pick a token for a request and call `handler` with its progress until the returned
+    /// registration is dropped
+    fn register(self: &Arc<Self>, handler: ProgressHandler) -> ProgressRegistration {
+        let token = ProgressToken::Number(self.next_token.fetch_add(1, Ordering::SeqCst) as i64);
+        self.handlers.lock().unwrap().insert(token.clone(), handler);
+        ProgressRegistration {
+            handlers: Arc::clone(self),
+            token,
+        }
+    }
+
+    fn notify(&self, notification: JsonRpcNotification) {
+        let params: ProgressNotificationParams =
+            match serde_json::from_value(notification.params.unwrap_or_default()) {
+                Ok(params) => params,
+                Err(e) => {
+                    tracing::debug!(error = %e, "Invalid progress notification from server");
+                    return;
+                }
+            };
+        let handler = self
+            .handlers
+            .lock()
+            .unwrap()
+            .get(&params.progress_token)
+            .cloned();
+        match handler {
+            Some(handler) => handler(params),
+            None => {
+                tracing::debug!(token = ?params.progress_token, "Progress for no pending request")
+            }
+        }
+    }
+}
+
+/// Keeps a progress handler registered for as long as its request runs
+struct ProgressRegistration {
+    handlers: Arc<ProgressHandlers>,
+    token: ProgressToken,
+}
+
+impl Drop for ProgressRegistration {
+    fn drop(&mut self) {
+        self.handlers.handlers.lock().unwrap().remove(&self.token);
+    }
+}
+
+/// The handlers for messages the server sends on its own, by method
+#[derive(Default)]
+struct ServerHandlers {
+    notifications: RwLock<HashMap<String, Vec<NotificationHandler>>>,
+    requests: RwLock<HashMap<String, RequestHandler>>,
+}
+
+impl ServerHandlers {
+    fn notify(&self, notification: JsonRpcNotification) {
+        let handlers = self
+            .notifications
+            .read()
+            .unwrap()
+            .get(&notification.method)
+            .cloned()
+            .unwrap_or_default();
+        if handlers.is_empty() {
+            tracing::debug!(method = %notification.method, "Unhandled notification from server");
+        }
+        for handler in handlers {
+            handler(notification.clone());
+        }
+    }
+
+    /// The response to a request from the server, for the client to send back
+    async fn answer(&self, request: JsonRpcRequest) -> JsonRpcMessage {
+        let id = request.id;
+        let handler = self.requests.read().unwrap().get(&request.method).cloned();
+        let result = match handler {
+            Some(handler) => handler(request).await,
+            None => Err(ErrorData {
+                code: METHOD_NOT_FOUND,
+                message: format!("Method not found: {}", request.method),
+                data: None,
+            }),
+        };
+        match result {
+            Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(result),
+                error: None,
+            }),
+            Err(error) => JsonRpcMessage::Error(JsonRpcError {
+                jsonrpc: "2.0".to_string(),
+                id,
+                error,
+            }),
+        }
+    }
+}
+
 #[async_trait::async_trait]
 pub trait McpClientTrait: Send + Sync {
     async fn initialize(
@@ -94,9 +222,83 @@ pub trait McpClientTrait: Send + Sync {
 
     async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, Error>;
 
+    /// Call a tool, passing the progress the server reports while it runs to `on_progress`
+    ///
+    /// Clients that cannot follow progress just call the tool.
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        on_progress: ProgressHandler,
+    ) -> Result<CallToolResult, Error> {
+        let _ = on_progress;
+        self.call_tool(name, arguments).await
+    }
+
     async fn list_prompts(&self, next_cursor: Option<String>) -> Result<ListPromptsResult, Error>;
 
     async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult, Error>;
+
+    /// Call `handler` with every notification the server sends with `method`
+    fn on_notification(&self, method: &str, handler: NotificationHandler);
+
+    /// Answer the requests the server sends with `method` with `handler`, replacing the
+    /// handler registered before
+    fn on_request(&self, method: &str, handler: RequestHandler);
+}
+
+/// Sends `notifications/cancelled` for a request when dropped while still armed, so the
+/// server stops working on requests we gave up on (the caller's future was dropped or the
+/// request timed out).
+struct CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage, Response = JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    service: Option<S>,
+    request_id: u64,
+}
+
+impl<S> CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage, Response = JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    /// The request completed, so there is nothing to cancel
+    fn disarm(&mut self) {
+        self.service = None;
+    }
+}
+
+impl<S> Drop for CancelOnDrop<S>
+where
+    S: Service<JsonRpcMessage, Response = JsonRpcMessage> + Send + 'static,
+    S::Future: Send,
+{
+    fn drop(&mut self) {
+        let Some(mut service) = self.service.take() else {
+            return;
+        };
+        // Without a runtime there is no way to reach the server anymore
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/cancelled".to_string(),
+            params: Some(serde_json::json!({
+                "requestId": self.request_id,
+                "reason": "The client is no longer waiting for this request",
+            })),
+        });
+        handle.spawn(async move {
+            let ready = service.ready().await.is_ok();
+            if !ready || service.call(notification).await.is_err() {
+                tracing::debug!("Failed to send notifications/cancelled");
+            }
+        });
+    }
 }
 
 /// The MCP client is the interface for MCP operations.
@@ -110,6 +312,8 @@ where
     next_id: AtomicU64,
     server_capabilities: Option<ServerCapabilities>,
     server_info: Option<Implementation>,
+    handlers: Arc<ServerHandlers>,
+    progress: Arc<ProgressHandlers>,
 }
 
 impl<S> McpClient<S>
@@ -119,12 +323,55 @@ where
     S::Future: Send,
 {
     pub fn new(service: S) -> Self {
-        Self {
+        let client = Self {
             service: Mutex::new(service),
             next_id: AtomicU64::new(1),
             server_capabilities: None,
             server_info: None,
-        }
+            handlers: Arc::new(ServerHandlers::default()),
+            progress: Arc::new(ProgressHandlers::default()),
+        };
+        let progress = Arc::clone(&client.progress);
+        client.on_notification(
+            "notifications/progress",
+            Arc::new(move |notification| progress.notify(notification)),
+        );
+        // Servers may check the client is still there at any time
+        client.on_request(
+            "ping",
+            Arc::new(|_| Box::pin(async { Ok(serde_json::json!({})) })),
+        );
+        client
+    }
+
+    /// Dispatch the requests and notifications the server sends on its own to the handlers
+    ///
+    /// Requests are answered concurrently, each on its own task, through the client's service.
+    pub fn with_server_messages(mut self, mut messages: ServerMessages) -> Self {
+        let handlers = Arc::clone(&self.handlers);
+        let service = self.service.get_mut().clone();
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                match message {
+                    JsonRpcMessage::Notification(notification) => handlers.notify(notification),
+                    JsonRpcMessage::Request(request) => {
+                        let handlers = Arc::clone(&handlers);
+                        let mut service = service.clone();
+                        tokio::spawn(async move {
+                            let method = request.method.clone();
+                            let response = handlers.answer(request).await;
+                            let ready = service.ready().await.is_ok();
+                            let sent = ready && service.call(response).await.is_ok();
+                            if !sent {
+                                tracing::warn!(%method, "Failed to answer a server request");
+                            }
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        });
+        self
     }
 
     /// Send a JSON-RPC request and check we don't get an error response.
@@ -132,37 +379,48 @@ where
     where
         R: for<'de> Deserialize<'de>,
     {
-        let mut service = self.service.lock().await;
+        // Each request works on its own clone, so requests do not wait for each other
+        let mut service = self.service.lock().await.clone();
         service.ready().await.map_err(|_| Error::NotReady)?;
 
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request_id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let request = JsonRpcMessage::Request(JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
-            id: Some(id),
+            id: Some(request_id),
             method: method.to_string(),
             params: Some(params.clone()),
         });
 
-        let response_msg = service
-            .call(request)
-            .await
-            .map_err(|e| Error::McpServerError {
-                server: self
-                    .server_info
-                    .as_ref()
-                    .map(|s| s.name.clone())
-                    .unwrap_or("".to_string()),
-                method: method.to_string(),
-                // we don't need include params because it can be really large
-                source: Box::new(e.into()),
-            })?;
+        // The initialize request must never be cancelled, everything else is cancelled
+        // on the server if we stop waiting for it before the response arrives
+        let mut cancel_guard = (method != "initialize").then(|| CancelOnDrop {
+            service: Some(service.clone()),
+            request_id,
+        });
+
+        let response_msg = service.call(request).await;
+        if response_msg.is_ok() {
+            if let Some(guard) = cancel_guard.as_mut() {
+                guard.disarm();
+            }
+        }
+        let response_msg = response_msg.map_err(|e| Error::McpServerError {
+            server: self
+                .server_info
+                .as_ref()
+                .map(|s| s.name.clone())
+                .unwrap_or("".to_string()),
+            method: method.to_string(),
+            // we don't need include params because it can be really large
+            source: Box::new(e.into()),
+        })?;
 
         match response_msg {
             JsonRpcMessage::Response(JsonRpcResponse {
                 id, result, error, ..
             }) => {
                 // Verify id matches
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcResponse".to_string(),
                     ));
@@ -179,7 +437,7 @@ where
                 }
             }
             JsonRpcMessage::Error(JsonRpcError { id, error, .. }) => {
-                if id != Some(self.next_id.load(Ordering::SeqCst) - 1) {
+                if id != Some(request_id) {
                     return Err(Error::UnexpectedResponse(
                         "id mismatch for JsonRpcError".to_string(),
                     ));
@@ -200,7 +458,7 @@ where
 
     /// Send a JSON-RPC notification.
     async fn send_notification(&self, method: &str, params: Value) -> Result<(), Error> {
-        let mut service = self.service.lock().await;
+        let mut service = self.service.lock().await.clone();
         service.ready().await.map_err(|_| Error::NotReady)?;
 
         let notification = JsonRpcMessage::Notification(JsonRpcNotification {
@@ -226,6 +484,23 @@ where
         Ok(())
     }
 
+    async fn send_tool_call(&self, params: Value) -> Result<CallToolResult, Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        // If tools is not supported, return an error
+        if self.server_capabilities.as_ref().unwrap().tools.is_none() {
+            return Err(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                message: "Server does not support 'tools' capability".to_string(),
+            });
+        }
+
+        // TODO ERROR: check that if there is an error, we send back is_error: true with msg
+        // https://modelcontextprotocol.io/docs/concepts/tools#error-handling-2
+        self.send_request("tools/call", params).await
+    }
+
     // Check if the client has completed initialization
     fn completed_initialization(&self) -> bool {
         self.server_capabilities.is_some()
@@ -333,22 +608,23 @@ where
     }
 
     async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, Error> {
-        if !self.completed_initialization() {
-            return Err(Error::NotInitialized);
-        }
-        // If tools is not supported, return an error
-        if self.server_capabilities.as_ref().unwrap().tools.is_none() {
-            return Err(Error::RpcError {
-                code: METHOD_NOT_FOUND,
-                message: "Server does not support 'tools' capability".to_string(),
-            });
-        }
-
         let params = serde_json::json!({ "name": name, "arguments": arguments });
+        self.send_tool_call(params).await
+    }
 
-        // TODO ERROR: check that if there is an error, we send back is_error: true with msg
-        // https://modelcontextprotocol.io/docs/concepts/tools#error-handling-2
-        self.send_request("tools/call", params).await
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        on_progress: ProgressHandler,
+    ) -> Result<CallToolResult, Error> {
+        let registration = self.progress.register(on_progress);
+        let params = serde_json::json!({
+            "name": name,
+            "arguments": arguments,
+            "_meta": { "progressToken": registration.token },
+        });
+        self.send_tool_call(params).await
     }
 
     async fn list_prompts(&self, next_cursor: Option<String>) -> Result<ListPromptsResult, Error> {
@@ -388,4 +664,149 @@ where
 
         self.send_request("prompts/get", params).await
     }
+
+    fn on_notification(&self, method: &str, handler: NotificationHandler) {
+        self.handlers
+            .notifications
+            .write()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push(handler);
+    }
+
+    fn on_request(&self, method: &str, handler: RequestHandler) {
+        self.handlers
+            .requests
+            .write()
+            .unwrap()
+            .insert(method.to_string(), handler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Error as TransportError;
+    use tokio::sync::mpsc;
+
+    fn request(id: u64, method: &str) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: method.to_string(),
+            params: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_server_messages_dispatch() {
+        // Everything the client sends ends up in `sent`
+        let (sent_tx, mut sent) = mpsc::unbounded_channel();
+        let service = tower::service_fn(move |message: JsonRpcMessage| {
+            let sent_tx = sent_tx.clone();
+            async move {
+                let _ = sent_tx.send(message);
+                Ok::<_, TransportError>(JsonRpcMessage::Nil)
+            }
+        });
+        let (server_tx, server_rx) = mpsc::unbounded_channel();
+        let client = McpClient::new(service).with_server_messages(server_rx);
+
+        let (notified_tx, mut notified) = mpsc::unbounded_channel();
+        client.on_notification(
+            "notifications/tools/list_changed",
+            Arc::new(move |notification| {
+                let _ = notified_tx.send(notification.method);
+            }),
+        );
+        server_tx
+            .send(JsonRpcMessage::Notification(JsonRpcNotification {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/tools/list_changed".to_string(),
+                params: None,
+            }))
+            .unwrap();
+        assert_eq!(
+            notified.recv().await.unwrap(),
+            "notifications/tools/list_changed"
+        );
+
+        server_tx.send(request(7, "ping")).unwrap();
+        match sent.recv().await.unwrap() {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, Some(7));
+                assert_eq!(response.result, Some(serde_json::json!({})));
+            }
+            other => panic!("Expected a response, got {:?}", other),
+        }
+
+        server_tx.send(request(8, "roots/list")).unwrap();
+        match sent.recv().await.unwrap() {
+            JsonRpcMessage::Error(error) => {
+                assert_eq!(error.id, Some(8));
+                assert_eq!(error.error.code, METHOD_NOT_FOUND);
+            }
+            other => panic!("Expected an error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_with_progress() {
+        let (server_tx, server_rx) = mpsc::unbounded_channel();
+        // Lets the server answer once the client has seen its progress
+        let (progressed_tx, progressed) = mpsc::unbounded_channel::<ProgressNotificationParams>();
+        let progressed = Arc::new(Mutex::new(progressed));
+        let service = tower::service_fn(move |message: JsonRpcMessage| {
+            let server_tx = server_tx.clone();
+            let progressed = Arc::clone(&progressed);
+            async move {
+                let JsonRpcMessage::Request(request) = message else {
+                    return Ok::<_, TransportError>(JsonRpcMessage::Nil);
+                };
+                let token = request.params.unwrap()["_meta"]["progressToken"].clone();
+                server_tx
+                    .send(JsonRpcMessage::Notification(JsonRpcNotification {
+                        jsonrpc: "2.0".to_string(),
+                        method: "notifications/progress".to_string(),
+                        params: Some(serde_json::json!({
+                            "progressToken": token,
+                            "progress": 1,
+                            "message": "Halfway"
+                        })),
+                    }))
+                    .unwrap();
+                progressed.lock().await.recv().await;
+                Ok(JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(serde_json::json!({"content": []})),
+                    error: None,
+                }))
+            }
+        });
+        let mut client = McpClient::new(service).with_server_messages(server_rx);
+        client.server_capabilities = Some(ServerCapabilities {
+            prompts: None,
+            resources: None,
+            tools: Some(mcp_core::protocol::ToolsCapability { list_changed: None }),
+        });
+
+        let seen = Arc::new(StdMutex::new(Vec::new()));
+        let on_progress: ProgressHandler = {
+            let seen = Arc::clone(&seen);
+            Arc::new(move |params: ProgressNotificationParams| {
+                seen.lock().unwrap().push(params.message.clone());
+                let _ = progressed_tx.send(params);
+            })
+        };
+        client
+            .call_tool_with_progress("build", serde_json::json!({}), on_progress)
+            .await
+            .unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![Some("Halfway".to_string())]);
+        // Progress for finished calls goes nowhere
+        assert!(client.progress.handlers.lock().unwrap().is_empty());
+    }
 }
@@ -2,6 +2,11 @@ pub mod client;
 pub mod service;
 pub mod transport;
 
-pub use client::{ClientCapabilities, ClientInfo, Error, McpClient, McpClientTrait};
+pub use client::{
+    ClientCapabilities, ClientInfo, Error, McpClient, McpClientTrait, NotificationHandler,
+    ProgressHandler, RequestHandler, SamplingCapability,
+};
 pub use service::McpService;
-pub use transport::{SseTransport, StdioTransport, Transport, TransportHandle};
+pub use transport::{
+    SseTransport, StdioTransport, StreamableHttpTransport, Transport, TransportHandle,
+};
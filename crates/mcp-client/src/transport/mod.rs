@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use mcp_core::protocol::JsonRpcMessage;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot, RwLock};
 
@@ -20,7 +21,7 @@ pub enum Error {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
-    #[error("Unsupported message type. JsonRpcMessage can only be Request or Notification.")]
+    #[error("Unsupported message type. JsonRpcMessage can only be Request, Notification or a response to the server.")]
     UnsupportedMessage,
 
     #[error("Stdio process error: {0}")]
@@ -29,6 +30,9 @@ pub enum Error {
     #[error("SSE connection error: {0}")]
     SseConnection(String),
 
+    #[error("Streamable HTTP error: {0}")]
+    StreamableHttpError(String),
+
     #[error("HTTP error: {status} - {message}")]
     HttpError { status: u16, message: String },
 }
@@ -58,6 +62,45 @@ pub trait Transport {
 #[async_trait]
 pub trait TransportHandle: Send + Sync + Clone + 'static {
     async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error>;
+
+    /// Take the requests and notifications the server sends on its own
+    ///
+    /// All clones of a handle share them, so only the first call gets them.
+    fn server_messages(&self) -> Option<ServerMessages>;
+}
+
+/// Requests and notifications sent by the server, in the order they arrived
+pub type ServerMessages = mpsc::UnboundedReceiver<JsonRpcMessage>;
+
+/// The server messages of a transport, kept by its handles until a client takes them
+pub type SharedServerMessages = Arc<Mutex<Option<ServerMessages>>>;
+
+pub fn server_messages_channel() -> (mpsc::UnboundedSender<JsonRpcMessage>, SharedServerMessages) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (tx, Arc::new(Mutex::new(Some(rx))))
+}
+
+/// Route a message read from the server
+///
+/// Responses and errors go to the request waiting for them, and the id they answer is
+/// returned. Anything else the server sent on its own goes to `server_messages`.
+pub async fn route_incoming(
+    message: JsonRpcMessage,
+    pending_requests: &PendingRequests,
+    server_messages: &mpsc::UnboundedSender<JsonRpcMessage>,
+) -> Option<u64> {
+    let id = match &message {
+        JsonRpcMessage::Response(response) => response.id,
+        JsonRpcMessage::Error(error) => error.id,
+        JsonRpcMessage::Request(_) | JsonRpcMessage::Notification(_) => {
+            // Nobody may be listening, and then there is no one to tell
+            let _ = server_messages.send(message);
+            return None;
+        }
+        JsonRpcMessage::Nil => None,
+    }?;
+    pending_requests.respond(&id.to_string(), Ok(message)).await;
+    Some(id)
 }
 
 // Helper function that contains the common send implementation
@@ -75,15 +118,18 @@ pub async fn send_message(
             sender.send(msg).await.map_err(|_| Error::ChannelClosed)?;
             Ok(response.await.map_err(|_| Error::ChannelClosed)??)
         }
-        JsonRpcMessage::Notification(notification) => {
+        // Notifications, and answers to the server's requests, get no response
+        JsonRpcMessage::Notification(_)
+        | JsonRpcMessage::Response(_)
+        | JsonRpcMessage::Error(_) => {
             let msg = TransportMessage {
-                message: JsonRpcMessage::Notification(notification),
+                message,
                 response_tx: None,
             };
             sender.send(msg).await.map_err(|_| Error::ChannelClosed)?;
             Ok(JsonRpcMessage::Nil)
         }
-        _ => Err(Error::UnsupportedMessage),
+        JsonRpcMessage::Nil => Err(Error::UnsupportedMessage),
     }
 }
 
@@ -133,3 +179,6 @@ pub use stdio::StdioTransport;
 
 pub mod sse;
 pub use sse::SseTransport;
+
+pub mod streamable_http;
+pub use streamable_http::StreamableHttpTransport;
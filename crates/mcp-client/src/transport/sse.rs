@@ -1,4 +1,7 @@
-use crate::transport::{Error, PendingRequests, TransportMessage};
+use crate::transport::{
+    route_incoming, server_messages_channel, Error, PendingRequests, ServerMessages,
+    SharedServerMessages, TransportMessage,
+};
 use async_trait::async_trait;
 use eventsource_client::{Client, SSE};
 use futures::TryStreamExt;
@@ -24,6 +27,8 @@ pub struct SseActor {
     receiver: mpsc::Receiver<TransportMessage>,
     /// Map of request-id -> oneshot sender
     pending_requests: Arc<PendingRequests>,
+    /// Where requests and notifications from the server go
+    server_messages: mpsc::UnboundedSender<JsonRpcMessage>,
     /// Base SSE URL
     sse_url: String,
     /// For sending HTTP POST requests
@@ -36,12 +41,14 @@ impl SseActor {
     pub fn new(
         receiver: mpsc::Receiver<TransportMessage>,
         pending_requests: Arc<PendingRequests>,
+        server_messages: mpsc::UnboundedSender<JsonRpcMessage>,
         sse_url: String,
         post_endpoint: Arc<RwLock<Option<String>>>,
     ) -> Self {
         Self {
             receiver,
             pending_requests,
+            server_messages,
             sse_url,
             post_endpoint,
             http_client: HttpClient::new(),
@@ -56,6 +63,7 @@ impl SseActor {
             Self::handle_incoming_messages(
                 self.sse_url.clone(),
                 Arc::clone(&self.pending_requests),
+                self.server_messages,
                 Arc::clone(&self.post_endpoint)
             ),
             Self::handle_outgoing_messages(
@@ -70,10 +78,12 @@ impl SseActor {
     /// Continuously reads SSE events from `sse_url`.
     /// - If an `endpoint` event is received, store it in `post_endpoint`.
     /// - If a `message` event is received, parse it as `JsonRpcMessage`
-    ///   and respond to pending requests if it's a `Response`.
+    ///   and respond to pending requests if it's a `Response`, or pass it on to
+    ///   `server_messages` if the server sent it on its own.
     async fn handle_incoming_messages(
         sse_url: String,
         pending_requests: Arc<PendingRequests>,
+        server_messages: mpsc::UnboundedSender<JsonRpcMessage>,
         post_endpoint: Arc<RwLock<Option<String>>>,
     ) {
         let client = match eventsource_client::ClientBuilder::for_url(&sse_url) {
@@ -111,23 +121,7 @@ impl SseActor {
                     // Attempt to parse the SSE data as a JsonRpcMessage
                     match serde_json::from_str::<JsonRpcMessage>(&e.data) {
                         Ok(message) => {
-                            match &message {
-                                JsonRpcMessage::Response(response) => {
-                                    if let Some(id) = &response.id {
-                                        pending_requests
-                                            .respond(&id.to_string(), Ok(message))
-                                            .await;
-                                    }
-                                }
-                                JsonRpcMessage::Error(error) => {
-                                    if let Some(id) = &error.id {
-                                        pending_requests
-                                            .respond(&id.to_string(), Ok(message))
-                                            .await;
-                                    }
-                                }
-                                _ => {} // TODO: Handle other variants (Request, etc.)
-                            }
+                            route_incoming(message, &pending_requests, &server_messages).await;
                         }
                         Err(err) => {
                             warn!("Failed to parse SSE message: {err}");
@@ -223,6 +217,7 @@ impl SseActor {
 #[derive(Clone)]
 pub struct SseTransportHandle {
     sender: mpsc::Sender<TransportMessage>,
+    server_messages: SharedServerMessages,
 }
 
 #[async_trait::async_trait]
@@ -230,6 +225,10 @@ impl TransportHandle for SseTransportHandle {
     async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
         send_message(&self.sender, message).await
     }
+
+    fn server_messages(&self) -> Option<ServerMessages> {
+        self.server_messages.lock().unwrap().take()
+    }
 }
 
 #[derive(Clone)]
@@ -282,11 +281,13 @@ impl Transport for SseTransport {
 
         let post_endpoint: Arc<RwLock<Option<String>>> = Arc::new(RwLock::new(None));
         let post_endpoint_clone = Arc::clone(&post_endpoint);
+        let (server_messages_tx, server_messages) = server_messages_channel();
 
         // Build the actor
         let actor = SseActor::new(
             rx,
             Arc::new(PendingRequests::new()),
+            server_messages_tx,
             self.sse_url.clone(),
             post_endpoint,
         );
@@ -301,7 +302,10 @@ impl Transport for SseTransport {
         )
         .await
         {
-            Ok(_) => Ok(SseTransportHandle { sender: tx }),
+            Ok(_) => Ok(SseTransportHandle {
+                sender: tx,
+                server_messages,
+            }),
             Err(e) => Err(Error::SseConnection(e.to_string())),
         }
     }
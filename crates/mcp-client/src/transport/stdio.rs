@@ -7,7 +7,10 @@ use mcp_core::protocol::JsonRpcMessage;
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::{mpsc, Mutex};
 
-use super::{send_message, Error, PendingRequests, Transport, TransportHandle, TransportMessage};
+use super::{
+    route_incoming, send_message, server_messages_channel, Error, PendingRequests, ServerMessages,
+    SharedServerMessages, Transport, TransportHandle, TransportMessage,
+};
 
 /// A `StdioTransport` uses a child process's stdin/stdout as a communication channel.
 ///
@@ -15,6 +18,8 @@ use super::{send_message, Error, PendingRequests, Transport, TransportHandle, Tr
 pub struct StdioActor {
     receiver: mpsc::Receiver<TransportMessage>,
     pending_requests: Arc<PendingRequests>,
+    /// Where requests and notifications from the server go
+    server_messages: mpsc::UnboundedSender<JsonRpcMessage>,
     _process: Child, // we store the process to keep it alive
     error_sender: mpsc::Sender<Error>,
     stdin: ChildStdin,
@@ -26,7 +31,11 @@ impl StdioActor {
     pub async fn run(mut self) {
         use tokio::pin;
 
-        let incoming = Self::handle_incoming_messages(self.stdout, self.pending_requests.clone());
+        let incoming = Self::handle_incoming_messages(
+            self.stdout,
+            self.pending_requests.clone(),
+            self.server_messages,
+        );
         let outgoing = Self::handle_outgoing_messages(
             self.receiver,
             self.stdin,
@@ -71,7 +80,11 @@ impl StdioActor {
         self.pending_requests.clear().await;
     }
 
-    async fn handle_incoming_messages(stdout: ChildStdout, pending_requests: Arc<PendingRequests>) {
+    async fn handle_incoming_messages(
+        stdout: ChildStdout,
+        pending_requests: Arc<PendingRequests>,
+        server_messages: mpsc::UnboundedSender<JsonRpcMessage>,
+    ) {
         let mut reader = BufReader::new(stdout);
         let mut line = String::new();
         loop {
@@ -87,19 +100,7 @@ impl StdioActor {
                             "Received incoming message"
                         );
 
-                        match &message {
-                            JsonRpcMessage::Response(response) => {
-                                if let Some(id) = &response.id {
-                                    pending_requests.respond(&id.to_string(), Ok(message)).await;
-                                }
-                            }
-                            JsonRpcMessage::Error(error) => {
-                                if let Some(id) = &error.id {
-                                    pending_requests.respond(&id.to_string(), Ok(message)).await;
-                                }
-                            }
-                            _ => {} // TODO: Handle other variants (Request, etc.)
-                        }
+                        route_incoming(message, &pending_requests, &server_messages).await;
                     }
                     line.clear();
                 }
@@ -159,6 +160,7 @@ impl StdioActor {
 pub struct StdioTransportHandle {
     sender: mpsc::Sender<TransportMessage>,
     error_receiver: Arc<Mutex<mpsc::Receiver<Error>>>,
+    server_messages: SharedServerMessages,
 }
 
 #[async_trait::async_trait]
@@ -169,6 +171,10 @@ impl TransportHandle for StdioTransportHandle {
         self.check_for_errors().await?;
         result
     }
+
+    fn server_messages(&self) -> Option<ServerMessages> {
+        self.server_messages.lock().unwrap().take()
+    }
 }
 
 impl StdioTransportHandle {
@@ -252,10 +258,12 @@ impl Transport for StdioTransport {
         let (process, stdin, stdout, stderr) = self.spawn_process().await?;
         let (message_tx, message_rx) = mpsc::channel(32);
         let (error_tx, error_rx) = mpsc::channel(1);
+        let (server_messages_tx, server_messages) = server_messages_channel();
 
         let actor = StdioActor {
             receiver: message_rx,
             pending_requests: Arc::new(PendingRequests::new()),
+            server_messages: server_messages_tx,
             _process: process,
             error_sender: error_tx,
             stdin,
@@ -268,6 +276,7 @@ impl Transport for StdioTransport {
         let handle = StdioTransportHandle {
             sender: message_tx,
             error_receiver: Arc::new(Mutex::new(error_rx)),
+            server_messages,
         };
         Ok(handle)
     }
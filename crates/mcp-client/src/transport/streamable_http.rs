@@ -0,0 +1,538 @@
+use crate::transport::{
+    route_incoming, server_messages_channel, Error, PendingRequests, ServerMessages,
+    SharedServerMessages, TransportMessage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use mcp_core::protocol::{JsonRpcMessage, JsonRpcRequest};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{Client as HttpClient, Response, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::Duration;
+use tracing::warn;
+
+use super::{send_message, Transport, TransportHandle};
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+/// How often a broken response stream is resumed before its requests fail
+const MAX_RESUME_ATTEMPTS: usize = 3;
+/// How long to wait before reopening the stream of server-initiated messages
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// A single event read from an SSE stream
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Splits an SSE byte stream into events, however the bytes are chunked
+#[derive(Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    id: Option<String>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed the next chunk of the stream, returning the events it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            // A blank line ends an event, a leading colon marks a comment
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        id: self.id.take(),
+                        event: self.event.take(),
+                        data: std::mem::take(&mut self.data).join("\n"),
+                    });
+                }
+                self.id = None;
+                self.event = None;
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value).to_string();
+            match field {
+                "id" => self.id = Some(value),
+                "event" => self.event = Some(value),
+                "data" => self.data.push(value),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// The endpoint and the session shared by the actor's tasks
+struct Connection {
+    http_client: HttpClient,
+    uri: String,
+    headers: HeaderMap,
+    session_id: RwLock<Option<String>>,
+    /// Where requests and notifications from the server go
+    server_messages: mpsc::UnboundedSender<JsonRpcMessage>,
+}
+
+impl Connection {
+    async fn with_session(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let request = request.headers(self.headers.clone());
+        match self.session_id.read().await.as_ref() {
+            Some(id) => request.header(SESSION_ID_HEADER, id),
+            None => request,
+        }
+    }
+
+    async fn post(&self, message: &JsonRpcMessage) -> Result<Response, Error> {
+        let request = self
+            .http_client
+            .post(&self.uri)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(message)?);
+        let response = self
+            .with_session(request)
+            .await
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+        self.check_status(response).await
+    }
+
+    async fn get(&self, last_event_id: Option<&str>) -> Result<Response, Error> {
+        let mut request = self
+            .http_client
+            .get(&self.uri)
+            .header(ACCEPT, "text/event-stream");
+        if let Some(id) = last_event_id {
+            request = request.header(LAST_EVENT_ID_HEADER, id);
+        }
+        let response = self
+            .with_session(request)
+            .await
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+        self.check_status(response).await
+    }
+
+    async fn delete(&self) -> Result<(), Error> {
+        let Some(id) = self.session_id.write().await.take() else {
+            return Ok(());
+        };
+        let response = self
+            .http_client
+            .delete(&self.uri)
+            .headers(self.headers.clone())
+            .header(SESSION_ID_HEADER, id)
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(e.to_string()))?;
+        // Servers that don't let clients end sessions answer 405
+        if response.status().is_success() || response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            Ok(())
+        } else {
+            Err(Error::HttpError {
+                status: response.status().as_u16(),
+                message: response.status().to_string(),
+            })
+        }
+    }
+
+    async fn check_status(&self, response: Response) -> Result<Response, Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        // The server forgot the session, the client has to initialize again
+        if status == StatusCode::NOT_FOUND && self.session_id.write().await.take().is_some() {
+            return Err(Error::HttpError {
+                status: status.as_u16(),
+                message: "MCP session expired".to_string(),
+            });
+        }
+        let message = response.text().await.unwrap_or_default();
+        Err(Error::HttpError {
+            status: status.as_u16(),
+            message: if message.is_empty() {
+                status.to_string()
+            } else {
+                message
+            },
+        })
+    }
+}
+
+/// The actor POSTs every outgoing message on its own task, so a slow response doesn't hold
+/// up the others, and reads the responses from whichever body the server picked: a JSON
+/// document or an SSE stream. Once a session is established it also keeps a GET stream open
+/// for messages the server sends on its own.
+pub struct StreamableHttpActor {
+    /// Receives messages (requests/notifications) from the handle
+    receiver: mpsc::Receiver<TransportMessage>,
+    /// Map of request-id -> oneshot sender
+    pending_requests: Arc<PendingRequests>,
+    connection: Arc<Connection>,
+}
+
+impl StreamableHttpActor {
+    pub async fn run(mut self) {
+        while let Some(transport_msg) = self.receiver.recv().await {
+            let request_id = match &transport_msg.message {
+                JsonRpcMessage::Request(JsonRpcRequest { id: Some(id), .. }) => Some(*id),
+                _ => None,
+            };
+            if let (Some(id), Some(response_tx)) = (request_id, transport_msg.response_tx) {
+                self.pending_requests
+                    .insert(id.to_string(), response_tx)
+                    .await;
+            }
+
+            tokio::spawn(Self::post_message(
+                Arc::clone(&self.connection),
+                Arc::clone(&self.pending_requests),
+                transport_msg.message,
+                request_id,
+            ));
+        }
+
+        // Every handle is gone, so nobody can use the session anymore
+        if let Err(e) = self.connection.delete().await {
+            warn!("Failed to end the MCP session: {e}");
+        }
+    }
+
+    async fn post_message(
+        connection: Arc<Connection>,
+        pending_requests: Arc<PendingRequests>,
+        message: JsonRpcMessage,
+        request_id: Option<u64>,
+    ) {
+        let had_session = connection.session_id.read().await.is_some();
+        let response = match connection.post(&message).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Streamable HTTP POST failed: {e}");
+                if let Some(id) = request_id {
+                    pending_requests.respond(&id.to_string(), Err(e)).await;
+                }
+                return;
+            }
+        };
+
+        if !had_session {
+            if let Some(id) = response
+                .headers()
+                .get(SESSION_ID_HEADER)
+                .and_then(|id| id.to_str().ok())
+            {
+                tracing::debug!("Started MCP session {id}");
+                *connection.session_id.write().await = Some(id.to_string());
+                tokio::spawn(Self::listen(
+                    Arc::clone(&connection),
+                    Arc::clone(&pending_requests),
+                ));
+            }
+        }
+
+        // Notifications and responses are only acknowledged
+        let Some(id) = request_id else {
+            return;
+        };
+        let is_event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+
+        let result = if is_event_stream {
+            let mut waiting = HashSet::from([id]);
+            Self::read_response_stream(&connection, &pending_requests, response, &mut waiting).await
+        } else {
+            match response.bytes().await {
+                Ok(body) => Self::dispatch_json(&connection, &pending_requests, &body).await,
+                Err(e) => Err(Error::StreamableHttpError(e.to_string())),
+            }
+        };
+        // Whatever went wrong, the request isn't left waiting for a response that won't come
+        let error = match result {
+            Ok(()) => Error::StreamableHttpError("The server sent no response".to_string()),
+            Err(e) => e,
+        };
+        pending_requests.respond(&id.to_string(), Err(error)).await;
+    }
+
+    /// Read an SSE response until every request it answers got its response, resuming the
+    /// stream with the id of the last event seen if it breaks off early
+    async fn read_response_stream(
+        connection: &Connection,
+        pending_requests: &PendingRequests,
+        response: Response,
+        waiting: &mut HashSet<u64>,
+    ) -> Result<(), Error> {
+        let mut last_event_id = None;
+        let mut response = response;
+        let mut attempts = 0;
+        loop {
+            Self::read_events(
+                connection,
+                pending_requests,
+                response,
+                &mut last_event_id,
+                waiting,
+            )
+            .await;
+            if waiting.is_empty() {
+                return Ok(());
+            }
+            let Some(last) = last_event_id.as_deref() else {
+                return Err(Error::StreamableHttpError(
+                    "The response stream ended without a response".to_string(),
+                ));
+            };
+            attempts += 1;
+            if attempts > MAX_RESUME_ATTEMPTS {
+                return Err(Error::StreamableHttpError(
+                    "The response stream could not be resumed".to_string(),
+                ));
+            }
+            tracing::debug!("Resuming the response stream after event {last}");
+            response = connection.get(Some(last)).await?;
+        }
+    }
+
+    /// Dispatch the events of an SSE body until it ends or has answered every request
+    ///
+    /// With no requests to wait for, it reads until the stream ends.
+    async fn read_events(
+        connection: &Connection,
+        pending_requests: &PendingRequests,
+        response: Response,
+        last_event_id: &mut Option<String>,
+        waiting: &mut HashSet<u64>,
+    ) {
+        let answers_requests = !waiting.is_empty();
+        let mut parser = SseParser::default();
+        let mut body = response.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let Ok(chunk) = chunk else {
+                break;
+            };
+            for event in parser.feed(&chunk) {
+                if event.id.is_some() {
+                    last_event_id.clone_from(&event.id);
+                }
+                match serde_json::from_str::<JsonRpcMessage>(&event.data) {
+                    Ok(message) => {
+                        let server_messages = &connection.server_messages;
+                        if let Some(id) =
+                            route_incoming(message, pending_requests, server_messages).await
+                        {
+                            waiting.remove(&id);
+                        }
+                    }
+                    Err(err) => warn!("Failed to parse SSE message: {err}"),
+                }
+            }
+            if answers_requests && waiting.is_empty() {
+                return;
+            }
+        }
+    }
+
+    /// Keep the stream of server-initiated messages open for as long as the session lasts
+    async fn listen(connection: Arc<Connection>, pending_requests: Arc<PendingRequests>) {
+        let mut last_event_id = None;
+        while connection.session_id.read().await.is_some() {
+            match connection.get(last_event_id.as_deref()).await {
+                Ok(response) => {
+                    let mut waiting = HashSet::new();
+                    Self::read_events(
+                        &connection,
+                        &pending_requests,
+                        response,
+                        &mut last_event_id,
+                        &mut waiting,
+                    )
+                    .await;
+                }
+                // Servers without such a stream answer 405
+                Err(Error::HttpError { status: 405, .. }) => return,
+                Err(e) => {
+                    tracing::debug!("Streamable HTTP GET stream unavailable: {e}");
+                    return;
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn dispatch_json(
+        connection: &Connection,
+        pending_requests: &PendingRequests,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        let value: serde_json::Value = serde_json::from_slice(body)?;
+        let messages = match value {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for message in messages {
+            let message = serde_json::from_value(message)?;
+            route_incoming(message, pending_requests, &connection.server_messages).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct StreamableHttpTransportHandle {
+    sender: mpsc::Sender<TransportMessage>,
+    server_messages: SharedServerMessages,
+}
+
+#[async_trait::async_trait]
+impl TransportHandle for StreamableHttpTransportHandle {
+    async fn send(&self, message: JsonRpcMessage) -> Result<JsonRpcMessage, Error> {
+        send_message(&self.sender, message).await
+    }
+
+    fn server_messages(&self) -> Option<ServerMessages> {
+        self.server_messages.lock().unwrap().take()
+    }
+}
+
+/// Talks to a server over the MCP Streamable HTTP transport, at a single endpoint
+#[derive(Clone)]
+pub struct StreamableHttpTransport {
+    uri: String,
+    env: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    connection: Arc<RwLock<Option<Arc<Connection>>>>,
+}
+
+impl StreamableHttpTransport {
+    pub fn new<S: Into<String>>(
+        uri: S,
+        env: HashMap<String, String>,
+        headers: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            uri: uri.into(),
+            env,
+            headers,
+            connection: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn header_map(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        for (key, value) in &self.headers {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| Error::StreamableHttpError(format!("Invalid header name: {key}")))?;
+            let value = HeaderValue::from_str(value).map_err(|_| {
+                Error::StreamableHttpError(format!("Invalid value for header {key}"))
+            })?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpTransport {
+    type Handle = StreamableHttpTransportHandle;
+
+    async fn start(&self) -> Result<Self::Handle, Error> {
+        // Set environment variables
+        for (key, value) in &self.env {
+            std::env::set_var(key, value);
+        }
+
+        // Create a channel for outgoing TransportMessages
+        let (tx, rx) = mpsc::channel(32);
+        let (server_messages_tx, server_messages) = server_messages_channel();
+
+        let connection = Arc::new(Connection {
+            http_client: HttpClient::new(),
+            uri: self.uri.clone(),
+            headers: self.header_map()?,
+            session_id: RwLock::new(None),
+            server_messages: server_messages_tx,
+        });
+        *self.connection.write().await = Some(Arc::clone(&connection));
+
+        // The session starts with the initialize request, so there is nothing to wait for
+        let actor = StreamableHttpActor {
+            receiver: rx,
+            pending_requests: Arc::new(PendingRequests::new()),
+            connection,
+        };
+        tokio::spawn(actor.run());
+
+        Ok(StreamableHttpTransportHandle {
+            sender: tx,
+            server_messages,
+        })
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        match self.connection.write().await.take() {
+            Some(connection) => connection.delete().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"id: 1\ndata: {\"a\":").is_empty());
+        let events =
+            parser.feed(b"1}\n\n: keep-alive\n\nevent: message\r\ndata:x\r\ndata: y\r\n\r\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    id: Some("1".to_string()),
+                    event: None,
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    id: None,
+                    event: Some("message".to_string()),
+                    data: "x\ny".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sse_parser_multibyte_split() {
+        let mut parser = SseParser::default();
+        let bytes = "data: é\n\n".as_bytes();
+        // Split inside the two bytes of the character
+        assert!(parser.feed(&bytes[..7]).is_empty());
+        let events = parser.feed(&bytes[7..]);
+        assert_eq!(events[0].data, "é");
+    }
+}
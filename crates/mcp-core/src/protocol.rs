@@ -4,6 +4,7 @@ use crate::{
     prompt::{Prompt, PromptMessage},
     resource::Resource,
     resource::ResourceContents,
+    role::Role,
     tool::Tool,
 };
 use serde::{Deserialize, Serialize};
@@ -237,11 +238,131 @@ pub struct GetPromptResult {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmptyResult {}
 
+/// A message of the conversation a server asks the client's model to continue
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+/// The parameters of `sampling/createMessage`, with which a server asks the client for a
+/// completion from its model
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    /// Hints on which model to use, left to the client to interpret
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Which context of the client's to include: "none", "thisServer" or "allServers"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: Content,
+    /// The model that produced the message
+    pub model: String,
+    /// Why the model stopped, e.g. "endTurn", "stopSequence" or "maxTokens"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Identifies the request a progress notification is about, chosen by whoever made the request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ProgressToken {
+    String(String),
+    Number(i64),
+}
+
+/// The parameters of `notifications/progress`, sent while a request that asked for progress
+/// with `_meta.progressToken` is still running
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressNotificationParams {
+    pub progress_token: ProgressToken,
+    /// How far along the request is, increasing with every notification
+    pub progress: f64,
+    /// What `progress` counts up to, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_progress_notification_params() {
+        let params: ProgressNotificationParams = serde_json::from_value(json!({
+            "progressToken": 7,
+            "progress": 2,
+            "total": 10
+        }))
+        .unwrap();
+        assert_eq!(params.progress_token, ProgressToken::Number(7));
+        assert_eq!(params.progress, 2.0);
+        assert_eq!(params.total, Some(10.0));
+        assert_eq!(params.message, None);
+
+        let params = ProgressNotificationParams {
+            progress_token: ProgressToken::String("build".to_string()),
+            progress: 1.0,
+            total: None,
+            message: Some("Compiling".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(params).unwrap(),
+            json!({"progressToken": "build", "progress": 1.0, "message": "Compiling"})
+        );
+    }
+
+    #[test]
+    fn test_create_message_params() {
+        let params: CreateMessageParams = serde_json::from_value(json!({
+            "messages": [{"role": "user", "content": {"type": "text", "text": "Hello"}}],
+            "systemPrompt": "Be brief",
+            "maxTokens": 100
+        }))
+        .unwrap();
+        assert_eq!(params.messages[0].role, Role::User);
+        assert_eq!(params.messages[0].content, Content::text("Hello"));
+        assert_eq!(params.system_prompt.as_deref(), Some("Be brief"));
+        assert_eq!(params.max_tokens, 100);
+
+        let result = CreateMessageResult {
+            role: Role::Assistant,
+            content: Content::text("Hi"),
+            model: "model".to_string(),
+            stop_reason: Some("endTurn".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({
+                "role": "assistant",
+                "content": {"type": "text", "text": "Hi"},
+                "model": "model",
+                "stopReason": "endTurn"
+            })
+        );
+    }
+
     #[test]
     fn test_notification_conversion() {
         let raw = JsonRpcRaw {
@@ -35,6 +35,21 @@ pub enum ServerError {
     Timeout(#[from] tower::timeout::error::Elapsed),
 }
 
+#[derive(Error, Debug)]
+pub enum PeerError {
+    #[error("Not connected to a client")]
+    Disconnected,
+
+    #[error("The client does not support {0}")]
+    Unsupported(String),
+
+    #[error("Client returned error {}: {}", .0.code, .0.message)]
+    Client(mcp_core::protocol::ErrorData),
+
+    #[error("JSON serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
 #[derive(Error, Debug)]
 pub enum RouterError {
     #[error("Method not found: {0}")]
@@ -1,20 +1,44 @@
 use std::{
+    collections::{HashMap, VecDeque},
     pin::Pin,
     task::{Context, Poll},
 };
 
+use async_trait::async_trait;
+use futures::future::{abortable, AbortHandle, Aborted};
+use futures::stream::FuturesUnordered;
 use futures::{Future, Stream};
 use mcp_core::protocol::{JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse};
 use pin_project::pin_project;
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tower_service::Service;
+use tracing::Instrument;
 
 mod errors;
-pub use errors::{BoxError, RouterError, ServerError, TransportError};
+pub use errors::{BoxError, PeerError, RouterError, ServerError, TransportError};
+
+pub mod peer;
+pub use peer::Peer;
+
+pub mod progress;
+pub use progress::Progress;
 
 pub mod router;
 pub use router::Router;
 
+pub mod streamable_http;
+pub use streamable_http::StreamableHttpServer;
+
+/// A bidirectional channel of JSON-RPC messages that a [`Server`] runs over
+///
+/// Incoming messages are read from the stream and replies go out through `write_message`.
+#[async_trait]
+pub trait ServerTransport:
+    Stream<Item = Result<JsonRpcMessage, TransportError>> + Unpin + Send
+{
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError>;
+}
+
 /// A transport layer that handles JSON-RPC messages over byte
 #[pin_project]
 pub struct ByteTransport<R, W> {
@@ -25,6 +49,8 @@ pub struct ByteTransport<R, W> {
     reader: BufReader<R>,
     #[pin]
     writer: W,
+    // The line read so far, kept across polls that return Pending before its newline arrives
+    line: Vec<u8>,
 }
 
 impl<R, W> ByteTransport<R, W>
@@ -38,6 +64,7 @@ where
             // allows the buffer to have the capacity to read very large calls
             reader: BufReader::with_capacity(2 * 1024 * 1024, reader),
             writer,
+            line: Vec::new(),
         }
     }
 }
@@ -51,15 +78,15 @@ where
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
-        let mut buf = Vec::new();
 
         let mut reader = this.reader.as_mut();
-        let mut read_future = Box::pin(reader.read_until(b'\n', &mut buf));
+        let mut read_future = Box::pin(reader.read_until(b'\n', this.line));
         match read_future.as_mut().poll(cx) {
             Poll::Ready(Ok(0)) => Poll::Ready(None), // EOF
             Poll::Ready(Ok(_)) => {
+                drop(read_future);
                 // Convert to UTF-8 string
-                let line = match String::from_utf8(buf) {
+                let line = match String::from_utf8(std::mem::take(this.line)) {
                     Ok(s) => s,
                     Err(e) => return Poll::Ready(Some(Err(TransportError::Utf8(e)))),
                 };
@@ -100,12 +127,13 @@ where
     }
 }
 
-impl<R, W> ByteTransport<R, W>
+#[async_trait]
+impl<R, W> ServerTransport for ByteTransport<R, W>
 where
-    R: AsyncRead + Unpin,
-    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
 {
-    pub async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), std::io::Error> {
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError> {
         let json = serde_json::to_string(&msg)?;
         Pin::new(&mut self.writer)
             .write_all(json.as_bytes())
@@ -116,9 +144,17 @@ where
     }
 }
 
+/// How many requests a [`Server`] handles at once unless told otherwise
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 16;
+
 /// The main server type that processes incoming requests
+///
+/// Requests are handled concurrently, up to a limit, and each response is written as soon as
+/// it is ready. Requests beyond the limit wait their turn.
 pub struct Server<S> {
     service: S,
+    peer: Peer,
+    max_concurrent_requests: usize,
 }
 
 impl<S> Server<S>
@@ -128,43 +164,82 @@ where
     S::Future: Send,
 {
     pub fn new(service: S) -> Self {
-        Self { service }
+        Self {
+            service,
+            peer: Peer::default(),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+        }
+    }
+
+    /// Connect `peer` to the client while the server runs, so whoever holds a clone of it can
+    /// make requests of the client
+    pub fn with_peer(mut self, peer: Peer) -> Self {
+        self.peer = peer;
+        self
     }
 
-    // TODO transport trait instead of byte transport if we implement others
-    pub async fn run<R, W>(self, mut transport: ByteTransport<R, W>) -> Result<(), ServerError>
+    /// Handle at most `limit` requests at once, a limit of 1 handles them in order
+    pub fn with_max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = limit.max(1);
+        self
+    }
+
+    pub async fn run<T: ServerTransport>(self, transport: T) -> Result<(), ServerError> {
+        self.run_until(transport, std::future::pending()).await
+    }
+
+    /// Run until the transport closes or `shutdown` completes
+    ///
+    /// Either way the server stops taking requests, drops those still waiting for their turn,
+    /// and returns once the requests it already started have been answered. After `shutdown`
+    /// the client's messages are still read, so running requests can get their answers from it,
+    /// but new requests are refused.
+    pub async fn run_until<T, F>(self, mut transport: T, shutdown: F) -> Result<(), ServerError>
     where
-        R: AsyncRead + Unpin,
-        W: AsyncWrite + Unpin,
+        T: ServerTransport,
+        F: Future<Output = ()>,
     {
         use futures::StreamExt;
-        let mut service = self.service;
+        let Server {
+            mut service,
+            peer,
+            max_concurrent_requests,
+        } = self;
+        let mut outgoing = peer.connect();
+        let mut shutdown = std::pin::pin!(shutdown);
+
+        // Requests waiting for one of the running ones to finish
+        let mut queued: VecDeque<JsonRpcRequest> = VecDeque::new();
+        let mut running = FuturesUnordered::new();
+        // How to cancel each running request, by request id
+        let mut cancels: HashMap<u64, AbortHandle> = HashMap::new();
+        let mut accepting = true;
+        let mut connected = true;
 
         tracing::info!("Server started");
-        while let Some(msg_result) = transport.next().await {
-            let _span = tracing::span!(tracing::Level::INFO, "message_processing").entered();
-            match msg_result {
-                Ok(msg) => {
-                    match msg {
-                        JsonRpcMessage::Request(request) => {
-                            // Serialize request for logging
-                            let id = request.id;
-                            let request_json = serde_json::to_string(&request)
-                                .unwrap_or_else(|_| "Failed to serialize request".to_string());
-
-                            tracing::info!(
-                                request_id = ?id,
-                                method = ?request.method,
-                                json = %request_json,
-                                "Received request"
-                            );
-
-                            // Process the request using our service
-                            let response = match service.call(request).await {
+        let result = async {
+            loop {
+                while running.len() < max_concurrent_requests {
+                    let Some(request) = queued.pop_front() else {
+                        break;
+                    };
+                    let id = request.id;
+                    let (call, cancel) = abortable(peer.scope(service.call(request)));
+                    if let Some(id) = id {
+                        cancels.insert(id, cancel);
+                    }
+                    // An entered span guard is not Send, so the span instruments the handling
+                    let span = tracing::span!(tracing::Level::INFO, "message_processing");
+                    running.push(
+                        async move {
+                            let response = call.await.map(|result| match result {
                                 Ok(resp) => resp,
                                 Err(e) => {
                                     let error_msg = e.into().to_string();
-                                    tracing::error!(error = %error_msg, "Request processing failed");
+                                    tracing::error!(
+                                        error = %error_msg,
+                                        "Request processing failed"
+                                    );
                                     JsonRpcResponse {
                                         jsonrpc: "2.0".to_string(),
                                         id,
@@ -176,73 +251,181 @@ where
                                         }),
                                     }
                                 }
-                            };
-
-                            // Serialize response for logging
-                            let response_json = serde_json::to_string(&response)
-                                .unwrap_or_else(|_| "Failed to serialize response".to_string());
-
-                            tracing::info!(
-                                response_id = ?response.id,
-                                json = %response_json,
-                                "Sending response"
-                            );
-                            // Send the response back
-                            if let Err(e) = transport
-                                .write_message(JsonRpcMessage::Response(response))
-                                .await
-                            {
-                                return Err(ServerError::Transport(TransportError::Io(e)));
-                            }
+                            });
+                            (id, response)
                         }
-                        JsonRpcMessage::Response(_)
-                        | JsonRpcMessage::Notification(_)
-                        | JsonRpcMessage::Nil
-                        | JsonRpcMessage::Error(_) => {
-                            // Ignore responses, notifications and nil messages for now
-                            continue;
+                        .instrument(span),
+                    );
+                }
+
+                if !accepting && running.is_empty() {
+                    return Ok(());
+                }
+
+                let event = tokio::select! {
+                    biased;
+                    _ = &mut shutdown, if accepting => Event::Shutdown,
+                    Some((id, response)) = running.next() => Event::Finished(id, response),
+                    Some(message) = outgoing.recv() => Event::Outgoing(message),
+                    msg_result = transport.next(), if connected => Event::Incoming(msg_result),
+                };
+
+                match event {
+                    Event::Shutdown => {
+                        tracing::info!("Server shutting down");
+                        accepting = false;
+                        for request in queued.drain(..) {
+                            transport
+                                .write_message(shutting_down_message(request.id))
+                                .await?;
                         }
                     }
-                }
-                Err(e) => {
-                    // Convert transport error to JSON-RPC error response
-                    let error = match e {
-                        TransportError::Json(_) | TransportError::InvalidMessage(_) => {
-                            mcp_core::protocol::ErrorData {
-                                code: mcp_core::protocol::PARSE_ERROR,
-                                message: e.to_string(),
-                                data: None,
-                            }
+                    Event::Incoming(None) => {
+                        // The client is gone, but the requests it made still get to finish
+                        connected = false;
+                        accepting = false;
+                        for request in queued.drain(..) {
+                            transport
+                                .write_message(shutting_down_message(request.id))
+                                .await?;
                         }
-                        TransportError::Protocol(_) => mcp_core::protocol::ErrorData {
-                            code: mcp_core::protocol::INVALID_REQUEST,
-                            message: e.to_string(),
-                            data: None,
-                        },
-                        _ => mcp_core::protocol::ErrorData {
-                            code: mcp_core::protocol::INTERNAL_ERROR,
-                            message: e.to_string(),
-                            data: None,
-                        },
-                    };
+                    }
+                    Event::Incoming(Some(Ok(JsonRpcMessage::Request(request)))) if !accepting => {
+                        transport
+                            .write_message(shutting_down_message(request.id))
+                            .await?;
+                    }
+                    Event::Incoming(Some(Ok(JsonRpcMessage::Request(request)))) => {
+                        // Serialize request for logging
+                        let request_json = serde_json::to_string(&request)
+                            .unwrap_or_else(|_| "Failed to serialize request".to_string());
+                        tracing::info!(
+                            request_id = ?request.id,
+                            method = ?request.method,
+                            json = %request_json,
+                            "Received request"
+                        );
 
-                    let error_response = JsonRpcMessage::Error(JsonRpcError {
-                        jsonrpc: "2.0".to_string(),
-                        id: None,
-                        error,
-                    });
+                        if request.method == "initialize" {
+                            peer.initialize(request.params.as_ref());
+                        }
+                        queued.push_back(request);
+                    }
+                    Event::Incoming(Some(Ok(JsonRpcMessage::Notification(notification)))) => {
+                        if notification.method == "notifications/cancelled" {
+                            let id = notification
+                                .params
+                                .as_ref()
+                                .and_then(|params| params.get("requestId"))
+                                .and_then(serde_json::Value::as_u64);
+                            if let Some(id) = id {
+                                tracing::info!(request_id = id, "Request cancelled by the client");
+                                queued.retain(|request| request.id != Some(id));
+                                if let Some(cancel) = cancels.remove(&id) {
+                                    cancel.abort();
+                                }
+                            }
+                        }
+                    }
+                    Event::Incoming(Some(Ok(
+                        message @ (JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_)),
+                    ))) => {
+                        // Answers to the requests the server made through its peer
+                        peer.resolve(message);
+                    }
+                    Event::Incoming(Some(Ok(JsonRpcMessage::Nil))) => {}
+                    Event::Incoming(Some(Err(e))) => {
+                        transport.write_message(transport_error_message(e)).await?;
+                    }
+                    Event::Outgoing(message) => transport.write_message(message).await?,
+                    Event::Finished(id, response) => {
+                        if let Some(id) = id {
+                            cancels.remove(&id);
+                        }
+                        // A cancelled request gets no response
+                        let Ok(response) = response else {
+                            continue;
+                        };
+                        // What the request sent through the peer, like its progress, goes out
+                        // before its response
+                        while let Ok(message) = outgoing.try_recv() {
+                            transport.write_message(message).await?;
+                        }
 
-                    if let Err(e) = transport.write_message(error_response).await {
-                        return Err(ServerError::Transport(TransportError::Io(e)));
+                        // Serialize response for logging
+                        let response_json = serde_json::to_string(&response)
+                            .unwrap_or_else(|_| "Failed to serialize response".to_string());
+                        tracing::info!(
+                            response_id = ?response.id,
+                            json = %response_json,
+                            "Sending response"
+                        );
+                        transport
+                            .write_message(JsonRpcMessage::Response(response))
+                            .await?;
                     }
                 }
             }
         }
+        .await;
 
-        Ok(())
+        peer.disconnect();
+        result
     }
 }
 
+/// What the server loop of [`Server::run_until`] acts on next
+enum Event {
+    Shutdown,
+    Incoming(Option<Result<JsonRpcMessage, TransportError>>),
+    Outgoing(JsonRpcMessage),
+    Finished(Option<u64>, Result<JsonRpcResponse, Aborted>),
+}
+
+/// The error response for a request the server will no longer handle
+fn shutting_down_message(id: Option<u64>) -> JsonRpcMessage {
+    JsonRpcMessage::Response(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: None,
+        error: Some(mcp_core::protocol::ErrorData {
+            code: mcp_core::protocol::INVALID_REQUEST,
+            message: "The server is shutting down".to_string(),
+            data: None,
+        }),
+    })
+}
+
+/// The error response for a message the transport could not read
+fn transport_error_message(e: TransportError) -> JsonRpcMessage {
+    // Convert transport error to JSON-RPC error response
+    let error = match e {
+        TransportError::Json(_) | TransportError::InvalidMessage(_) => {
+            mcp_core::protocol::ErrorData {
+                code: mcp_core::protocol::PARSE_ERROR,
+                message: e.to_string(),
+                data: None,
+            }
+        }
+        TransportError::Protocol(_) => mcp_core::protocol::ErrorData {
+            code: mcp_core::protocol::INVALID_REQUEST,
+            message: e.to_string(),
+            data: None,
+        },
+        _ => mcp_core::protocol::ErrorData {
+            code: mcp_core::protocol::INTERNAL_ERROR,
+            message: e.to_string(),
+            data: None,
+        },
+    };
+
+    JsonRpcMessage::Error(JsonRpcError {
+        jsonrpc: "2.0".to_string(),
+        id: None,
+        error,
+    })
+}
+
 // Define a specific service implementation that we need for any
 // Any router implements this
 pub trait BoundedService:
@@ -267,3 +450,254 @@ impl<T> BoundedService for T where
         + 'static
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+
+    /// Connects a server to the test through channels
+    struct ChannelTransport {
+        incoming: mpsc::UnboundedReceiver<JsonRpcMessage>,
+        outgoing: mpsc::UnboundedSender<JsonRpcMessage>,
+    }
+
+    impl Stream for ChannelTransport {
+        type Item = Result<JsonRpcMessage, TransportError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.incoming.poll_recv(cx).map(|message| message.map(Ok))
+        }
+    }
+
+    #[async_trait]
+    impl ServerTransport for ChannelTransport {
+        async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError> {
+            let _ = self.outgoing.send(msg);
+            Ok(())
+        }
+    }
+
+    /// Answers each request after sleeping for its `ms` parameter
+    fn sleepy_service() -> impl Service<
+        JsonRpcRequest,
+        Response = JsonRpcResponse,
+        Error = BoxError,
+        Future = impl Future<Output = Result<JsonRpcResponse, BoxError>> + Send,
+    > + Send {
+        tower::service_fn(|request: JsonRpcRequest| async move {
+            let ms = request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("ms"))
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            Ok(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(json!({})),
+                error: None,
+            })
+        })
+    }
+
+    fn sleep_request(id: u64, ms: u64) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: "sleep".to_string(),
+            params: Some(json!({"ms": ms})),
+        })
+    }
+
+    fn response_id(message: Option<JsonRpcMessage>) -> Option<u64> {
+        match message {
+            Some(JsonRpcMessage::Response(response)) => response.id,
+            other => panic!("Expected a response, got {:?}", other),
+        }
+    }
+
+    /// The test's end of a running server
+    struct TestClient {
+        tx: mpsc::UnboundedSender<JsonRpcMessage>,
+        rx: mpsc::UnboundedReceiver<JsonRpcMessage>,
+        shutdown: oneshot::Sender<()>,
+        run: tokio::task::JoinHandle<Result<(), ServerError>>,
+    }
+
+    fn start<S>(server: Server<S>) -> TestClient
+    where
+        S: Service<JsonRpcRequest, Response = JsonRpcResponse> + Send + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send,
+    {
+        let (tx, incoming) = mpsc::unbounded_channel();
+        let (outgoing, rx) = mpsc::unbounded_channel();
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let transport = ChannelTransport { incoming, outgoing };
+        let run = tokio::spawn(server.run_until(transport, async {
+            let _ = shutdown_rx.await;
+        }));
+        TestClient {
+            tx,
+            rx,
+            shutdown,
+            run,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_answered_as_they_finish() {
+        let TestClient {
+            tx: client_tx,
+            rx: mut client_rx,
+            shutdown: _shutdown,
+            ..
+        } = start(Server::new(sleepy_service()));
+
+        client_tx.send(sleep_request(1, 300)).unwrap();
+        client_tx.send(sleep_request(2, 0)).unwrap();
+        assert_eq!(response_id(client_rx.recv().await), Some(2));
+        assert_eq!(response_id(client_rx.recv().await), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let server = Server::new(sleepy_service()).with_max_concurrent_requests(1);
+        let TestClient {
+            tx: client_tx,
+            rx: mut client_rx,
+            shutdown: _shutdown,
+            ..
+        } = start(server);
+
+        client_tx.send(sleep_request(1, 300)).unwrap();
+        client_tx.send(sleep_request(2, 0)).unwrap();
+        assert_eq!(response_id(client_rx.recv().await), Some(1));
+        assert_eq!(response_id(client_rx.recv().await), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_gets_no_response() {
+        let TestClient {
+            tx: client_tx,
+            rx: mut client_rx,
+            shutdown: _shutdown,
+            run,
+        } = start(Server::new(sleepy_service()));
+
+        client_tx.send(sleep_request(1, 300)).unwrap();
+        client_tx
+            .send(JsonRpcMessage::Notification(
+                mcp_core::protocol::JsonRpcNotification {
+                    jsonrpc: "2.0".to_string(),
+                    method: "notifications/cancelled".to_string(),
+                    params: Some(json!({"requestId": 1, "reason": "Took too long"})),
+                },
+            ))
+            .unwrap();
+        client_tx.send(sleep_request(2, 0)).unwrap();
+        assert_eq!(response_id(client_rx.recv().await), Some(2));
+
+        // Nothing else comes once the client leaves and the server stops
+        drop(client_tx);
+        run.await.unwrap().unwrap();
+        assert!(client_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_finishes_running_requests() {
+        let TestClient {
+            tx: client_tx,
+            rx: mut client_rx,
+            shutdown,
+            run,
+        } = start(Server::new(sleepy_service()));
+
+        client_tx.send(sleep_request(1, 300)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        shutdown.send(()).unwrap();
+
+        // Requests after the shutdown are refused
+        client_tx.send(sleep_request(2, 0)).unwrap();
+        match client_rx.recv().await {
+            Some(JsonRpcMessage::Response(response)) => {
+                assert_eq!(response.id, Some(2));
+                assert!(response.error.is_some());
+            }
+            other => panic!("Expected a response, got {:?}", other),
+        }
+        assert_eq!(response_id(client_rx.recv().await), Some(1));
+        run.await.unwrap().unwrap();
+        assert!(client_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_refuses_queued_requests() {
+        let server = Server::new(sleepy_service()).with_max_concurrent_requests(1);
+        let TestClient {
+            tx: client_tx,
+            rx: mut client_rx,
+            shutdown,
+            run,
+        } = start(server);
+
+        client_tx.send(sleep_request(1, 300)).unwrap();
+        client_tx.send(sleep_request(2, 0)).unwrap();
+        client_tx.send(sleep_request(3, 0)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        shutdown.send(()).unwrap();
+
+        // The requests still waiting for their turn are refused, the running one finishes
+        for id in [2, 3] {
+            match client_rx.recv().await {
+                Some(JsonRpcMessage::Response(response)) => {
+                    assert_eq!(response.id, Some(id));
+                    assert_eq!(
+                        response.error.map(|error| error.code),
+                        Some(mcp_core::protocol::INVALID_REQUEST)
+                    );
+                }
+                other => panic!("Expected a response, got {:?}", other),
+            }
+        }
+        assert_eq!(response_id(client_rx.recv().await), Some(1));
+        run.await.unwrap().unwrap();
+        assert!(client_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requests_report_progress_through_the_current_peer() {
+        let service = tower::service_fn(|request: JsonRpcRequest| async move {
+            let progress = Progress::new(
+                Some(mcp_core::protocol::ProgressToken::Number(1)),
+                Peer::current().expect("Requests run with the server's peer"),
+            );
+            progress.report(1.0, Some(2.0), None);
+            Ok::<_, BoxError>(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(json!({})),
+                error: None,
+            })
+        });
+        let TestClient {
+            tx: client_tx,
+            rx: mut client_rx,
+            shutdown: _shutdown,
+            ..
+        } = start(Server::new(service));
+
+        client_tx.send(sleep_request(1, 0)).unwrap();
+        match client_rx.recv().await {
+            Some(JsonRpcMessage::Notification(notification)) => {
+                assert_eq!(notification.method, "notifications/progress");
+            }
+            other => panic!("Expected a progress notification, got {:?}", other),
+        }
+        assert_eq!(response_id(client_rx.recv().await), Some(1));
+    }
+}
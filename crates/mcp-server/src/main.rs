@@ -5,7 +5,7 @@ use mcp_core::prompt::{Prompt, PromptArgument};
 use mcp_core::tool::ToolAnnotations;
 use mcp_core::{handler::ToolError, protocol::ServerCapabilities, resource::Resource, tool::Tool};
 use mcp_server::router::{CapabilitiesBuilder, RouterService};
-use mcp_server::{ByteTransport, Router, Server};
+use mcp_server::{ByteTransport, Router, Server, StreamableHttpServer};
 use serde_json::Value;
 use std::{future::Future, pin::Pin, sync::Arc};
 use tokio::{
@@ -228,7 +228,17 @@ async fn main() -> Result<()> {
     tracing::info!("Starting MCP server");
 
     // Create an instance of our counter router
-    let router = RouterService(CounterRouter::new());
+    let counter = CounterRouter::new();
+
+    // `--http [addr]` serves it over Streamable HTTP, every session sharing the one counter
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--http") {
+        let addr = args.next().unwrap_or_else(|| "127.0.0.1:8000".to_string());
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        let server = StreamableHttpServer::new(move |_| RouterService(counter.clone()));
+        return Ok(server.serve(listener).await?);
+    }
+    let router = RouterService(counter);
 
     // Create and run the server
     let server = Server::new(router);
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use mcp_core::protocol::{
+    CreateMessageParams, CreateMessageResult, ErrorData, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest,
+};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::PeerError;
+
+tokio::task_local! {
+    /// The peer of the server handling the current request
+    static CURRENT_PEER: Peer;
+}
+
+type PendingRequests = HashMap<u64, oneshot::Sender<Result<Value, ErrorData>>>;
+
+/// The client on the other end of a [`Server`](crate::Server), for the requests and
+/// notifications the server sends on its own
+///
+/// A router keeps a clone of the peer, and the server running the router connects it to its
+/// transport with [`Server::with_peer`](crate::Server::with_peer). Requests made while no
+/// server is running fail with [`PeerError::Disconnected`].
+#[derive(Clone, Default)]
+pub struct Peer {
+    inner: Arc<PeerInner>,
+}
+
+#[derive(Default)]
+struct PeerInner {
+    outgoing: Mutex<Option<mpsc::UnboundedSender<JsonRpcMessage>>>,
+    pending: Mutex<PendingRequests>,
+    next_id: AtomicU64,
+    /// What the client advertised in its initialize request
+    client_capabilities: Mutex<Option<Value>>,
+}
+
+impl Peer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The peer of the server handling the request this is called for, if any
+    pub fn current() -> Option<Peer> {
+        CURRENT_PEER.try_with(Peer::clone).ok()
+    }
+
+    /// Run `future`, which handles a request, with this peer as [`Peer::current`]
+    pub(crate) fn scope<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        CURRENT_PEER.scope(self.clone(), future)
+    }
+
+    /// Whether the client said it answers `sampling/createMessage` requests
+    pub fn supports_sampling(&self) -> bool {
+        self.inner
+            .client_capabilities
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|capabilities| capabilities.get("sampling"))
+            .is_some_and(|sampling| !sampling.is_null())
+    }
+
+    /// Ask the client for a completion from its model
+    pub async fn create_message(
+        &self,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, PeerError> {
+        if !self.supports_sampling() {
+            return Err(PeerError::Unsupported("sampling".to_string()));
+        }
+        let result = self
+            .request("sampling/createMessage", serde_json::to_value(params)?)
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Send a request to the client and wait for its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, PeerError> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let (tx, rx) = oneshot::channel();
+        self.inner.pending.lock().unwrap().insert(id, tx);
+
+        let request = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: method.to_string(),
+            params: Some(params),
+        });
+        if let Err(e) = self.send(request) {
+            self.inner.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        rx.await
+            .map_err(|_| PeerError::Disconnected)?
+            .map_err(PeerError::Client)
+    }
+
+    /// Send a notification to the client
+    pub fn notify(&self, method: &str, params: Option<Value>) -> Result<(), PeerError> {
+        self.send(JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }))
+    }
+
+    fn send(&self, message: JsonRpcMessage) -> Result<(), PeerError> {
+        let outgoing = self.inner.outgoing.lock().unwrap();
+        outgoing
+            .as_ref()
+            .ok_or(PeerError::Disconnected)?
+            .send(message)
+            .map_err(|_| PeerError::Disconnected)
+    }
+
+    /// Start taking messages for the client, which the server writes to its transport
+    pub(crate) fn connect(&self) -> mpsc::UnboundedReceiver<JsonRpcMessage> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.inner.outgoing.lock().unwrap() = Some(tx);
+        rx
+    }
+
+    /// Fail whatever still waits on the client, once the server stops
+    pub(crate) fn disconnect(&self) {
+        self.inner.outgoing.lock().unwrap().take();
+        self.inner.pending.lock().unwrap().clear();
+        self.inner.client_capabilities.lock().unwrap().take();
+    }
+
+    /// Remember the capabilities from the client's initialize request
+    pub(crate) fn initialize(&self, params: Option<&Value>) {
+        *self.inner.client_capabilities.lock().unwrap() = params
+            .and_then(|params| params.get("capabilities"))
+            .cloned();
+    }
+
+    /// Hand the client's response to the request waiting for it
+    pub(crate) fn resolve(&self, message: JsonRpcMessage) {
+        let (id, result) = match message {
+            JsonRpcMessage::Response(response) => match response.error {
+                Some(error) => (response.id, Err(error)),
+                None => (response.id, Ok(response.result.unwrap_or_default())),
+            },
+            JsonRpcMessage::Error(error) => (error.id, Err(error.error)),
+            _ => return,
+        };
+        let waiting = id.and_then(|id| self.inner.pending.lock().unwrap().remove(&id));
+        match waiting {
+            Some(tx) => {
+                let _ = tx.send(result);
+            }
+            None => tracing::warn!(?id, "Response from the client to no pending request"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::protocol::JsonRpcResponse;
+    use mcp_core::{Content, Role};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_create_message_round_trip() {
+        let peer = Peer::new();
+        let mut outgoing = peer.connect();
+        peer.initialize(Some(&json!({"capabilities": {"sampling": {}}})));
+
+        let client = peer.clone();
+        let answer = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = outgoing.recv().await else {
+                panic!("Expected a request");
+            };
+            assert_eq!(request.method, "sampling/createMessage");
+            client.resolve(JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(json!({
+                    "role": "assistant",
+                    "content": {"type": "text", "text": "Hi"},
+                    "model": "model"
+                })),
+                error: None,
+            }));
+        });
+
+        let result = peer
+            .create_message(CreateMessageParams {
+                messages: vec![],
+                model_preferences: None,
+                system_prompt: None,
+                include_context: None,
+                temperature: None,
+                max_tokens: 10,
+                stop_sequences: None,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        answer.await.unwrap();
+        assert_eq!(result.role, Role::Assistant);
+        assert_eq!(result.content, Content::text("Hi"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_a_connected_client() {
+        let peer = Peer::new();
+        assert!(matches!(
+            peer.request("ping", json!({})).await,
+            Err(PeerError::Disconnected)
+        ));
+
+        // Without the capability, sampling is refused before anything is sent
+        let _outgoing = peer.connect();
+        peer.initialize(Some(&json!({"capabilities": {}})));
+        assert!(!peer.supports_sampling());
+    }
+}
@@ -0,0 +1,81 @@
+use mcp_core::protocol::{ProgressNotificationParams, ProgressToken};
+
+use crate::Peer;
+
+/// Lets a tool tell the client how far along it is, while the client waits for its result
+///
+/// Reports go out as `notifications/progress` with the token the client sent in
+/// `_meta.progressToken`. When the client asked for no progress, or is gone, reporting does
+/// nothing, so tools can report without checking first.
+#[derive(Clone, Default)]
+pub struct Progress {
+    token: Option<ProgressToken>,
+    peer: Peer,
+}
+
+impl Progress {
+    pub fn new(token: Option<ProgressToken>, peer: Peer) -> Self {
+        Self { token, peer }
+    }
+
+    /// Whether the client asked for progress
+    pub fn is_requested(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Report `progress` out of `total`, if known
+    ///
+    /// `progress` should grow with every report, even when there is no `total`.
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<String>) {
+        let Some(token) = &self.token else {
+            return;
+        };
+        let params = ProgressNotificationParams {
+            progress_token: token.clone(),
+            progress,
+            total,
+            message,
+        };
+        let Ok(params) = serde_json::to_value(params) else {
+            return;
+        };
+        if let Err(e) = self.peer.notify("notifications/progress", Some(params)) {
+            tracing::debug!(error = %e, "Failed to report progress");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::protocol::JsonRpcMessage;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_report_progress() {
+        let peer = Peer::new();
+        let mut outgoing = peer.connect();
+
+        // Nothing is sent for requests that did not ask for progress
+        Progress::new(None, peer.clone()).report(1.0, None, None);
+
+        let progress = Progress::new(Some(ProgressToken::Number(3)), peer.clone());
+        assert!(progress.is_requested());
+        progress.report(1.0, Some(4.0), Some("Building".to_string()));
+
+        let Some(JsonRpcMessage::Notification(notification)) = outgoing.recv().await else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/progress");
+        assert_eq!(
+            notification.params,
+            Some(json!({
+                "progressToken": 3,
+                "progress": 1.0,
+                "total": 4.0,
+                "message": "Building"
+            }))
+        );
+        assert!(outgoing.try_recv().is_err());
+    }
+}
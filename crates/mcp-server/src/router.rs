@@ -21,7 +21,7 @@ use mcp_core::{
 use serde_json::Value;
 use tower_service::Service;
 
-use crate::{BoxError, RouterError};
+use crate::{BoxError, Peer, Progress, RouterError};
 
 /// Builder for configuring and constructing capabilities
 pub struct CapabilitiesBuilder {
@@ -92,6 +92,18 @@ pub trait Router: Send + Sync + 'static {
         tool_name: &str,
         arguments: Value,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>>;
+    /// Like `call_tool`, for tools that report their progress while they run
+    ///
+    /// Calls `call_tool` unless overridden, so routers without long running tools can ignore it.
+    fn call_tool_with_progress(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        progress: Progress,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        let _ = progress;
+        self.call_tool(tool_name, arguments)
+    }
     fn list_resources(&self) -> Vec<mcp_core::resource::Resource>;
     fn read_resource(
         &self,
@@ -172,7 +184,16 @@ pub trait Router: Send + Sync + 'static {
 
             let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
 
-            let result = match self.call_tool(name, arguments).await {
+            let token = params
+                .get("_meta")
+                .and_then(|meta| meta.get("progressToken"))
+                .and_then(|token| serde_json::from_value(token.clone()).ok());
+            let progress = Progress::new(token, Peer::current().unwrap_or_default());
+
+            let result = match self
+                .call_tool_with_progress(name, arguments, progress)
+                .await
+            {
                 Ok(result) => CallToolResult {
                     content: result,
                     is_error: None,
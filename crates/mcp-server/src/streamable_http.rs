@@ -0,0 +1,853 @@
+//! The MCP "Streamable HTTP" transport
+//!
+//! A single endpoint serves every session. Clients POST JSON-RPC messages to it and get the
+//! responses back either as a JSON body or as an SSE stream, GET it to open a stream for
+//! messages the server sends on its own, and DELETE it to end their session. Sessions are
+//! identified by the `Mcp-Session-Id` header handed out with the initialize response.
+//!
+//! Every SSE event carries an id, so a client that lost a stream can GET the endpoint with
+//! `Last-Event-ID` and have the events it missed replayed before the stream carries on.
+//!
+//! Requests from browsers are only served for allowed origins, so a page can't reach a local
+//! server through DNS rebinding. Sessions left idle, with no open stream, expire.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Json, Router,
+};
+use futures::{stream, Future, Stream, StreamExt};
+use mcp_core::protocol::{ErrorData, JsonRpcError, JsonRpcMessage, INVALID_REQUEST, PARSE_ERROR};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use crate::{BoundedService, Peer, Server, ServerError, ServerTransport, TransportError};
+
+/// The path the endpoint is served at
+pub const MCP_ENDPOINT: &str = "/mcp";
+/// The header that carries the session id on every request after initialize
+pub const SESSION_ID_HEADER: &str = "mcp-session-id";
+/// The header a client resumes a stream with
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// How many events each session keeps for clients resuming a stream
+const EVENT_HISTORY_SIZE: usize = 1024;
+/// Messages not answering a request go out on the stream opened with GET
+const STANDALONE_STREAM: u64 = 0;
+/// How long a session with no open stream is kept after the client was last heard from
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+type ServiceFactory = dyn Fn(Peer) -> Box<dyn BoundedService> + Send + Sync;
+
+/// Serves MCP sessions over Streamable HTTP
+///
+/// Each session gets its own service from the factory, run by a [`Server`] of its own. The
+/// factory is handed the [`Peer`] of the session's client, for routers that make requests of it.
+#[derive(Clone)]
+pub struct StreamableHttpServer {
+    state: Arc<HttpState>,
+}
+
+struct HttpState {
+    factory: Box<ServiceFactory>,
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+    /// Origins browsers may call from besides the local host, None allows any
+    allowed_origins: Option<Vec<String>>,
+    session_ttl: Duration,
+}
+
+impl StreamableHttpServer {
+    pub fn new<F, S>(factory: F) -> Self
+    where
+        F: Fn(Peer) -> S + Send + Sync + 'static,
+        S: BoundedService,
+    {
+        Self {
+            state: Arc::new(HttpState {
+                factory: Box::new(move |peer| Box::new(factory(peer))),
+                sessions: Mutex::new(HashMap::new()),
+                allowed_origins: Some(Vec::new()),
+                session_ttl: DEFAULT_SESSION_TTL,
+            }),
+        }
+    }
+
+    /// Also serve browsers on these origins, e.g. `https://app.example.com`
+    ///
+    /// Requests without an `Origin` header and from the local host are always served.
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.state_mut().allowed_origins = Some(origins);
+        self
+    }
+
+    /// Serve browsers on any origin, for servers that authenticate their clients themselves
+    pub fn with_any_origin(mut self) -> Self {
+        self.state_mut().allowed_origins = None;
+        self
+    }
+
+    /// How long a session with no open stream is kept after the client was last heard from
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.state_mut().session_ttl = ttl;
+        self
+    }
+
+    fn state_mut(&mut self) -> &mut HttpState {
+        Arc::get_mut(&mut self.state).expect("Configure the server before serving it")
+    }
+
+    /// A router serving the endpoint at [`MCP_ENDPOINT`], to serve or merge into another
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route(
+                MCP_ENDPOINT,
+                get(handle_get).post(handle_post).delete(handle_delete),
+            )
+            .with_state(self.state.clone())
+    }
+
+    pub async fn serve(self, listener: TcpListener) -> Result<(), ServerError> {
+        tracing::info!(
+            "Serving MCP over Streamable HTTP at http://{}{}",
+            listener
+                .local_addr()
+                .map_err(|e| ServerError::Transport(e.into()))?,
+            MCP_ENDPOINT
+        );
+        axum::serve(listener, self.router())
+            .await
+            .map_err(|e| ServerError::Transport(e.into()))
+    }
+}
+
+/// An event as it was sent, kept so it can be replayed
+#[derive(Clone)]
+struct StoredEvent {
+    id: u64,
+    stream: u64,
+    message: JsonRpcMessage,
+}
+
+/// Where the messages a session's server writes go
+#[derive(Default)]
+struct Outbox {
+    next_event_id: u64,
+    next_stream_id: u64,
+    history: VecDeque<StoredEvent>,
+    /// The connected streams, by stream id
+    streams: HashMap<u64, mpsc::UnboundedSender<StoredEvent>>,
+    /// The stream each unanswered request is answered on
+    pending: HashMap<u64, u64>,
+    /// How many requests each open POST stream is still waiting on
+    remaining: HashMap<u64, usize>,
+}
+
+impl Outbox {
+    /// Open a stream for the responses to a POST's requests
+    fn open_stream(&mut self, request_ids: &[u64]) -> mpsc::UnboundedReceiver<StoredEvent> {
+        self.next_stream_id += 1;
+        let stream = self.next_stream_id;
+        for id in request_ids {
+            self.pending.insert(*id, stream);
+        }
+        self.remaining.insert(stream, request_ids.len());
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.streams.insert(stream, tx);
+        rx
+    }
+
+    fn deliver(&mut self, message: JsonRpcMessage) {
+        let answered = match &message {
+            JsonRpcMessage::Response(response) => response.id,
+            JsonRpcMessage::Error(error) => error.id,
+            _ => None,
+        };
+        let stream = answered
+            .and_then(|id| self.pending.remove(&id))
+            .unwrap_or(STANDALONE_STREAM);
+
+        self.next_event_id += 1;
+        let event = StoredEvent {
+            id: self.next_event_id,
+            stream,
+            message,
+        };
+        if self.history.len() == EVENT_HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(event.clone());
+
+        if let Some(tx) = self.streams.get(&stream) {
+            if tx.send(event).is_err() {
+                self.streams.remove(&stream);
+            }
+        }
+
+        // A POST stream ends once all of its requests are answered
+        if stream != STANDALONE_STREAM {
+            if let Some(remaining) = self.remaining.get_mut(&stream) {
+                *remaining = remaining.saturating_sub(1);
+                if *remaining == 0 {
+                    self.remaining.remove(&stream);
+                    self.streams.remove(&stream);
+                }
+            }
+        }
+    }
+
+    /// Stop waiting for the response to a cancelled request, which the server won't send
+    fn cancel(&mut self, id: u64) {
+        let Some(stream) = self.pending.remove(&id) else {
+            return;
+        };
+        if let Some(remaining) = self.remaining.get_mut(&stream) {
+            *remaining = remaining.saturating_sub(1);
+            if *remaining == 0 {
+                self.remaining.remove(&stream);
+                self.streams.remove(&stream);
+            }
+        }
+    }
+
+    /// Whether a client is still listening on one of the streams or waiting for a response
+    fn in_use(&self) -> bool {
+        !self.pending.is_empty() || self.streams.values().any(|tx| !tx.is_closed())
+    }
+
+    /// Reconnect the stream an event was sent on, replaying what came after it
+    ///
+    /// Without an event id, or with one that is no longer in the history, this opens a new
+    /// standalone stream.
+    fn resume(
+        &mut self,
+        last_event_id: Option<u64>,
+    ) -> (
+        Vec<StoredEvent>,
+        Option<mpsc::UnboundedReceiver<StoredEvent>>,
+    ) {
+        let stream = last_event_id
+            .and_then(|last| self.history.iter().find(|event| event.id == last))
+            .map(|event| event.stream)
+            .unwrap_or(STANDALONE_STREAM);
+        let replay = match last_event_id {
+            Some(last) => self
+                .history
+                .iter()
+                .filter(|event| event.stream == stream && event.id > last)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // A POST stream that already got all of its responses has nothing left to send
+        if stream != STANDALONE_STREAM && !self.remaining.contains_key(&stream) {
+            return (replay, None);
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.streams.insert(stream, tx);
+        (replay, Some(rx))
+    }
+}
+
+struct Session {
+    /// Messages from the client to the session's server
+    incoming: mpsc::UnboundedSender<JsonRpcMessage>,
+    outbox: Arc<Mutex<Outbox>>,
+    /// When the client last made a request in this session
+    last_seen: Mutex<Instant>,
+}
+
+/// Connects a session's [`Server`] to the HTTP handlers
+struct SessionTransport {
+    incoming: mpsc::UnboundedReceiver<JsonRpcMessage>,
+    outbox: Arc<Mutex<Outbox>>,
+}
+
+impl Stream for SessionTransport {
+    type Item = Result<JsonRpcMessage, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_recv(cx).map(|message| message.map(Ok))
+    }
+}
+
+#[async_trait]
+impl ServerTransport for SessionTransport {
+    async fn write_message(&mut self, msg: JsonRpcMessage) -> Result<(), TransportError> {
+        self.outbox.lock().unwrap().deliver(msg);
+        Ok(())
+    }
+}
+
+impl HttpState {
+    /// The session a request names, an unknown one is an error to answer with 404
+    fn session(&self, headers: &HeaderMap) -> Result<Option<Arc<Session>>, StatusCode> {
+        let Some(id) = headers.get(SESSION_ID_HEADER) else {
+            return Ok(None);
+        };
+        let id = id.to_str().unwrap_or_default();
+        self.expire_idle_sessions();
+        match self.sessions.lock().unwrap().get(id) {
+            Some(session) => {
+                *session.last_seen.lock().unwrap() = Instant::now();
+                Ok(Some(session.clone()))
+            }
+            None => Err(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// Drop the sessions of clients that went away without ending them, which ends their
+    /// servers
+    fn expire_idle_sessions(&self) {
+        self.sessions.lock().unwrap().retain(|id, session| {
+            let keep = session.last_seen.lock().unwrap().elapsed() < self.session_ttl
+                || session.outbox.lock().unwrap().in_use();
+            if !keep {
+                tracing::info!(session_id = id, "Expired idle MCP session");
+            }
+            keep
+        });
+    }
+
+    /// Whether a request may be served, judging by the page it came from, if any
+    fn allows_origin(&self, headers: &HeaderMap) -> bool {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return true;
+        };
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        let Some(allowed) = &self.allowed_origins else {
+            return true;
+        };
+        is_local_origin(origin) || allowed.iter().any(|allowed| allowed == origin)
+    }
+
+    fn start_session(&self) -> (String, Arc<Session>) {
+        self.expire_idle_sessions();
+        let id = uuid::Uuid::new_v4().to_string();
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        let outbox = Arc::new(Mutex::new(Outbox::default()));
+        let transport = SessionTransport {
+            incoming: incoming_rx,
+            outbox: outbox.clone(),
+        };
+
+        let peer = Peer::new();
+        let server = Server::new((self.factory)(peer.clone())).with_peer(peer);
+        let session_id = id.clone();
+        // Naming the type up front spares the compiler proving the run future is Send itself
+        let run: Pin<Box<dyn Future<Output = Result<(), ServerError>> + Send>> =
+            Box::pin(server.run(transport));
+        tokio::spawn(async move {
+            if let Err(e) = run.await {
+                tracing::error!(session_id, error = %e, "MCP session failed");
+            }
+        });
+
+        let session = Arc::new(Session {
+            incoming: incoming_tx,
+            outbox,
+            last_seen: Mutex::new(Instant::now()),
+        });
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), session.clone());
+        tracing::info!(session_id = id, "Started MCP session");
+        (id, session)
+    }
+}
+
+/// Whether an origin is a page served from this machine
+fn is_local_origin(origin: &str) -> bool {
+    let Some((scheme, rest)) = origin.split_once("://") else {
+        return false;
+    };
+    if scheme != "http" && scheme != "https" {
+        return false;
+    }
+    // Strip the port, minding the brackets of an IPv6 address
+    let host = match rest.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => rest.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn error_response(status: StatusCode, code: i32, message: impl Into<String>) -> Response {
+    let error = JsonRpcMessage::Error(JsonRpcError {
+        jsonrpc: "2.0".to_string(),
+        id: None,
+        error: ErrorData {
+            code,
+            message: message.into(),
+            data: None,
+        },
+    });
+    (status, Json(error)).into_response()
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"))
+}
+
+/// Parse a POST body, which is a single message or a batch of them
+fn parse_messages(body: &str) -> Result<(Vec<JsonRpcMessage>, bool), serde_json::Error> {
+    let value: serde_json::Value = serde_json::from_str(body)?;
+    match value {
+        serde_json::Value::Array(values) => {
+            let messages = values
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()?;
+            Ok((messages, true))
+        }
+        value => Ok((vec![serde_json::from_value(value)?], false)),
+    }
+}
+
+fn sse_event(event: &StoredEvent) -> Result<Event, Infallible> {
+    let data = serde_json::to_string(&event.message).unwrap_or_default();
+    Ok(Event::default().id(event.id.to_string()).data(data))
+}
+
+fn sse_response(
+    replay: Vec<StoredEvent>,
+    live: Option<mpsc::UnboundedReceiver<StoredEvent>>,
+) -> Response {
+    let live = stream::unfold(live, |live| async move {
+        let mut rx = live?;
+        let event = rx.recv().await?;
+        Some((event, Some(rx)))
+    });
+    let events = stream::iter(replay).chain(live);
+    Sse::new(events.map(|event| sse_event(&event)))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn with_session_id(mut response: Response, session_id: Option<String>) -> Response {
+    if let Some(value) = session_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        response.headers_mut().insert(SESSION_ID_HEADER, value);
+    }
+    response
+}
+
+async fn handle_post(
+    State(state): State<Arc<HttpState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if !state.allows_origin(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let (messages, batch) = match parse_messages(&body) {
+        Ok(parsed) => parsed,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, PARSE_ERROR, e.to_string()),
+    };
+
+    let is_initialize = messages.iter().any(|message| {
+        matches!(message, JsonRpcMessage::Request(request) if request.method == "initialize")
+    });
+    let (session, new_session_id) = match state.session(&headers) {
+        Ok(Some(session)) => (session, None),
+        Ok(None) if is_initialize => {
+            let (id, session) = state.start_session();
+            (session, Some(id))
+        }
+        Ok(None) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                INVALID_REQUEST,
+                "Missing Mcp-Session-Id header, a session starts with an initialize request",
+            )
+        }
+        Err(status) => return status.into_response(),
+    };
+
+    let request_ids: Vec<u64> = messages
+        .iter()
+        .filter_map(|message| match message {
+            JsonRpcMessage::Request(request) => request.id,
+            _ => None,
+        })
+        .collect();
+
+    // Register the stream first, so no response can arrive before anyone listens for it
+    let rx = if request_ids.is_empty() {
+        None
+    } else {
+        Some(session.outbox.lock().unwrap().open_stream(&request_ids))
+    };
+    for message in &messages {
+        // A cancelled request gets no response, so the stream waiting for one stops waiting
+        let JsonRpcMessage::Notification(notification) = message else {
+            continue;
+        };
+        if notification.method != "notifications/cancelled" {
+            continue;
+        }
+        let cancelled = notification
+            .params
+            .as_ref()
+            .and_then(|params| params.get("requestId"))
+            .and_then(serde_json::Value::as_u64);
+        if let Some(id) = cancelled {
+            session.outbox.lock().unwrap().cancel(id);
+        }
+    }
+    for message in messages {
+        if session.incoming.send(message).is_err() {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    }
+
+    // Notifications and responses to the server's requests have nothing to wait for
+    let Some(mut rx) = rx else {
+        return with_session_id(StatusCode::ACCEPTED.into_response(), new_session_id);
+    };
+
+    let response = if accepts_event_stream(&headers) {
+        sse_response(Vec::new(), Some(rx))
+    } else {
+        let mut responses = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if matches!(
+                event.message,
+                JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_)
+            ) {
+                responses.push(event.message);
+            }
+        }
+        if batch {
+            Json(responses).into_response()
+        } else {
+            match responses.pop() {
+                Some(response) => Json(response).into_response(),
+                // The request was cancelled
+                None => StatusCode::ACCEPTED.into_response(),
+            }
+        }
+    };
+    with_session_id(response, new_session_id)
+}
+
+async fn handle_get(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if !state.allows_origin(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if !accepts_event_stream(&headers) {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    }
+    let session = match state.session(&headers) {
+        Ok(Some(session)) => session,
+        Ok(None) => return StatusCode::BAD_REQUEST.into_response(),
+        Err(status) => return status.into_response(),
+    };
+
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.parse().ok());
+    let (replay, live) = session.outbox.lock().unwrap().resume(last_event_id);
+    sse_response(replay, live)
+}
+
+async fn handle_delete(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> StatusCode {
+    if !state.allows_origin(&headers) {
+        return StatusCode::FORBIDDEN;
+    }
+    let Some(id) = headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+    else {
+        return StatusCode::BAD_REQUEST;
+    };
+    // Dropping the session closes its server's input, which ends the server
+    match state.sessions.lock().unwrap().remove(id) {
+        Some(_) => {
+            tracing::info!(session_id = id, "Ended MCP session");
+            StatusCode::NO_CONTENT
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoxError;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use mcp_core::protocol::{JsonRpcRequest, JsonRpcResponse};
+    use serde_json::json;
+    use tower::ServiceExt;
+    use tower_service::Service;
+
+    /// Answers every request with its own method name, except `hang` which never finishes
+    struct EchoService;
+
+    impl Service<JsonRpcRequest> for EchoService {
+        type Response = JsonRpcResponse;
+        type Error = BoxError;
+        type Future = Pin<Box<dyn Future<Output = Result<JsonRpcResponse, BoxError>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: JsonRpcRequest) -> Self::Future {
+            Box::pin(async move {
+                if request.method == "hang" {
+                    std::future::pending::<()>().await;
+                }
+                Ok(JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(json!({ "method": request.method })),
+                    error: None,
+                })
+            })
+        }
+    }
+
+    fn post(session_id: Option<&str>, accept: &str, body: serde_json::Value) -> Request<Body> {
+        let mut request = Request::post(MCP_ENDPOINT)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, accept);
+        if let Some(id) = session_id {
+            request = request.header(SESSION_ID_HEADER, id);
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    fn request(id: u64, method: &str) -> serde_json::Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method })
+    }
+
+    async fn initialize(router: &Router) -> String {
+        let response = router
+            .clone()
+            .oneshot(post(None, "application/json", request(1, "initialize")))
+            .await
+            .unwrap();
+        response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let router = StreamableHttpServer::new(|_| EchoService).router();
+
+        let response = router
+            .clone()
+            .oneshot(post(None, "application/json", request(1, "ping")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = router
+            .clone()
+            .oneshot(post(None, "application/json", request(1, "initialize")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let session_id = response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = serde_json::from_str(&body_text(response).await).unwrap();
+        assert_eq!(body["id"], 1);
+        assert_eq!(body["result"]["method"], "initialize");
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = router
+            .clone()
+            .oneshot(post(Some(&session_id), "application/json", notification))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let delete = Request::delete(MCP_ENDPOINT)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(delete).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = router
+            .oneshot(post(
+                Some(&session_id),
+                "application/json",
+                request(2, "ping"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sse_response_and_resume() {
+        let router = StreamableHttpServer::new(|_| EchoService).router();
+        let response = router
+            .clone()
+            .oneshot(post(None, "application/json", request(1, "initialize")))
+            .await
+            .unwrap();
+        let session_id = response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let accept = "application/json, text/event-stream";
+        let batch = json!([request(2, "tools/list"), request(3, "prompts/list")]);
+        let response = router
+            .clone()
+            .oneshot(post(Some(&session_id), accept, batch))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let body = body_text(response).await;
+        assert!(body.contains("id: 2\n"));
+        assert!(body.contains("id: 3\n"));
+        assert!(body.contains("tools/list"));
+        assert!(body.contains("prompts/list"));
+
+        // Resuming after the first event of the stream replays the rest of it
+        let resume = Request::get(MCP_ENDPOINT)
+            .header(header::ACCEPT, "text/event-stream")
+            .header(SESSION_ID_HEADER, &session_id)
+            .header(LAST_EVENT_ID_HEADER, "2")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(resume).await.unwrap();
+        let body = body_text(response).await;
+        assert!(!body.contains("id: 2\n"));
+        assert!(body.contains("id: 3\n"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_completes_post() {
+        let router = StreamableHttpServer::new(|_| EchoService).router();
+        let session_id = initialize(&router).await;
+
+        for accept in ["application/json", "application/json, text/event-stream"] {
+            let pending = tokio::spawn(router.clone().oneshot(post(
+                Some(&session_id),
+                accept,
+                request(2, "hang"),
+            )));
+            // Let the request reach the server before cancelling it
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let cancel = json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": { "requestId": 2 },
+            });
+            let response = router
+                .clone()
+                .oneshot(post(Some(&session_id), "application/json", cancel))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+            let response = tokio::time::timeout(Duration::from_secs(5), async {
+                let response = pending.await.unwrap().unwrap();
+                body_text(response).await
+            })
+            .await;
+            assert!(response.is_ok(), "POST of a cancelled request hung");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_origin_check() {
+        let router = StreamableHttpServer::new(|_| EchoService).router();
+        let with_origin = |origin: &str| {
+            let mut request = post(None, "application/json", request(1, "initialize"));
+            request
+                .headers_mut()
+                .insert(header::ORIGIN, origin.parse().unwrap());
+            request
+        };
+
+        let response = router
+            .clone()
+            .oneshot(with_origin("http://evil.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = router
+            .clone()
+            .oneshot(with_origin("http://localhost:3000"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let router = StreamableHttpServer::new(|_| EchoService)
+            .with_allowed_origins(vec!["https://app.example.com".to_string()])
+            .router();
+        let response = router
+            .oneshot(with_origin("https://app.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_is_local_origin() {
+        assert!(is_local_origin("http://localhost"));
+        assert!(is_local_origin("https://127.0.0.1:8080"));
+        assert!(is_local_origin("http://[::1]:3000"));
+        assert!(!is_local_origin("http://localhost.evil.com"));
+        assert!(!is_local_origin("file://localhost"));
+        assert!(!is_local_origin("null"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_session_expires() {
+        let router = StreamableHttpServer::new(|_| EchoService)
+            .with_session_ttl(Duration::from_millis(50))
+            .router();
+        let session_id = initialize(&router).await;
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let response = router
+            .oneshot(post(
+                Some(&session_id),
+                "application/json",
+                request(2, "ping"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,7 +1,12 @@
 import { useState, useCallback, useEffect, useRef, useId } from 'react';
 import useSWR from 'swr';
 import { getSecretKey } from '../config';
-import { Message, createUserMessage, hasCompletedToolCalls } from '../types/message';
+import {
+  Message,
+  createUserMessage,
+  hasCompletedToolCalls,
+  isToolProgress,
+} from '../types/message';
 
 // Ensure TextDecoder is available in the global scope
 const TextDecoder = globalThis.TextDecoder;
@@ -227,6 +232,11 @@ export function useMessageStream({
 
                 switch (parsedEvent.type) {
                   case 'Message': {
+                    // The progress of running tools is not part of the conversation
+                    if (isToolProgress(parsedEvent.message)) {
+                      break;
+                    }
+
                     // Create a new message object with the properties preserved or defaulted
                     const newMessage = {
                       ...parsedEvent.message,
@@ -78,13 +78,31 @@ export interface ContextLengthExceededContent {
   msg: string;
 }
 
+export interface BudgetExceededContent {
+  type: 'budgetExceeded';
+  limit: 'turns' | 'toolCalls' | 'tokens' | 'cost';
+  max: number;
+  used: number;
+  msg: string;
+}
+
+export interface ToolProgressContent {
+  type: 'toolProgress';
+  id: string;
+  progress: number;
+  total?: number;
+  message?: string;
+}
+
 export type MessageContent =
   | TextContent
   | ImageContent
   | ToolRequestMessageContent
   | ToolResponseMessageContent
   | ToolConfirmationRequestMessageContent
-  | ContextLengthExceededContent;
+  | ContextLengthExceededContent
+  | BudgetExceededContent
+  | ToolProgressContent;
 
 export interface Message {
   id?: string;
@@ -184,13 +202,15 @@ function generateId(): string {
 export function getTextContent(message: Message): string {
   return message.content
     .filter(
-      (content): content is TextContent | ContextLengthExceededContent =>
-        content.type === 'text' || content.type === 'contextLengthExceeded'
+      (content): content is TextContent | ContextLengthExceededContent | BudgetExceededContent =>
+        content.type === 'text' ||
+        content.type === 'contextLengthExceeded' ||
+        content.type === 'budgetExceeded'
     )
     .map((content) => {
       if (content.type === 'text') {
         return content.text;
-      } else if (content.type === 'contextLengthExceeded') {
+      } else if (content.type === 'contextLengthExceeded' || content.type === 'budgetExceeded') {
         return content.msg;
       }
       return '';
@@ -219,6 +239,10 @@ export function getToolConfirmationContent(
   );
 }
 
+export function isToolProgress(message: Message): boolean {
+  return message.content.some((content) => content.type === 'toolProgress');
+}
+
 export function hasCompletedToolCalls(message: Message): boolean {
   const toolRequests = getToolRequests(message);
   if (toolRequests.length === 0) return false;